@@ -0,0 +1,504 @@
+//! Load generator for the websocket server.
+//!
+//! Each simulated group connects a client for every party, creates
+//! key generation sessions followed by signing sessions and relays
+//! synthetic round messages that are shaped like the GG2020 rounds
+//! so that the server sees realistic traffic without the cost of the
+//! protocol.
+use anyhow::{anyhow, Result};
+use hdrhistogram::Histogram;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use mpc_websocket::{
+    services::{
+        GROUP_CREATE, GROUP_JOIN, NOTIFY_PROPOSAL, NOTIFY_PROPOSAL_EVENT, SESSION_CREATE,
+        SESSION_CREATE_EVENT, SESSION_FINISH, SESSION_SIGNUP, SESSION_SIGNUP_EVENT,
+    },
+    Parameters, SessionKind,
+};
+
+use crate::client::Client;
+use crate::session::{GroupInfo, Party, RoundMsg, SessionInfo};
+
+/// Shape of a round; whether messages are sent peer to peer
+/// and the approximate size in bytes of the encoded message body.
+const KEYGEN_ROUNDS: [(bool, usize); 4] = [
+    // Commitment, Paillier key and ring-pedersen parameters
+    (false, 6144),
+    // Decommitment
+    (false, 1024),
+    // Encrypted secret share for each party
+    (true, 1024),
+    // Discrete log proof
+    (false, 2048),
+];
+
+/// Shape of the offline stage rounds followed by the partial signature.
+const SIGN_ROUNDS: [(bool, usize); 7] = [
+    // Commitment and encrypted nonce share
+    (false, 2048),
+    // Multiplicative to additive responses with range proofs
+    (true, 4096),
+    // Delta share and commitment
+    (false, 512),
+    // Decommitment
+    (false, 256),
+    // R dash with PDL proofs for each party
+    (false, 4096),
+    // S share with proof
+    (false, 512),
+    // Partial signature
+    (false, 128),
+];
+
+/// Options for the load generator.
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    /// URL of the websocket server.
+    pub server: String,
+    /// Number of concurrent groups.
+    pub groups: usize,
+    /// Sessions run in sequence by each group; every session
+    /// generates a key and then signs with `threshold + 1` parties.
+    pub sessions: usize,
+    /// Parameters for each group.
+    pub params: Parameters,
+    /// Maximum time to wait for a reply or event.
+    pub timeout: Duration,
+}
+
+/// Statistics collected while running the load generator.
+#[derive(Debug)]
+struct Stats {
+    requests: u64,
+    events: u64,
+    bytes: u64,
+    sessions: u64,
+    signatures: u64,
+    request_latency: Histogram<u64>,
+    round_latency: Histogram<u64>,
+    errors: HashMap<String, u64>,
+}
+
+impl Stats {
+    fn new() -> Result<Self> {
+        Ok(Self {
+            requests: 0,
+            events: 0,
+            bytes: 0,
+            sessions: 0,
+            signatures: 0,
+            request_latency: Histogram::new(3)?,
+            round_latency: Histogram::new(3)?,
+            errors: Default::default(),
+        })
+    }
+
+    /// Record a completed round.
+    ///
+    /// Every incoming message for a round is delivered as a
+    /// separate event so each message counts as one event.
+    fn round(&mut self, messages: usize, elapsed: Duration) -> Result<()> {
+        self.events += messages as u64;
+        self.round_latency.record(elapsed.as_micros() as u64)?;
+        Ok(())
+    }
+}
+
+type SharedStats = Arc<Mutex<Stats>>;
+
+/// Report generated when the load generator completes.
+#[derive(Debug)]
+pub struct Report {
+    elapsed: Duration,
+    stats: Stats,
+}
+
+impl Report {
+    /// Total number of errors.
+    pub fn errors(&self) -> u64 {
+        self.stats.errors.values().sum()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.elapsed.as_secs_f64();
+        let stats = &self.stats;
+        writeln!(f, "elapsed      {:.2}s", secs)?;
+        writeln!(f, "sessions     {}", stats.sessions)?;
+        writeln!(f, "signatures   {}", stats.signatures)?;
+        writeln!(
+            f,
+            "requests     {} ({:.1}/s)",
+            stats.requests,
+            stats.requests as f64 / secs
+        )?;
+        writeln!(
+            f,
+            "events       {} ({:.1}/s)",
+            stats.events,
+            stats.events as f64 / secs
+        )?;
+        writeln!(
+            f,
+            "sent         {:.2} MiB ({:.2} MiB/s)",
+            stats.bytes as f64 / 1048576.0,
+            stats.bytes as f64 / 1048576.0 / secs
+        )?;
+        for (name, histogram) in [
+            ("request", &stats.request_latency),
+            ("round", &stats.round_latency),
+        ] {
+            writeln!(
+                f,
+                "{:<12} p50 {:.2}ms p90 {:.2}ms p99 {:.2}ms max {:.2}ms",
+                name,
+                histogram.value_at_quantile(0.5) as f64 / 1000.0,
+                histogram.value_at_quantile(0.9) as f64 / 1000.0,
+                histogram.value_at_quantile(0.99) as f64 / 1000.0,
+                histogram.max() as f64 / 1000.0,
+            )?;
+        }
+        write!(f, "errors       {}", self.errors())?;
+        let mut errors = stats.errors.iter().collect::<Vec<_>>();
+        errors.sort_by(|a, b| b.1.cmp(a.1));
+        for (error, count) in errors {
+            write!(f, "\n  {:>6} {}", count, error)?;
+        }
+        Ok(())
+    }
+}
+
+/// Run the load generator and report the results.
+pub async fn bench(options: BenchOptions) -> Result<Report> {
+    options.params.validate()?;
+
+    let stats: SharedStats = Arc::new(Mutex::new(Stats::new()?));
+    let start = Instant::now();
+
+    let groups = (0..options.groups)
+        .map(|_| {
+            let options = options.clone();
+            let stats = Arc::clone(&stats);
+            tokio::spawn(async move {
+                if let Err(e) = run_group(&options, &stats).await {
+                    error(&stats, e);
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for group in groups {
+        group.await?;
+    }
+
+    let elapsed = start.elapsed();
+    let stats = Arc::try_unwrap(stats)
+        .map_err(|_| anyhow!("statistics are still in use"))?
+        .into_inner()
+        .unwrap();
+    Ok(Report { elapsed, stats })
+}
+
+/// Create a group, connect the other parties and run the sessions.
+async fn run_group(options: &BenchOptions, stats: &SharedStats) -> Result<()> {
+    let mut owner = Client::connect(&options.server).await?;
+    let group_id: Uuid = timed(
+        options,
+        stats,
+        owner.rpc(GROUP_CREATE, ("bench", &options.params)),
+    )
+    .await?;
+
+    // Joining the group must complete before the owner creates
+    // a session otherwise the other parties miss the event
+    let mut parties = Vec::new();
+    for _ in 1..options.params.parties {
+        let mut client = Client::connect(&options.server).await?;
+        let group: GroupInfo = timed(options, stats, client.rpc(GROUP_JOIN, group_id)).await?;
+        let options = options.clone();
+        let stats = Arc::clone(stats);
+        parties.push(tokio::spawn(async move {
+            let result = run_party(&options, &stats, client, group, false).await;
+            if let Err(e) = result {
+                error(&stats, e);
+            }
+        }));
+    }
+
+    let group = GroupInfo {
+        uuid: group_id,
+        params: options.params.clone(),
+    };
+    let result = run_party(options, stats, owner, group, true).await;
+    for party in parties {
+        party.await?;
+    }
+    result
+}
+
+/// Run the sessions for a single party.
+///
+/// The owner creates each session and the other parties wait
+/// for the session to be created. After key generation the owner
+/// proposes a signing session that the parties with key generation
+/// numbers up to `threshold + 1` sign up to.
+async fn run_party(
+    options: &BenchOptions,
+    stats: &SharedStats,
+    mut client: Client,
+    group: GroupInfo,
+    owner: bool,
+) -> Result<()> {
+    let signers = options.params.threshold + 1;
+    for _ in 0..options.sessions {
+        let session_id = if owner {
+            let session: SessionInfo = timed(
+                options,
+                stats,
+                client.rpc(
+                    SESSION_CREATE,
+                    (group.uuid, SessionKind::Keygen, Value::Null),
+                ),
+            )
+            .await?;
+            session.uuid
+        } else {
+            let (_, session) = event(
+                options,
+                stats,
+                client.event(|(name, _)| name == SESSION_CREATE_EVENT),
+            )
+            .await?;
+            let session: SessionInfo = serde_json::from_value(session)?;
+            session.uuid
+        };
+
+        let (returned, number) = run_session(
+            options,
+            stats,
+            client,
+            &group,
+            session_id,
+            SessionKind::Keygen,
+        )
+        .await?;
+        client = returned;
+        if owner {
+            stats.lock().unwrap().sessions += 1;
+        }
+
+        let session_id: Uuid = if owner {
+            let session: SessionInfo = timed(
+                options,
+                stats,
+                client.rpc(SESSION_CREATE, (group.uuid, SessionKind::Sign, Value::Null)),
+            )
+            .await?;
+            let proposal_id = Uuid::new_v4().to_string();
+            let _: Option<Value> = timed(
+                options,
+                stats,
+                client.rpc(
+                    NOTIFY_PROPOSAL,
+                    (group.uuid, session.uuid, proposal_id, "0".repeat(64)),
+                ),
+            )
+            .await?;
+            session.uuid
+        } else {
+            let (_, proposal) = event(
+                options,
+                stats,
+                client.event(|(name, _)| name == NOTIFY_PROPOSAL_EVENT),
+            )
+            .await?;
+            serde_json::from_value(proposal["sessionId"].clone())?
+        };
+        if number > signers {
+            continue;
+        }
+
+        let (returned, number) = run_session(
+            options,
+            stats,
+            client,
+            &group,
+            session_id,
+            SessionKind::Sign,
+        )
+        .await?;
+        client = returned;
+        if number == 1 {
+            stats.lock().unwrap().signatures += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Sign up to a session, relay the rounds for the kind of session
+/// and finish the session.
+///
+/// Returns the client and the party number for the session.
+async fn run_session(
+    options: &BenchOptions,
+    stats: &SharedStats,
+    mut client: Client,
+    group: &GroupInfo,
+    session_id: Uuid,
+    kind: SessionKind,
+) -> Result<(Client, u16)> {
+    let (rounds, parties): (&[(bool, usize)], u16) = match kind {
+        SessionKind::Sign => (&SIGN_ROUNDS, group.params.threshold + 1),
+        _ => (&KEYGEN_ROUNDS, group.params.parties),
+    };
+
+    let number: u16 = timed(
+        options,
+        stats,
+        client.rpc(SESSION_SIGNUP, (group.uuid, session_id, &kind)),
+    )
+    .await?;
+    let session = session_id.to_string();
+    event(
+        options,
+        stats,
+        client.event(|(name, value)| {
+            name == SESSION_SIGNUP_EVENT && value.as_str() == Some(&session)
+        }),
+    )
+    .await?;
+
+    let group_id = group.uuid;
+    let info = GroupInfo {
+        uuid: group_id,
+        params: group.params.clone(),
+    };
+    let mut party = Party::new(client, info, session_id, kind);
+    for (round, (peer_to_peer, size)) in rounds.iter().enumerate() {
+        let round = round as u16 + 1;
+        let started = Instant::now();
+        let body = "0".repeat(*size);
+        for receiver in receivers(*peer_to_peer, number, parties) {
+            let message = RoundMsg {
+                round,
+                sender: number,
+                receiver,
+                uuid: session_id,
+                body: body.clone(),
+            };
+            timed(options, stats, party.send(vec![message])).await?;
+            stats.lock().unwrap().bytes += *size as u64;
+        }
+        let incoming = wait(options, party.receive::<String>(round)).await?;
+        stats
+            .lock()
+            .unwrap()
+            .round(incoming.len(), started.elapsed())?;
+    }
+
+    let client = party.client;
+    let _: Option<Value> = timed(
+        options,
+        stats,
+        client.rpc(SESSION_FINISH, (group_id, session_id, number)),
+    )
+    .await?;
+    Ok((client, number))
+}
+
+/// Receivers for the messages a party sends in a round; a broadcast
+/// has a single message without a receiver.
+fn receivers(peer_to_peer: bool, number: u16, parties: u16) -> Vec<Option<u16>> {
+    if peer_to_peer {
+        (1..=parties)
+            .filter(|i| *i != number)
+            .map(Some)
+            .collect::<Vec<_>>()
+    } else {
+        vec![None]
+    }
+}
+
+/// Wait for a request and record the latency.
+async fn timed<T>(
+    options: &BenchOptions,
+    stats: &SharedStats,
+    future: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let started = Instant::now();
+    let result = tokio::time::timeout(options.timeout, future)
+        .await
+        .map_err(|_| anyhow!("request timed out"))??;
+    let mut writer = stats.lock().unwrap();
+    writer.requests += 1;
+    writer
+        .request_latency
+        .record(started.elapsed().as_micros() as u64)?;
+    Ok(result)
+}
+
+/// Wait for an event and count it.
+async fn event<T>(
+    options: &BenchOptions,
+    stats: &SharedStats,
+    future: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let result = wait(options, future).await?;
+    stats.lock().unwrap().events += 1;
+    Ok(result)
+}
+
+/// Wait for events without counting them.
+async fn wait<T>(options: &BenchOptions, future: impl Future<Output = Result<T>>) -> Result<T> {
+    tokio::time::timeout(options.timeout, future)
+        .await
+        .map_err(|_| anyhow!("event timed out"))?
+}
+
+/// Record an error.
+fn error(stats: &SharedStats, error: anyhow::Error) {
+    let mut writer = stats.lock().unwrap();
+    *writer.errors.entry(error.to_string()).or_default() += 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_counts_each_message() {
+        let mut stats = Stats::new().unwrap();
+        stats.round(2, Duration::from_millis(5)).unwrap();
+        stats.round(0, Duration::from_millis(1)).unwrap();
+        assert_eq!(2, stats.events);
+        assert_eq!(2, stats.round_latency.len());
+    }
+
+    #[test]
+    fn round_receivers() {
+        assert_eq!(vec![None], receivers(false, 2, 3));
+        assert_eq!(vec![Some(1), Some(3)], receivers(true, 2, 3));
+    }
+
+    #[tokio::test]
+    async fn invalid_parameters() {
+        let options = BenchOptions {
+            server: String::new(),
+            groups: 1,
+            sessions: 1,
+            params: Parameters {
+                parties: 3,
+                threshold: 3,
+            },
+            timeout: Duration::from_secs(1),
+        };
+        assert!(bench(options).await.is_err());
+    }
+}
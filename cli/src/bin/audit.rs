@@ -0,0 +1,28 @@
+use clap::Parser;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+
+use mpc_websocket::{audit::verify, Result};
+
+#[derive(Debug, Parser)]
+#[clap(
+    name = "mpc-audit-verify",
+    about = "Verify the hash chain of an MPC websocket server audit log"
+)]
+struct Options {
+    /// Expected hash of the last entry to detect truncation.
+    #[structopt(long)]
+    head: Option<String>,
+    /// Path to the audit log file.
+    #[structopt(parse(from_os_str))]
+    file: PathBuf,
+}
+
+fn main() -> Result<()> {
+    let opts: Options = Parser::parse();
+    let reader = BufReader::new(File::open(&opts.file)?);
+    let head = verify(reader, opts.head.as_deref())?;
+    println!("{} entries verified, head {}", head.entries, head.hash);
+    Ok(())
+}
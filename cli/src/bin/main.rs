@@ -1,44 +1,346 @@
-use clap::Parser;
+use anyhow::Result;
+use clap::{Parser, Subcommand};
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::time::Duration;
+use uuid::Uuid;
 
-use mpc_websocket::{Result, Server};
+use mpc_websocket::{config::ServerConfig, CacheControl, Parameters, Server, UnixSocket};
+use mpc_websocket_cli::{bench::BenchOptions, keygen, parse_digest};
+
+/// Default URL for the websocket server.
+const SERVER: &str = "ws://127.0.0.1:3030/mpc";
 
 #[derive(Debug, Parser)]
 #[clap(
     name = "mpc-websocket",
-    about = "Websocket server for MPC key generation and signing"
+    version,
+    about = "Websocket server and parties for MPC key generation and signing"
 )]
 struct Options {
-    /// Bind to host:port.
-    #[structopt(short, long)]
-    bind: Option<String>,
-    /// Path to static files to serve
-    #[structopt(parse(from_os_str))]
-    files: Option<PathBuf>,
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Start the websocket server.
+    Serve {
+        /// Load settings from a TOML config file.
+        #[clap(short, long, parse(from_os_str))]
+        config: Option<PathBuf>,
+        /// Bind to host:port.
+        #[clap(short, long)]
+        bind: Option<String>,
+        /// Listen on a Unix domain socket.
+        #[clap(short, long, parse(from_os_str))]
+        unix_socket: Option<PathBuf>,
+        /// Octal permissions for the Unix domain socket, for example 660.
+        #[clap(long, requires = "unix_socket")]
+        unix_socket_mode: Option<String>,
+        /// Do not listen on TCP.
+        #[clap(long, requires = "unix_socket")]
+        no_tcp: bool,
+        /// Append lifecycle events to an audit log file.
+        #[clap(short, long, parse(from_os_str))]
+        audit_log: Option<PathBuf>,
+        /// Do not serve static files.
+        #[clap(long)]
+        no_static: bool,
+        /// Do not serve index.html for unknown paths.
+        #[clap(long)]
+        no_spa_fallback: bool,
+        /// Cache-Control for static files as pattern=value,
+        /// for example '/assets/*=max-age=31536000'.
+        #[clap(long, parse(try_from_str = parse_cache_control))]
+        cache_control: Vec<CacheControl>,
+        /// Add a header to every response as 'Name: value'.
+        #[clap(long = "header", parse(try_from_str = parse_header))]
+        headers: Vec<(String, String)>,
+        /// Path to static files to serve
+        #[clap(parse(from_os_str))]
+        files: Option<PathBuf>,
+    },
+    /// Join a group and generate a key share.
+    Keygen {
+        /// URL of the websocket server.
+        #[clap(short, long, default_value = SERVER)]
+        server: String,
+        /// Group identifier.
+        #[clap(short, long)]
+        group: Uuid,
+        /// Session identifier, when omitted wait for a session.
+        #[clap(long)]
+        session: Option<Uuid>,
+        /// Write the key share to this file.
+        #[clap(short, long, parse(from_os_str))]
+        output: PathBuf,
+    },
+    /// Join a group and sign a message digest using a key share.
+    Sign {
+        /// URL of the websocket server.
+        #[clap(short, long, default_value = SERVER)]
+        server: String,
+        /// Group identifier.
+        #[clap(short, long)]
+        group: Uuid,
+        /// Session identifier, when omitted create a session.
+        #[clap(long)]
+        session: Option<Uuid>,
+        /// Key share file.
+        #[clap(short, long, parse(from_os_str))]
+        key_share: PathBuf,
+        /// Hex-encoded 32 byte message digest.
+        #[clap(short, long)]
+        message: String,
+        /// Write the signature to this file.
+        #[clap(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+    /// Generate key shares and sign without a server.
+    ///
+    /// Runs every party in this process passing messages
+    /// through in-memory channels.
+    Simulate {
+        /// Number of parties.
+        #[clap(short = 'n', long, default_value = "3")]
+        parties: u16,
+        /// Signing threshold.
+        #[clap(short, long, default_value = "1")]
+        threshold: u16,
+        /// Directory for the key share and signature files.
+        #[clap(short, long, parse(from_os_str), default_value = ".")]
+        output: PathBuf,
+        /// Sign a hex-encoded 32 byte message digest using
+        /// the first threshold + 1 key shares.
+        #[clap(short, long)]
+        sign: Option<String>,
+    },
+    /// Generate load against a running server.
+    Bench {
+        /// URL of the websocket server.
+        #[clap(short, long, default_value = SERVER)]
+        server: String,
+        /// Number of concurrent groups.
+        #[clap(short, long, default_value = "10")]
+        groups: usize,
+        /// Key generation and signing sessions run by each group.
+        #[clap(long, default_value = "10")]
+        sessions: usize,
+        /// Number of parties in each group.
+        #[clap(short = 'n', long, default_value = "3")]
+        parties: u16,
+        /// Signing threshold for each group.
+        #[clap(short, long, default_value = "1")]
+        threshold: u16,
+        /// Seconds to wait for a reply or event.
+        #[clap(long, default_value = "30")]
+        timeout: u64,
+    },
+    /// Verify a signature for a message digest.
+    Verify {
+        /// Hex-encoded 32 byte message digest.
+        #[clap(short, long)]
+        message: String,
+        /// Hex-encoded public key.
+        #[clap(short, long)]
+        public_key: Option<String>,
+        /// Expected address for the signer.
+        #[clap(short, long)]
+        address: Option<String>,
+        /// Signature file.
+        #[clap(parse(from_os_str))]
+        signature: PathBuf,
+    },
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let opts: Options = Parser::parse();
-    let bind = opts.bind.unwrap_or_else(|| "127.0.0.1:3030".to_string());
-    let addr = SocketAddr::from_str(&bind)?;
-
-    let static_files = if let Some(static_files) = opts.files {
-        if static_files.is_absolute() {
-            static_files
-        } else {
-            let cwd = std::env::current_dir()?;
-            cwd.join(static_files)
+    match opts.command {
+        Command::Serve {
+            config,
+            bind,
+            unix_socket,
+            unix_socket_mode,
+            no_tcp,
+            audit_log,
+            no_static,
+            no_spa_fallback,
+            cache_control,
+            headers,
+            files,
+        } => {
+            let mut config = ServerConfig::load(config.as_deref())?;
+            if let Some(bind) = bind {
+                config.bind = SocketAddr::from_str(&bind)?;
+            }
+            if let Some(path) = unix_socket {
+                let mode = unix_socket_mode
+                    .map(|mode| u32::from_str_radix(&mode, 8))
+                    .transpose()?;
+                config.unix_socket = Some(UnixSocket { path, mode });
+            }
+            if no_tcp {
+                config.tcp = false;
+            }
+            if !cache_control.is_empty() {
+                // Command line rules take precedence as the first match wins
+                config
+                    .static_files
+                    .cache_control
+                    .splice(0..0, cache_control);
+            }
+            config.headers.extend(headers);
+            serve(config, audit_log, no_static, no_spa_fallback, files).await?
+        }
+        Command::Keygen {
+            server,
+            group,
+            session,
+            output,
+        } => {
+            let key_share = mpc_websocket_cli::keygen::keygen(&server, group, session).await?;
+            keygen::write(&key_share, &output)?;
+            println!("{}", key_share.address);
+        }
+        Command::Sign {
+            server,
+            group,
+            session,
+            key_share,
+            message,
+            output,
+        } => {
+            let key_share = keygen::read(&key_share)?;
+            let message = parse_digest(&message)?;
+            let signature =
+                mpc_websocket_cli::sign::sign(&server, group, session, key_share, message).await?;
+            let contents = serde_json::to_string_pretty(&signature)?;
+            if let Some(output) = output {
+                std::fs::write(output, contents)?;
+            } else {
+                println!("{}", contents);
+            }
+        }
+        Command::Simulate {
+            parties,
+            threshold,
+            output,
+            sign,
+        } => {
+            let message = sign.as_deref().map(parse_digest).transpose()?;
+            let key_shares = mpc_websocket_cli::simulate::keygen(parties, threshold)?;
+            std::fs::create_dir_all(&output)?;
+            for key_share in &key_shares {
+                let file = output.join(format!("key-share-{}.json", key_share.local_key.i));
+                keygen::write(key_share, &file)?;
+                println!("{}", file.display());
+            }
+            if let Some(message) = message {
+                let signers = mpc_websocket_cli::simulate::quorum(&key_shares, threshold)?;
+                let signature = mpc_websocket_cli::simulate::sign(signers, message)?;
+                let file = output.join("signature.json");
+                std::fs::write(&file, serde_json::to_string_pretty(&signature)?)?;
+                println!("{}", file.display());
+            }
+        }
+        Command::Bench {
+            server,
+            groups,
+            sessions,
+            parties,
+            threshold,
+            timeout,
+        } => {
+            let options = BenchOptions {
+                server,
+                groups,
+                sessions,
+                params: Parameters { parties, threshold },
+                timeout: Duration::from_secs(timeout),
+            };
+            let report = mpc_websocket_cli::bench::bench(options).await?;
+            println!("{}", report);
+            if report.errors() > 0 {
+                std::process::exit(1);
+            }
+        }
+        Command::Verify {
+            message,
+            public_key,
+            address,
+            signature,
+        } => {
+            let message = parse_digest(&message)?;
+            let signature = serde_json::from_slice(&std::fs::read(signature)?)?;
+            let public_key = public_key
+                .map(|key| hex::decode(key.trim_start_matches("0x")))
+                .transpose()?;
+            let verified =
+                mpc_websocket_cli::verify::verify(message, signature, public_key, address)?;
+            println!("public key {}", hex::encode(&verified.public_key));
+            println!("address {}", verified.address);
         }
-    } else {
+    }
+    Ok(())
+}
+
+/// Parse a header given as `Name: value`.
+fn parse_header(value: &str) -> std::result::Result<(String, String), String> {
+    let (name, value) = value
+        .split_once(':')
+        .ok_or_else(|| format!("header {:?} must be 'Name: value'", value))?;
+    Ok((name.trim().to_string(), value.trim().to_string()))
+}
+
+/// Parse a Cache-Control rule given as `pattern=value`.
+fn parse_cache_control(value: &str) -> std::result::Result<CacheControl, String> {
+    let (pattern, value) = value
+        .split_once('=')
+        .ok_or_else(|| format!("cache control {:?} must be 'pattern=value'", value))?;
+    Ok(CacheControl {
+        pattern: pattern.to_string(),
+        value: value.to_string(),
+    })
+}
+
+/// Start the server.
+///
+/// Command line options take precedence over the config file
+/// and environment variables.
+async fn serve(
+    mut config: ServerConfig,
+    audit_log: Option<PathBuf>,
+    no_static: bool,
+    no_spa_fallback: bool,
+    files: Option<PathBuf>,
+) -> Result<()> {
+    if audit_log.is_some() {
+        config.audit_log = audit_log;
+    }
+    if no_static {
+        config.static_files.enabled = false;
+    }
+    if no_spa_fallback {
+        config.static_files.spa_fallback = false;
+    }
+    if files.is_some() {
+        config.static_files.directory = files;
+    }
+
+    if config.static_files.enabled && config.static_files.directory.is_none() {
         let mut static_files = std::env::current_dir()?;
         static_files.pop();
         static_files.push("demo");
         static_files.push("dist");
-        static_files
-    };
+        config.static_files.directory = Some(static_files);
+    }
+    config.validate()?;
 
-    Server::start("mpc", (addr.ip(), addr.port()), static_files).await
+    let options = config.options()?;
+    let listen = config.listen();
+    Server::start_listeners(config.path, listen, options).await?;
+    Ok(())
 }
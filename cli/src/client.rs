@@ -0,0 +1,145 @@
+//! JSON-RPC client for the websocket server.
+use anyhow::{anyhow, bail, Result};
+use futures_util::{SinkExt, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, oneshot};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+type Pending = Arc<Mutex<HashMap<u64, oneshot::Sender<Value>>>>;
+
+/// Event emitted by the server as an `(event, payload)` tuple.
+pub type Event = (String, Value);
+
+/// Websocket client that sends JSON-RPC requests and receives events.
+pub struct Client {
+    id: u64,
+    tx: mpsc::UnboundedSender<Message>,
+    pending: Pending,
+    events: mpsc::UnboundedReceiver<Event>,
+    backlog: VecDeque<Event>,
+}
+
+impl Client {
+    /// Connect to the websocket server at `url`.
+    pub async fn connect(url: &str) -> Result<Self> {
+        let (socket, _) = connect_async(url).await?;
+        let (mut writer, mut reader) = socket.split();
+
+        let (tx, mut rx) = mpsc::unbounded_channel::<Message>();
+        let (events_tx, events) = mpsc::unbounded_channel::<Event>();
+        let pending: Pending = Arc::new(Mutex::new(HashMap::new()));
+
+        tokio::spawn(async move {
+            while let Some(message) = rx.recv().await {
+                if writer.send(message).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        let responses = Arc::clone(&pending);
+        tokio::spawn(async move {
+            while let Some(Ok(message)) = reader.next().await {
+                let text = if let Message::Text(text) = message {
+                    text
+                } else {
+                    continue;
+                };
+                let value: Value = match serde_json::from_str(&text) {
+                    Ok(value) => value,
+                    Err(_) => continue,
+                };
+
+                // Reply to a request
+                if let Some(id) = value.get("id").and_then(|id| id.as_u64()) {
+                    if let Some(reply) = responses.lock().unwrap().remove(&id) {
+                        let _ = reply.send(value);
+                        continue;
+                    }
+                }
+
+                // Event notification
+                if let Some(Value::Array(result)) = value.get("result") {
+                    if let (Some(Value::String(event)), Some(payload)) =
+                        (result.get(0), result.get(1))
+                    {
+                        let _ = events_tx.send((event.clone(), payload.clone()));
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            id: 0,
+            tx,
+            pending,
+            events,
+            backlog: VecDeque::new(),
+        })
+    }
+
+    /// Call a method and wait for the result.
+    pub async fn rpc<P: Serialize, R: DeserializeOwned>(
+        &mut self,
+        method: &str,
+        params: P,
+    ) -> Result<R> {
+        self.id += 1;
+        let id = self.id;
+        let (reply, response) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id, reply);
+        self.send(json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        }))?;
+
+        let mut response = response
+            .await
+            .map_err(|_| anyhow!("connection closed waiting for {}", method))?;
+        if let Some(error) = response.get("error") {
+            bail!("{} failed: {}", method, error["message"]);
+        }
+        Ok(serde_json::from_value(response["result"].take())?)
+    }
+
+    /// Call a method without waiting for a reply.
+    pub fn notify<P: Serialize>(&mut self, method: &str, params: P) -> Result<()> {
+        self.send(json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params,
+        }))
+    }
+
+    /// Wait for the next event that matches a predicate.
+    ///
+    /// Events that do not match are kept so they can be
+    /// received later.
+    pub async fn event<F>(&mut self, mut predicate: F) -> Result<Event>
+    where
+        F: FnMut(&Event) -> bool,
+    {
+        if let Some(position) = self.backlog.iter().position(&mut predicate) {
+            return Ok(self.backlog.remove(position).unwrap());
+        }
+        while let Some(event) = self.events.recv().await {
+            if predicate(&event) {
+                return Ok(event);
+            }
+            self.backlog.push_back(event);
+        }
+        bail!("connection closed waiting for event")
+    }
+
+    fn send(&self, value: Value) -> Result<()> {
+        self.tx
+            .send(Message::Text(serde_json::to_string(&value)?))
+            .map_err(|_| anyhow!("connection closed"))
+    }
+}
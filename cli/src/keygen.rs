@@ -0,0 +1,94 @@
+//! Join a key generation session as a native party.
+use anyhow::Result;
+use curv::elliptic::curves::secp256_k1::Secp256k1;
+use mpc_ecdsa_core::{keygen::ROUNDS, KeyGenerator, PartySignup};
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::keygen::LocalKey;
+use serde_json::Value;
+use std::path::Path;
+use uuid::Uuid;
+
+use mpc_websocket::{
+    services::{
+        GROUP_JOIN, SESSION_CREATE_EVENT, SESSION_FINISH, SESSION_JOIN, SESSION_SIGNUP,
+        SESSION_SIGNUP_EVENT,
+    },
+    SessionKind,
+};
+
+use crate::client::Client;
+use crate::session::{GroupInfo, Party, SessionInfo};
+
+/// Generated key share compatible with the webassembly bindings.
+pub type KeyShare = mpc_ecdsa_types::KeyShare<LocalKey<Secp256k1>>;
+
+/// Read a key share from a JSON file.
+///
+/// Fails if the key share version is not supported.
+pub fn read(path: &Path) -> Result<KeyShare> {
+    Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+}
+
+/// Write a key share to a JSON file.
+pub fn write(key_share: &KeyShare, path: &Path) -> Result<()> {
+    Ok(std::fs::write(path, serde_json::to_vec_pretty(key_share)?)?)
+}
+
+/// Join a group and generate a key share.
+///
+/// When `session_id` is not given wait for another member
+/// of the group to create a key generation session.
+pub async fn keygen(server: &str, group_id: Uuid, session_id: Option<Uuid>) -> Result<KeyShare> {
+    let mut client = Client::connect(server).await?;
+    let group: GroupInfo = client.rpc(GROUP_JOIN, group_id).await?;
+
+    let session_id = if let Some(session_id) = session_id {
+        let session: SessionInfo = client
+            .rpc(SESSION_JOIN, (group_id, session_id, SessionKind::Keygen))
+            .await?;
+        session.uuid
+    } else {
+        eprintln!("waiting for a key generation session in {}", group_id);
+        let (_, session) = client
+            .event(|(name, _)| name == SESSION_CREATE_EVENT)
+            .await?;
+        let session: SessionInfo = serde_json::from_value(session)?;
+        session.uuid
+    };
+
+    let number: u16 = client
+        .rpc(SESSION_SIGNUP, (group_id, session_id, SessionKind::Keygen))
+        .await?;
+    eprintln!("signed up to session {} as party {}", session_id, number);
+
+    let session = session_id.to_string();
+    client
+        .event(|(name, value)| name == SESSION_SIGNUP_EVENT && value.as_str() == Some(&session))
+        .await?;
+
+    let mut generator = KeyGenerator::new(
+        group.params.clone(),
+        PartySignup {
+            number,
+            uuid: session,
+        },
+    )?;
+    let mut party = Party::new(client, group, session_id, SessionKind::Keygen);
+    let (mut round, messages) = generator.proceed()?;
+    party.send_round(messages).await?;
+    for _ in 0..ROUNDS {
+        for message in party.receive_round(round).await? {
+            generator.handle_incoming(message)?;
+        }
+        let (next, messages) = generator.proceed()?;
+        party.send_round(messages).await?;
+        round = next;
+    }
+    let key_share = generator.create()?;
+
+    let _: Option<Value> = party
+        .client
+        .rpc(SESSION_FINISH, (group_id, session_id, number))
+        .await?;
+
+    Ok(key_share)
+}
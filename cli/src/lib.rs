@@ -0,0 +1,127 @@
+//! Native parties and utilities for the MPC websocket server.
+#![deny(missing_docs)]
+pub mod bench;
+pub mod client;
+pub mod keygen;
+pub mod session;
+pub mod sign;
+pub mod simulate;
+pub mod verify;
+
+pub use mpc_ecdsa_types::address;
+
+/// Parse a hex-encoded 32 byte message digest.
+pub fn parse_digest(value: &str) -> anyhow::Result<[u8; 32]> {
+    let bytes = hex::decode(value.trim_start_matches("0x"))?;
+    let digest: [u8; 32] = bytes
+        .as_slice()
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("message must be a 32 byte digest"))?;
+    Ok(digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use mpc_websocket::{services::GROUP_CREATE, Parameters, Server, ServerOptions, SessionKind};
+    use serde_json::Value;
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    use crate::{client::Client, session::SessionInfo};
+
+    #[test]
+    fn parse_digest_hex() {
+        let digest = [0xab; 32];
+        let value = hex::encode(digest);
+        assert_eq!(digest, parse_digest(&value).unwrap());
+        assert_eq!(digest, parse_digest(&format!("0x{}", value)).unwrap());
+
+        assert!(parse_digest("").is_err());
+        assert!(parse_digest("zz").is_err());
+        assert!(parse_digest(&hex::encode([0u8; 31])).is_err());
+        assert!(parse_digest(&hex::encode([0u8; 33])).is_err());
+    }
+
+    /// Start a server on a free port and wait until it accepts
+    /// connections; returns the websocket URL.
+    async fn server() -> Result<String> {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+        tokio::spawn(Server::start_with_options(
+            "mpc",
+            addr,
+            ServerOptions::default(),
+        ));
+        let url = format!("ws://{}/mpc", addr);
+        for _ in 0..100 {
+            if Client::connect(&url).await.is_ok() {
+                return Ok(url);
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        anyhow::bail!("server did not start on {}", addr)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn server_keygen_sign() -> Result<()> {
+        let url = server().await?;
+        let params = Parameters {
+            parties: 3,
+            threshold: 1,
+        };
+
+        let mut owner = Client::connect(&url).await?;
+        let group_id: Uuid = owner.rpc(GROUP_CREATE, ("test", &params)).await?;
+        let session: SessionInfo = owner
+            .rpc(
+                mpc_websocket::services::SESSION_CREATE,
+                (group_id, SessionKind::Keygen, Value::Null),
+            )
+            .await?;
+
+        let parties = (0..params.parties)
+            .map(|_| {
+                let url = url.clone();
+                tokio::spawn(
+                    async move { keygen::keygen(&url, group_id, Some(session.uuid)).await },
+                )
+            })
+            .collect::<Vec<_>>();
+        let mut key_shares = Vec::new();
+        for party in parties {
+            key_shares.push(party.await??);
+        }
+        let address = key_shares[0].address.clone();
+        assert!(key_shares.iter().all(|k| k.address == address));
+
+        let session: SessionInfo = owner
+            .rpc(
+                mpc_websocket::services::SESSION_CREATE,
+                (group_id, SessionKind::Sign, Value::Null),
+            )
+            .await?;
+        let message = [1u8; 32];
+        let signers = key_shares
+            .into_iter()
+            .take(params.threshold as usize + 1)
+            .map(|key_share| {
+                let url = url.clone();
+                tokio::spawn(async move {
+                    sign::sign(&url, group_id, Some(session.uuid), key_share, message).await
+                })
+            })
+            .collect::<Vec<_>>();
+        for signer in signers {
+            let signature = signer.await??;
+            let verified = verify::verify(
+                message,
+                serde_json::to_value(&signature)?,
+                None,
+                Some(address.clone()),
+            )?;
+            assert_eq!(address, verified.address);
+        }
+        Ok(())
+    }
+}
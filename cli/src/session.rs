@@ -0,0 +1,160 @@
+//! Round-based protocol sessions relayed through the websocket server.
+use anyhow::{bail, Result};
+use round_based::Msg;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use mpc_websocket::{
+    services::{SESSION_MESSAGE, SESSION_MESSAGE_EVENT},
+    Parameters, SessionKind,
+};
+
+use crate::client::Client;
+
+/// Wrapper for a round `Msg` that includes the round number
+/// and session identifier expected by the server and other clients.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RoundMsg<B> {
+    /// Round number.
+    pub round: u16,
+    /// Party that sent the message.
+    pub sender: u16,
+    /// Receiver for peer to peer messages.
+    pub receiver: Option<u16>,
+    /// Session identifier.
+    pub uuid: Uuid,
+    /// Message body.
+    pub body: B,
+}
+
+/// Group information returned when joining a group.
+#[derive(Debug, Deserialize)]
+pub struct GroupInfo {
+    /// Group identifier.
+    pub uuid: Uuid,
+    /// Key generation parameters.
+    pub params: Parameters,
+}
+
+/// Session information returned when creating or joining a session.
+#[derive(Debug, Deserialize)]
+pub struct SessionInfo {
+    /// Session identifier.
+    pub uuid: Uuid,
+    /// Public value associated with the session.
+    pub value: Option<Value>,
+}
+
+/// Party participating in a session.
+pub struct Party {
+    /// Websocket client.
+    pub client: Client,
+    /// The group.
+    pub group: GroupInfo,
+    /// Session identifier.
+    pub session_id: Uuid,
+    /// Kind of session.
+    pub kind: SessionKind,
+    /// Number of messages each party receives per round.
+    pub expected: usize,
+    rounds: HashMap<u16, Vec<Value>>,
+}
+
+impl Party {
+    /// Create a party for a session.
+    pub fn new(client: Client, group: GroupInfo, session_id: Uuid, kind: SessionKind) -> Self {
+        let expected = match kind {
+            SessionKind::Keygen | SessionKind::Refresh | SessionKind::Reshare => {
+                group.params.parties as usize - 1
+            }
+            SessionKind::Sign => group.params.threshold as usize,
+        };
+        Self {
+            client,
+            group,
+            session_id,
+            kind,
+            expected,
+            rounds: Default::default(),
+        }
+    }
+
+    /// Broadcast or send peer to peer messages for a round.
+    pub async fn send<B: Serialize>(&mut self, messages: Vec<RoundMsg<B>>) -> Result<()> {
+        for message in messages {
+            let _: Option<Value> = self
+                .client
+                .rpc(
+                    SESSION_MESSAGE,
+                    (self.group.uuid, self.session_id, &self.kind, message),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Wait until all the messages for a round have been received.
+    pub async fn receive<B: DeserializeOwned>(&mut self, round: u16) -> Result<Vec<RoundMsg<B>>> {
+        loop {
+            if let Some(messages) = self.rounds.get(&round) {
+                if messages.len() == self.expected {
+                    let messages = self.rounds.remove(&round).unwrap();
+                    return messages
+                        .into_iter()
+                        .map(|m| Ok(serde_json::from_value(m)?))
+                        .collect();
+                }
+            }
+
+            let (_, message) = self
+                .client
+                .event(|(name, _)| name == SESSION_MESSAGE_EVENT)
+                .await?;
+            let message_round = message["round"].as_u64().unwrap_or_default() as u16;
+            if message["uuid"].as_str() != Some(&self.session_id.to_string()) {
+                bail!("message is for the wrong session");
+            }
+            let messages = self.rounds.entry(message_round).or_default();
+            if messages.len() == self.expected {
+                bail!("received too many messages for round {}", message_round);
+            }
+            messages.push(message);
+        }
+    }
+
+    /// Send the messages for a round returned by a protocol.
+    pub async fn send_round<B: Serialize>(
+        &mut self,
+        messages: Vec<mpc_ecdsa_core::RoundMsg<B>>,
+    ) -> Result<()> {
+        let uuid = self.session_id;
+        let messages = messages
+            .into_iter()
+            .map(|m| RoundMsg {
+                round: m.round,
+                sender: m.sender,
+                receiver: m.receiver,
+                uuid,
+                body: m.body,
+            })
+            .collect();
+        self.send(messages).await
+    }
+
+    /// Wait until all the messages for a round have been received
+    /// and convert them to be handled by a protocol.
+    pub async fn receive_round<B: DeserializeOwned>(&mut self, round: u16) -> Result<Vec<Msg<B>>> {
+        Ok(self
+            .receive::<B>(round)
+            .await?
+            .into_iter()
+            .map(|m| Msg {
+                sender: m.sender,
+                receiver: m.receiver,
+                body: m.body,
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,146 @@
+//! Join a signing session as a native party.
+use anyhow::{anyhow, Result};
+use mpc_ecdsa_core::{sign::ROUNDS, Signer};
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::sign::{
+    OfflineProtocolMessage, PartialSignature,
+};
+use serde_json::Value;
+use uuid::Uuid;
+
+use mpc_websocket::{
+    services::{
+        GROUP_JOIN, NOTIFY_PROPOSAL, NOTIFY_SIGNED, SESSION_CREATE, SESSION_JOIN, SESSION_SIGNUP,
+        SESSION_SIGNUP_EVENT,
+    },
+    SessionKind,
+};
+
+use crate::client::Client;
+use crate::keygen::KeyShare;
+use crate::session::{GroupInfo, Party, RoundMsg, SessionInfo};
+
+/// Round used to exchange key share indices.
+const ROUND_PARTICIPANTS: u16 = 0;
+
+/// Round used to exchange partial signatures.
+const ROUND_PARTIAL: u16 = 8;
+
+pub use mpc_ecdsa_core::Signature;
+
+/// Join a group and sign a 32 byte message digest.
+///
+/// When `session_id` is not given a signing session is created
+/// and a proposal is sent to the other members of the group.
+pub async fn sign(
+    server: &str,
+    group_id: Uuid,
+    session_id: Option<Uuid>,
+    key_share: KeyShare,
+    message: [u8; 32],
+) -> Result<Signature> {
+    let mut client = Client::connect(server).await?;
+    let group: GroupInfo = client.rpc(GROUP_JOIN, group_id).await?;
+
+    let session_id = if let Some(session_id) = session_id {
+        session_id
+    } else {
+        let session: SessionInfo = client
+            .rpc(SESSION_CREATE, (group_id, SessionKind::Sign, Value::Null))
+            .await?;
+        let proposal_id = Uuid::new_v4().to_string();
+        let _: Option<Value> = client
+            .rpc(
+                NOTIFY_PROPOSAL,
+                (group_id, session.uuid, proposal_id, hex::encode(message)),
+            )
+            .await?;
+        eprintln!("created signing session {}", session.uuid);
+        session.uuid
+    };
+
+    let _: SessionInfo = client
+        .rpc(SESSION_JOIN, (group_id, session_id, SessionKind::Sign))
+        .await?;
+    let number: u16 = client
+        .rpc(SESSION_SIGNUP, (group_id, session_id, SessionKind::Sign))
+        .await?;
+    eprintln!("signed up to session {} as party {}", session_id, number);
+
+    let session = session_id.to_string();
+    client
+        .event(|(name, value)| name == SESSION_SIGNUP_EVENT && value.as_str() == Some(&session))
+        .await?;
+
+    let mut party = Party::new(client, group, session_id, SessionKind::Sign);
+
+    // Share the index of our key share so the participants
+    // can be ordered by party signup number
+    let index = key_share.local_key.i;
+    party
+        .send(vec![RoundMsg {
+            round: ROUND_PARTICIPANTS,
+            sender: index,
+            receiver: None,
+            uuid: session_id,
+            body: number,
+        }])
+        .await?;
+    let incoming = party.receive::<u16>(ROUND_PARTICIPANTS).await?;
+    let mut participants = incoming
+        .into_iter()
+        .map(|m| (m.sender, m.body))
+        .collect::<Vec<_>>();
+    participants.push((index, number));
+    participants.sort_by_key(|(_, number)| *number);
+    let participants = participants.into_iter().map(|(i, _)| i).collect();
+
+    let mut signer = Signer::new(number, participants, key_share.local_key)?;
+    let (mut round, messages) = proceed(&mut signer)?;
+    party.send_round(messages).await?;
+    for _ in 0..ROUNDS {
+        for message in party.receive_round(round).await? {
+            signer.handle_incoming(message)?;
+        }
+        let (next, messages) = proceed(&mut signer)?;
+        party.send_round(messages).await?;
+        round = next;
+    }
+
+    let partial = signer.partial(message)?;
+    party
+        .send(vec![RoundMsg {
+            round: ROUND_PARTIAL,
+            sender: number,
+            receiver: None,
+            uuid: session_id,
+            body: partial,
+        }])
+        .await?;
+    let partials = party
+        .receive::<PartialSignature>(ROUND_PARTIAL)
+        .await?
+        .into_iter()
+        .map(|m| m.body)
+        .collect::<Vec<_>>();
+
+    let result = signer.create(&partials)?;
+
+    // First party lets non-participants know the result
+    if number == 1 {
+        let _: Option<Value> = party
+            .client
+            .rpc(NOTIFY_SIGNED, (group_id, session_id, &result))
+            .await?;
+    }
+
+    Ok(result)
+}
+
+/// Proceed to the next round of the offline stage.
+fn proceed(
+    signer: &mut Signer,
+) -> Result<(u16, Vec<mpc_ecdsa_core::RoundMsg<OfflineProtocolMessage>>)> {
+    signer
+        .proceed()?
+        .ok_or_else(|| anyhow!("offline stage is waiting for messages"))
+}
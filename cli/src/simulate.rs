@@ -0,0 +1,94 @@
+//! Run key generation and signing for every party in a single process.
+//!
+//! Messages are routed between the parties in memory so no server
+//! is required; useful for generating test fixtures.
+use anyhow::{anyhow, bail, Result};
+use mpc_ecdsa_core::{simulate, Parameters};
+
+use crate::keygen::KeyShare;
+use crate::sign::Signature;
+
+/// Generate a key share for each of `parties` parties.
+///
+/// Fails if the parameters are not valid for key generation.
+pub fn keygen(parties: u16, threshold: u16) -> Result<Vec<KeyShare>> {
+    let parameters = Parameters { parties, threshold };
+    parameters.validate()?;
+    Ok(simulate::keygen(parameters)?)
+}
+
+/// Key shares for the first signing quorum (`t + 1` parties).
+pub fn quorum(key_shares: &[KeyShare], threshold: u16) -> Result<&[KeyShare]> {
+    key_shares.get(..=threshold as usize).ok_or_else(|| {
+        anyhow!(
+            "signing requires {} key shares but only {} were given",
+            threshold as usize + 1,
+            key_shares.len()
+        )
+    })
+}
+
+/// Sign a 32 byte message digest with the given key shares.
+///
+/// The number of key shares must be greater than the threshold
+/// used when the key shares were generated.
+pub fn sign(key_shares: &[KeyShare], message: [u8; 32]) -> Result<Signature> {
+    let first = key_shares
+        .first()
+        .ok_or_else(|| anyhow!("no key shares to sign with"))?;
+    let threshold = first.local_key.t as usize;
+    if key_shares.len() <= threshold {
+        bail!(
+            "signing requires {} key shares but only {} were given",
+            threshold + 1,
+            key_shares.len()
+        );
+    }
+
+    let mut signatures = simulate::sign(key_shares, message)?;
+    Ok(signatures.remove(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_parameters() {
+        assert!(keygen(1, 1).is_err());
+        assert!(keygen(3, 0).is_err());
+        assert!(keygen(3, 3).is_err());
+        assert!(keygen(2, 5).is_err());
+    }
+
+    #[test]
+    fn too_few_key_shares() {
+        assert!(sign(&[], [0u8; 32]).is_err());
+
+        let key_shares = keygen(3, 2).unwrap();
+        assert!(quorum(&key_shares, 3).is_err());
+        assert!(sign(&key_shares[..2], [0u8; 32]).is_err());
+    }
+
+    #[test]
+    fn keygen_sign() {
+        let key_shares = keygen(3, 1).unwrap();
+        assert_eq!(3, key_shares.len());
+        assert!(key_shares
+            .iter()
+            .all(|key_share| key_share.address == key_shares[0].address));
+
+        let message = [7u8; 32];
+        let signers = quorum(&key_shares, 1).unwrap();
+        assert_eq!(2, signers.len());
+        let signature = sign(signers, message).unwrap();
+        let verified = crate::verify::verify(
+            message,
+            serde_json::to_value(&signature).unwrap(),
+            Some(key_shares[0].public_key.clone()),
+            Some(key_shares[0].address.clone()),
+        )
+        .unwrap();
+        assert_eq!(key_shares[0].address, verified.address);
+    }
+}
@@ -0,0 +1,90 @@
+//! Verify ECDSA signatures produced by the signers.
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Signature in the format produced by `SignatureRecid`.
+#[derive(Debug, Deserialize)]
+struct RecoverableSignature {
+    r: Value,
+    s: Value,
+    recid: u8,
+}
+
+/// Public key recovered from a verified signature.
+#[derive(Debug)]
+pub struct Verified {
+    /// Uncompressed public key.
+    pub public_key: Vec<u8>,
+    /// Address for the public key.
+    pub address: String,
+}
+
+/// Verify a signature for a 32 byte message digest.
+///
+/// The signature may be the output of the `sign` command or
+/// a bare signature with `r`, `s` and `recid` fields.
+///
+/// When a public key or address is given the signature must
+/// be valid for that key otherwise the recovered public key
+/// is returned.
+pub fn verify(
+    message: [u8; 32],
+    signature: Value,
+    public_key: Option<Vec<u8>>,
+    address: Option<String>,
+) -> Result<Verified> {
+    let signature = if let Some(inner) = signature.get("signature") {
+        inner.clone()
+    } else {
+        signature
+    };
+    let signature: RecoverableSignature = serde_json::from_value(signature)?;
+
+    let mut rs = [0u8; 64];
+    rs[..32].copy_from_slice(&scalar(&signature.r)?);
+    rs[32..].copy_from_slice(&scalar(&signature.s)?);
+
+    let message = libsecp256k1::Message::parse(&message);
+    let sig = libsecp256k1::Signature::parse_standard(&rs)?;
+    let recid = libsecp256k1::RecoveryId::parse(signature.recid)?;
+    let recovered = libsecp256k1::recover(&message, &sig, &recid)?;
+
+    if let Some(public_key) = public_key {
+        let public_key = libsecp256k1::PublicKey::parse_slice(&public_key, None)?;
+        if !libsecp256k1::verify(&message, &sig, &public_key) {
+            bail!("signature is not valid for the public key");
+        }
+    }
+
+    let public_key = recovered.serialize().to_vec();
+    let recovered_address = crate::address(&public_key)?;
+    if let Some(address) = address {
+        if !address.eq_ignore_ascii_case(&recovered_address) {
+            bail!("signature is not valid for address {}", address);
+        }
+    }
+
+    Ok(Verified {
+        public_key,
+        address: recovered_address,
+    })
+}
+
+/// Convert a serialized scalar to 32 big-endian bytes.
+///
+/// Scalars are objects with a `scalar` field that is either
+/// an array of bytes or a hex string.
+fn scalar(value: &Value) -> Result<[u8; 32]> {
+    let bytes = match &value["scalar"] {
+        Value::String(encoded) => hex::decode(encoded)?,
+        value @ Value::Array(_) => serde_json::from_value(value.clone())?,
+        _ => bail!("invalid scalar in signature"),
+    };
+    if bytes.len() > 32 {
+        bail!("scalar is longer than 32 bytes");
+    }
+    let mut result = [0u8; 32];
+    result[32 - bytes.len()..].copy_from_slice(&bytes);
+    Ok(result)
+}
@@ -0,0 +1,686 @@
+//! Serve static assets for the browser client.
+use headers::{
+    AcceptRanges, ContentRange, ETag, HeaderMapExt, IfMatch, IfModifiedSince,
+    IfNoneMatch, IfRange, IfUnmodifiedSince, LastModified, Range,
+};
+use percent_encoding::percent_decode_str;
+use serde::{Deserialize, Serialize};
+use std::io::SeekFrom;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+use warp::http::{
+    header::{
+        ACCEPT_ENCODING, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_LENGTH,
+        CONTENT_TYPE, VARY,
+    },
+    HeaderMap, HeaderValue, Method, Response, StatusCode,
+};
+use warp::hyper::Body;
+use warp::path::FullPath;
+use warp::{Filter, Rejection};
+
+/// Name of the file served for directories and the SPA fallback.
+const INDEX: &str = "index.html";
+
+/// Cache-Control header value for files whose path matches a pattern.
+///
+/// Patterns are matched against the path of the file that is served
+/// relative to the directory, so a directory or an SPA fallback
+/// matches `/index.html` rather than the request path.
+///
+/// Patterns may contain a single `*` wildcard, for example
+/// `/assets/*`, `*.wasm` or `/index.html`. The first matching
+/// rule wins.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CacheControl {
+    /// Pattern to match against the path of the served file.
+    pub pattern: String,
+    /// Value for the Cache-Control header.
+    pub value: String,
+}
+
+impl CacheControl {
+    /// Determine if this rule matches the path of a file.
+    fn matches(&self, path: &str) -> bool {
+        if let Some((prefix, suffix)) = self.pattern.split_once('*') {
+            path.len() >= prefix.len() + suffix.len()
+                && path.starts_with(prefix)
+                && path.ends_with(suffix)
+        } else {
+            self.pattern == path
+        }
+    }
+}
+
+/// Options for serving static files.
+#[derive(Debug, Clone, Default)]
+pub struct StaticFiles {
+    /// Directory containing the static files.
+    ///
+    /// When `None` static file serving is disabled.
+    pub directory: Option<PathBuf>,
+    /// Serve `index.html` for paths without a file extension that
+    /// do not exist so that deep links into a single page
+    /// application can be routed by the client.
+    pub spa_fallback: bool,
+    /// Serve precompressed `.br` and `.gz` variants of a file
+    /// when the client accepts the encoding.
+    pub precompressed: bool,
+    /// Cache-Control rules for the paths of served files.
+    pub cache_control: Vec<CacheControl>,
+}
+
+impl StaticFiles {
+    /// Serve static files from a directory with the SPA fallback
+    /// and precompressed variants enabled.
+    pub fn new(directory: PathBuf) -> Self {
+        Self {
+            directory: Some(directory),
+            spa_fallback: true,
+            precompressed: true,
+            cache_control: Vec::new(),
+        }
+    }
+}
+
+/// Filter that serves the static files for GET and HEAD requests.
+///
+/// Rejects all requests when static file serving is disabled.
+pub(crate) fn filter(
+    options: StaticFiles,
+) -> impl Filter<Extract = (Response<Body>,), Error = Rejection> + Clone {
+    let options = Arc::new(options);
+    warp::get()
+        .or(warp::head())
+        .unify()
+        .and(warp::method())
+        .and(warp::path::full())
+        .and(warp::header::headers_cloned())
+        .and_then(move |method: Method, path: FullPath, headers: HeaderMap| {
+            let options = Arc::clone(&options);
+            async move {
+                serve(&options, &method, path.as_str(), &headers).await
+            }
+        })
+}
+
+/// Resolve a request path to a file and serve it.
+///
+/// Conditional and range requests are handled against the
+/// representation that is selected for the client so that
+/// precompressed variants have their own entity tag. The file
+/// is streamed and HEAD requests receive the headers only.
+async fn serve(
+    options: &StaticFiles,
+    method: &Method,
+    path: &str,
+    request: &HeaderMap,
+) -> Result<Response<Body>, Rejection> {
+    let root = options.directory.as_ref().ok_or_else(warp::reject)?;
+    let relative = sanitize(path).ok_or_else(warp::reject::not_found)?;
+
+    let mut file = root.join(&relative);
+    if file.is_dir() {
+        file.push(INDEX);
+    }
+
+    if !file.is_file() {
+        let has_extension = relative.extension().is_some();
+        if options.spa_fallback && !has_extension {
+            file = root.join(INDEX);
+        } else {
+            return Err(warp::reject::not_found());
+        }
+    }
+
+    let mime = mime_guess::from_path(&file).first_or_octet_stream();
+    let accept_encoding = request
+        .get(ACCEPT_ENCODING)
+        .and_then(|value| value.to_str().ok());
+    let (variant, content_encoding) =
+        select(&file, options.precompressed, accept_encoding);
+    let metadata = tokio::fs::metadata(&variant)
+        .await
+        .map_err(|_| warp::reject::not_found())?;
+    let modified = metadata.modified().ok();
+    let etag = entity_tag(&metadata, content_encoding);
+
+    let mut response = Response::new(Body::empty());
+    let headers = response.headers_mut();
+    if let Ok(value) = HeaderValue::from_str(mime.as_ref()) {
+        headers.insert(CONTENT_TYPE, value);
+    }
+    if options.precompressed {
+        headers.insert(VARY, HeaderValue::from_static("accept-encoding"));
+    }
+    if let Some(content_encoding) = content_encoding {
+        headers.insert(
+            CONTENT_ENCODING,
+            HeaderValue::from_static(content_encoding),
+        );
+    }
+    let served = served_path(root, &file);
+    if let Some(rule) =
+        options.cache_control.iter().find(|r| r.matches(&served))
+    {
+        if let Ok(value) = HeaderValue::from_str(&rule.value) {
+            headers.insert(CACHE_CONTROL, value);
+        }
+    }
+    headers.typed_insert(AcceptRanges::bytes());
+    if let Some(etag) = &etag {
+        headers.typed_insert(etag.clone());
+    }
+    if let Some(modified) = modified {
+        headers.typed_insert(LastModified::from(modified));
+    }
+
+    match precondition(request, etag.as_ref(), modified) {
+        Precondition::Passed => {}
+        Precondition::NotModified => {
+            *response.status_mut() = StatusCode::NOT_MODIFIED;
+            return Ok(response);
+        }
+        Precondition::Failed => {
+            *response.status_mut() = StatusCode::PRECONDITION_FAILED;
+            return Ok(response);
+        }
+    }
+
+    let length = metadata.len();
+
+    let range = request.typed_get::<Range>().filter(|_| {
+        match request.typed_get::<IfRange>() {
+            Some(if_range) => !if_range.is_modified(
+                etag.as_ref(),
+                modified.map(LastModified::from).as_ref(),
+            ),
+            None => true,
+        }
+    });
+
+    if let Some(range) = range {
+        // Multiple ranges are not supported so only
+        // the first range is served
+        let bounds = range.iter().next();
+        match bounds.and_then(|bounds| satisfiable(bounds, length)) {
+            Some((start, end)) => {
+                let headers = response.headers_mut();
+                if let Ok(content_range) =
+                    ContentRange::bytes(start..=end, length)
+                {
+                    headers.typed_insert(content_range);
+                }
+                *response.status_mut() = StatusCode::PARTIAL_CONTENT;
+                stream(&mut response, method, &variant, start, end - start + 1)
+                    .await?;
+            }
+            None => {
+                response
+                    .headers_mut()
+                    .typed_insert(ContentRange::unsatisfied_bytes(length));
+                *response.status_mut() = StatusCode::RANGE_NOT_SATISFIABLE;
+            }
+        }
+        return Ok(response);
+    }
+
+    stream(&mut response, method, &variant, 0, length).await?;
+    Ok(response)
+}
+
+/// Set the content length and stream part of a file as the body
+/// of a response; the body is empty for HEAD requests.
+async fn stream(
+    response: &mut Response<Body>,
+    method: &Method,
+    file: &Path,
+    start: u64,
+    length: u64,
+) -> Result<(), Rejection> {
+    response
+        .headers_mut()
+        .insert(CONTENT_LENGTH, HeaderValue::from(length));
+    if method == Method::HEAD {
+        return Ok(());
+    }
+    let mut file = tokio::fs::File::open(file)
+        .await
+        .map_err(|_| warp::reject::not_found())?;
+    file.seek(SeekFrom::Start(start))
+        .await
+        .map_err(|_| warp::reject::not_found())?;
+    *response.body_mut() =
+        Body::wrap_stream(ReaderStream::new(file.take(length)));
+    Ok(())
+}
+
+/// Path of a file relative to the root directory
+/// using forward slashes, eg: `/assets/app.js`.
+fn served_path(root: &Path, file: &Path) -> String {
+    let relative = file.strip_prefix(root).unwrap_or(file);
+    let mut path = String::new();
+    for component in relative.components() {
+        path.push('/');
+        path.push_str(&component.as_os_str().to_string_lossy());
+    }
+    path
+}
+
+/// Outcome of evaluating the conditional request headers.
+#[derive(Debug, PartialEq)]
+enum Precondition {
+    Passed,
+    NotModified,
+    Failed,
+}
+
+/// Evaluate the conditional request headers in the order
+/// given by RFC 7232 section 6.
+fn precondition(
+    request: &HeaderMap,
+    etag: Option<&ETag>,
+    modified: Option<SystemTime>,
+) -> Precondition {
+    if let Some(if_match) = request.typed_get::<IfMatch>() {
+        if !etag
+            .map(|etag| if_match.precondition_passes(etag))
+            .unwrap_or(false)
+        {
+            return Precondition::Failed;
+        }
+    } else if let (Some(since), Some(modified)) =
+        (request.typed_get::<IfUnmodifiedSince>(), modified)
+    {
+        if !since.precondition_passes(modified) {
+            return Precondition::Failed;
+        }
+    }
+
+    if let Some(if_none_match) = request.typed_get::<IfNoneMatch>() {
+        if let Some(etag) = etag {
+            if !if_none_match.precondition_passes(etag) {
+                return Precondition::NotModified;
+            }
+        }
+    } else if let (Some(since), Some(modified)) =
+        (request.typed_get::<IfModifiedSince>(), modified)
+    {
+        if !since.is_modified(modified) {
+            return Precondition::NotModified;
+        }
+    }
+    Precondition::Passed
+}
+
+/// Entity tag for a file derived from the length, modification
+/// time and content encoding.
+fn entity_tag(
+    metadata: &std::fs::Metadata,
+    content_encoding: Option<&str>,
+) -> Option<ETag> {
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    let mut tag = format!(
+        "\"{:x}-{:x}-{:x}",
+        metadata.len(),
+        modified.as_secs(),
+        modified.subsec_nanos()
+    );
+    if let Some(content_encoding) = content_encoding {
+        tag.push('-');
+        tag.push_str(content_encoding);
+    }
+    tag.push('"');
+    tag.parse().ok()
+}
+
+/// Resolve a byte range to inclusive offsets within a file.
+///
+/// Returns `None` when the range cannot be satisfied.
+fn satisfiable(
+    bounds: (Bound<u64>, Bound<u64>),
+    length: u64,
+) -> Option<(u64, u64)> {
+    if length == 0 {
+        return None;
+    }
+    match bounds {
+        (Bound::Included(start), end) => {
+            let end = match end {
+                Bound::Included(end) => end.min(length - 1),
+                Bound::Unbounded => length - 1,
+                Bound::Excluded(_) => return None,
+            };
+            if start <= end {
+                Some((start, end))
+            } else {
+                None
+            }
+        }
+        // Suffix range for the last bytes of the file
+        (Bound::Unbounded, Bound::Included(suffix)) if suffix > 0 => {
+            Some((length - suffix.min(length), length - 1))
+        }
+        _ => None,
+    }
+}
+
+/// Select a precompressed variant of a file accepted by the client.
+fn select(
+    file: &Path,
+    precompressed: bool,
+    accept_encoding: Option<&str>,
+) -> (PathBuf, Option<&'static str>) {
+    if let (true, Some(accept_encoding)) = (precompressed, accept_encoding) {
+        for (encoding, extension) in [("br", "br"), ("gzip", "gz")] {
+            if !accepts(accept_encoding, encoding) {
+                continue;
+            }
+            let mut name = file.as_os_str().to_owned();
+            name.push(".");
+            name.push(extension);
+            let variant = PathBuf::from(name);
+            if variant.is_file() {
+                return (variant, Some(encoding));
+            }
+        }
+    }
+    (file.to_path_buf(), None)
+}
+
+/// Determine if an Accept-Encoding header includes an encoding.
+fn accepts(accept_encoding: &str, encoding: &str) -> bool {
+    accept_encoding.split(',').any(|item| {
+        let mut parts = item.split(';');
+        let name = parts.next().unwrap_or_default().trim();
+        let rejected = parts.any(|p| {
+            let p = p.trim();
+            p == "q=0" || p == "q=0.0" || p == "q=0.00" || p == "q=0.000"
+        });
+        name.eq_ignore_ascii_case(encoding) && !rejected
+    })
+}
+
+/// Convert a request path into a relative file system path.
+///
+/// Returns `None` if the path attempts to escape the root directory.
+fn sanitize(path: &str) -> Option<PathBuf> {
+    let mut buf = PathBuf::new();
+    for segment in path.trim_start_matches('/').split('/') {
+        let segment = percent_decode_str(segment).decode_utf8().ok()?;
+        if segment.is_empty() || segment == "." {
+            continue;
+        } else if segment == ".." || segment.contains(['/', '\\']) {
+            return None;
+        }
+        buf.push(segment.as_ref());
+    }
+    Some(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use headers::HeaderMapExt;
+
+    /// Create a directory of static files.
+    fn fixture() -> std::io::Result<PathBuf> {
+        let root = std::env::temp_dir()
+            .join(format!("mpc-websocket-assets-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("assets"))?;
+        std::fs::write(root.join(INDEX), "<html></html>")?;
+        std::fs::write(root.join("assets/app.js"), "0123456789")?;
+        std::fs::write(root.join("assets/app.js.gz"), "gzipped")?;
+        Ok(root)
+    }
+
+    fn options(root: &Path) -> StaticFiles {
+        let mut options = StaticFiles::new(root.to_path_buf());
+        options.cache_control.push(CacheControl {
+            pattern: "/assets/*".to_string(),
+            value: "immutable".to_string(),
+        });
+        options
+    }
+
+    async fn body(response: Response<Body>) -> Vec<u8> {
+        warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap()
+            .to_vec()
+    }
+
+    #[test]
+    fn sanitize_paths() {
+        assert_eq!(Some(PathBuf::new()), sanitize("/"));
+        assert_eq!(Some(PathBuf::from("a/b.js")), sanitize("/a//./b.js"));
+        assert_eq!(Some(PathBuf::from("a b")), sanitize("/a%20b"));
+        assert_eq!(None, sanitize("/../etc/passwd"));
+        assert_eq!(None, sanitize("/a/%2e%2e/%2e%2e/etc"));
+        assert_eq!(None, sanitize("/a%2fb"));
+        assert_eq!(None, sanitize("/a%5cb"));
+        assert_eq!(None, sanitize("/%ff"));
+    }
+
+    #[test]
+    fn accept_encoding() {
+        assert!(accepts("gzip, deflate, br", "br"));
+        assert!(accepts("GZIP;q=0.5", "gzip"));
+        assert!(!accepts("gzip;q=0, br", "gzip"));
+        assert!(!accepts("deflate", "gzip"));
+        assert!(!accepts("", "br"));
+    }
+
+    #[test]
+    fn cache_control_matches() {
+        let rule = |pattern: &str| CacheControl {
+            pattern: pattern.to_string(),
+            value: String::new(),
+        };
+        assert!(rule("/assets/*").matches("/assets/app.js"));
+        assert!(!rule("/assets/*").matches("/index.html"));
+        assert!(rule("*.wasm").matches("/pkg/client.wasm"));
+        assert!(!rule("*.wasm").matches("/pkg/client.js"));
+        assert!(rule("/index.html").matches("/index.html"));
+        assert!(!rule("/index.html").matches("/index.html.gz"));
+        // Prefix and suffix must not overlap
+        assert!(!rule("/a*a").matches("/a"));
+    }
+
+    #[test]
+    fn satisfiable_ranges() {
+        use Bound::*;
+        assert_eq!(Some((0, 4)), satisfiable((Included(0), Included(4)), 10));
+        assert_eq!(Some((5, 9)), satisfiable((Included(5), Unbounded), 10));
+        assert_eq!(Some((5, 9)), satisfiable((Included(5), Included(99)), 10));
+        assert_eq!(Some((7, 9)), satisfiable((Unbounded, Included(3)), 10));
+        assert_eq!(Some((0, 9)), satisfiable((Unbounded, Included(99)), 10));
+        assert_eq!(None, satisfiable((Included(10), Unbounded), 10));
+        assert_eq!(None, satisfiable((Included(4), Included(2)), 10));
+        assert_eq!(None, satisfiable((Unbounded, Included(0)), 10));
+        assert_eq!(None, satisfiable((Included(0), Unbounded), 0));
+    }
+
+    #[tokio::test]
+    async fn serve_files() -> std::io::Result<()> {
+        let root = fixture()?;
+        let options = options(&root);
+        let mut request = HeaderMap::new();
+
+        let response =
+            serve(&options, &Method::GET, "/", &request).await.unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!("text/html", response.headers()[CONTENT_TYPE]);
+        assert!(response.headers().get(CACHE_CONTROL).is_none());
+        assert_eq!(b"<html></html>".to_vec(), body(response).await);
+
+        // Deep links fall back to the index
+        let response = serve(&options, &Method::GET, "/sign/1", &request)
+            .await
+            .unwrap();
+        assert_eq!(b"<html></html>".to_vec(), body(response).await);
+        assert!(serve(&options, &Method::GET, "/missing.js", &request)
+            .await
+            .is_err());
+        assert!(serve(&options, &Method::GET, "/../secret", &request)
+            .await
+            .is_err());
+
+        let response =
+            serve(&options, &Method::GET, "/assets/app.js", &request)
+                .await
+                .unwrap();
+        assert_eq!("immutable", response.headers()[CACHE_CONTROL]);
+        assert_eq!("bytes", response.headers()["accept-ranges"]);
+        assert!(response.headers().get(CONTENT_ENCODING).is_none());
+        let identity = response.headers().typed_get::<ETag>().unwrap();
+        assert_eq!(b"0123456789".to_vec(), body(response).await);
+
+        // Precompressed variant has a different entity tag
+        request.insert(ACCEPT_ENCODING, HeaderValue::from_static("gzip"));
+        let response =
+            serve(&options, &Method::GET, "/assets/app.js", &request)
+                .await
+                .unwrap();
+        assert_eq!("gzip", response.headers()[CONTENT_ENCODING]);
+        let gzip = response.headers().typed_get::<ETag>().unwrap();
+        assert_ne!(identity, gzip);
+        assert_eq!(b"gzipped".to_vec(), body(response).await);
+
+        // Cache rules match the file that is served so the
+        // fallback for a missing asset is not immutable
+        let response =
+            serve(&options, &Method::GET, "/assets/missing", &HeaderMap::new())
+                .await
+                .unwrap();
+        assert!(response.headers().get(CACHE_CONTROL).is_none());
+        assert_eq!(b"<html></html>".to_vec(), body(response).await);
+
+        // HEAD has the headers for the full response without a body
+        let response =
+            serve(&options, &Method::HEAD, "/assets/app.js", &HeaderMap::new())
+                .await
+                .unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!("10", response.headers()[CONTENT_LENGTH]);
+        assert_eq!("immutable", response.headers()[CACHE_CONTROL]);
+        assert!(body(response).await.is_empty());
+
+        let disabled = StaticFiles::default();
+        assert!(serve(&disabled, &Method::GET, "/", &HeaderMap::new())
+            .await
+            .is_err());
+
+        std::fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn serve_conditional_requests() -> std::io::Result<()> {
+        let root = fixture()?;
+        let options = options(&root);
+
+        let response =
+            serve(&options, &Method::GET, "/assets/app.js", &HeaderMap::new())
+                .await
+                .unwrap();
+        let etag = response.headers().typed_get::<ETag>().unwrap();
+        let modified = response.headers().typed_get::<LastModified>().unwrap();
+
+        let mut request = HeaderMap::new();
+        request.typed_insert(IfNoneMatch::from(etag.clone()));
+        let response =
+            serve(&options, &Method::GET, "/assets/app.js", &request)
+                .await
+                .unwrap();
+        assert_eq!(StatusCode::NOT_MODIFIED, response.status());
+        assert_eq!("immutable", response.headers()[CACHE_CONTROL]);
+        assert!(body(response).await.is_empty());
+
+        let mut request = HeaderMap::new();
+        request.typed_insert(IfModifiedSince::from(SystemTime::from(modified)));
+        let response =
+            serve(&options, &Method::GET, "/assets/app.js", &request)
+                .await
+                .unwrap();
+        assert_eq!(StatusCode::NOT_MODIFIED, response.status());
+
+        let mut request = HeaderMap::new();
+        request
+            .typed_insert(IfMatch::from("\"stale\"".parse::<ETag>().unwrap()));
+        let response =
+            serve(&options, &Method::GET, "/assets/app.js", &request)
+                .await
+                .unwrap();
+        assert_eq!(StatusCode::PRECONDITION_FAILED, response.status());
+
+        let mut request = HeaderMap::new();
+        request.typed_insert(IfMatch::from(etag));
+        let response =
+            serve(&options, &Method::GET, "/assets/app.js", &request)
+                .await
+                .unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+
+        std::fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn serve_range_requests() -> std::io::Result<()> {
+        let root = fixture()?;
+        let options = options(&root);
+
+        let mut request = HeaderMap::new();
+        request.typed_insert(Range::bytes(2..5).unwrap());
+        let response =
+            serve(&options, &Method::GET, "/assets/app.js", &request)
+                .await
+                .unwrap();
+        assert_eq!(StatusCode::PARTIAL_CONTENT, response.status());
+        assert_eq!("bytes 2-4/10", response.headers()["content-range"]);
+        assert_eq!("3", response.headers()[CONTENT_LENGTH]);
+        let etag = response.headers().typed_get::<ETag>().unwrap();
+        assert_eq!(b"234".to_vec(), body(response).await);
+
+        let mut request = HeaderMap::new();
+        request.typed_insert(Range::bytes(20..).unwrap());
+        let response =
+            serve(&options, &Method::GET, "/assets/app.js", &request)
+                .await
+                .unwrap();
+        assert_eq!(StatusCode::RANGE_NOT_SATISFIABLE, response.status());
+        assert_eq!("bytes */10", response.headers()["content-range"]);
+
+        // Range is honoured when the entity tag matches
+        let mut request = HeaderMap::new();
+        request.typed_insert(Range::bytes(8..).unwrap());
+        request.typed_insert(IfRange::etag(etag));
+        let response =
+            serve(&options, &Method::GET, "/assets/app.js", &request)
+                .await
+                .unwrap();
+        assert_eq!(StatusCode::PARTIAL_CONTENT, response.status());
+        assert_eq!(b"89".to_vec(), body(response).await);
+
+        // Full representation when the entity tag is stale
+        let mut request = HeaderMap::new();
+        request.typed_insert(Range::bytes(8..).unwrap());
+        request
+            .typed_insert(IfRange::etag("\"stale\"".parse::<ETag>().unwrap()));
+        let response =
+            serve(&options, &Method::GET, "/assets/app.js", &request)
+                .await
+                .unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!(b"0123456789".to_vec(), body(response).await);
+
+        std::fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+}
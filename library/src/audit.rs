@@ -0,0 +1,357 @@
+//! Tamper-evident audit log of group and session lifecycle events.
+//!
+//! The audit log is an append-only file of JSON lines where each
+//! entry records the event, the connection that caused it and a
+//! timestamp. Message bodies, proposal messages and signing results
+//! are *never* written to the audit log.
+//!
+//! Every entry includes the hash of the previous entry and a SHA-256
+//! hash of its own contents so that editing, removing or reordering
+//! entries breaks the chain. To detect truncation of the end of the
+//! log compare the head returned by [verify](verify) with a head
+//! recorded elsewhere; the server emits the head of the chain in its
+//! trace logs each time an entry is appended.
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+use crate::{Result, ServerError, SessionKind};
+
+/// Hash used as the previous hash for the first entry.
+pub const GENESIS_HASH: &str =
+    "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Lifecycle event recorded in the audit log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum AuditEvent {
+    /// Group was created.
+    #[serde(rename_all = "camelCase")]
+    GroupCreate {
+        /// The group identifier.
+        group_id: Uuid,
+        /// Number of parties.
+        parties: u16,
+        /// Signing threshold.
+        threshold: u16,
+    },
+    /// Client joined a group.
+    #[serde(rename_all = "camelCase")]
+    GroupJoin {
+        /// The group identifier.
+        group_id: Uuid,
+    },
+    /// Session was created.
+    #[serde(rename_all = "camelCase")]
+    SessionCreate {
+        /// The group identifier.
+        group_id: Uuid,
+        /// The session identifier.
+        session_id: Uuid,
+        /// Kind of session.
+        session_kind: SessionKind,
+    },
+    /// Client joined a session.
+    #[serde(rename_all = "camelCase")]
+    SessionJoin {
+        /// The group identifier.
+        group_id: Uuid,
+        /// The session identifier.
+        session_id: Uuid,
+    },
+    /// Client signed up to a session.
+    #[serde(rename_all = "camelCase")]
+    SessionSignup {
+        /// The group identifier.
+        group_id: Uuid,
+        /// The session identifier.
+        session_id: Uuid,
+        /// Party number issued to the client.
+        party_number: u16,
+    },
+    /// Client loaded a party number into a session.
+    #[serde(rename_all = "camelCase")]
+    SessionLoad {
+        /// The group identifier.
+        group_id: Uuid,
+        /// The session identifier.
+        session_id: Uuid,
+        /// Party number loaded by the client.
+        party_number: u16,
+    },
+    /// Client marked a session as finished.
+    #[serde(rename_all = "camelCase")]
+    SessionFinish {
+        /// The group identifier.
+        group_id: Uuid,
+        /// The session identifier.
+        session_id: Uuid,
+        /// Party number of the client.
+        party_number: u16,
+    },
+    /// Client sent a signing proposal.
+    #[serde(rename_all = "camelCase")]
+    NotifyProposal {
+        /// The group identifier.
+        group_id: Uuid,
+        /// The session identifier.
+        session_id: Uuid,
+        /// Identifier for the proposal.
+        proposal_id: String,
+    },
+    /// Client sent a signing result.
+    #[serde(rename_all = "camelCase")]
+    NotifySigned {
+        /// The group identifier.
+        group_id: Uuid,
+        /// The session identifier.
+        session_id: Uuid,
+    },
+}
+
+/// Entry in the audit log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Position of the entry in the log starting at zero.
+    pub sequence: u64,
+    /// Milliseconds since the UNIX epoch.
+    pub timestamp: u64,
+    /// Connection that caused the event.
+    #[serde(rename = "connId")]
+    pub conn_id: usize,
+    /// The lifecycle event.
+    pub event: AuditEvent,
+    /// Hash of the previous entry.
+    pub previous: String,
+    /// Hash of this entry.
+    pub hash: String,
+}
+
+impl AuditEntry {
+    /// Compute the hash for this entry.
+    ///
+    /// The hash covers every field except the hash itself.
+    pub fn digest(&self) -> Result<String> {
+        let contents = serde_json::to_vec(&(
+            self.sequence,
+            self.timestamp,
+            self.conn_id,
+            &self.event,
+        ))?;
+        let mut hasher = Sha256::new();
+        hasher.update(self.previous.as_bytes());
+        hasher.update(&contents);
+        Ok(hex::encode(hasher.finalize()))
+    }
+}
+
+/// Last entry in a verified audit log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditHead {
+    /// Number of entries in the log.
+    pub entries: u64,
+    /// Hash of the last entry.
+    pub hash: String,
+}
+
+impl Default for AuditHead {
+    fn default() -> Self {
+        Self {
+            entries: 0,
+            hash: GENESIS_HASH.to_string(),
+        }
+    }
+}
+
+/// Append-only audit log file.
+#[derive(Debug)]
+pub struct AuditLog {
+    inner: Mutex<(File, AuditHead)>,
+}
+
+impl AuditLog {
+    /// Open an audit log file for appending.
+    ///
+    /// If the file already exists it is verified and new entries
+    /// are chained to the last entry.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let head = if path.exists() {
+            verify(BufReader::new(File::open(path)?), None)?
+        } else {
+            Default::default()
+        };
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            inner: Mutex::new((file, head)),
+        })
+    }
+
+    /// Get the head of the audit log.
+    pub fn head(&self) -> AuditHead {
+        let inner = self.inner.lock().unwrap();
+        inner.1.clone()
+    }
+
+    /// Append an event to the audit log.
+    pub fn append(&self, conn_id: usize, event: AuditEvent) -> Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        let (file, head) = &mut *inner;
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+
+        let mut entry = AuditEntry {
+            sequence: head.entries,
+            timestamp,
+            conn_id,
+            event,
+            previous: head.hash.clone(),
+            hash: String::new(),
+        };
+        entry.hash = entry.digest()?;
+
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        file.write_all(&line)?;
+        file.flush()?;
+
+        head.entries += 1;
+        head.hash = entry.hash;
+
+        tracing::info!(
+            sequence = entry.sequence,
+            hash = %head.hash,
+            "audit");
+        Ok(())
+    }
+}
+
+/// Verify the hash chain of an audit log.
+///
+/// When `expected_head` is given the log must end with an entry
+/// that has the expected hash which detects truncation.
+///
+/// Returns the head of the audit log.
+pub fn verify<R: BufRead>(
+    reader: R,
+    expected_head: Option<&str>,
+) -> Result<AuditHead> {
+    let mut head: AuditHead = Default::default();
+    for line in reader.lines() {
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        let entry: AuditEntry = serde_json::from_str(&line)?;
+        if entry.sequence != head.entries {
+            return Err(ServerError::AuditSequence(head.entries));
+        }
+        if entry.previous != head.hash {
+            return Err(ServerError::AuditChain(entry.sequence));
+        }
+        if entry.digest()? != entry.hash {
+            return Err(ServerError::AuditHash(entry.sequence));
+        }
+        head.entries += 1;
+        head.hash = entry.hash;
+    }
+
+    if let Some(expected) = expected_head {
+        if expected != head.hash {
+            return Err(ServerError::AuditHead(expected.to_string()));
+        }
+    }
+    Ok(head)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_file() -> Result<(std::path::PathBuf, AuditHead)> {
+        let path = std::env::temp_dir()
+            .join(format!("mpc-websocket-audit-{}.jsonl", Uuid::new_v4()));
+        let log = AuditLog::open(&path)?;
+        let group_id = Uuid::new_v4();
+        let session_id = Uuid::new_v4();
+        log.append(
+            1,
+            AuditEvent::GroupCreate {
+                group_id,
+                parties: 3,
+                threshold: 1,
+            },
+        )?;
+        log.append(2, AuditEvent::GroupJoin { group_id })?;
+        log.append(
+            1,
+            AuditEvent::SessionSignup {
+                group_id,
+                session_id,
+                party_number: 1,
+            },
+        )?;
+        Ok((path, log.head()))
+    }
+
+    #[test]
+    fn audit_log_verify() -> Result<()> {
+        let (path, head) = log_file()?;
+        let contents = std::fs::read_to_string(&path)?;
+        assert_eq!(3, head.entries);
+        assert_eq!(head, verify(contents.as_bytes(), Some(&head.hash))?);
+
+        // Reopening continues the chain
+        let log = AuditLog::open(&path)?;
+        log.append(
+            3,
+            AuditEvent::GroupJoin {
+                group_id: Uuid::nil(),
+            },
+        )?;
+        let contents = std::fs::read_to_string(&path)?;
+        assert_eq!(4, verify(contents.as_bytes(), None)?.entries);
+
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn audit_log_detect_tampering() -> Result<()> {
+        let (path, head) = log_file()?;
+        let contents = std::fs::read_to_string(&path)?;
+        std::fs::remove_file(&path)?;
+        let lines = contents.lines().collect::<Vec<_>>();
+
+        // Edited entry
+        let edited = contents.replacen("\"connId\":2", "\"connId\":7", 1);
+        assert!(matches!(
+            verify(edited.as_bytes(), None),
+            Err(ServerError::AuditHash(1))
+        ));
+
+        // Removed entry
+        let removed = format!("{}\n{}\n", lines[0], lines[2]);
+        assert!(matches!(
+            verify(removed.as_bytes(), None),
+            Err(ServerError::AuditSequence(1))
+        ));
+
+        // Truncated log
+        let truncated = format!("{}\n{}\n", lines[0], lines[1]);
+        assert!(verify(truncated.as_bytes(), None).is_ok());
+        assert!(matches!(
+            verify(truncated.as_bytes(), Some(&head.hash)),
+            Err(ServerError::AuditHead(_))
+        ));
+        Ok(())
+    }
+}
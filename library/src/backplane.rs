@@ -0,0 +1,282 @@
+//! Pub/sub backplane for running multiple server instances.
+//!
+//! Connected clients are only known to the server instance that
+//! accepted the websocket so a group whose members are spread across
+//! several replicas needs a way to route requests and notifications
+//! between instances.
+//!
+//! Every group is *owned* by the instance that created it; the owner
+//! holds the group and session state and processes all requests for
+//! the group. Instances that receive a request for a group owned by
+//! another instance forward it over the backplane and the owner
+//! publishes the resulting deliveries back to whichever instance
+//! holds the target connection.
+//!
+//! Group state is held in memory by the owner and is not replicated
+//! so there is no failover: when the owning instance stops, its
+//! groups and sessions are lost and clients must create a new group
+//! and start their sessions again. Requests forwarded to a stopped
+//! owner are not answered so implementations backed by a message
+//! broker should expire the ownership records of an instance that
+//! stops, for example with a lease the owner renews; once the record
+//! expires the group is reported as missing to clients.
+//!
+//! Connection identifiers are allocated by the backplane so that
+//! they are unique across all instances.
+//!
+//! The [MemoryBackplane](MemoryBackplane) routes envelopes between
+//! servers running in the same process and is intended for testing;
+//! production deployments should implement [Backplane](Backplane)
+//! on top of a shared message broker.
+use async_trait::async_trait;
+use json_rpc2::Request;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::{Result, ServerError};
+
+/// Message exchanged between server instances.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Envelope {
+    /// Instance that published the envelope.
+    pub source: Uuid,
+    /// Instance that should handle the envelope.
+    ///
+    /// When `None` the envelope is sent to all other instances.
+    pub target: Option<Uuid>,
+    /// Payload for the envelope.
+    pub payload: Payload,
+}
+
+/// Payload carried by an envelope.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Payload {
+    /// Request received from a client connected to the source
+    /// instance that must be processed by the instance owning the group.
+    Request {
+        /// Connection that sent the request.
+        conn_id: usize,
+        /// The JSON-RPC request.
+        request: Request,
+    },
+    /// Message that should be written to a client connection.
+    Deliver {
+        /// Connection that should receive the message.
+        conn_id: usize,
+        /// Serialized JSON-RPC response or notification.
+        message: String,
+        /// Whether the connection should be closed after
+        /// the message has been sent.
+        close: bool,
+    },
+    /// Client connection was closed.
+    Disconnected {
+        /// The connection identifier.
+        conn_id: usize,
+    },
+}
+
+/// Routes deliveries and tracks group ownership between server instances.
+#[async_trait]
+pub trait Backplane: std::fmt::Debug + Send + Sync {
+    /// Unique identifier for this server instance.
+    fn instance(&self) -> Uuid;
+
+    /// Allocate a connection identifier that is unique across
+    /// all server instances.
+    ///
+    /// Implementations backed by a message broker typically use
+    /// an atomic counter in the broker.
+    async fn next_connection_id(&self) -> Result<usize>;
+
+    /// Publish an envelope to other server instances.
+    async fn publish(&self, envelope: Envelope) -> Result<()>;
+
+    /// Receive envelopes published by other server instances.
+    ///
+    /// The server calls this once when it starts.
+    fn subscribe(&self) -> Result<mpsc::UnboundedReceiver<Envelope>>;
+
+    /// Record this instance as the owner of a group.
+    ///
+    /// Returns an error if another instance already owns the group.
+    async fn claim(&self, group_id: Uuid) -> Result<()>;
+
+    /// Get the instance that owns a group.
+    async fn owner(&self, group_id: Uuid) -> Result<Option<Uuid>>;
+
+    /// Release ownership of a group.
+    async fn release(&self, group_id: Uuid) -> Result<()>;
+}
+
+/// Shared state for in-process backplanes.
+#[derive(Debug, Default)]
+struct Hub {
+    subscribers: Vec<(Uuid, mpsc::UnboundedSender<Envelope>)>,
+    owners: HashMap<Uuid, Uuid>,
+}
+
+/// Backplane that connects server instances running in the same process.
+///
+/// Create the first backplane with `new()` and then call `connect()` to
+/// create a backplane for each additional server instance.
+#[derive(Debug, Clone)]
+pub struct MemoryBackplane {
+    instance: Uuid,
+    hub: Arc<Mutex<Hub>>,
+    connections: Arc<AtomicUsize>,
+}
+
+impl MemoryBackplane {
+    /// Create a new in-process backplane.
+    pub fn new() -> Self {
+        Self {
+            instance: Uuid::new_v4(),
+            hub: Arc::new(Mutex::new(Default::default())),
+            connections: Arc::new(AtomicUsize::new(1)),
+        }
+    }
+
+    /// Create a backplane for another instance sharing this hub.
+    pub fn connect(&self) -> Self {
+        Self {
+            instance: Uuid::new_v4(),
+            hub: Arc::clone(&self.hub),
+            connections: Arc::clone(&self.connections),
+        }
+    }
+}
+
+impl Default for MemoryBackplane {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Backplane for MemoryBackplane {
+    fn instance(&self) -> Uuid {
+        self.instance
+    }
+
+    async fn next_connection_id(&self) -> Result<usize> {
+        Ok(self.connections.fetch_add(1, Ordering::Relaxed))
+    }
+
+    async fn publish(&self, envelope: Envelope) -> Result<()> {
+        let mut hub = self.hub.lock().unwrap();
+        hub.subscribers.retain(|(instance, tx)| {
+            if *instance == envelope.source {
+                return true;
+            }
+            if let Some(target) = &envelope.target {
+                if target != instance {
+                    return true;
+                }
+            }
+            tx.send(envelope.clone()).is_ok()
+        });
+        Ok(())
+    }
+
+    fn subscribe(&self) -> Result<mpsc::UnboundedReceiver<Envelope>> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut hub = self.hub.lock().unwrap();
+        hub.subscribers.push((self.instance, tx));
+        Ok(rx)
+    }
+
+    async fn claim(&self, group_id: Uuid) -> Result<()> {
+        let mut hub = self.hub.lock().unwrap();
+        match hub.owners.get(&group_id) {
+            Some(owner) if owner != &self.instance => {
+                Err(ServerError::GroupOwnedByInstance(group_id, *owner))
+            }
+            _ => {
+                hub.owners.insert(group_id, self.instance);
+                Ok(())
+            }
+        }
+    }
+
+    async fn owner(&self, group_id: Uuid) -> Result<Option<Uuid>> {
+        let hub = self.hub.lock().unwrap();
+        Ok(hub.owners.get(&group_id).copied())
+    }
+
+    async fn release(&self, group_id: Uuid) -> Result<()> {
+        let mut hub = self.hub.lock().unwrap();
+        if hub.owners.get(&group_id) == Some(&self.instance) {
+            hub.owners.remove(&group_id);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn memory_backplane_routes_envelopes() -> Result<()> {
+        let a = MemoryBackplane::new();
+        let b = a.connect();
+        let c = a.connect();
+
+        let mut rx_a = a.subscribe()?;
+        let mut rx_b = b.subscribe()?;
+        let mut rx_c = c.subscribe()?;
+
+        // Broadcast reaches every other instance
+        a.publish(Envelope {
+            source: a.instance(),
+            target: None,
+            payload: Payload::Disconnected { conn_id: 1 },
+        })
+        .await?;
+        assert!(rx_b.try_recv().is_ok());
+        assert!(rx_c.try_recv().is_ok());
+        assert!(rx_a.try_recv().is_err());
+
+        // Targeted envelope only reaches the target
+        a.publish(Envelope {
+            source: a.instance(),
+            target: Some(c.instance()),
+            payload: Payload::Disconnected { conn_id: 1 },
+        })
+        .await?;
+        assert!(rx_b.try_recv().is_err());
+        assert!(rx_c.try_recv().is_ok());
+
+        assert_ne!(
+            a.next_connection_id().await?,
+            b.next_connection_id().await?
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn memory_backplane_group_ownership() -> Result<()> {
+        let a = MemoryBackplane::new();
+        let b = a.connect();
+        let group_id = Uuid::new_v4();
+
+        a.claim(group_id).await?;
+        assert_eq!(Some(a.instance()), b.owner(group_id).await?);
+        assert!(b.claim(group_id).await.is_err());
+
+        // Only the owner may release the group
+        b.release(group_id).await?;
+        assert_eq!(Some(a.instance()), a.owner(group_id).await?);
+        a.release(group_id).await?;
+        assert_eq!(None, b.owner(group_id).await?);
+        b.claim(group_id).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,613 @@
+//! Configuration file for the server.
+//!
+//! Configuration is loaded from a TOML file and then environment
+//! variables prefixed with `MPC_WEBSOCKET_` are applied so that
+//! containerized deployments can override individual settings:
+//!
+//! ```toml
+//! bind = "0.0.0.0:3030"
+//! path = "mpc"
+//! audit-log = "audit.jsonl"
+//!
+//! [unix-socket]
+//! path = "/run/mpc-websocket.sock"
+//! mode = 0o660
+//!
+//! [static]
+//! directory = "demo/dist"
+//! spa-fallback = true
+//!
+//! [[static.cache-control]]
+//! pattern = "/assets/*"
+//! value = "public, max-age=31536000, immutable"
+//!
+//! [headers]
+//! Cross-Origin-Embedder-Policy = "require-corp"
+//! Cross-Origin-Opener-Policy = "same-origin"
+//!
+//! [tls]
+//! cert = "cert.pem"
+//! key = "key.pem"
+//!
+//! [limits]
+//! max-message-size = 1048576
+//! max-connections = 1024
+//! max-groups = 256
+//!
+//! [ttl]
+//! group = 86400
+//! session = 3600
+//!
+//! [log]
+//! format = "json"
+//! filter = "mpc_websocket=info"
+//! ```
+//!
+//! The environment variable for a setting is the upper case path
+//! of the setting with dashes and dots replaced by underscores, for
+//! example `MPC_WEBSOCKET_LIMITS_MAX_GROUPS`. Unknown variables
+//! with the prefix are an error so that typos fail fast.
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing_subscriber::EnvFilter;
+use warp::http::header::{HeaderName, HeaderValue};
+
+use crate::{
+    audit::AuditLog, CacheControl, Listen, Result, ServerError, ServerOptions,
+    StaticFiles, UnixSocket,
+};
+
+/// Prefix for environment variable overrides.
+pub const ENV_PREFIX: &str = "MPC_WEBSOCKET_";
+
+/// Server configuration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ServerConfig {
+    /// Address to bind to.
+    pub bind: SocketAddr,
+    /// Listen on the TCP bind address.
+    ///
+    /// Disable to only listen on a Unix domain socket.
+    pub tcp: bool,
+    /// Listen on a Unix domain socket.
+    pub unix_socket: Option<UnixSocket>,
+    /// Path for the websocket endpoint.
+    pub path: String,
+    /// Append lifecycle events to an audit log file.
+    pub audit_log: Option<PathBuf>,
+    /// Static file serving.
+    #[serde(rename = "static")]
+    pub static_files: StaticConfig,
+    /// Headers added to every HTTP response.
+    ///
+    /// Defaults to the cross-origin isolation headers required
+    /// by the client; setting this table replaces the defaults.
+    pub headers: BTreeMap<String, String>,
+    /// Serve over TLS.
+    pub tls: TlsConfig,
+    /// Resource limits.
+    pub limits: Limits,
+    /// Expiry for groups and sessions.
+    pub ttl: Ttl,
+    /// Logging options.
+    pub log: LogConfig,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind: SocketAddr::from(([127, 0, 0, 1], 3030)),
+            tcp: true,
+            unix_socket: None,
+            path: "mpc".to_string(),
+            audit_log: None,
+            static_files: Default::default(),
+            headers: ServerOptions::default().headers.into_iter().collect(),
+            tls: Default::default(),
+            limits: Default::default(),
+            ttl: Default::default(),
+            log: Default::default(),
+        }
+    }
+}
+
+/// Static file configuration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct StaticConfig {
+    /// Whether to serve static files.
+    pub enabled: bool,
+    /// Directory containing the static files.
+    pub directory: Option<PathBuf>,
+    /// Serve `index.html` for unknown paths without an extension.
+    pub spa_fallback: bool,
+    /// Serve precompressed variants of files.
+    pub precompressed: bool,
+    /// Cache-Control rules for the paths of served files.
+    pub cache_control: Vec<CacheControl>,
+}
+
+impl Default for StaticConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            directory: None,
+            spa_fallback: true,
+            precompressed: true,
+            cache_control: Vec::new(),
+        }
+    }
+}
+
+/// TLS configuration.
+///
+/// Both the certificate and private key must be given to enable TLS.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct TlsConfig {
+    /// PEM encoded certificate chain.
+    pub cert: Option<PathBuf>,
+    /// PEM encoded private key.
+    pub key: Option<PathBuf>,
+}
+
+impl TlsConfig {
+    /// Determine if TLS is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.cert.is_some() || self.key.is_some()
+    }
+}
+
+/// Resource limits for the server.
+///
+/// A limit that is not set is unbounded.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Limits {
+    /// Maximum size in bytes of a websocket message.
+    pub max_message_size: Option<usize>,
+    /// Maximum number of connected clients.
+    pub max_connections: Option<usize>,
+    /// Maximum number of groups.
+    pub max_groups: Option<usize>,
+}
+
+/// Expiry in seconds for groups and sessions.
+///
+/// Groups and sessions that are not set to expire live until
+/// all clients in the group have disconnected.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Ttl {
+    /// Seconds after creation that a group is removed.
+    pub group: Option<u64>,
+    /// Seconds after creation that a session is removed.
+    pub session: Option<u64>,
+}
+
+/// Format for log output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable text.
+    Text,
+    /// JSON lines.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = ();
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Logging configuration.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct LogConfig {
+    /// Log format.
+    ///
+    /// When not set debug builds log text and release builds
+    /// log JSON.
+    pub format: Option<LogFormat>,
+    /// Trace filter directives.
+    ///
+    /// When not set the `RUST_LOG` environment variable is used
+    /// and if that is not set a default filter.
+    pub filter: Option<String>,
+}
+
+impl ServerConfig {
+    /// Load a config file and apply environment variable overrides.
+    ///
+    /// The result is not validated so that callers can apply other
+    /// overrides, such as command line options, before calling
+    /// `validate()`.
+    ///
+    /// When no file is given the defaults are used.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let mut config = if let Some(path) = path {
+            let contents = std::fs::read_to_string(path)?;
+            Self::from_toml(&contents)?
+        } else {
+            Default::default()
+        };
+        config.apply_env(std::env::vars())?;
+        Ok(config)
+    }
+
+    /// Parse a config from TOML.
+    pub fn from_toml(contents: &str) -> Result<Self> {
+        Ok(toml::from_str(contents)?)
+    }
+
+    /// Apply `MPC_WEBSOCKET_*` overrides from environment variables.
+    pub fn apply_env<I>(&mut self, vars: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        for (name, value) in vars {
+            let key = if let Some(key) = name.strip_prefix(ENV_PREFIX) {
+                key
+            } else {
+                continue;
+            };
+
+            match key {
+                "BIND" => self.bind = parse(&name, &value)?,
+                "TCP" => self.tcp = parse(&name, &value)?,
+                "UNIX_SOCKET_PATH" => {
+                    self.unix_socket_mut().path = PathBuf::from(value)
+                }
+                "UNIX_SOCKET_MODE" => {
+                    let mode =
+                        u32::from_str_radix(&value, 8).map_err(|_| {
+                            ServerError::ConfigEnv(name.clone(), value.clone())
+                        })?;
+                    self.unix_socket_mut().mode = Some(mode);
+                }
+                "PATH" => self.path = value,
+                "AUDIT_LOG" => self.audit_log = Some(PathBuf::from(value)),
+                "STATIC_ENABLED" => {
+                    self.static_files.enabled = parse(&name, &value)?
+                }
+                "STATIC_DIRECTORY" => {
+                    self.static_files.directory = Some(PathBuf::from(value))
+                }
+                "STATIC_SPA_FALLBACK" => {
+                    self.static_files.spa_fallback = parse(&name, &value)?
+                }
+                "STATIC_PRECOMPRESSED" => {
+                    self.static_files.precompressed = parse(&name, &value)?
+                }
+                "TLS_CERT" => self.tls.cert = Some(PathBuf::from(value)),
+                "TLS_KEY" => self.tls.key = Some(PathBuf::from(value)),
+                "LIMITS_MAX_MESSAGE_SIZE" => {
+                    self.limits.max_message_size = Some(parse(&name, &value)?)
+                }
+                "LIMITS_MAX_CONNECTIONS" => {
+                    self.limits.max_connections = Some(parse(&name, &value)?)
+                }
+                "LIMITS_MAX_GROUPS" => {
+                    self.limits.max_groups = Some(parse(&name, &value)?)
+                }
+                "TTL_GROUP" => self.ttl.group = Some(parse(&name, &value)?),
+                "TTL_SESSION" => self.ttl.session = Some(parse(&name, &value)?),
+                "LOG_FORMAT" => self.log.format = Some(parse(&name, &value)?),
+                "LOG_FILTER" => self.log.filter = Some(value),
+                _ => return Err(ServerError::ConfigEnvUnknown(name)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate the configuration.
+    pub fn validate(&self) -> Result<()> {
+        if let Some(socket) = &self.unix_socket {
+            if socket.path.as_os_str().is_empty() {
+                return Err(ServerError::ConfigZero("unix-socket.path"));
+            }
+            if let Some(mode) = socket.mode {
+                if mode > 0o777 {
+                    return Err(ServerError::ConfigUnixSocketMode(mode));
+                }
+            }
+        }
+        if self.listen().is_empty() {
+            return Err(ServerError::NoListeners);
+        }
+
+        if self.path.is_empty() || self.path.contains('/') {
+            return Err(ServerError::ConfigPath(self.path.clone()));
+        }
+
+        if self.static_files.enabled {
+            if let Some(directory) = &self.static_files.directory {
+                if !directory.is_dir() {
+                    return Err(ServerError::NotDirectory(directory.clone()));
+                }
+            }
+        }
+
+        for rule in &self.static_files.cache_control {
+            if rule.pattern.matches('*').count() > 1 {
+                return Err(ServerError::ConfigCacheControl(
+                    rule.pattern.clone(),
+                ));
+            }
+            HeaderValue::from_str(&rule.value)?;
+        }
+
+        for (name, value) in &self.headers {
+            HeaderName::from_bytes(name.as_bytes())?;
+            HeaderValue::from_str(value)?;
+        }
+
+        if self.tls.is_enabled() {
+            for file in [&self.tls.cert, &self.tls.key] {
+                match file {
+                    Some(file) if !file.is_file() => {
+                        return Err(ServerError::ConfigFile(file.clone()))
+                    }
+                    None => return Err(ServerError::ConfigTls),
+                    _ => {}
+                }
+            }
+        }
+
+        for (name, value) in [
+            ("limits.max-message-size", self.limits.max_message_size),
+            ("limits.max-connections", self.limits.max_connections),
+            ("limits.max-groups", self.limits.max_groups),
+            ("ttl.group", self.ttl.group.map(|v| v as usize)),
+            ("ttl.session", self.ttl.session.map(|v| v as usize)),
+        ] {
+            if value == Some(0) {
+                return Err(ServerError::ConfigZero(name));
+            }
+        }
+
+        if let Some(filter) = &self.log.filter {
+            EnvFilter::try_new(filter)
+                .map_err(|_| ServerError::ConfigLogFilter(filter.clone()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Addresses and sockets to listen on.
+    pub fn listen(&self) -> Vec<Listen> {
+        let mut listen = Vec::new();
+        if self.tcp {
+            listen.push(Listen::Tcp(self.bind));
+        }
+        if let Some(socket) = &self.unix_socket {
+            listen.push(Listen::Unix(socket.clone()));
+        }
+        listen
+    }
+
+    /// Get the Unix socket config creating it when necessary.
+    fn unix_socket_mut(&mut self) -> &mut UnixSocket {
+        self.unix_socket.get_or_insert_with(|| UnixSocket {
+            path: PathBuf::new(),
+            mode: None,
+        })
+    }
+
+    /// Create server options from this configuration.
+    ///
+    /// Opens the audit log when one is configured.
+    pub fn options(&self) -> Result<ServerOptions> {
+        let audit = if let Some(audit_log) = &self.audit_log {
+            Some(Arc::new(AuditLog::open(audit_log)?))
+        } else {
+            None
+        };
+
+        let static_files =
+            match (self.static_files.enabled, &self.static_files.directory) {
+                (true, Some(directory)) => StaticFiles {
+                    directory: Some(directory.clone()),
+                    spa_fallback: self.static_files.spa_fallback,
+                    precompressed: self.static_files.precompressed,
+                    cache_control: self.static_files.cache_control.clone(),
+                },
+                _ => Default::default(),
+            };
+
+        Ok(ServerOptions {
+            static_files,
+            audit,
+            tls: self.tls.clone(),
+            headers: self
+                .headers
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+            limits: self.limits.clone(),
+            ttl: self.ttl.clone(),
+            log: self.log.clone(),
+            ..Default::default()
+        })
+    }
+}
+
+/// Parse the value of an environment variable.
+fn parse<T: FromStr>(name: &str, value: &str) -> Result<T> {
+    value.parse().map_err(|_| {
+        ServerError::ConfigEnv(name.to_string(), value.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn config_file_and_env_overrides() -> Result<()> {
+        let mut config = ServerConfig::from_toml(
+            r#"
+bind = "0.0.0.0:8080"
+
+[unix-socket]
+path = "/run/mpc-websocket.sock"
+mode = 0o600
+
+[limits]
+max-groups = 16
+
+[ttl]
+session = 600
+
+[log]
+format = "json"
+"#,
+        )?;
+        assert_eq!("mpc", config.path);
+        assert!(config.static_files.spa_fallback);
+
+        config.apply_env(env(&[
+            ("MPC_WEBSOCKET_LIMITS_MAX_GROUPS", "32"),
+            ("MPC_WEBSOCKET_LOG_FORMAT", "text"),
+            ("MPC_WEBSOCKET_UNIX_SOCKET_MODE", "660"),
+            ("HOME", "/root"),
+        ]))?;
+        config.validate()?;
+        assert_eq!(8080, config.bind.port());
+        assert_eq!(Some(32), config.limits.max_groups);
+        assert_eq!(Some(600), config.ttl.session);
+        assert_eq!(Some(LogFormat::Text), config.log.format);
+        assert_eq!(
+            Some(0o660),
+            config.unix_socket.as_ref().and_then(|s| s.mode)
+        );
+        assert_eq!(2, config.listen().len());
+        Ok(())
+    }
+
+    #[test]
+    fn config_cache_control_and_headers() -> Result<()> {
+        let config = ServerConfig::default();
+        let options = config.options()?;
+        assert_eq!(
+            ServerOptions::default().headers.len(),
+            options.headers.len()
+        );
+
+        let config = ServerConfig::from_toml(
+            r#"
+[static]
+directory = "src"
+
+[[static.cache-control]]
+pattern = "/assets/*"
+value = "public, max-age=31536000, immutable"
+
+[headers]
+X-Frame-Options = "DENY"
+"#,
+        )?;
+        config.validate()?;
+        let options = config.options()?;
+        assert_eq!(
+            vec![CacheControl {
+                pattern: "/assets/*".to_string(),
+                value: "public, max-age=31536000, immutable".to_string(),
+            }],
+            options.static_files.cache_control
+        );
+        assert_eq!(
+            vec![("X-Frame-Options".to_string(), "DENY".to_string())],
+            options.headers
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn config_invalid() {
+        assert!(matches!(
+            ServerConfig::from_toml("bind = \"localhost\""),
+            Err(ServerError::ConfigToml(_))
+        ));
+        assert!(matches!(
+            ServerConfig::from_toml("[limits]\nmax-sessions = 1"),
+            Err(ServerError::ConfigToml(_))
+        ));
+
+        let mut config = ServerConfig::default();
+        assert!(matches!(
+            config.apply_env(env(&[("MPC_WEBSOCKET_BIN", "0.0.0.0:80")])),
+            Err(ServerError::ConfigEnvUnknown(_))
+        ));
+        assert!(matches!(
+            config.apply_env(env(&[("MPC_WEBSOCKET_TTL_GROUP", "1h")])),
+            Err(ServerError::ConfigEnv(_, _))
+        ));
+
+        config.limits.max_connections = Some(0);
+        assert!(matches!(
+            config.validate(),
+            Err(ServerError::ConfigZero("limits.max-connections"))
+        ));
+
+        let config = ServerConfig {
+            path: "api/mpc".to_string(),
+            ..Default::default()
+        };
+        assert!(matches!(config.validate(), Err(ServerError::ConfigPath(_))));
+
+        let config = ServerConfig {
+            tcp: false,
+            ..Default::default()
+        };
+        assert!(matches!(config.validate(), Err(ServerError::NoListeners)));
+
+        // Overrides applied after loading can add a listener
+        let path = std::env::temp_dir().join(format!(
+            "mpc-websocket-config-{}.toml",
+            uuid::Uuid::new_v4()
+        ));
+        std::fs::write(&path, "tcp = false").unwrap();
+        let mut config = ServerConfig::load(Some(&path)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        config.unix_socket_mut().path = PathBuf::from("/tmp/mpc.sock");
+        assert!(config.validate().is_ok());
+
+        let mut config = ServerConfig::default();
+        config.tls.cert = Some(PathBuf::from("Cargo.toml"));
+        assert!(matches!(config.validate(), Err(ServerError::ConfigTls)));
+
+        let mut config = ServerConfig::default();
+        config.static_files.cache_control.push(CacheControl {
+            pattern: "/*/*.js".to_string(),
+            value: "no-cache".to_string(),
+        });
+        assert!(matches!(
+            config.validate(),
+            Err(ServerError::ConfigCacheControl(_))
+        ));
+
+        let mut config = ServerConfig::default();
+        config
+            .headers
+            .insert("Bad Header".to_string(), "value".to_string());
+        assert!(matches!(config.validate(), Err(ServerError::HeaderName(_))));
+    }
+}
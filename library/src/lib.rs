@@ -20,8 +20,24 @@
 //!
 //! The associated session data is typically used by signing sessions
 //! to indicate the message or transaction that will be signed.
+//!
+//! To run more than one server instance behind a load balancer
+//! configure a [Backplane](backplane::Backplane) using
+//! [ServerOptions](ServerOptions); see the [backplane](backplane)
+//! module for details.
+//!
+//! An optional [audit](audit) log records group and session lifecycle
+//! events (but never message bodies) in a hash-chained file.
+//!
+//! Servers may be configured from a TOML file with environment
+//! variable overrides, see the [config](config) module.
 #![deny(missing_docs)]
+mod assets;
+pub mod audit;
+pub mod backplane;
+pub mod config;
 mod server;
 pub mod services;
 
+pub use assets::{CacheControl, StaticFiles};
 pub use server::*;
@@ -5,23 +5,32 @@ use std::sync::{
     atomic::{AtomicUsize, Ordering},
     Arc,
 };
+use std::time::{Duration, Instant};
 
-use futures_util::{SinkExt, StreamExt, TryFutureExt};
+use futures_util::{future::BoxFuture, SinkExt, StreamExt, TryFutureExt};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use thiserror::Error;
 use tokio::sync::{mpsc, Mutex, RwLock};
 use tokio_stream::wrappers::UnboundedReceiverStream;
+#[cfg(unix)]
+use tokio_stream::wrappers::UnixListenerStream;
 use uuid::Uuid;
-use warp::http::header::{HeaderMap, HeaderValue};
+use warp::http::header::{HeaderMap, HeaderName, HeaderValue};
 use warp::ws::{Message, WebSocket};
 use warp::Filter;
 
+use crate::assets::{self, StaticFiles};
+use crate::audit::AuditLog;
+use crate::backplane::{Backplane, Envelope, Payload};
+use crate::config::{Limits, LogConfig, LogFormat, TlsConfig, Ttl};
 use crate::services::*;
 use json_rpc2::{Request, Response};
 
 use tracing_subscriber::fmt::format::FmtSpan;
 
+pub use mpc_ecdsa_types::Parameters;
+
 /// Global unique connection id counter.
 static CONNECTION_ID: AtomicUsize = AtomicUsize::new(1);
 
@@ -44,6 +53,89 @@ pub enum ServerError {
     #[error("party number already exists for session {0}")]
     PartyNumberAlreadyExists(Uuid),
 
+    /// Error generated when a group is already owned by
+    /// another server instance.
+    #[error("group {0} is owned by instance {1}")]
+    GroupOwnedByInstance(Uuid, Uuid),
+
+    /// Error generated when an audit log entry is out of sequence.
+    #[error("audit log entry {0} is missing or out of sequence")]
+    AuditSequence(u64),
+
+    /// Error generated when an audit log entry is not chained
+    /// to the previous entry.
+    #[error("audit log entry {0} is not chained to the previous entry")]
+    AuditChain(u64),
+
+    /// Error generated when the hash of an audit log entry
+    /// does not match the entry contents.
+    #[error("audit log entry {0} hash does not match its contents")]
+    AuditHash(u64),
+
+    /// Error generated when an audit log does not end with the
+    /// expected hash.
+    #[error("audit log does not end with the expected hash {0}")]
+    AuditHead(String),
+
+    /// Error generated when a config file cannot be parsed.
+    #[error("invalid config file: {0}")]
+    ConfigToml(#[from] toml::de::Error),
+
+    /// Error generated when a config environment variable
+    /// has an invalid value.
+    #[error("environment variable {0} has an invalid value {1:?}")]
+    ConfigEnv(String, String),
+
+    /// Error generated when an environment variable with the
+    /// config prefix is not recognized.
+    #[error("unknown config environment variable {0}")]
+    ConfigEnvUnknown(String),
+
+    /// Error generated when the websocket path is not
+    /// a single path segment.
+    #[error("websocket path {0:?} must be a single non-empty path segment")]
+    ConfigPath(String),
+
+    /// Error generated when a file in the config does not exist.
+    #[error("config file {0} does not exist")]
+    ConfigFile(PathBuf),
+
+    /// Error generated when only one of the TLS certificate
+    /// and key is configured.
+    #[error("TLS requires both a certificate and a private key")]
+    ConfigTls,
+
+    /// Error generated when a config limit or expiry is zero.
+    #[error("config option {0} must be greater than zero")]
+    ConfigZero(&'static str),
+
+    /// Error generated when a Cache-Control pattern has more
+    /// than one wildcard.
+    #[error("cache control pattern {0:?} may only contain a single wildcard")]
+    ConfigCacheControl(String),
+
+    /// Error generated when a log filter is invalid.
+    #[error("invalid log filter {0:?}")]
+    ConfigLogFilter(String),
+
+    /// Error generated when the server has nothing to listen on.
+    #[error("server must listen on at least one address or socket")]
+    NoListeners,
+
+    /// Error generated when a file that is not a socket exists
+    /// at the path for a Unix domain socket.
+    #[error("{0} exists and is not a socket")]
+    UnixSocketExists(PathBuf),
+
+    /// Error generated when Unix domain sockets are not supported
+    /// on the platform.
+    #[error("unix domain socket {0} is not supported on this platform")]
+    UnixSocketUnsupported(PathBuf),
+
+    /// Error generated when Unix domain socket permissions are invalid.
+    #[error("unix domain socket mode {0:o} is not valid permission bits")]
+    ConfigUnixSocketMode(u32),
+
     /// Error generated parsing a socket address.
     #[error(transparent)]
     NetAddrParse(#[from] std::net::AddrParseError),
@@ -52,6 +144,18 @@ pub enum ServerError {
     #[error(transparent)]
     Io(#[from] std::io::Error),
 
+    /// Error generated parsing a header name.
+    #[error(transparent)]
+    HeaderName(#[from] warp::http::header::InvalidHeaderName),
+
+    /// Error generated parsing a header value.
+    #[error(transparent)]
+    HeaderValue(#[from] warp::http::header::InvalidHeaderValue),
+
+    /// Error generated by the JSON library.
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
     /// Error generated by the JSON-RPC services.
     #[error(transparent)]
     JsonRpcError(#[from] json_rpc2::Error),
@@ -60,27 +164,6 @@ pub enum ServerError {
 /// Result type for server errors.
 pub type Result<T> = std::result::Result<T, ServerError>;
 
-/// Parameters used during key generation and signing.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Parameters {
-    /// Number of parties `n`.
-    pub parties: u16,
-    /// Threshold for signing `t`.
-    ///
-    /// The threshold must be crossed (`t + 1`) for signing
-    /// to commence.
-    pub threshold: u16,
-}
-
-impl Default for Parameters {
-    fn default() -> Self {
-        return Self {
-            parties: 3,
-            threshold: 1,
-        };
-    }
-}
-
 /// Represents the type of session.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum SessionKind {
@@ -90,6 +173,12 @@ pub enum SessionKind {
     /// Signing session.
     #[serde(rename = "sign")]
     Sign,
+    /// Key share refresh session.
+    #[serde(rename = "refresh")]
+    Refresh,
+    /// Session to reshare a key to a new committee.
+    #[serde(rename = "reshare")]
+    Reshare,
 }
 
 impl Default for SessionKind {
@@ -99,7 +188,7 @@ impl Default for SessionKind {
 }
 
 /// Group is a collection of connected websocket clients.
-#[derive(Debug, Default, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Group {
     /// Unique identifier for the group.
     pub uuid: Uuid,
@@ -113,6 +202,22 @@ pub struct Group {
     /// Sessions belonging to this group.
     #[serde(skip)]
     pub(crate) sessions: HashMap<Uuid, Session>,
+    /// When the group was created.
+    #[serde(skip)]
+    pub(crate) created: Instant,
+}
+
+impl Default for Group {
+    fn default() -> Self {
+        Self {
+            uuid: Default::default(),
+            params: Default::default(),
+            label: Default::default(),
+            clients: Default::default(),
+            sessions: Default::default(),
+            created: Instant::now(),
+        }
+    }
 }
 
 impl Group {
@@ -126,6 +231,7 @@ impl Group {
             sessions: Default::default(),
             params,
             label,
+            created: Instant::now(),
         }
     }
 }
@@ -156,6 +262,10 @@ pub struct Session {
     /// marked the session as finished.
     #[serde(skip)]
     pub(crate) finished: HashSet<u16>,
+
+    /// When the session was created.
+    #[serde(skip)]
+    pub(crate) created: Instant,
 }
 
 impl Default for Session {
@@ -166,6 +276,7 @@ impl Default for Session {
             party_signups: Default::default(),
             finished: Default::default(),
             value: None,
+            created: Instant::now(),
         }
     }
 }
@@ -178,22 +289,32 @@ impl From<(SessionKind, Option<Value>)> for Session {
             party_signups: Default::default(),
             finished: Default::default(),
             value: value.1,
+            created: Instant::now(),
         }
     }
 }
 
 impl Session {
+    /// Number of dealers leaving the committee in a resharing
+    /// session from the `retiring` field of the session value.
+    pub fn retiring(&self) -> u16 {
+        self.value
+            .as_ref()
+            .and_then(|value| value.get("retiring"))
+            .and_then(Value::as_u64)
+            .and_then(|retiring| u16::try_from(retiring).ok())
+            .unwrap_or(0)
+    }
+
     /// Signup to a session.
     ///
     /// This marks a connected client as actively participating in
     /// this session and issues them a unique party signup number.
     pub fn signup(&mut self, conn: usize) -> u16 {
-        let last = self.party_signups.last();
-        let num = if last.is_none() {
-            1
-        } else {
-            let (num, _) = last.unwrap();
+        let num = if let Some((num, _)) = self.party_signups.last() {
             num + 1
+        } else {
+            1
         };
         self.party_signups.push((num, conn));
         num
@@ -215,14 +336,12 @@ impl Session {
         if party_number > parameters.parties {
             return Err(ServerError::PartyNumberOutOfRange);
         }
-        if let Some(_) = self
+        if self
             .party_signups
             .iter()
-            .find(|(num, _)| num == &party_number)
+            .any(|(num, _)| num == &party_number)
         {
-            return Err(ServerError::PartyNumberAlreadyExists(
-                self.uuid.clone(),
-            ));
+            return Err(ServerError::PartyNumberAlreadyExists(self.uuid));
         }
         self.party_signups.push((party_number, conn));
         Ok(())
@@ -236,6 +355,23 @@ pub struct State {
     pub clients: HashMap<usize, mpsc::UnboundedSender<Message>>,
     /// Groups keyed by unique identifier (UUID)
     pub groups: HashMap<Uuid, Group>,
+    /// Backplane used to communicate with other server instances.
+    pub backplane: Option<Arc<dyn Backplane>>,
+    /// Audit log for lifecycle events.
+    pub audit: Option<Arc<AuditLog>>,
+    /// Resource limits.
+    pub limits: Limits,
+}
+
+/// Allocate a unique identifier for a new connection.
+async fn next_connection_id(
+    backplane: Option<Arc<dyn Backplane>>,
+) -> Result<usize> {
+    if let Some(backplane) = backplane {
+        backplane.next_connection_id().await
+    } else {
+        Ok(CONNECTION_ID.fetch_add(1, Ordering::Relaxed))
+    }
 }
 
 /// Notification sent by the server to multiple connected clients.
@@ -287,6 +423,142 @@ impl Default for Notification {
     }
 }
 
+/// Additional options for the server.
+#[derive(Debug)]
+pub struct ServerOptions {
+    /// Options for serving static files.
+    pub static_files: StaticFiles,
+    /// Headers added to every HTTP response.
+    ///
+    /// The default headers enable cross-origin isolation which
+    /// is required for the webassembly threads used by the client.
+    pub headers: Vec<(String, String)>,
+    /// Backplane used to route requests and notifications
+    /// when running multiple server instances.
+    pub backplane: Option<Arc<dyn Backplane>>,
+    /// Audit log for group and session lifecycle events.
+    pub audit: Option<Arc<AuditLog>>,
+    /// Serve over TLS.
+    pub tls: TlsConfig,
+    /// Resource limits.
+    pub limits: Limits,
+    /// Expiry for groups and sessions.
+    pub ttl: Ttl,
+    /// Logging options.
+    pub log: LogConfig,
+}
+
+impl Default for ServerOptions {
+    fn default() -> Self {
+        Self {
+            static_files: Default::default(),
+            headers: vec![
+                (
+                    "Cross-Origin-Embedder-Policy".to_string(),
+                    "require-corp".to_string(),
+                ),
+                (
+                    "Cross-Origin-Opener-Policy".to_string(),
+                    "same-origin".to_string(),
+                ),
+            ],
+            backplane: None,
+            audit: None,
+            tls: Default::default(),
+            limits: Default::default(),
+            ttl: Default::default(),
+            log: Default::default(),
+        }
+    }
+}
+
+/// Unix domain socket for the server to listen on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UnixSocket {
+    /// Path for the socket file.
+    pub path: PathBuf,
+    /// Permissions for the socket file, for example `0o660`.
+    ///
+    /// When not set the permissions are determined by the umask.
+    #[serde(default)]
+    pub mode: Option<u32>,
+}
+
+impl UnixSocket {
+    /// Bind a listener to the socket path.
+    ///
+    /// A stale socket file left by a previous server is replaced
+    /// but any other existing file is an error.
+    ///
+    /// When a mode is given the socket is bound inside a private
+    /// directory, its permissions are set and then it is moved into
+    /// place so that it is never reachable with the default mode.
+    #[cfg(unix)]
+    fn bind(&self) -> Result<tokio::net::UnixListener> {
+        use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+        if let Ok(metadata) = std::fs::symlink_metadata(&self.path) {
+            if !metadata.file_type().is_socket() {
+                return Err(ServerError::UnixSocketExists(self.path.clone()));
+            }
+            std::fs::remove_file(&self.path)?;
+        }
+
+        let mode = if let Some(mode) = self.mode {
+            mode
+        } else {
+            return Ok(tokio::net::UnixListener::bind(&self.path)?);
+        };
+
+        let parent = match self.path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => std::path::Path::new("."),
+        };
+        // Keep the name short as socket paths are limited in length
+        let mut name = Uuid::new_v4().to_simple().to_string();
+        name.truncate(8);
+        let private = parent.join(format!(".{}", name));
+        std::fs::DirBuilder::new().mode(0o700).create(&private)?;
+        let temporary = private.join("s");
+        let result = tokio::net::UnixListener::bind(&temporary)
+            .map_err(ServerError::from)
+            .and_then(|listener| {
+                std::fs::set_permissions(
+                    &temporary,
+                    std::fs::Permissions::from_mode(mode),
+                )?;
+                std::fs::rename(&temporary, &self.path)?;
+                Ok(listener)
+            });
+        let _ = std::fs::remove_file(&temporary);
+        std::fs::remove_dir(&private)?;
+        result
+    }
+}
+
+/// Address for the server to listen on.
+#[derive(Debug, Clone)]
+pub enum Listen {
+    /// TCP socket address.
+    Tcp(SocketAddr),
+    /// Unix domain socket.
+    ///
+    /// TLS is not used for Unix domain sockets.
+    Unix(UnixSocket),
+}
+
+impl From<SocketAddr> for Listen {
+    fn from(addr: SocketAddr) -> Self {
+        Self::Tcp(addr)
+    }
+}
+
+impl From<UnixSocket> for Listen {
+    fn from(socket: UnixSocket) -> Self {
+        Self::Unix(socket)
+    }
+}
+
 /// MPC websocket server handling JSON-RPC requests.
 pub struct Server;
 
@@ -304,12 +576,53 @@ impl Server {
         addr: impl Into<SocketAddr>,
         static_files: PathBuf,
     ) -> Result<()> {
-        // Filter traces based on the RUST_LOG env var.
-        let filter = std::env::var("RUST_LOG").unwrap_or_else(|_| {
-            "tracing=info,warp=debug,mpc_websocket=info".to_owned()
+        let options = ServerOptions {
+            static_files: StaticFiles::new(static_files),
+            ..Default::default()
+        };
+        Server::start_with_options(path, addr, options).await
+    }
+
+    /// Start the server with additional options.
+    ///
+    /// When a backplane is given the server subscribes to envelopes
+    /// from other instances and forwards requests for groups owned
+    /// by other instances.
+    pub async fn start_with_options(
+        path: impl Into<String>,
+        addr: impl Into<SocketAddr>,
+        options: ServerOptions,
+    ) -> Result<()> {
+        let listen = vec![Listen::Tcp(addr.into())];
+        Server::start_listeners(path, listen, options).await
+    }
+
+    /// Start the server listening on TCP addresses and
+    /// Unix domain sockets.
+    pub async fn start_listeners(
+        path: impl Into<String>,
+        listen: Vec<Listen>,
+        options: ServerOptions,
+    ) -> Result<()> {
+        let path = path.into();
+        if listen.is_empty() {
+            return Err(ServerError::NoListeners);
+        }
+
+        // Filter traces based on the config or the RUST_LOG env var.
+        let filter = options.log.filter.clone().unwrap_or_else(|| {
+            std::env::var("RUST_LOG").unwrap_or_else(|_| {
+                "tracing=info,warp=debug,mpc_websocket=info".to_owned()
+            })
         });
 
-        if cfg!(debug_assertions) {
+        let format = options.log.format.unwrap_or(if cfg!(debug_assertions) {
+            LogFormat::Text
+        } else {
+            LogFormat::Json
+        });
+
+        if format == LogFormat::Text {
             tracing_subscriber::fmt()
                 .with_env_filter(filter)
                 .with_span_events(FmtSpan::CLOSE)
@@ -322,51 +635,177 @@ impl Server {
                 .init();
         }
 
+        let mut static_files = options.static_files;
+        if let Some(directory) = static_files.directory.take() {
+            if !directory.is_dir() {
+                return Err(ServerError::NotDirectory(directory));
+            }
+            let directory = directory.canonicalize()?;
+            let static_path = directory.to_string_lossy().into_owned();
+            tracing::info!(%static_path);
+            static_files.directory = Some(directory);
+        }
+        tracing::info!(%path);
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in options.headers {
+            headers.insert(
+                HeaderName::from_bytes(name.as_bytes())?,
+                HeaderValue::from_str(&value)?,
+            );
+        }
+
+        let backplane = options.backplane;
         let state = Arc::new(RwLock::new(State {
             clients: HashMap::new(),
             groups: Default::default(),
+            backplane: backplane.clone(),
+            audit: options.audit,
+            limits: options.limits.clone(),
         }));
-        let state = warp::any().map(move || state.clone());
 
-        if !static_files.is_dir() {
-            return Err(ServerError::NotDirectory(static_files));
+        if let Some(backplane) = backplane {
+            subscribe_backplane(backplane, &state)?;
         }
 
-        let static_files = static_files.canonicalize()?;
-        let static_path = static_files.to_string_lossy().into_owned();
-        tracing::info!(%static_path);
-        tracing::info!(path);
+        if options.ttl.group.is_some() || options.ttl.session.is_some() {
+            let ttl = options.ttl.clone();
+            let state = Arc::clone(&state);
+            tokio::task::spawn(async move {
+                expire(ttl, state).await;
+            });
+        }
 
-        let client = warp::any().and(warp::fs::dir(static_files));
+        let state = warp::any().map(move || state.clone());
 
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            "Cross-Origin-Embedder-Policy",
-            HeaderValue::from_static("require-corp"),
-        );
-        headers.insert(
-            "Cross-Origin-Opener-Policy",
-            HeaderValue::from_static("same-origin"),
-        );
+        let client = assets::filter(static_files);
 
+        let max_message_size = options.limits.max_message_size;
         let websocket = warp::path(path).and(warp::ws()).and(state).map(
-            |ws: warp::ws::Ws, state| {
+            move |ws: warp::ws::Ws, state| {
+                let ws = if let Some(size) = max_message_size {
+                    ws.max_message_size(size).max_frame_size(size)
+                } else {
+                    ws
+                };
                 ws.on_upgrade(move |socket| client_connected(socket, state))
             },
         );
 
         let routes = websocket
-            .or(client)
+            .map(|reply| Box::new(reply) as Box<dyn warp::Reply>)
+            .or(client.map(|reply| Box::new(reply) as Box<dyn warp::Reply>))
+            .unify()
             .with(warp::reply::with::headers(headers))
             .with(warp::trace::request());
 
-        warp::serve(routes).run(addr).await;
+        let mut servers: Vec<BoxFuture<'static, ()>> = Vec::new();
+        for listen in listen {
+            let server = warp::serve(routes.clone());
+            match listen {
+                Listen::Tcp(addr) => {
+                    tracing::info!(%addr, "listen");
+                    if let (Some(cert), Some(key)) =
+                        (&options.tls.cert, &options.tls.key)
+                    {
+                        tracing::info!("tls");
+                        let server = server
+                            .tls()
+                            .cert_path(cert)
+                            .key_path(key)
+                            .run(addr);
+                        servers.push(Box::pin(server));
+                    } else {
+                        servers.push(Box::pin(server.run(addr)));
+                    }
+                }
+                #[cfg(unix)]
+                Listen::Unix(socket) => {
+                    let listener = socket.bind()?;
+                    let socket_path =
+                        socket.path.to_string_lossy().into_owned();
+                    tracing::info!(%socket_path, "listen");
+                    let incoming = UnixListenerStream::new(listener);
+                    servers.push(Box::pin(server.run_incoming(incoming)));
+                }
+                #[cfg(not(unix))]
+                Listen::Unix(socket) => {
+                    return Err(ServerError::UnixSocketUnsupported(
+                        socket.path,
+                    ));
+                }
+            }
+        }
+
+        futures_util::future::join_all(servers).await;
         Ok(())
     }
 }
 
+/// Periodically remove groups and sessions that have expired.
+async fn expire(ttl: Ttl, state: Arc<RwLock<State>>) {
+    let group_ttl = ttl.group.map(Duration::from_secs);
+    let session_ttl = ttl.session.map(Duration::from_secs);
+    let shortest = group_ttl.into_iter().chain(session_ttl).min();
+    let period = shortest
+        .map(|ttl| ttl / 4)
+        .unwrap_or_default()
+        .clamp(Duration::from_secs(1), Duration::from_secs(60));
+
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        let mut writer = state.write().await;
+
+        let mut expired_groups = Vec::new();
+        for (group_id, group) in writer.groups.iter_mut() {
+            if let Some(ttl) = group_ttl {
+                if group.created.elapsed() >= ttl {
+                    expired_groups.push(*group_id);
+                    continue;
+                }
+            }
+            if let Some(ttl) = session_ttl {
+                group.sessions.retain(|session_id, session| {
+                    let expired = session.created.elapsed() >= ttl;
+                    if expired {
+                        tracing::info!(%session_id, "session expired");
+                    }
+                    !expired
+                });
+            }
+        }
+
+        for key in expired_groups {
+            writer.groups.remove(&key);
+            tracing::info!(%key, "group expired");
+            if let Some(backplane) = &writer.backplane {
+                if let Err(e) = backplane.release(key).await {
+                    tracing::warn!(?e, %key, "failed to release group");
+                }
+            }
+        }
+    }
+}
+
 async fn client_connected(ws: WebSocket, state: Arc<RwLock<State>>) {
-    let conn_id = CONNECTION_ID.fetch_add(1, Ordering::Relaxed);
+    let backplane = {
+        let reader = state.read().await;
+        if let Some(max) = reader.limits.max_connections {
+            if reader.clients.len() >= max {
+                tracing::warn!(max, "connection limit reached");
+                return;
+            }
+        }
+        reader.backplane.clone()
+    };
+    let conn_id = match next_connection_id(backplane).await {
+        Ok(conn_id) => conn_id,
+        Err(e) => {
+            tracing::error!(?e, "failed to allocate connection id");
+            return;
+        }
+    };
 
     tracing::info!(conn_id, "connected");
 
@@ -392,9 +831,8 @@ async fn client_connected(ws: WebSocket, state: Arc<RwLock<State>>) {
 
             let reader = should_close.read().await;
             if *reader {
-                match user_ws_tx.close().await {
-                    Err(e) => tracing::warn!(?e, "failed to close websocket"),
-                    _ => {}
+                if let Err(e) = user_ws_tx.close().await {
+                    tracing::warn!(?e, "failed to close websocket")
                 }
                 break;
             }
@@ -446,18 +884,27 @@ async fn rpc_request(
     close_flag: &mut Arc<RwLock<bool>>,
     request: Request,
     state: &Arc<RwLock<State>>,
+) {
+    if forward_request(conn_id, &request, state).await {
+        return;
+    }
+    rpc_serve(conn_id, Some(close_flag), request, state).await;
+}
+
+/// Serve a request that belongs to this server instance.
+///
+/// When `close_flag` is `None` the request was forwarded from another
+/// instance and the response is routed back over the backplane.
+async fn rpc_serve(
+    conn_id: usize,
+    close_flag: Option<&mut Arc<RwLock<bool>>>,
+    request: Request,
+    state: &Arc<RwLock<State>>,
 ) {
     use json_rpc2::futures::*;
 
-    let service: Box<
-        dyn Service<
-            Data = (
-                usize,
-                Arc<RwLock<State>>,
-                Arc<Mutex<Option<Notification>>>,
-            ),
-        >,
-    > = Box::new(ServiceHandler {});
+    let service: Box<dyn Service<Data = ServiceData>> =
+        Box::new(ServiceHandler {});
     let server = Server::new(vec![&service]);
 
     let notification: Arc<Mutex<Option<Notification>>> =
@@ -470,15 +917,21 @@ async fn rpc_request(
         )
         .await
     {
-        rpc_response(conn_id, &response, state).await;
+        let close = if let Some(error) = response.error() {
+            error.data.as_deref() == Some(CLOSE_CONNECTION)
+        } else {
+            false
+        };
 
-        if let Some(error) = response.error() {
-            if let Some(data) = &error.data {
-                if data == CLOSE_CONNECTION {
-                    let mut writer = close_flag.write().await;
-                    *writer = true;
-                }
+        if let Some(close_flag) = close_flag {
+            rpc_response(conn_id, &response, state).await;
+            if close {
+                let mut writer = close_flag.write().await;
+                *writer = true;
             }
+        } else {
+            let msg = serde_json::to_string(&response).unwrap();
+            send_message(conn_id, msg, close, state).await;
         }
     }
 
@@ -488,6 +941,116 @@ async fn rpc_request(
     }
 }
 
+/// Get the group identifier targeted by a request.
+fn request_group(request: &Request) -> Option<Uuid> {
+    if request.method() == GROUP_CREATE {
+        return None;
+    }
+    let id = match request.params() {
+        Some(Value::String(id)) => Some(id),
+        Some(Value::Array(params)) => {
+            if let Some(Value::String(id)) = params.first() {
+                Some(id)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    };
+    id.and_then(|id| Uuid::parse_str(id).ok())
+}
+
+/// Forward a request to the instance that owns the target group.
+///
+/// Returns `true` if the request was forwarded.
+async fn forward_request(
+    conn_id: usize,
+    request: &Request,
+    state: &Arc<RwLock<State>>,
+) -> bool {
+    let group_id = if let Some(group_id) = request_group(request) {
+        group_id
+    } else {
+        return false;
+    };
+
+    let backplane = {
+        let reader = state.read().await;
+        if reader.groups.contains_key(&group_id) {
+            return false;
+        }
+        if let Some(backplane) = &reader.backplane {
+            Arc::clone(backplane)
+        } else {
+            return false;
+        }
+    };
+
+    let owner = match backplane.owner(group_id).await {
+        Ok(Some(owner)) if owner != backplane.instance() => owner,
+        Ok(_) => return false,
+        Err(e) => {
+            tracing::warn!(?e, %group_id, "backplane owner lookup failed");
+            return false;
+        }
+    };
+
+    tracing::debug!(conn_id, %group_id, %owner, "forward request");
+    let envelope = Envelope {
+        source: backplane.instance(),
+        target: Some(owner),
+        payload: Payload::Request {
+            conn_id,
+            request: request.clone(),
+        },
+    };
+    if let Err(e) = backplane.publish(envelope).await {
+        tracing::error!(?e, "backplane publish failed");
+    }
+    true
+}
+
+/// Handle envelopes published by other server instances.
+fn subscribe_backplane(
+    backplane: Arc<dyn Backplane>,
+    state: &Arc<RwLock<State>>,
+) -> Result<()> {
+    let mut rx = backplane.subscribe()?;
+    let instance = backplane.instance();
+    tracing::info!(%instance, "backplane");
+    let state = Arc::clone(state);
+    tokio::task::spawn(async move {
+        while let Some(envelope) = rx.recv().await {
+            backplane_incoming(envelope, &state).await;
+        }
+    });
+    Ok(())
+}
+
+/// Handle an envelope published by another server instance.
+async fn backplane_incoming(envelope: Envelope, state: &Arc<RwLock<State>>) {
+    match envelope.payload {
+        Payload::Request { conn_id, request } => {
+            rpc_serve(conn_id, None, request, state).await;
+        }
+        Payload::Deliver {
+            conn_id,
+            message,
+            close,
+        } => {
+            if let Some(tx) = state.read().await.clients.get(&conn_id) {
+                let _ = tx.send(Message::text(message));
+                if close {
+                    let _ = tx.send(Message::close());
+                }
+            }
+        }
+        Payload::Disconnected { conn_id } => {
+            prune_connection(conn_id, state).await;
+        }
+    }
+}
+
 /// Remove `filters` from a list of clients.
 fn filter_clients(
     clients: Vec<usize>,
@@ -505,18 +1068,20 @@ fn filter_clients(
 
 /// Send notification to connected client(s).
 async fn rpc_notify(state: &Arc<RwLock<State>>, notification: Notification) {
-    let reader = state.read().await;
+    // The state lock is only held while the recipients are
+    // collected as sending may publish to the backplane
     match notification {
         Notification::Group {
             group_id,
             filter,
             response,
         } => {
-            let clients = if let Some(group) = reader.groups.get(&group_id) {
-                group.clients.clone()
-            } else {
-                vec![0usize]
-            };
+            let clients =
+                if let Some(group) = state.read().await.groups.get(&group_id) {
+                    group.clients.clone()
+                } else {
+                    vec![0usize]
+                };
 
             let clients = filter_clients(clients, filter);
             for conn_id in clients {
@@ -529,18 +1094,19 @@ async fn rpc_notify(state: &Arc<RwLock<State>>, notification: Notification) {
             filter,
             response,
         } => {
-            let clients = if let Some(group) = reader.groups.get(&group_id) {
-                if let Some(session) = group.sessions.get(&session_id) {
-                    session.party_signups.iter().map(|i| i.1.clone()).collect()
-                } else {
-                    tracing::warn!(
+            let clients =
+                if let Some(group) = state.read().await.groups.get(&group_id) {
+                    if let Some(session) = group.sessions.get(&session_id) {
+                        session.party_signups.iter().map(|i| i.1).collect()
+                    } else {
+                        tracing::warn!(
                         %session_id,
                         "notification session does not exist");
+                        vec![0usize]
+                    }
+                } else {
                     vec![0usize]
-                }
-            } else {
-                vec![0usize]
-            };
+                };
 
             let clients = filter_clients(clients, filter);
             for conn_id in clients {
@@ -561,16 +1127,49 @@ async fn rpc_response(
     conn_id: usize,
     response: &json_rpc2::Response,
     state: &Arc<RwLock<State>>,
+) {
+    tracing::debug!(?response, "send response");
+    let msg = serde_json::to_string(response).unwrap();
+    send_message(conn_id, msg, false, state).await;
+}
+
+/// Write a message to a client connection.
+///
+/// Connections that belong to another server instance are
+/// reached via the backplane.
+async fn send_message(
+    conn_id: usize,
+    msg: String,
+    close: bool,
+    state: &Arc<RwLock<State>>,
 ) {
     tracing::debug!(conn_id, "send message");
-    if let Some(tx) = state.read().await.clients.get(&conn_id) {
-        tracing::debug!(?response, "send response");
-        let msg = serde_json::to_string(response).unwrap();
+    let (tx, backplane) = {
+        let reader = state.read().await;
+        (
+            reader.clients.get(&conn_id).cloned(),
+            reader.backplane.clone(),
+        )
+    };
+    if let Some(tx) = tx {
         if let Err(_disconnected) = tx.send(Message::text(msg)) {
             // The tx is disconnected, our `client_disconnected` code
             // should be happening in another task, nothing more to
             // do here.
         }
+    } else if let Some(backplane) = backplane {
+        let envelope = Envelope {
+            source: backplane.instance(),
+            target: None,
+            payload: Payload::Deliver {
+                conn_id,
+                message: msg,
+                close,
+            },
+        };
+        if let Err(e) = backplane.publish(envelope).await {
+            tracing::error!(?e, "backplane publish failed");
+        }
     } else {
         tracing::warn!(conn_id, "could not find tx for websocket");
     }
@@ -579,13 +1178,35 @@ async fn rpc_response(
 async fn client_disconnected(conn_id: usize, state: &Arc<RwLock<State>>) {
     tracing::info!(conn_id, "disconnected");
 
+    // Stream closed up, so remove from the client list
+    let backplane = {
+        let mut writer = state.write().await;
+        writer.clients.remove(&conn_id);
+        writer.backplane.clone()
+    };
+
+    // Let other instances remove the connection from their groups
+    if let Some(backplane) = backplane {
+        let envelope = Envelope {
+            source: backplane.instance(),
+            target: None,
+            payload: Payload::Disconnected { conn_id },
+        };
+        if let Err(e) = backplane.publish(envelope).await {
+            tracing::error!(?e, "backplane publish failed");
+        }
+    }
+
+    prune_connection(conn_id, state).await;
+}
+
+/// Remove a connection from all groups and prune empty groups.
+async fn prune_connection(conn_id: usize, state: &Arc<RwLock<State>>) {
     // FIXME: prune session party signups for disconnected clients?
 
     let mut empty_groups: Vec<Uuid> = Vec::new();
     {
         let mut writer = state.write().await;
-        // Stream closed up, so remove from the client list
-        writer.clients.remove(&conn_id);
         // Remove the connection from any client groups
         for (key, group) in writer.groups.iter_mut() {
             if let Some(index) =
@@ -596,7 +1217,7 @@ async fn client_disconnected(conn_id: usize, state: &Arc<RwLock<State>>) {
 
             // Group has no more connected clients so flag it for removal
             if group.clients.is_empty() {
-                empty_groups.push(key.clone());
+                empty_groups.push(*key);
             }
         }
     }
@@ -606,5 +1227,215 @@ async fn client_disconnected(conn_id: usize, state: &Arc<RwLock<State>>) {
     for key in empty_groups {
         writer.groups.remove(&key);
         tracing::info!(%key, "removed group");
+        if let Some(backplane) = &writer.backplane {
+            if let Err(e) = backplane.release(key).await {
+                tracing::warn!(?e, %key, "failed to release group");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backplane::MemoryBackplane;
+    use serde_json::json;
+
+    /// Create the state for a server instance using a backplane.
+    fn instance(backplane: MemoryBackplane) -> Result<Arc<RwLock<State>>> {
+        let backplane: Arc<dyn Backplane> = Arc::new(backplane);
+        let state = Arc::new(RwLock::new(State {
+            clients: HashMap::new(),
+            groups: Default::default(),
+            backplane: Some(Arc::clone(&backplane)),
+            audit: None,
+            limits: Default::default(),
+        }));
+        subscribe_backplane(backplane, &state)?;
+        Ok(state)
+    }
+
+    /// Add a client connection to a server instance.
+    async fn connect(
+        state: &Arc<RwLock<State>>,
+    ) -> (usize, mpsc::UnboundedReceiver<Message>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let backplane = state.read().await.backplane.clone();
+        let conn_id = next_connection_id(backplane).await.unwrap();
+        state.write().await.clients.insert(conn_id, tx);
+        (conn_id, rx)
+    }
+
+    /// Send a request from a client connection.
+    async fn call(
+        state: &Arc<RwLock<State>>,
+        conn_id: usize,
+        method: &str,
+        params: Value,
+    ) {
+        let request = Request::new_reply(method, Some(params));
+        let mut close_flag = Arc::new(RwLock::new(false));
+        rpc_request(conn_id, &mut close_flag, request, state).await;
+    }
+
+    /// Wait for the next message written to a client connection.
+    async fn receive(rx: &mut mpsc::UnboundedReceiver<Message>) -> Value {
+        let message = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("timed out waiting for message")
+            .expect("client channel closed");
+        serde_json::from_str(message.to_str().unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn backplane_routes_between_instances() -> Result<()> {
+        let backplane = MemoryBackplane::new();
+        let server_a = instance(backplane.connect())?;
+        let server_b = instance(backplane.connect())?;
+
+        let (conn_a, mut rx_a) = connect(&server_a).await;
+        let (conn_b, mut rx_b) = connect(&server_b).await;
+
+        // Group is owned by the instance that created it
+        call(
+            &server_a,
+            conn_a,
+            GROUP_CREATE,
+            json!(["test", {
+                "parties": 3,
+                "threshold": 1,
+            }]),
+        )
+        .await;
+        let response = receive(&mut rx_a).await;
+        let group_id = response["result"].as_str().unwrap().to_string();
+        let group_uuid = Uuid::parse_str(&group_id).unwrap();
+        assert!(server_a.read().await.groups.contains_key(&group_uuid));
+        assert!(!server_b.read().await.groups.contains_key(&group_uuid));
+
+        // Request is forwarded to the owner and the
+        // response is delivered back to the client
+        call(&server_b, conn_b, GROUP_JOIN, json!(group_id)).await;
+        let response = receive(&mut rx_b).await;
+        assert_eq!(json!(group_id), response["result"]["uuid"]);
+        {
+            let reader = server_a.read().await;
+            let group = reader.groups.get(&group_uuid).unwrap();
+            assert_eq!(vec![conn_a, conn_b], group.clients);
+        }
+
+        // Notifications reach clients connected to other instances
+        call(
+            &server_a,
+            conn_a,
+            SESSION_CREATE,
+            json!([group_id, "keygen", null]),
+        )
+        .await;
+        let response = receive(&mut rx_a).await;
+        let session_id = response["result"]["uuid"].clone();
+        let notification = receive(&mut rx_b).await;
+        assert_eq!(json!(SESSION_CREATE_EVENT), notification["result"][0]);
+        assert_eq!(session_id, notification["result"][1]["uuid"]);
+
+        // Disconnecting from the other instance prunes the group
+        client_disconnected(conn_b, &server_b).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let reader = server_a.read().await;
+        let group = reader.groups.get(&group_uuid).unwrap();
+        assert_eq!(vec![conn_a], group.clients);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn proposal_requires_membership() -> Result<()> {
+        let path = std::env::temp_dir()
+            .join(format!("mpc-websocket-audit-{}.jsonl", Uuid::new_v4()));
+        let log = Arc::new(AuditLog::open(&path)?);
+        let state = Arc::new(RwLock::new(State {
+            clients: HashMap::new(),
+            groups: Default::default(),
+            backplane: None,
+            audit: Some(Arc::clone(&log)),
+            limits: Default::default(),
+        }));
+
+        let (owner, mut rx_owner) = connect(&state).await;
+        let (outsider, mut rx_outsider) = connect(&state).await;
+
+        call(
+            &state,
+            owner,
+            GROUP_CREATE,
+            json!(["test", {
+                "parties": 3,
+                "threshold": 1,
+            }]),
+        )
+        .await;
+        let response = receive(&mut rx_owner).await;
+        let group_id = response["result"].clone();
+        call(
+            &state,
+            owner,
+            SESSION_CREATE,
+            json!([group_id, "sign", null]),
+        )
+        .await;
+        let response = receive(&mut rx_owner).await;
+        let session_id = response["result"]["uuid"].clone();
+        let entries = log.head().entries;
+
+        // Connection is not a member of the group
+        call(
+            &state,
+            outsider,
+            NOTIFY_PROPOSAL,
+            json!([group_id, session_id, "proposal", "message"]),
+        )
+        .await;
+        let response = receive(&mut rx_outsider).await;
+        assert!(response["error"].is_object());
+        assert_eq!(entries, log.head().entries);
+
+        call(
+            &state,
+            owner,
+            NOTIFY_PROPOSAL,
+            json!([group_id, session_id, "proposal", "message"]),
+        )
+        .await;
+        let response = receive(&mut rx_owner).await;
+        assert!(response["error"].is_null());
+        assert_eq!(entries + 1, log.head().entries);
+
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn unix_socket_mode() -> Result<()> {
+        use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+        let directory = std::env::temp_dir()
+            .join(format!("mpc-socket-{}", Uuid::new_v4().to_simple()));
+        std::fs::create_dir(&directory)?;
+        let socket = UnixSocket {
+            path: directory.join("mpc.sock"),
+            mode: Some(0o600),
+        };
+
+        // Stale socket from a previous server is replaced
+        for _ in 0..2 {
+            let _listener = socket.bind()?;
+            let metadata = std::fs::metadata(&socket.path)?;
+            assert!(metadata.file_type().is_socket());
+            assert_eq!(0o600, metadata.permissions().mode() & 0o777);
+        }
+        // Private directory used to bind the socket is removed
+        assert_eq!(1, std::fs::read_dir(&directory)?.count());
+
+        std::fs::remove_dir_all(&directory)?;
+        Ok(())
     }
 }
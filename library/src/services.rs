@@ -30,7 +30,7 @@
 //!
 //! ### Session.create
 //! * `group_id`: The `String` UUID for the group.
-//! * `kind`: The `String` kind of session (either `keygen` or `sign`).
+//! * `kind`: The `String` kind of session (`keygen`, `sign`, `refresh` or `reshare`).
 //!
 //! Create a new session.
 //!
@@ -40,7 +40,7 @@
 //!
 //! * `group_id`: The `String` UUID for the group.
 //! * `session_id`: The `String` UUID for the session.
-//! * `kind`: The `String` kind of session (either `keygen` or `sign`).
+//! * `kind`: The `String` kind of session (`keygen`, `sign`, `refresh` or `reshare`).
 //!
 //! Join an existing session.
 //!
@@ -50,11 +50,11 @@
 //!
 //! * `group_id`: The `String` UUID for the group.
 //! * `session_id`: The `String` UUID for the session.
-//! * `kind`: The `String` kind of session (either `keygen` or `sign`).
+//! * `kind`: The `String` kind of session (`keygen`, `sign`, `refresh` or `reshare`).
 //!
 //! Register as a co-operating party for a session.
 //!
-//! When the required number of parties have signed up to a session a `sessionSignup` event is emitted to all the clients in the session. For key generation there must be `parties` clients in the session and for signing there must be `threshold + 1` clients registered for the session.
+//! When the required number of parties have signed up to a session a `sessionSignup` event is emitted to all the clients in the session. For key generation and refresh there must be `parties` clients in the session and for signing there must be `threshold + 1` clients registered for the session. For resharing there must be `parties` clients plus the number of dealers leaving the committee given by the `retiring` field of the session value.
 //!
 //! Returns the party signup number.
 //!
@@ -62,7 +62,7 @@
 //!
 //! * `group_id`: The `String` UUID for the group.
 //! * `session_id`: The `String` UUID for the session.
-//! * `kind`: The `String` kind of session (must be `keygen`).
+//! * `kind`: The `String` kind of session (`keygen`, `refresh` or `reshare`).
 //! * `number`: The `u16` party signup number.
 //!
 //! Load a client into a given slot (party signup number). This is used to allow the party signup numbers allocated to saved key shares to be assigned and validated in the context of a session.
@@ -77,7 +77,7 @@
 //!
 //! * `group_id`: The `String` UUID for the group.
 //! * `session_id`: The `String` UUID for the session.
-//! * `kind`: The `String` kind of session (either `keygen` or `sign`).
+//! * `kind`: The `String` kind of session (`keygen`, `sign`, `refresh` or `reshare`).
 //! * `message`: The message to broadcast or send peer to peer.
 //!
 //! Relay a message to all the other peers in the session (broadcast) or send directly to another peer.
@@ -131,6 +131,7 @@ use thiserror::Error;
 use tokio::sync::{Mutex, RwLock};
 use uuid::Uuid;
 
+use super::audit::{AuditEvent, AuditLog};
 use super::server::{
     Group, Notification, Parameters, Session, SessionKind, State,
 };
@@ -138,18 +139,15 @@ use super::server::{
 /// Error thrown by the JSON-RPC services.
 #[derive(Debug, Error)]
 pub enum ServiceError {
-    /// Error generated when a parties parameter is too small.
-    #[error("parties must be greater than one")]
-    PartiesTooSmall,
-    /// Error generated when a parties parameter is too small.
-    #[error("threshold must be greater than zero")]
-    ThresholdTooSmall,
-    /// Error generated when the threshold exceeds the parties.
-    #[error("threshold must be less than parties")]
-    ThresholdRange,
+    /// Error generated when the group parameters are invalid.
+    #[error(transparent)]
+    Parameters(#[from] mpc_ecdsa_types::Error),
     /// Error generated when a group has enough connections.
     #[error("group {0} is full, cannot accept new connections")]
     GroupFull(Uuid),
+    /// Error generated when the server has the maximum number of groups.
+    #[error("server is full, cannot create more than {0} groups")]
+    GroupLimit(usize),
     /// Error generated when a group does not exist.
     #[error("group {0} does not exist")]
     GroupDoesNotExist(Uuid),
@@ -217,6 +215,10 @@ pub const NOTIFY_PROPOSAL_EVENT: &str = "notifyProposal";
 /// Notification sent when a proposal has been signed.
 pub const NOTIFY_SIGNED_EVENT: &str = "notifySigned";
 
+/// Data passed to the service handler for each request.
+pub(crate) type ServiceData =
+    (usize, Arc<RwLock<State>>, Arc<Mutex<Option<Notification>>>);
+
 type GroupCreateParams = (String, Parameters);
 type SessionCreateParams = (Uuid, SessionKind, Option<Value>);
 type SessionJoinParams = (Uuid, Uuid, SessionKind);
@@ -253,7 +255,7 @@ pub struct ServiceHandler;
 
 #[async_trait]
 impl Service for ServiceHandler {
-    type Data = (usize, Arc<RwLock<State>>, Arc<Mutex<Option<Notification>>>);
+    type Data = ServiceData;
 
     async fn handle(
         &self,
@@ -266,37 +268,54 @@ impl Service for ServiceHandler {
                 let params: GroupCreateParams = req.deserialize()?;
                 let (label, parameters) = params;
 
-                // If parties is less than two then may as well
-                // use a standard single-party ECDSA private key
-                if parameters.parties <= 1 {
-                    return Err(Error::from(Box::from(
-                        ServiceError::PartiesTooSmall,
-                    )));
-                // If threshold is zero then it only
-                // takes a single party to sign a request which
-                // defeats the point of MPC
-                } else if parameters.threshold == 0 {
-                    return Err(Error::from(Box::from(
-                        ServiceError::ThresholdTooSmall,
-                    )));
-                // Threshold must be in range `(t + 1) <= n`
-                } else if parameters.threshold >= parameters.parties {
-                    return Err(Error::from(Box::from(
-                        ServiceError::ThresholdRange,
-                    )));
-                }
+                parameters.validate().map_err(|e| {
+                    Error::from(Box::from(ServiceError::from(e)))
+                })?;
 
                 let group =
                     Group::new(*conn_id, parameters.clone(), label.clone());
-                let res = serde_json::to_value(&group.uuid).unwrap();
+                let res = serde_json::to_value(group.uuid).unwrap();
                 let mut writer = state.write().await;
-                writer.groups.insert(group.uuid.clone(), group);
+                if let Some(max) = writer.limits.max_groups {
+                    if writer.groups.len() >= max {
+                        return Err(Error::from(Box::from(
+                            ServiceError::GroupLimit(max),
+                        )));
+                    }
+                }
+                // Other instances forward requests for this group to us
+                if let Some(backplane) = &writer.backplane {
+                    backplane
+                        .claim(group.uuid)
+                        .await
+                        .map_err(|e| Error::from(Box::from(e)))?;
+                }
+                // Release the claim when the group cannot be audited
+                // so the backplane does not route to a missing group
+                if let Err(e) = audit(
+                    &writer.audit,
+                    *conn_id,
+                    AuditEvent::GroupCreate {
+                        group_id: group.uuid,
+                        parties: parameters.parties,
+                        threshold: parameters.threshold,
+                    },
+                ) {
+                    if let Some(backplane) = &writer.backplane {
+                        if let Err(e) = backplane.release(group.uuid).await {
+                            tracing::warn!(?e, "failed to release group");
+                        }
+                    }
+                    return Err(e);
+                }
+                writer.groups.insert(group.uuid, group);
                 Some((req, res).into())
             }
             GROUP_JOIN => {
                 let (conn_id, state, _) = ctx;
                 let group_id: Uuid = req.deserialize()?;
                 let mut writer = state.write().await;
+                let log = writer.audit.clone();
                 if let Some(group) = writer.groups.get_mut(&group_id) {
                     if group.clients.len() == group.params.parties as usize {
                         let error = ServiceError::GroupFull(group_id);
@@ -306,11 +325,14 @@ impl Service for ServiceHandler {
                         );
                         Some((req, err).into())
                     } else {
-                        if let None =
-                            group.clients.iter().find(|c| *c == conn_id)
-                        {
+                        if !group.clients.contains(conn_id) {
                             group.clients.push(*conn_id);
                         }
+                        audit(
+                            &log,
+                            *conn_id,
+                            AuditEvent::GroupJoin { group_id },
+                        )?;
                         let res = serde_json::to_value(group).unwrap();
                         Some((req, res).into())
                     }
@@ -325,13 +347,26 @@ impl Service for ServiceHandler {
                 let params: SessionCreateParams = req.deserialize()?;
                 let (group_id, kind, value) = params;
                 let mut writer = state.write().await;
+                let log = writer.audit.clone();
                 let group =
-                    get_group_mut(&conn_id, &group_id, &mut writer.groups)?;
+                    get_group_mut(conn_id, &group_id, &mut writer.groups)?;
                 let session = Session::from((kind.clone(), value));
-                let key = session.uuid.clone();
+                let key = session.uuid;
+                audit(
+                    &log,
+                    *conn_id,
+                    AuditEvent::SessionCreate {
+                        group_id,
+                        session_id: key,
+                        session_kind: kind.clone(),
+                    },
+                )?;
                 group.sessions.insert(key, session.clone());
 
-                if let SessionKind::Keygen = kind {
+                if let SessionKind::Keygen
+                | SessionKind::Refresh
+                | SessionKind::Reshare = kind
+                {
                     let value =
                         serde_json::to_value((SESSION_CREATE_EVENT, &session))
                             .unwrap();
@@ -356,10 +391,19 @@ impl Service for ServiceHandler {
                 let (group_id, session_id, _kind) = params;
 
                 let mut writer = state.write().await;
+                let log = writer.audit.clone();
                 let group =
-                    get_group_mut(&conn_id, &group_id, &mut writer.groups)?;
+                    get_group_mut(conn_id, &group_id, &mut writer.groups)?;
                 if let Some(session) = group.sessions.get_mut(&session_id) {
-                    let res = serde_json::to_value(&session).unwrap();
+                    audit(
+                        &log,
+                        *conn_id,
+                        AuditEvent::SessionJoin {
+                            group_id,
+                            session_id,
+                        },
+                    )?;
+                    let res = serde_json::to_value(session).unwrap();
                     Some((req, res).into())
                 } else {
                     return Err(Error::from(Box::from(
@@ -373,19 +417,25 @@ impl Service for ServiceHandler {
                 let (group_id, session_id, kind) = params;
 
                 let mut writer = state.write().await;
+                let log = writer.audit.clone();
                 let group =
-                    get_group_mut(&conn_id, &group_id, &mut writer.groups)?;
+                    get_group_mut(conn_id, &group_id, &mut writer.groups)?;
                 if let Some(session) = group.sessions.get_mut(&session_id) {
                     let party_number = session.signup(*conn_id);
 
                     tracing::info!(party_number, "session signup {}", conn_id);
+                    audit(
+                        &log,
+                        *conn_id,
+                        AuditEvent::SessionSignup {
+                            group_id,
+                            session_id,
+                            party_number,
+                        },
+                    )?;
 
                     // Enough parties are signed up to the session
-                    if threshold(
-                        &kind,
-                        &group.params,
-                        session.party_signups.len(),
-                    ) {
+                    if threshold(&kind, &group.params, session) {
                         let value = serde_json::to_value((
                             SESSION_SIGNUP_EVENT,
                             &session_id,
@@ -403,7 +453,7 @@ impl Service for ServiceHandler {
                         *writer = Some(ctx);
                     }
 
-                    let res = serde_json::to_value(&party_number).unwrap();
+                    let res = serde_json::to_value(party_number).unwrap();
                     Some((req, res).into())
                 } else {
                     return Err(Error::from(Box::from(
@@ -419,18 +469,25 @@ impl Service for ServiceHandler {
                 let (group_id, session_id, kind, party_number) = params;
 
                 let mut writer = state.write().await;
+                let log = writer.audit.clone();
                 let group =
-                    get_group_mut(&conn_id, &group_id, &mut writer.groups)?;
+                    get_group_mut(conn_id, &group_id, &mut writer.groups)?;
                 if let Some(session) = group.sessions.get_mut(&session_id) {
-                    let res = serde_json::to_value(&party_number).unwrap();
+                    let res = serde_json::to_value(party_number).unwrap();
                     match session.load(&group.params, *conn_id, party_number) {
                         Ok(_) => {
+                            audit(
+                                &log,
+                                *conn_id,
+                                AuditEvent::SessionLoad {
+                                    group_id,
+                                    session_id,
+                                    party_number,
+                                },
+                            )?;
+
                             // Enough parties are loaded into the session
-                            if threshold(
-                                &kind,
-                                &group.params,
-                                session.party_signups.len(),
-                            ) {
+                            if threshold(&kind, &group.params, session) {
                                 let value = serde_json::to_value((
                                     SESSION_LOAD_EVENT,
                                     &session_id,
@@ -464,8 +521,9 @@ impl Service for ServiceHandler {
                 let (group_id, session_id, party_number) = params;
 
                 let mut writer = state.write().await;
+                let log = writer.audit.clone();
                 let group =
-                    get_group_mut(&conn_id, &group_id, &mut writer.groups)?;
+                    get_group_mut(conn_id, &group_id, &mut writer.groups)?;
                 if let Some(session) = group.sessions.get_mut(&session_id) {
                     let existing_signup = session
                         .party_signups
@@ -482,11 +540,20 @@ impl Service for ServiceHandler {
                         }
 
                         session.finished.insert(party_number);
+                        audit(
+                            &log,
+                            *conn_id,
+                            AuditEvent::SessionFinish {
+                                group_id,
+                                session_id,
+                                party_number,
+                            },
+                        )?;
 
                         let mut signups = session
                             .party_signups
                             .iter()
-                            .map(|(n, _)| n.clone())
+                            .map(|(n, _)| *n)
                             .collect::<Vec<u16>>();
                         let mut completed = session
                             .finished
@@ -537,7 +604,7 @@ impl Service for ServiceHandler {
 
                 // Check we have valid group / session
                 let (_group, session) = get_group_session(
-                    &conn_id,
+                    conn_id,
                     &group_id,
                     &session_id,
                     &reader.groups,
@@ -588,10 +655,28 @@ impl Service for ServiceHandler {
                 Some(req.into())
             }
             NOTIFY_PROPOSAL => {
-                let (conn_id, _state, notification) = ctx;
+                let (conn_id, state, notification) = ctx;
                 let params: NotifyProposalParams = req.deserialize()?;
                 let (group_id, session_id, proposal_id, message) = params;
 
+                let reader = state.read().await;
+                get_group_session(
+                    conn_id,
+                    &group_id,
+                    &session_id,
+                    &reader.groups,
+                )?;
+                audit(
+                    &reader.audit,
+                    *conn_id,
+                    AuditEvent::NotifyProposal {
+                        group_id,
+                        session_id,
+                        proposal_id: proposal_id.clone(),
+                    },
+                )?;
+                drop(reader);
+
                 let proposal = Proposal {
                     session_id,
                     proposal_id,
@@ -624,16 +709,25 @@ impl Service for ServiceHandler {
                 let reader = state.read().await;
 
                 let (_group, session) = get_group_session(
-                    &conn_id,
+                    conn_id,
                     &group_id,
                     &session_id,
                     &reader.groups,
                 )?;
 
+                audit(
+                    &reader.audit,
+                    *conn_id,
+                    AuditEvent::NotifySigned {
+                        group_id,
+                        session_id,
+                    },
+                )?;
+
                 let participants = session
                     .party_signups
                     .iter()
-                    .map(|(_, c)| c.clone())
+                    .map(|(_, c)| *c)
                     .collect::<Vec<usize>>();
 
                 let value =
@@ -658,6 +752,19 @@ impl Service for ServiceHandler {
     }
 }
 
+/// Record a lifecycle event when an audit log is configured.
+fn audit(
+    log: &Option<Arc<AuditLog>>,
+    conn_id: usize,
+    event: AuditEvent,
+) -> Result<()> {
+    if let Some(log) = log {
+        log.append(conn_id, event)
+            .map_err(|e| Error::from(Box::from(e)))?;
+    }
+    Ok(())
+}
+
 fn get_group_mut<'a>(
     conn_id: &usize,
     group_id: &Uuid,
@@ -665,18 +772,17 @@ fn get_group_mut<'a>(
 ) -> Result<&'a mut Group> {
     if let Some(group) = groups.get_mut(group_id) {
         // Verify connection is part of the group clients
-        if let Some(_) = group.clients.iter().find(|c| *c == conn_id) {
+        if group.clients.contains(conn_id) {
             Ok(group)
         } else {
-            return Err(Error::from(Box::from(ServiceError::BadConnection(
-                *conn_id,
-                group_id.clone(),
-            ))));
+            Err(Error::from(Box::from(ServiceError::BadConnection(
+                *conn_id, *group_id,
+            ))))
         }
     } else {
-        return Err(Error::from(Box::from(ServiceError::GroupDoesNotExist(
-            group_id.clone(),
-        ))));
+        Err(Error::from(Box::from(ServiceError::GroupDoesNotExist(
+            *group_id,
+        ))))
     }
 }
 
@@ -687,18 +793,17 @@ fn get_group<'a>(
 ) -> Result<&'a Group> {
     if let Some(group) = groups.get(group_id) {
         // Verify connection is part of the group clients
-        if let Some(_) = group.clients.iter().find(|c| *c == conn_id) {
+        if group.clients.contains(conn_id) {
             Ok(group)
         } else {
-            return Err(Error::from(Box::from(ServiceError::BadConnection(
-                *conn_id,
-                group_id.clone(),
-            ))));
+            Err(Error::from(Box::from(ServiceError::BadConnection(
+                *conn_id, *group_id,
+            ))))
         }
     } else {
-        return Err(Error::from(Box::from(ServiceError::GroupDoesNotExist(
-            group_id.clone(),
-        ))));
+        Err(Error::from(Box::from(ServiceError::GroupDoesNotExist(
+            *group_id,
+        ))))
     }
 }
 
@@ -712,9 +817,9 @@ fn get_group_session<'a>(
     if let Some(session) = group.sessions.get(session_id) {
         Ok((group, session))
     } else {
-        return Err(Error::from(Box::from(ServiceError::SessionDoesNotExist(
-            session_id.clone(),
-        ))));
+        Err(Error::from(Box::from(ServiceError::SessionDoesNotExist(
+            *session_id,
+        ))))
     }
 }
 
@@ -722,13 +827,14 @@ fn get_group_session<'a>(
 fn threshold(
     kind: &SessionKind,
     params: &Parameters,
-    num_entries: usize,
+    session: &Session,
 ) -> bool {
     let parties = params.parties as usize;
     let threshold = params.threshold as usize;
     let required_num_entries = match kind {
-        SessionKind::Keygen => parties,
+        SessionKind::Keygen | SessionKind::Refresh => parties,
+        SessionKind::Reshare => parties + session.retiring() as usize,
         SessionKind::Sign => threshold + 1,
     };
-    num_entries == required_num_entries
+    session.party_signups.len() == required_num_entries
 }
@@ -0,0 +1,191 @@
+import { WebSocketClient } from './clients/websocket';
+
+import {
+  Round,
+  RoundBased,
+  StreamTransport,
+  SinkTransport,
+  onTransition as onTransitionLog,
+} from './round-based';
+import { getParticipants } from './sign';
+import {
+  Message,
+  SessionInfo,
+  EcdsaWorker,
+  GroupInfo,
+  PartySignup,
+  FrostCurve,
+  FrostKeyShare,
+  FrostKeyGenerator,
+  FrostSigner,
+  FrostSignature,
+} from '.';
+
+/**
+ * Starts the round-based processing to generate a FROST key share.
+ *
+ * @param worker - The worker implementation.
+ * @param stream - The stream for sending messages.
+ * @param sink - The sink for receiving messages.
+ * @param info - The session information.
+ * @param curve - The curve, either `secp256k1` (BIP340) or `ed25519`.
+ * @param onTransition - Optional transition handler.
+ */
+export async function generateFrostKeyShare(
+  worker: EcdsaWorker,
+  stream: StreamTransport,
+  sink: SinkTransport,
+  info: SessionInfo,
+  curve: FrostCurve,
+  onTransition?: (previousRound: string, current: string) => void,
+): Promise<FrostKeyShare> {
+  /* eslint-disable @typescript-eslint/no-explicit-any */
+  const keygen: FrostKeyGenerator = await new (
+    worker.FrostKeyGenerator as any
+  )(curve, info.parameters, info.partySignup);
+
+  const doTransition = (previousRound: string, current: string) => {
+    onTransitionLog(previousRound, current);
+    if (onTransition) {
+      onTransition(previousRound, current);
+    }
+  };
+
+  const standardTransition = async (
+    incoming: Message[],
+  ): Promise<[number, Message[]]> => {
+    for (const message of incoming) {
+      await keygen.handleIncoming(message);
+    }
+    return await keygen.proceed();
+  };
+
+  const rounds: Round[] = [
+    {
+      name: 'FROST_KEYGEN_ROUND_1',
+      transition: async (): Promise<[number, Message[]]> => {
+        return await keygen.proceed();
+      },
+    },
+    {
+      name: 'FROST_KEYGEN_ROUND_2',
+      transition: standardTransition,
+    },
+  ];
+
+  const finalizer = {
+    name: 'FROST_KEYGEN_FINALIZE',
+    finalize: async (incoming: Message[]) => {
+      await standardTransition(incoming);
+      return keygen.create();
+    },
+  };
+
+  const handler = new RoundBased<FrostKeyShare>(
+    rounds,
+    finalizer,
+    doTransition,
+    stream,
+    sink,
+  );
+  return handler.start();
+}
+
+/**
+ * Sign a message with a FROST key share.
+ *
+ * Signing takes two rounds after the participants are
+ * exchanged and does not require a pre-hashed message.
+ *
+ * @param websocket - The websocket client implementation.
+ * @param worker - The worker implementation.
+ * @param stream - The stream for sending messages.
+ * @param sink - The sink for receiving messages.
+ * @param message - The message to be signed.
+ * @param keyShare - The private key share.
+ * @param group - The group information.
+ * @param partySignup - The party signup information for the session.
+ * @param onTransition - Optional transition handler.
+ */
+export async function signFrost(
+  websocket: WebSocketClient,
+  worker: EcdsaWorker,
+  stream: StreamTransport,
+  sink: SinkTransport,
+  message: Uint8Array,
+  keyShare: FrostKeyShare,
+  group: GroupInfo,
+  partySignup: PartySignup,
+  onTransition?: (previousRound: string, current: string) => void,
+): Promise<FrostSignature> {
+  const info = {
+    groupId: group.uuid,
+    sessionId: partySignup.uuid,
+    parameters: group.params,
+    partySignup,
+  };
+
+  const doTransition = (previousRound: string, current: string) => {
+    onTransitionLog(previousRound, current);
+    if (onTransition) {
+      onTransition(previousRound, current);
+    }
+  };
+
+  const participants = await getParticipants(
+    info,
+    keyShare,
+    stream,
+    sink,
+    doTransition,
+  );
+
+  /* eslint-disable @typescript-eslint/no-explicit-any */
+  const signer: FrostSigner = await new (worker.FrostSigner as any)(
+    info.partySignup.number,
+    participants,
+    keyShare,
+    Array.from(message),
+  );
+
+  const standardTransition = async (
+    incoming: Message[],
+  ): Promise<[number, Message[]]> => {
+    for (const msg of incoming) {
+      await signer.handleIncoming(msg);
+    }
+    return await signer.proceed();
+  };
+
+  const rounds: Round[] = [
+    {
+      name: 'FROST_SIGN_ROUND_1',
+      transition: async (): Promise<[number, Message[]]> => {
+        return await signer.proceed();
+      },
+    },
+    {
+      name: 'FROST_SIGN_ROUND_2',
+      transition: standardTransition,
+    },
+  ];
+
+  const finalizer = {
+    name: 'FROST_SIGN_FINALIZE',
+    finalize: async (incoming: Message[]) => {
+      await standardTransition(incoming);
+      return signer.create();
+    },
+  };
+
+  const handler = new RoundBased<FrostSignature>(
+    rounds,
+    finalizer,
+    doTransition,
+    stream,
+    sink,
+  );
+  const signature = await handler.start();
+  websocket.removeAllListeners('sessionMessage');
+  return signature;
+}
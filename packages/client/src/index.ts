@@ -1,8 +1,29 @@
-import { KeyGenerator, Signer } from '@metamask/mpc-ecdsa-wasm';
+import {
+  BatchSigner,
+  FrostKeyGenerator,
+  FrostSigner,
+  KeyGenerator,
+  KeyRefresher,
+  KeyResharer,
+  PresignaturePool,
+  Signer,
+} from '@metamask/mpc-ecdsa-wasm';
 
-export { KeyGenerator, Signer } from '@metamask/mpc-ecdsa-wasm';
+export {
+  BatchSigner,
+  FrostKeyGenerator,
+  FrostSigner,
+  KeyGenerator,
+  KeyRefresher,
+  KeyResharer,
+  PresignaturePool,
+  Signer,
+} from '@metamask/mpc-ecdsa-wasm';
 
+export * from './frost';
 export * from './keygen';
+export * from './refresh';
+export * from './reshare';
 export * from './sign';
 export * from './transports/websocket';
 export * from './clients/websocket';
@@ -11,20 +32,140 @@ export { StreamTransport, SinkTransport } from './round-based';
 export enum SessionKind {
   KEYGEN = 'keygen',
   SIGN = 'sign',
+  REFRESH = 'refresh',
+  RESHARE = 'reshare',
 }
 
 export type EcdsaWorker = {
   KeyGenerator(
     parameters: Parameters,
     partySignup: PartySignup,
+    onProgress?: (event: KeygenEvent) => void,
   ): Promise<KeyGenerator>;
 
   Signer(
     index: number,
     participants: number[],
-    localKey: LocalKey,
+    keyShare: KeyShare,
+    path?: string,
   ): Promise<Signer>;
 
+  // Messages are 32 byte digests wrapped using `Array.from`.
+  BatchSigner(
+    index: number,
+    participants: number[],
+    keyShare: KeyShare,
+    messages: number[][],
+    path?: string,
+  ): Promise<BatchSigner>;
+
+  KeyRefresher(keyShare: KeyShare): Promise<KeyRefresher>;
+
+  // Key is the 32 byte encryption key for the presignatures,
+  // used is the used set persisted by the holder of the key
+  // share and state is a value returned by `export()`.
+  PresignaturePool(
+    key: number[],
+    used: UsedSet,
+    state?: PresignaturePoolState,
+  ): Promise<PresignaturePool>;
+
+  KeyResharer(
+    parameters: Parameters,
+    partySignup: PartySignup,
+    key: ReshareKey,
+    keyShare?: KeyShare,
+  ): Promise<KeyResharer>;
+
+  // Curve is either `secp256k1` (BIP340) or `ed25519`.
+  FrostKeyGenerator(
+    curve: FrostCurve,
+    parameters: Parameters,
+    partySignup: PartySignup,
+  ): Promise<FrostKeyGenerator>;
+
+  // Message is wrapped using `Array.from`; for BIP340 it is
+  // usually a 32 byte hash, Ed25519 signs the message directly.
+  FrostSigner(
+    index: number,
+    participants: number[],
+    keyShare: FrostKeyShare,
+    message: number[],
+  ): Promise<FrostSigner>;
+
+  // Check a key share is intact before it is used for signing.
+  verifyKeyShare(keyShare: KeyShare): Promise<KeyShareReport>;
+
+  // Derive a child public key using a non-hardened
+  // derivation path (eg: `m/0/1`); the key share must
+  // have a chain code.
+  deriveChildKey(keyShare: KeyShare, path: string): Promise<ChildKey>;
+
+  // Encode a signature as bytes; defaults to a 65 byte
+  // Ethereum signature with a low `s` value.
+  encodeSignature(
+    signature: SignResult,
+    options?: EncodeOptions,
+  ): Promise<number[]>;
+
+  // EIP-191 `personal_sign` digest to pass to `Signer.partial()`;
+  // message is wrapped using `Array.from`.
+  hashMessage(message: number[]): Promise<number[]>;
+
+  // EIP-712 digest to pass to `Signer.partial()`.
+  hashTypedData(typedData: TypedData): Promise<number[]>;
+
+  // Bitcoin address for the public key of a key share.
+  bitcoinAddress(
+    publicKey: number[],
+    options?: BitcoinAddressOptions,
+  ): Promise<string>;
+
+  // Hash to pass to `Signer.partial()` for a PSBT input;
+  // the PSBT is the binary encoding wrapped using `Array.from`.
+  psbtSighash(psbt: number[], index: number): Promise<number[]>;
+
+  // Insert a signature for a PSBT input and return the updated PSBT;
+  // fails unless the public key can spend the input and the
+  // signature verifies for the input sighash.
+  psbtInsertSignature(
+    psbt: number[],
+    index: number,
+    publicKey: number[],
+    signature: SignResult,
+  ): Promise<number[]>;
+
+  // Hash of a transaction to pass to `Signer.partial()`.
+  transactionHash(transaction: Transaction): Promise<number[]>;
+
+  // Assemble the raw signed transaction for broadcast.
+  signTransaction(
+    transaction: Transaction,
+    signature: SignResult,
+  ): Promise<SignedTransaction>;
+
+  // Export the public key of a key share; returns a hex string
+  // for `compressed`, DER bytes for `spki`, a string for `pem`
+  // and an object for `jwk`.
+  exportPublicKey(
+    publicKey: number[],
+    format: PublicKeyFormat,
+  ): Promise<string | number[] | Jwk>;
+
+  // Parse an exported public key into the 65 byte uncompressed
+  // SEC1 encoding used by key shares.
+  importPublicKey(
+    value: string | number[] | Jwk,
+    format: PublicKeyFormat,
+  ): Promise<number[]>;
+
+  // Ethereum address with an EIP-55 checksum for a public key.
+  checksumAddress(publicKey: number[]): Promise<string>;
+
+  // Validate an Ethereum address (the checksum is verified for
+  // mixed case) and return the EIP-55 checksum encoding.
+  parseAddress(address: string): Promise<string>;
+
   // Value is a `Uint8Array` wrapped into a sequence
   // using `Array.from` so it is deserialized correctly.
   keccak256(value: number[]): Promise<Uint8Array>;
@@ -56,11 +197,66 @@ export type Parameters = {
   threshold: number;
 };
 
+// Expensive step of key generation.
+export type KeygenStep = 'paillierKeys' | 'verifyProofs' | 'round';
+
+// Event emitted before and after each step of key generation.
+export type KeygenEvent = {
+  step: KeygenStep;
+  // Whether the step has completed.
+  done: boolean;
+  // Round the step proceeds from.
+  round: number;
+  totalRounds: number;
+};
+
+// Progress of key generation returned by `progress()`.
+export type KeygenProgress = {
+  round: number;
+  totalRounds: number;
+  // Messages received and expected for the current round.
+  received: number;
+  expected: number;
+  isFinished: boolean;
+  wantsToProceed: boolean;
+};
+
 // Private key share for GG2020.
 export type KeyShare = {
+  // Version of the key share format, key shares created
+  // before the format was versioned do not include it.
+  version?: number;
   localKey: LocalKey;
   publicKey: number[];
   address: string;
+  // Secret chain code for child key derivation generated
+  // with the key share; not in the first version.
+  chainCode?: number[];
+};
+
+// Key being reshared to a new committee; every member of
+// the new committee must know it before resharing starts.
+export type ReshareKey = {
+  // Uncompressed public key.
+  publicKey: number[];
+  // Threshold of the existing key shares.
+  threshold: number;
+  // Number of dealers that are leaving the committee; they sign
+  // up to the session after the members of the new committee.
+  retiring?: number;
+};
+
+// Result of a check performed by `verifyKeyShare()`.
+export type KeyShareCheck = {
+  name: string;
+  passed: boolean;
+  errors: string[];
+};
+
+// Report returned by `verifyKeyShare()`.
+export type KeyShareReport = {
+  valid: boolean;
+  checks: KeyShareCheck[];
 };
 
 // Opaque type for the private key share.
@@ -73,6 +269,50 @@ export type LocalKey = {
   n: number;
 };
 
+// Curve for FROST key shares.
+export type FrostCurve = 'secp256k1' | 'ed25519';
+
+// Private key share for FROST.
+export type FrostKeyShare = {
+  localKey: LocalKey & { curve: FrostCurve };
+  // The 32 byte x-only key for BIP340 or the Ed25519 key.
+  publicKey: number[];
+};
+
+// Signature generated by FROST; the 64 byte signature verifies
+// with any BIP340 or Ed25519 implementation.
+export type FrostSignature = {
+  curve: FrostCurve;
+  signature: number[];
+  publicKey: number[];
+};
+
+// Value encrypted by the webassembly module; used for
+// presignatures.
+export type EncryptedBlob = {
+  id: string;
+  nonce: number[];
+  ciphertext: number[];
+};
+
+// Persisted state of a presignature pool.
+export type PresignaturePoolState = {
+  presignatures: EncryptedBlob[];
+};
+
+// Identifiers of consumed presignatures; owned by the holder of
+// the key share, it only grows and must be persisted every time
+// it changes, never replaced with an earlier copy.
+export type UsedSet = string[];
+
+// Child public key derived from a key share.
+export type ChildKey = {
+  path: string;
+  publicKey: number[];
+  address: string;
+  chainCode: number[];
+};
+
 // Generated by the server to signal this party wants
 // to be included in key generation.
 //
@@ -105,6 +345,104 @@ export type SessionInfo = {
   partySignup: PartySignup;
 };
 
+// Thrown by the webassembly module when a protocol round fails.
+//
+// Culprits are the parties to blame for the failure; party
+// numbers for key generation and the key share indices of the
+// participants for signing. Empty when the failure cannot be
+// attributed to a party.
+export type ProtocolError = {
+  round: number;
+  kind: string;
+  culprits: number[];
+  message: string;
+};
+
+// Options for encoding a signature.
+export type EncodeOptions = {
+  // Either `rsv` (default), `compact` or `der`.
+  format?: 'rsv' | 'compact' | 'der';
+  // Chain identifier for an EIP-155 `v` value.
+  chainId?: number;
+  // Normalize to a low `s` value, defaults to `true`.
+  lowS?: boolean;
+};
+
+// Formats for exporting a public key.
+export type PublicKeyFormat = 'compressed' | 'spki' | 'pem' | 'jwk';
+
+// JSON Web Key for a secp256k1 public key.
+export type Jwk = {
+  kty: 'EC';
+  crv: 'secp256k1';
+  x: string;
+  y: string;
+};
+
+// Options for deriving a Bitcoin address.
+export type BitcoinAddressOptions = {
+  // Either `p2wpkh` (default) or `p2pkh`.
+  type?: 'p2pkh' | 'p2wpkh';
+  // Either `bitcoin` (default), `testnet` or `regtest`.
+  network?: 'bitcoin' | 'testnet' | 'regtest';
+};
+
+// Unsigned integer as a number, decimal string or
+// `0x` prefixed hex string.
+export type Quantity = number | string;
+
+export type AccessListItem = {
+  address: string;
+  storageKeys: string[];
+};
+
+// Fields shared by all transaction types; `to` is omitted
+// for contract creation and `data` is a hex string.
+type TransactionFields = {
+  nonce: Quantity;
+  gasLimit: Quantity;
+  to?: string;
+  value?: Quantity;
+  data?: string;
+};
+
+// Ethereum transaction; a legacy transaction uses EIP-155
+// replay protection when `chainId` is given.
+export type Transaction =
+  | (TransactionFields & {
+      type: 'legacy';
+      chainId?: Quantity;
+      gasPrice: Quantity;
+    })
+  | (TransactionFields & {
+      type: 'eip2930';
+      chainId: Quantity;
+      gasPrice: Quantity;
+      accessList?: AccessListItem[];
+    })
+  | (TransactionFields & {
+      type: 'eip1559';
+      chainId: Quantity;
+      maxPriorityFeePerGas: Quantity;
+      maxFeePerGas: Quantity;
+      accessList?: AccessListItem[];
+    });
+
+// Raw signed transaction and its hash as hex strings.
+export type SignedTransaction = {
+  raw: string;
+  hash: string;
+};
+
+// Typed structured data for EIP-712 in the format
+// used by `eth_signTypedData_v4`.
+export type TypedData = {
+  types: Record<string, { name: string; type: string }[]>;
+  primaryType: string;
+  domain: Record<string, any>;
+  message: Record<string, any>;
+};
+
 // Result of signing a message.
 export type SignResult = {
   r: SignValue;
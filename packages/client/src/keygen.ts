@@ -1,3 +1,4 @@
+import * as Comlink from 'comlink';
 import {
   Round,
   RoundBased,
@@ -5,7 +6,14 @@ import {
   SinkTransport,
   onTransition as onTransitionLog,
 } from './round-based';
-import { Message, KeyShare, SessionInfo, EcdsaWorker, KeyGenerator } from '.';
+import {
+  Message,
+  KeyShare,
+  KeygenEvent,
+  SessionInfo,
+  EcdsaWorker,
+  KeyGenerator,
+} from '.';
 
 /**
  * Starts the round-based processing to generate a key share.
@@ -15,6 +23,8 @@ import { Message, KeyShare, SessionInfo, EcdsaWorker, KeyGenerator } from '.';
  * @param sink - The sink for receiving messages.
  * @param info - The session information.
  * @param onTransition - Optional transition handler.
+ * @param onProgress - Optional handler for progress events from
+ * the expensive steps such as Paillier key generation.
  */
 export async function generateKeyShare(
   worker: EcdsaWorker,
@@ -22,11 +32,14 @@ export async function generateKeyShare(
   sink: SinkTransport,
   info: SessionInfo,
   onTransition?: (previousRound: string, current: string) => void,
+  onProgress?: (event: KeygenEvent) => void,
 ): Promise<KeyShare> {
   /* eslint-disable @typescript-eslint/no-explicit-any */
   const keygen: KeyGenerator = await new (worker.KeyGenerator as any)(
     info.parameters,
     info.partySignup,
+    // Functions must be proxied to be called from the worker
+    onProgress ? Comlink.proxy(onProgress) : undefined,
   );
 
   const doTransition = (previousRound: string, current: string) => {
@@ -66,6 +79,16 @@ export async function generateKeyShare(
       name: 'KEYGEN_ROUND_4',
       transition: standardTransition,
     },
+    // Chain code contributions
+    {
+      name: 'KEYGEN_ROUND_5',
+      transition: standardTransition,
+    },
+    // Chain code confirmations
+    {
+      name: 'KEYGEN_ROUND_6',
+      transition: standardTransition,
+    },
   ];
 
   const finalizer = {
@@ -0,0 +1,87 @@
+import {
+  Round,
+  RoundBased,
+  StreamTransport,
+  SinkTransport,
+  onTransition as onTransitionLog,
+} from './round-based';
+import { Message, KeyShare, EcdsaWorker, KeyRefresher } from '.';
+
+/**
+ * Starts the round-based processing to refresh a key share.
+ *
+ * Every party in the group must take part and the party signup
+ * number must match the index of the key share. The public key
+ * and address are unchanged; the previous key share must be
+ * discarded once the refreshed key share has been saved.
+ *
+ * @param worker - The worker implementation.
+ * @param stream - The stream for sending messages.
+ * @param sink - The sink for receiving messages.
+ * @param keyShare - The key share to refresh.
+ * @param onTransition - Optional transition handler.
+ */
+export async function refreshKeyShare(
+  worker: EcdsaWorker,
+  stream: StreamTransport,
+  sink: SinkTransport,
+  keyShare: KeyShare,
+  onTransition?: (previousRound: string, current: string) => void,
+): Promise<KeyShare> {
+  /* eslint-disable @typescript-eslint/no-explicit-any */
+  const refresher: KeyRefresher = await new (worker.KeyRefresher as any)(
+    keyShare,
+  );
+
+  const doTransition = (previousRound: string, current: string) => {
+    // Call standard onTransition for logs
+    onTransitionLog(previousRound, current);
+    // Call custom handler for UI progress updates
+    if (onTransition) {
+      onTransition(previousRound, current);
+    }
+  };
+
+  const standardTransition = async (
+    incoming: Message[],
+  ): Promise<[number, Message[]]> => {
+    for (const message of incoming) {
+      await refresher.handleIncoming(message);
+    }
+    return await refresher.proceed();
+  };
+
+  const rounds: Round[] = [
+    {
+      name: 'REFRESH_ROUND_1',
+      transition: async (): Promise<[number, Message[]]> => {
+        return await refresher.proceed();
+      },
+    },
+    {
+      name: 'REFRESH_ROUND_2',
+      transition: standardTransition,
+    },
+    {
+      name: 'REFRESH_ROUND_3',
+      transition: standardTransition,
+    },
+  ];
+
+  const finalizer = {
+    name: 'REFRESH_FINALIZE',
+    finalize: async (incoming: Message[]) => {
+      await standardTransition(incoming);
+      return refresher.create();
+    },
+  };
+
+  const handler = new RoundBased<KeyShare>(
+    rounds,
+    finalizer,
+    doTransition,
+    stream,
+    sink,
+  );
+  return handler.start();
+}
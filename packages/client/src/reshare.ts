@@ -0,0 +1,107 @@
+import {
+  Round,
+  RoundBased,
+  StreamTransport,
+  SinkTransport,
+  onTransition as onTransitionLog,
+} from './round-based';
+import {
+  Message,
+  KeyShare,
+  ReshareKey,
+  SessionInfo,
+  EcdsaWorker,
+  KeyResharer,
+} from '.';
+
+/**
+ * Starts the round-based processing to reshare a key to a new committee.
+ *
+ * The session must belong to a group created with the parameters
+ * for the new committee and every member of the new committee must
+ * take part. At least `t + 1` parties must provide their existing
+ * key share; the public key and address are unchanged. Dealers that
+ * are leaving the committee sign up after the members, set
+ * `key.retiring` and receive `null` instead of a key share.
+ *
+ * Existing key shares remain valid after resharing so they
+ * must be deleted by their holders.
+ *
+ * @param worker - The worker implementation.
+ * @param stream - The stream for sending messages.
+ * @param sink - The sink for receiving messages.
+ * @param info - The session information for the new committee.
+ * @param key - The public key being reshared and the threshold of
+ * the existing key shares.
+ * @param keyShare - Existing key share when this party is a dealer.
+ * @param onTransition - Optional transition handler.
+ */
+export async function reshareKeyShare(
+  worker: EcdsaWorker,
+  stream: StreamTransport,
+  sink: SinkTransport,
+  info: SessionInfo,
+  key: ReshareKey,
+  keyShare?: KeyShare,
+  onTransition?: (previousRound: string, current: string) => void,
+): Promise<KeyShare | null> {
+  /* eslint-disable @typescript-eslint/no-explicit-any */
+  const resharer: KeyResharer = await new (worker.KeyResharer as any)(
+    info.parameters,
+    info.partySignup,
+    key,
+    keyShare || null,
+  );
+
+  const doTransition = (previousRound: string, current: string) => {
+    // Call standard onTransition for logs
+    onTransitionLog(previousRound, current);
+    // Call custom handler for UI progress updates
+    if (onTransition) {
+      onTransition(previousRound, current);
+    }
+  };
+
+  const standardTransition = async (
+    incoming: Message[],
+  ): Promise<[number, Message[]]> => {
+    for (const message of incoming) {
+      await resharer.handleIncoming(message);
+    }
+    return await resharer.proceed();
+  };
+
+  const rounds: Round[] = [
+    {
+      name: 'RESHARE_ROUND_1',
+      transition: async (): Promise<[number, Message[]]> => {
+        return await resharer.proceed();
+      },
+    },
+    {
+      name: 'RESHARE_ROUND_2',
+      transition: standardTransition,
+    },
+  ];
+
+  // Dealers that are leaving the committee sign up after the
+  // members and do not receive a key share
+  const retiring = info.partySignup.number > info.parameters.parties;
+
+  const finalizer = {
+    name: 'RESHARE_FINALIZE',
+    finalize: async (incoming: Message[]) => {
+      await standardTransition(incoming);
+      return retiring ? null : resharer.create();
+    },
+  };
+
+  const handler = new RoundBased<KeyShare | null>(
+    rounds,
+    finalizer,
+    doTransition,
+    stream,
+    sink,
+  );
+  return handler.start();
+}
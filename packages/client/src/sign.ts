@@ -15,7 +15,12 @@ import {
   PartySignup,
   EcdsaWorker,
   Signer,
+  BatchSigner,
+  PresignaturePool,
+  UsedSet,
+  EncryptedBlob,
   GroupInfo,
+  FrostKeyShare,
 } from '.';
 
 /**
@@ -31,9 +36,9 @@ import {
  * @param sink - The sink for receiving messages.
  * @param onTransition - Transition handler.
  */
-async function getParticipants(
+export async function getParticipants(
   info: SessionInfo,
-  keyShare: KeyShare,
+  keyShare: KeyShare | FrostKeyShare,
   stream: StreamTransport,
   sink: SinkTransport,
   onTransition: (previousRound: string, current: string) => void,
@@ -106,7 +111,7 @@ async function getParticipants(
  * @param onTransition - Transition handler.
  */
 async function offlineStage(
-  signer: Signer,
+  signer: Signer | BatchSigner,
   stream: StreamTransport,
   sink: SinkTransport,
   onTransition: (previousRound: string, current: string) => void,
@@ -168,6 +173,13 @@ async function offlineStage(
   return handler.start();
 }
 
+// Online step of signing; either a signer that has completed
+// the offline stage or a presignature from a pool.
+type OnlineSigner = {
+  partial(message: number[]): Promise<any>;
+  create(partials: any[]): Promise<SignMessage>;
+};
+
 /**
  * Process the partial signatures to generate a complete signature.
  *
@@ -179,7 +191,7 @@ async function offlineStage(
  * @param onTransition - Transition handler.
  */
 async function partialSignature(
-  signer: Signer,
+  signer: OnlineSigner,
   info: SessionInfo,
   message: Uint8Array,
   stream: StreamTransport,
@@ -237,6 +249,7 @@ async function partialSignature(
  * @param keyShare - The private key share.
  * @param message - The message to be signed.
  * @param onTransition - Transition handler.
+ * @param path - Non-hardened derivation path for a child key.
  */
 async function signMessage(
   websocket: WebSocketClient,
@@ -247,6 +260,7 @@ async function signMessage(
   keyShare: KeyShare,
   message: Uint8Array,
   onTransition: (previousRound: string, current: string) => void,
+  path?: string,
 ): Promise<SignMessage> {
   const participants = await getParticipants(
     info,
@@ -260,7 +274,8 @@ async function signMessage(
   const signer: Signer = await new (worker.Signer as any)(
     info.partySignup.number,
     participants,
-    keyShare.localKey,
+    keyShare,
+    path,
   );
 
   await offlineStage(signer, stream, sink, onTransition);
@@ -289,6 +304,8 @@ async function signMessage(
  * @param group - The group information.
  * @param partySignup - The party signup information for the session.
  * @param onTransition - Optional transition handler.
+ * @param path - Optional non-hardened derivation path (eg: `m/0/1`)
+ * to sign using a child key.
  */
 export async function sign(
   websocket: WebSocketClient,
@@ -300,6 +317,7 @@ export async function sign(
   group: GroupInfo,
   partySignup: PartySignup,
   onTransition?: (previousRound: string, current: string) => void,
+  path?: string,
 ): Promise<SignMessage> {
   const sessionInfo = {
     groupId: group.uuid,
@@ -326,7 +344,241 @@ export async function sign(
     keyShare,
     message,
     doTransition,
+    path,
   );
 
   return signedMessage;
 }
+
+/**
+ * Sign a batch of messages in one session.
+ *
+ * An offline stage is run for every message over the same
+ * participants; the signatures are in the same order as
+ * the messages.
+ *
+ * @param websocket - The websocket client implementation.
+ * @param worker - The worker implementation.
+ * @param stream - The stream for sending messages.
+ * @param sink - The sink for receiving messages.
+ * @param messages - The messages to be signed; each must be a pre-hashed 32 byte array.
+ * @param keyShare - The private key share.
+ * @param group - The group information.
+ * @param partySignup - The party signup information for the session.
+ * @param onTransition - Optional transition handler.
+ * @param path - Optional non-hardened derivation path (eg: `m/0/1`)
+ * to sign using a child key.
+ */
+export async function signBatch(
+  websocket: WebSocketClient,
+  worker: EcdsaWorker,
+  stream: StreamTransport,
+  sink: SinkTransport,
+  messages: Uint8Array[],
+  keyShare: KeyShare,
+  group: GroupInfo,
+  partySignup: PartySignup,
+  onTransition?: (previousRound: string, current: string) => void,
+  path?: string,
+): Promise<SignMessage[]> {
+  const info = {
+    groupId: group.uuid,
+    sessionId: partySignup.uuid,
+    parameters: group.params,
+    partySignup,
+  };
+
+  const doTransition = (previousRound: string, current: string) => {
+    onTransitionLog(previousRound, current);
+    if (onTransition) {
+      onTransition(previousRound, current);
+    }
+  };
+
+  const participants = await getParticipants(
+    info,
+    keyShare,
+    stream,
+    sink,
+    doTransition,
+  );
+
+  /* eslint-disable @typescript-eslint/no-explicit-any */
+  const signer: BatchSigner = await new (worker.BatchSigner as any)(
+    info.partySignup.number,
+    participants,
+    keyShare,
+    messages.map((message) => Array.from(message)),
+    path,
+  );
+
+  await offlineStage(signer, stream, sink, doTransition);
+
+  const rounds: Round[] = [
+    {
+      name: 'SIGN_ROUND_8',
+      transition: async (): Promise<[number, Message[]]> => {
+        const partials = await signer.partial();
+        const round = 8;
+        const partialMessage: Message = {
+          round,
+          uuid: info.sessionId,
+          sender: info.partySignup.number,
+          receiver: null,
+          body: partials,
+        };
+
+        return [round, [partialMessage]];
+      },
+    },
+  ];
+
+  const finalizer = {
+    name: 'SIGN_PARTIAL',
+    finalize: async (incoming: Message[]) => {
+      const partials = incoming.map((msg) => msg.body);
+      return await signer.create(partials);
+    },
+  };
+
+  const handler = new RoundBased<SignMessage[]>(
+    rounds,
+    finalizer,
+    doTransition,
+    stream,
+    sink,
+  );
+
+  const signed = await handler.start();
+  websocket.removeAllListeners('sessionMessage');
+  return signed;
+}
+
+/**
+ * Run the offline stage ahead of time and export the result as an
+ * encrypted presignature.
+ *
+ * The session identifier is used as the presignature identifier
+ * so every participant stores the presignature under the same
+ * identifier; the same participants must take part in the online
+ * step using `signWithPresignature()`.
+ *
+ * @param websocket - The websocket client implementation.
+ * @param worker - The worker implementation.
+ * @param stream - The stream for sending messages.
+ * @param sink - The sink for receiving messages.
+ * @param keyShare - The private key share.
+ * @param group - The group information.
+ * @param partySignup - The party signup information for the session.
+ * @param key - The 32 byte key used to encrypt the presignature.
+ * @param onTransition - Optional transition handler.
+ */
+export async function presign(
+  websocket: WebSocketClient,
+  worker: EcdsaWorker,
+  stream: StreamTransport,
+  sink: SinkTransport,
+  keyShare: KeyShare,
+  group: GroupInfo,
+  partySignup: PartySignup,
+  key: number[],
+  onTransition?: (previousRound: string, current: string) => void,
+): Promise<EncryptedBlob> {
+  const info = {
+    groupId: group.uuid,
+    sessionId: partySignup.uuid,
+    parameters: group.params,
+    partySignup,
+  };
+
+  const doTransition = (previousRound: string, current: string) => {
+    onTransitionLog(previousRound, current);
+    if (onTransition) {
+      onTransition(previousRound, current);
+    }
+  };
+
+  const participants = await getParticipants(
+    info,
+    keyShare,
+    stream,
+    sink,
+    doTransition,
+  );
+
+  /* eslint-disable @typescript-eslint/no-explicit-any */
+  const signer: Signer = await new (worker.Signer as any)(
+    info.partySignup.number,
+    participants,
+    keyShare,
+  );
+
+  await offlineStage(signer, stream, sink, doTransition);
+  websocket.removeAllListeners('sessionMessage');
+  return await signer.presignature(info.sessionId, key);
+}
+
+/**
+ * Sign a message using a presignature from a pool.
+ *
+ * The presignature is reserved and the updated used set is given
+ * to `persistUsed()` which must store it before the partial
+ * signature is created; the pool and any pool created with the
+ * used set refuse to reuse a presignature.
+ *
+ * @param websocket - The websocket client implementation.
+ * @param pool - The presignature pool.
+ * @param id - The presignature identifier.
+ * @param persistUsed - Persists the used set of the holder.
+ * @param stream - The stream for sending messages.
+ * @param sink - The sink for receiving messages.
+ * @param message - The message to be signed; must be a pre-hashed 32 byte array.
+ * @param group - The group information.
+ * @param partySignup - The party signup information for the session.
+ * @param onTransition - Optional transition handler.
+ */
+export async function signWithPresignature(
+  websocket: WebSocketClient,
+  pool: PresignaturePool,
+  id: string,
+  persistUsed: (used: UsedSet) => Promise<void>,
+  stream: StreamTransport,
+  sink: SinkTransport,
+  message: Uint8Array,
+  group: GroupInfo,
+  partySignup: PartySignup,
+  onTransition?: (previousRound: string, current: string) => void,
+): Promise<SignMessage> {
+  const info = {
+    groupId: group.uuid,
+    sessionId: partySignup.uuid,
+    parameters: group.params,
+    partySignup,
+  };
+
+  const doTransition = (previousRound: string, current: string) => {
+    onTransitionLog(previousRound, current);
+    if (onTransition) {
+      onTransition(previousRound, current);
+    }
+  };
+
+  const signer: OnlineSigner = {
+    partial: async (value: number[]) => {
+      await persistUsed(await pool.reserve(id));
+      return await pool.partial(id, value);
+    },
+    create: (partials: any[]) => pool.create(id, partials),
+  };
+
+  const signed = await partialSignature(
+    signer,
+    info,
+    message,
+    stream,
+    sink,
+    doTransition,
+  );
+  websocket.removeAllListeners('sessionMessage');
+  return signed;
+}
@@ -0,0 +1,421 @@
+//! Sign several messages in one session.
+//!
+//! A completed offline stage can only sign a single message so a
+//! batch runs one offline stage for every message; the stages share
+//! the participants and advance together so each round sends one
+//! message per receiver whose body holds the messages for every
+//! stage in order. The stages are processed in parallel when a
+//! thread pool is available.
+use curv::{arithmetic::Converter, elliptic::curves::Secp256k1, BigInt};
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::{
+    keygen::LocalKey,
+    sign::{
+        CompletedOfflineStage, OfflineProtocolMessage, OfflineStage, PartialSignature, SignManual,
+    },
+};
+use rayon::prelude::*;
+use round_based::{Msg, StateMachine};
+use std::collections::BTreeMap;
+
+use crate::{
+    complete, sign::ROUNDS, Error, ProtocolError, Result, RoundMsg, Signature, ONLINE_ROUND,
+};
+
+/// Kind of error for a batch message that is malformed.
+const INVALID_BATCH: &str = "InvalidBatch";
+
+/// Round-based signing protocol for a batch of messages.
+pub struct BatchSigner {
+    participants: Vec<u16>,
+    messages: Vec<BigInt>,
+    stages: Vec<OfflineStage>,
+    completed: Option<Vec<CompletedOfflineStage>>,
+    failed: Option<u16>,
+}
+
+impl BatchSigner {
+    /// Create a signer for a batch of 32 byte message digests.
+    ///
+    /// The index is the party number for the signing session
+    /// and participants are the key share indices of the parties
+    /// ordered by party number.
+    pub fn new(
+        index: u16,
+        participants: Vec<u16>,
+        local_key: LocalKey<Secp256k1>,
+        messages: &[[u8; 32]],
+    ) -> Result<Self> {
+        if messages.is_empty() {
+            return Err(Error::EmptyBatch);
+        }
+        let messages = messages
+            .iter()
+            .map(|message| BigInt::from_bytes(message))
+            .collect::<Vec<_>>();
+        let stages = messages
+            .iter()
+            .map(|_| OfflineStage::new(index, participants.clone(), local_key.clone()))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(Self {
+            participants,
+            messages,
+            stages,
+            completed: None,
+            failed: None,
+        })
+    }
+
+    /// Handle an incoming message.
+    ///
+    /// The message is checked before it is given to the offline
+    /// stages; if a stage rejects a body after another stage has
+    /// accepted one the stages have diverged and the batch fails.
+    pub fn handle_incoming(
+        &mut self,
+        message: RoundMsg<Vec<OfflineProtocolMessage>>,
+    ) -> Result<()> {
+        self.check()?;
+        let round = self.current_round()?;
+        if message.body.len() != self.stages.len() {
+            return Err(self.invalid(
+                round,
+                message.sender,
+                format!(
+                    "expected {} messages from party {}, got {}",
+                    self.stages.len(),
+                    message.sender,
+                    message.body.len()
+                ),
+            ));
+        }
+        if message.round < round || message.round > ROUNDS {
+            return Err(self.invalid(
+                round,
+                message.sender,
+                format!(
+                    "message from party {} is for round {} in round {}",
+                    message.sender, message.round, round
+                ),
+            ));
+        }
+
+        let (sender, receiver) = (message.sender, message.receiver);
+        let participants = &self.participants;
+        let mut bodies = message.body.into_iter();
+        let (first, rest) = self.stages.split_first_mut().unwrap();
+        first
+            .handle_incoming(Msg {
+                sender,
+                receiver,
+                body: bodies.next().unwrap(),
+            })
+            .map_err(|e| ProtocolError::signing(round, &e, participants))?;
+        let result = rest
+            .par_iter_mut()
+            .zip(bodies.collect::<Vec<_>>().into_par_iter())
+            .map(|(stage, body)| {
+                stage.handle_incoming(Msg {
+                    sender,
+                    receiver,
+                    body,
+                })
+            })
+            .collect::<std::result::Result<Vec<_>, _>>();
+        if let Err(e) = result {
+            self.failed = Some(round);
+            return Err(ProtocolError::signing(round, &e, &self.participants).into());
+        }
+        Ok(())
+    }
+
+    /// Proceed to the next round and return the round number
+    /// with the messages to send to the other parties.
+    ///
+    /// Returns `None` when the offline stages are waiting
+    /// for more messages.
+    pub fn proceed(&mut self) -> Result<Option<(u16, Vec<RoundMsg<Vec<OfflineProtocolMessage>>>)>> {
+        self.check()?;
+        let round = self.current_round()?;
+        if !self.stages.iter().all(|stage| stage.wants_to_proceed()) {
+            return Ok(None);
+        }
+        let queues = self
+            .stages
+            .par_iter_mut()
+            .map(|stage| {
+                stage.proceed()?;
+                Ok(stage.message_queue().drain(..).collect::<Vec<_>>())
+            })
+            .collect::<std::result::Result<Vec<_>, <OfflineStage as StateMachine>::Err>>();
+        let queues = match queues {
+            Ok(queues) => queues,
+            Err(e) => {
+                self.failed = Some(round);
+                return Err(ProtocolError::signing(round, &e, &self.participants).into());
+            }
+        };
+
+        let mut bodies: BTreeMap<(u16, Option<u16>), Vec<OfflineProtocolMessage>> = BTreeMap::new();
+        for queue in queues {
+            for message in queue {
+                bodies
+                    .entry((message.sender, message.receiver))
+                    .or_default()
+                    .push(message.body);
+            }
+        }
+
+        let round = self.current_round()?;
+        let messages = bodies
+            .into_iter()
+            .map(|((sender, receiver), body)| RoundMsg {
+                round,
+                sender,
+                receiver,
+                body,
+            })
+            .collect::<Vec<_>>();
+        Ok(Some((round, messages)))
+    }
+
+    /// Complete the offline stages and return the partial
+    /// signatures for every message in the batch that must be
+    /// sent to the other signing participants.
+    pub fn partial(&mut self) -> Result<Vec<PartialSignature>> {
+        self.check()?;
+        if self.stages.is_empty() {
+            return Err(Error::OfflineStageCompleted);
+        }
+        if let Some(stage) = self.stages.iter().find(|stage| !stage.is_finished()) {
+            return Err(Error::OfflineStageNotFinished(stage.current_round()));
+        }
+        let completed = self
+            .stages
+            .drain(..)
+            .map(|mut stage| Ok(stage.pick_output().ok_or(Error::CompletedOfflineStage)??))
+            .collect::<Result<Vec<_>>>()?;
+        let partials = completed
+            .iter()
+            .zip(self.messages.iter())
+            .map(|(completed_offline_stage, data)| {
+                let (_sign, partial) =
+                    SignManual::new(data.clone(), completed_offline_stage.clone())?;
+                Ok(partial)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        self.completed = Some(completed);
+        Ok(partials)
+    }
+
+    /// Create and verify the signatures.
+    ///
+    /// The partials are the values returned by `partial()` for
+    /// each of the other participants; the signatures are in the
+    /// same order as the messages.
+    pub fn create(&mut self, partials: &[Vec<PartialSignature>]) -> Result<Vec<Signature>> {
+        if partials
+            .iter()
+            .any(|batch| batch.len() != self.messages.len())
+        {
+            return Err(ProtocolError {
+                round: ONLINE_ROUND,
+                kind: INVALID_BATCH.to_string(),
+                culprits: vec![],
+                message: format!(
+                    "expected {} partial signatures from every party",
+                    self.messages.len()
+                ),
+            }
+            .into());
+        }
+        let completed = self.completed.take().ok_or(Error::CompletedOfflineStage)?;
+        completed
+            .into_iter()
+            .zip(self.messages.iter())
+            .enumerate()
+            .map(|(position, (completed_offline_stage, data))| {
+                let partials = partials
+                    .iter()
+                    .map(|batch| batch[position].clone())
+                    .collect::<Vec<_>>();
+                complete(completed_offline_stage, data.clone(), &partials)
+            })
+            .collect()
+    }
+
+    /// Fail when the stages diverged in an earlier call.
+    fn check(&self) -> Result<()> {
+        match self.failed {
+            Some(round) => Err(Error::BatchFailed(round)),
+            None => Ok(()),
+        }
+    }
+
+    /// Current round of the offline stages.
+    fn current_round(&self) -> Result<u16> {
+        self.stages
+            .first()
+            .map(|stage| stage.current_round())
+            .ok_or(Error::OfflineStageCompleted)
+    }
+
+    /// Error for a malformed batch that blames the sender.
+    fn invalid(&self, round: u16, sender: u16, message: String) -> Error {
+        let culprits = (sender as usize)
+            .checked_sub(1)
+            .and_then(|position| self.participants.get(position))
+            .copied()
+            .into_iter()
+            .collect();
+        ProtocolError {
+            round,
+            kind: INVALID_BATCH.to_string(),
+            culprits,
+            message,
+        }
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{simulate, Parameters};
+
+    type BatchMsg = RoundMsg<Vec<OfflineProtocolMessage>>;
+
+    /// Messages for a party including the round number.
+    fn route(messages: &[BatchMsg], party: u16) -> Vec<BatchMsg> {
+        messages
+            .iter()
+            .filter(|m| m.sender != party && m.receiver.map(|r| r == party).unwrap_or(true))
+            .cloned()
+            .collect()
+    }
+
+    fn signers(key_shares: &[crate::KeyShare], batch: &[[u8; 32]]) -> Vec<BatchSigner> {
+        key_shares[..2]
+            .iter()
+            .enumerate()
+            .map(|(position, key_share)| {
+                BatchSigner::new(
+                    position as u16 + 1,
+                    vec![1, 2],
+                    key_share.local_key.clone(),
+                    batch,
+                )
+                .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn batch_sign() {
+        let key_shares = simulate::keygen(Parameters::default()).unwrap();
+        let batch = [[1u8; 32], [2u8; 32]];
+        let mut signers = signers(&key_shares, &batch);
+
+        let mut messages = Vec::new();
+        for signer in signers.iter_mut() {
+            messages.extend(signer.proceed().unwrap().unwrap().1);
+        }
+        let mut previous = Vec::new();
+        for _ in 0..ROUNDS {
+            // A message for an earlier round is rejected without
+            // changing any of the stages
+            if let Some(stale) = route(&previous, 1).pop() {
+                match signers[0].handle_incoming(stale) {
+                    Err(Error::Protocol(error)) => {
+                        assert_eq!(error.kind, INVALID_BATCH);
+                        assert_eq!(error.culprits, vec![2]);
+                    }
+                    _ => panic!("expected invalid batch"),
+                }
+            }
+
+            let mut outgoing = Vec::new();
+            for (position, signer) in signers.iter_mut().enumerate() {
+                for message in route(&messages, position as u16 + 1) {
+                    signer.handle_incoming(message).unwrap();
+                }
+                if let Some((_, messages)) = signer.proceed().unwrap() {
+                    outgoing.extend(messages);
+                }
+            }
+            previous = std::mem::replace(&mut messages, outgoing);
+        }
+
+        let partials = signers
+            .iter_mut()
+            .map(|signer| signer.partial().unwrap())
+            .collect::<Vec<_>>();
+        let signatures = signers[0].create(&partials[1..]).unwrap();
+        assert_eq!(signatures.len(), batch.len());
+        for signature in signatures {
+            assert_eq!(signature.public_key, key_shares[0].public_key);
+        }
+
+        let error = signers[1].create(&[vec![]]);
+        assert!(matches!(error, Err(Error::Protocol(_))));
+    }
+
+    #[test]
+    fn batch_failed() {
+        let key_shares = simulate::keygen(Parameters::default()).unwrap();
+        let mut signers = signers(&key_shares, &[[1u8; 32], [2u8; 32]]);
+
+        let mut messages = Vec::new();
+        for signer in signers.iter_mut() {
+            messages.extend(signer.proceed().unwrap().unwrap().1);
+        }
+        let mut outgoing = Vec::new();
+        for (position, signer) in signers.iter_mut().enumerate() {
+            for message in route(&messages, position as u16 + 1) {
+                signer.handle_incoming(message).unwrap();
+            }
+            outgoing.extend(signer.proceed().unwrap().unwrap().1);
+        }
+
+        // The first stage accepts the current body and the second
+        // stage rejects the body from the previous round
+        let mut message = route(&outgoing, 1).pop().unwrap();
+        message.body[1] = route(&messages, 1).pop().unwrap().body[0].clone();
+        assert!(matches!(
+            signers[0].handle_incoming(message),
+            Err(Error::Protocol(_))
+        ));
+
+        let message = route(&outgoing, 1).pop().unwrap();
+        assert!(matches!(
+            signers[0].handle_incoming(message),
+            Err(Error::BatchFailed(2))
+        ));
+        assert!(matches!(signers[0].proceed(), Err(Error::BatchFailed(2))));
+        assert!(matches!(signers[0].partial(), Err(Error::BatchFailed(2))));
+    }
+
+    #[test]
+    fn invalid_batch() {
+        let key_shares = simulate::keygen(Parameters::default()).unwrap();
+        let local_key = key_shares[2].local_key.clone();
+        assert!(matches!(
+            BatchSigner::new(1, vec![3, 1], local_key.clone(), &[]),
+            Err(Error::EmptyBatch)
+        ));
+
+        let mut signer = BatchSigner::new(1, vec![3, 1], local_key, &[[0u8; 32]; 2]).unwrap();
+        let error = signer.handle_incoming(RoundMsg {
+            round: 1,
+            sender: 2,
+            receiver: None,
+            body: vec![],
+        });
+        match error {
+            Err(Error::Protocol(error)) => {
+                assert_eq!(error.kind, INVALID_BATCH);
+                assert_eq!(error.culprits, vec![1]);
+            }
+            _ => panic!("expected invalid batch"),
+        }
+    }
+}
@@ -0,0 +1,221 @@
+//! Structured errors for identifiable abort.
+//!
+//! When GG2020 detects a misbehaving party the error from
+//! multi-party-ecdsa carries an `ErrorType` with the error kind and
+//! the zero-based positions of the bad actors. The `ErrorType` is
+//! wrapped at different depths for each round so the `Blame` trait
+//! finds it for each error enum; its fields are private so they are
+//! read through the serde representation.
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::{
+    state_machine::{keygen, sign},
+    ErrorType,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Error from a protocol round that may identify the
+/// parties to blame.
+pub trait Blame: fmt::Display {
+    /// Kind of error when there is no error type.
+    const KIND: &'static str;
+
+    /// Error type with the kind and bad actors.
+    fn error_type(&self) -> Option<&ErrorType>;
+}
+
+impl Blame for keygen::Error {
+    const KIND: &'static str = "KeygenError";
+
+    fn error_type(&self) -> Option<&ErrorType> {
+        match self {
+            keygen::Error::ProceedRound(error) => match error {
+                keygen::ProceedError::Round2VerifyCommitments(error_type)
+                | keygen::ProceedError::Round3VerifyVssConstruct(error_type)
+                | keygen::ProceedError::Round4VerifyDLogProof(error_type) => Some(error_type),
+                #[allow(unreachable_patterns)]
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+impl Blame for sign::Error {
+    const KIND: &'static str = "OfflineStageError";
+
+    fn error_type(&self) -> Option<&ErrorType> {
+        match self {
+            sign::Error::ProceedRound(error) => round_error_type(error),
+            _ => None,
+        }
+    }
+}
+
+impl Blame for sign::SignError {
+    const KIND: &'static str = "SignError";
+
+    fn error_type(&self) -> Option<&ErrorType> {
+        match self {
+            sign::SignError::LocalSigning(error) | sign::SignError::CompleteSigning(error) => {
+                round_error_type(error)
+            }
+        }
+    }
+}
+
+/// Error type for a failed signing round.
+fn round_error_type(error: &sign::ProceedError) -> Option<&ErrorType> {
+    match error {
+        sign::ProceedError::Round1(error_type)
+        | sign::ProceedError::Round2Stage4(error_type)
+        | sign::ProceedError::Round3(error_type)
+        | sign::ProceedError::Round5(error_type)
+        | sign::ProceedError::Round6VerifyProof(error_type) => Some(error_type),
+        #[allow(unreachable_patterns)]
+        _ => None,
+    }
+}
+
+/// Fields of an `ErrorType`.
+#[derive(Deserialize)]
+struct Fields {
+    error_type: String,
+    bad_actors: Vec<usize>,
+}
+
+impl Fields {
+    fn read(error_type: &ErrorType) -> Option<Self> {
+        serde_json::to_value(error_type)
+            .and_then(serde_json::from_value)
+            .ok()
+    }
+}
+
+/// Error thrown when a protocol round fails.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProtocolError {
+    /// Round that failed.
+    pub round: u16,
+    /// Kind of error.
+    pub kind: String,
+    /// Parties to blame, empty when the failure cannot be
+    /// attributed to a party.
+    ///
+    /// For key generation these are party numbers and for
+    /// signing the key share indices of the participants.
+    pub culprits: Vec<u16>,
+    /// Error message.
+    pub message: String,
+}
+
+impl ProtocolError {
+    /// Create a protocol error for a failed key generation round.
+    ///
+    /// The bad actors are positions in the list of parties so
+    /// the culprits are the party numbers.
+    pub fn new<E: Blame>(round: u16, error: &E) -> Self {
+        Self::blame(round, error, |position| Some(position as u16 + 1))
+    }
+
+    /// Create a protocol error for a failed signing round.
+    ///
+    /// The bad actors are positions in the list of signing
+    /// participants so the culprits are the key share indices
+    /// of the participants at those positions.
+    pub fn signing<E: Blame>(round: u16, error: &E, participants: &[u16]) -> Self {
+        Self::blame(round, error, |position| participants.get(position).copied())
+    }
+
+    fn blame<E, F>(round: u16, error: &E, culprit: F) -> Self
+    where
+        E: Blame,
+        F: Fn(usize) -> Option<u16>,
+    {
+        let (kind, culprits) = match error.error_type().and_then(Fields::read) {
+            Some(fields) => (
+                fields.error_type,
+                fields.bad_actors.into_iter().filter_map(culprit).collect(),
+            ),
+            None => (E::KIND.to_string(), vec![]),
+        };
+        Self {
+            round,
+            kind,
+            culprits,
+            message: error.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "round {} failed: {}", self.round, self.message)
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::{
+        state_machine::{keygen, sign},
+        ErrorType,
+    };
+
+    fn error_type(error_type: &str, bad_actors: &[usize]) -> ErrorType {
+        serde_json::from_value(serde_json::json!({
+            "error_type": error_type,
+            "bad_actors": bad_actors,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn keygen_culprits_are_party_numbers() {
+        let error = keygen::Error::ProceedRound(keygen::ProceedError::Round2VerifyCommitments(
+            error_type("invalid decommitment", &[0, 2]),
+        ));
+        let error = ProtocolError::new(2, &error);
+        assert_eq!(error.kind, "invalid decommitment");
+        assert_eq!(error.culprits, vec![1, 3]);
+    }
+
+    #[test]
+    fn signing_culprits_are_participants() {
+        let participants = [3, 1, 2];
+        let error = sign::Error::ProceedRound(sign::ProceedError::Round5(error_type(
+            "phase5 verify pdl error",
+            &[0, 1],
+        )));
+        let error = ProtocolError::signing(5, &error, &participants);
+        assert_eq!(error.kind, "phase5 verify pdl error");
+        assert_eq!(error.culprits, vec![3, 1]);
+
+        // Positions outside of the participants are not blamed
+        let error =
+            sign::Error::ProceedRound(sign::ProceedError::Round3(error_type("bad delta", &[1, 5])));
+        let error = ProtocolError::signing(3, &error, &[2, 4]);
+        assert_eq!(error.culprits, vec![4]);
+    }
+
+    #[test]
+    fn unattributed_failure() {
+        let error =
+            sign::Error::ProceedRound(sign::ProceedError::Round3(error_type("bad delta", &[])));
+        let error = ProtocolError::signing(3, &error, &[1, 2]);
+        assert_eq!(error.kind, "bad delta");
+        assert!(error.culprits.is_empty());
+
+        let error = sign::SignError::CompleteSigning(sign::ProceedError::Round5(error_type(
+            "phase5 verify pdl error",
+            &[1],
+        )));
+        let error = ProtocolError::signing(7, &error, &[1, 2]);
+        assert_eq!(error.culprits, vec![2]);
+
+        let error = ProtocolError::new(1, &keygen::Error::DoublePickOutput);
+        assert_eq!(error.kind, "KeygenError");
+        assert!(error.culprits.is_empty());
+    }
+}
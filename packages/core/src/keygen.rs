@@ -0,0 +1,694 @@
+//! Key generation.
+//!
+//! The key shares are generated by the GG2020 key generation
+//! protocol which is followed by two rounds that jointly generate
+//! the secret chain code for BIP32 child key derivation.
+//!
+//! Every party samples a random contribution and sends it to each
+//! other party encrypted with a key derived from the Diffie-Hellman
+//! secret of their key shares (`x_i * X_j`) so the contributions are
+//! not visible to the server; the chain code is the digest of all
+//! the contributions ordered by party. In the final round every
+//! party broadcasts a digest of the chain code so that parties
+//! which were sent inconsistent contributions are detected.
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use curv::elliptic::curves::{secp256_k1::Secp256k1, Scalar};
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::keygen::{
+    Keygen, LocalKey, ProtocolMessage,
+};
+use round_based::{Msg, StateMachine};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+
+use crate::{round_messages, Error, Parameters, PartySignup, ProtocolError, Result, RoundMsg};
+
+/// Number of rounds in the key generation protocol.
+pub const ROUNDS: u16 = 6;
+
+/// Round that receives the chain code contributions.
+const CHAIN_CODE_ROUND: u16 = 5;
+
+/// Round that receives the chain code digests.
+const CONFIRM_ROUND: u16 = 6;
+
+/// Domain separator for the contribution encryption keys.
+const CONTRIBUTION_DOMAIN: &[u8] = b"mpc-ecdsa/keygen/chain-code/contribution";
+
+/// Domain separator for the chain code.
+const CHAIN_CODE_DOMAIN: &[u8] = b"mpc-ecdsa/keygen/chain-code";
+
+/// Domain separator for the chain code digest.
+const CONFIRM_DOMAIN: &[u8] = b"mpc-ecdsa/keygen/chain-code/confirm";
+
+/// Kind of error for a chain code contribution that cannot be
+/// decrypted or a chain code that the parties do not agree on.
+const INVALID_CHAIN_CODE: &str = "InvalidChainCode";
+
+/// Expensive step of key generation reported to a progress handler.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum KeygenStep {
+    /// Generating the Paillier key and ring-pedersen parameters
+    /// with the proofs that they are well formed.
+    PaillierKeys,
+    /// Verifying the Paillier key and ring-pedersen proofs
+    /// from the other parties.
+    VerifyProofs,
+    /// Computing the messages for a round.
+    Round,
+}
+
+impl KeygenStep {
+    /// Step performed when proceeding from a round.
+    fn from_round(round: u16) -> Self {
+        match round {
+            0 => KeygenStep::PaillierKeys,
+            2 => KeygenStep::VerifyProofs,
+            _ => KeygenStep::Round,
+        }
+    }
+}
+
+/// Event emitted before and after each step.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeygenEvent {
+    /// Step being performed.
+    pub step: KeygenStep,
+    /// Whether the step has completed.
+    pub done: bool,
+    /// Round the step proceeds from.
+    pub round: u16,
+    /// Total number of rounds.
+    #[serde(rename = "totalRounds")]
+    pub total_rounds: u16,
+}
+
+/// Progress of key generation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeygenProgress {
+    /// Current round.
+    pub round: u16,
+    /// Total number of rounds.
+    #[serde(rename = "totalRounds")]
+    pub total_rounds: u16,
+    /// Messages received for the current round.
+    pub received: u16,
+    /// Messages expected for the current round.
+    pub expected: u16,
+    /// Whether key generation has finished.
+    #[serde(rename = "isFinished")]
+    pub is_finished: bool,
+    /// Whether enough messages have been received to proceed.
+    #[serde(rename = "wantsToProceed")]
+    pub wants_to_proceed: bool,
+}
+
+/// Generated key share.
+pub type KeyShare = mpc_ecdsa_types::KeyShare<LocalKey<Secp256k1>>;
+
+/// Message sent during key generation.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum KeygenMessage {
+    /// Message for the GG2020 key generation protocol.
+    Protocol(ProtocolMessage),
+    /// Chain code contribution encrypted for the receiver.
+    Contribution(Vec<u8>),
+    /// Digest of the chain code computed by the sender.
+    Confirm([u8; 32]),
+}
+
+/// Round-based key share generator.
+pub struct KeyGenerator {
+    inner: Keygen,
+    parameters: Parameters,
+    party_signup: PartySignup,
+    round: u16,
+    local_key: Option<LocalKey<Secp256k1>>,
+    contributions: BTreeMap<u16, [u8; 32]>,
+    confirmations: BTreeMap<u16, [u8; 32]>,
+    chain_code: Option<[u8; 32]>,
+    received: u16,
+}
+
+impl KeyGenerator {
+    /// Create a key generator.
+    pub fn new(parameters: Parameters, party_signup: PartySignup) -> Result<Self> {
+        parameters.validate()?;
+        Ok(Self {
+            inner: Keygen::new(
+                party_signup.number,
+                parameters.threshold,
+                parameters.parties,
+            )?,
+            parameters,
+            party_signup,
+            round: 0,
+            local_key: None,
+            contributions: Default::default(),
+            confirmations: Default::default(),
+            chain_code: None,
+            received: 0,
+        })
+    }
+
+    /// Parameters for key generation.
+    pub fn parameters(&self) -> &Parameters {
+        &self.parameters
+    }
+
+    /// Party signup for the session.
+    pub fn party_signup(&self) -> &PartySignup {
+        &self.party_signup
+    }
+
+    /// Current round.
+    pub fn current_round(&self) -> u16 {
+        if self.round == 0 {
+            self.inner.current_round()
+        } else {
+            self.round
+        }
+    }
+
+    /// Total number of rounds.
+    pub fn total_rounds(&self) -> u16 {
+        ROUNDS
+    }
+
+    /// Number of messages received for the current round.
+    pub fn received(&self) -> u16 {
+        self.received
+    }
+
+    /// Number of messages expected for the current round.
+    ///
+    /// Every round expects a message from each of the other
+    /// parties; nothing is expected before the first round
+    /// or once key generation has finished.
+    pub fn expected(&self) -> u16 {
+        if self.current_round() == 0 || self.is_finished() {
+            0
+        } else {
+            self.parameters.parties - 1
+        }
+    }
+
+    /// Whether key generation has finished.
+    pub fn is_finished(&self) -> bool {
+        self.round > ROUNDS
+    }
+
+    /// Whether enough messages have been received to proceed
+    /// to the next round.
+    pub fn wants_to_proceed(&self) -> bool {
+        if self.round == 0 {
+            self.inner.wants_to_proceed()
+        } else {
+            !self.is_finished() && self.received == self.expected()
+        }
+    }
+
+    /// Progress of key generation.
+    pub fn progress(&self) -> KeygenProgress {
+        KeygenProgress {
+            round: self.current_round(),
+            total_rounds: self.total_rounds(),
+            received: self.received(),
+            expected: self.expected(),
+            is_finished: self.is_finished(),
+            wants_to_proceed: self.wants_to_proceed(),
+        }
+    }
+
+    /// Handle an incoming message.
+    pub fn handle_incoming(&mut self, message: Msg<KeygenMessage>) -> Result<()> {
+        let round = self.current_round();
+        let sender = message.sender;
+        match message.body {
+            KeygenMessage::Protocol(body) if self.round == 0 => {
+                self.inner
+                    .handle_incoming(Msg {
+                        sender,
+                        receiver: message.receiver,
+                        body,
+                    })
+                    .map_err(|e| ProtocolError::new(round, &e))?;
+            }
+            KeygenMessage::Contribution(ciphertext) if round == CHAIN_CODE_ROUND => {
+                self.check_sender(sender)?;
+                if self.contributions.contains_key(&sender) {
+                    return Err(self.duplicate(sender));
+                }
+                let me = self.party_signup.number;
+                let contribution = self
+                    .cipher(sender, me)
+                    .decrypt(Nonce::from_slice(&[0u8; 12]), ciphertext.as_slice())
+                    .ok()
+                    .and_then(|plaintext| plaintext.as_slice().try_into().ok())
+                    .ok_or_else(|| {
+                        self.blame(
+                            sender,
+                            INVALID_CHAIN_CODE,
+                            format!(
+                                "party {} sent a chain code contribution that could not be decrypted",
+                                sender
+                            ),
+                        )
+                    })?;
+                self.contributions.insert(sender, contribution);
+            }
+            KeygenMessage::Confirm(digest) if round == CONFIRM_ROUND => {
+                self.check_sender(sender)?;
+                if self.confirmations.contains_key(&sender) {
+                    return Err(self.duplicate(sender));
+                }
+                self.confirmations.insert(sender, digest);
+            }
+            _ => {
+                return Err(self.blame(
+                    sender,
+                    "WrongRound",
+                    format!("party {} sent a message for the wrong round", sender),
+                ))
+            }
+        }
+        self.received += 1;
+        Ok(())
+    }
+
+    /// Proceed to the next round and return the round number
+    /// with the messages to send to the other parties.
+    pub fn proceed(&mut self) -> Result<(u16, Vec<RoundMsg<KeygenMessage>>)> {
+        self.proceed_with_progress(|_| {})
+    }
+
+    /// Proceed to the next round and call the progress handler
+    /// before and after the step performed for the round.
+    pub fn proceed_with_progress<F: FnMut(KeygenEvent)>(
+        &mut self,
+        mut progress: F,
+    ) -> Result<(u16, Vec<RoundMsg<KeygenMessage>>)> {
+        let round = self.current_round();
+        let mut event = KeygenEvent {
+            step: KeygenStep::from_round(round),
+            done: false,
+            round,
+            total_rounds: self.total_rounds(),
+        };
+        progress(event.clone());
+        let messages = match self.round {
+            0 => self.proceed_protocol()?,
+            CHAIN_CODE_ROUND => self.confirm()?,
+            CONFIRM_ROUND => {
+                self.finish()?;
+                vec![]
+            }
+            _ => return Err(Error::KeygenFinished),
+        };
+        event.done = true;
+        progress(event);
+        self.received = 0;
+        Ok((self.current_round(), messages))
+    }
+
+    /// Create the key share.
+    pub fn create(&mut self) -> Result<KeyShare> {
+        if !self.is_finished() {
+            return Err(Error::KeygenNotFinished);
+        }
+        let chain_code = self.chain_code;
+        let local_key = self.local_key.take().ok_or(Error::KeygenFinished)?;
+        let public_key = local_key.public_key().to_bytes(false).to_vec();
+        Ok(KeyShare::new(local_key, public_key, chain_code)?)
+    }
+
+    /// Proceed with the GG2020 protocol; once it has finished
+    /// send the chain code contributions.
+    fn proceed_protocol(&mut self) -> Result<Vec<RoundMsg<KeygenMessage>>> {
+        let round = self.inner.current_round();
+        self.inner
+            .proceed()
+            .map_err(|e| ProtocolError::new(round, &e))?;
+        if !self.inner.is_finished() {
+            let messages = self
+                .inner
+                .message_queue()
+                .drain(..)
+                .map(|m| Msg {
+                    sender: m.sender,
+                    receiver: m.receiver,
+                    body: KeygenMessage::Protocol(m.body),
+                })
+                .collect();
+            return Ok(round_messages(self.inner.current_round(), messages));
+        }
+
+        let local_key = self.inner.pick_output().ok_or(Error::KeygenNotFinished)??;
+        self.local_key = Some(local_key);
+        self.round = CHAIN_CODE_ROUND;
+
+        let me = self.party_signup.number;
+        let mut contribution = [0u8; 32];
+        contribution.copy_from_slice(&Scalar::<Secp256k1>::random().to_bytes());
+        self.contributions.insert(me, contribution);
+        (1..=self.parameters.parties)
+            .filter(|receiver| *receiver != me)
+            .map(|receiver| {
+                let ciphertext = self
+                    .cipher(me, receiver)
+                    .encrypt(Nonce::from_slice(&[0u8; 12]), &contribution[..])
+                    .map_err(|_| {
+                        self.failure(
+                            INVALID_CHAIN_CODE,
+                            "failed to encrypt the chain code contribution".to_string(),
+                        )
+                    })?;
+                Ok(RoundMsg {
+                    round: CHAIN_CODE_ROUND,
+                    sender: me,
+                    receiver: Some(receiver),
+                    body: KeygenMessage::Contribution(ciphertext),
+                })
+            })
+            .collect()
+    }
+
+    /// Compute the chain code and broadcast its digest.
+    fn confirm(&mut self) -> Result<Vec<RoundMsg<KeygenMessage>>> {
+        self.expect(self.contributions.len() - 1)?;
+        let chain_code = self
+            .contributions
+            .values()
+            .fold(
+                Sha256::new().chain_update(CHAIN_CODE_DOMAIN),
+                |hasher, contribution| hasher.chain_update(contribution),
+            )
+            .finalize()
+            .into();
+        let digest = self.digest(&chain_code);
+        self.chain_code = Some(chain_code);
+        self.round = CONFIRM_ROUND;
+        Ok(vec![RoundMsg {
+            round: CONFIRM_ROUND,
+            sender: self.party_signup.number,
+            receiver: None,
+            body: KeygenMessage::Confirm(digest),
+        }])
+    }
+
+    /// Check every party computed the same chain code.
+    fn finish(&mut self) -> Result<()> {
+        self.expect(self.confirmations.len())?;
+        let digest = self.digest(self.chain_code.as_ref().unwrap());
+        let culprits = self
+            .confirmations
+            .iter()
+            .filter(|(_, confirmation)| **confirmation != digest)
+            .map(|(sender, _)| *sender)
+            .collect::<Vec<_>>();
+        if !culprits.is_empty() {
+            return Err(ProtocolError {
+                round: CONFIRM_ROUND,
+                kind: INVALID_CHAIN_CODE.to_string(),
+                culprits,
+                message: "parties computed different chain codes".to_string(),
+            }
+            .into());
+        }
+        self.round = ROUNDS + 1;
+        Ok(())
+    }
+
+    /// Cipher for the chain code contribution from the sender
+    /// to the receiver.
+    ///
+    /// The key is derived from the Diffie-Hellman secret of the key
+    /// shares with the session and direction so it is only used once.
+    fn cipher(&self, sender: u16, receiver: u16) -> ChaCha20Poly1305 {
+        let local_key = self.local_key.as_ref().unwrap();
+        let other = if sender == local_key.i {
+            receiver
+        } else {
+            sender
+        };
+        let secret = &local_key.pk_vec[other as usize - 1] * &local_key.keys_linear.x_i;
+        let key = Sha256::new()
+            .chain_update(CONTRIBUTION_DOMAIN)
+            .chain_update(self.party_signup.uuid.as_bytes())
+            .chain_update(&secret.to_bytes(true)[..])
+            .chain_update(sender.to_be_bytes())
+            .chain_update(receiver.to_be_bytes())
+            .finalize();
+        ChaCha20Poly1305::new(Key::from_slice(&key))
+    }
+
+    /// Digest of a chain code broadcast in the final round.
+    fn digest(&self, chain_code: &[u8; 32]) -> [u8; 32] {
+        Sha256::new()
+            .chain_update(CONFIRM_DOMAIN)
+            .chain_update(self.party_signup.uuid.as_bytes())
+            .chain_update(chain_code)
+            .finalize()
+            .into()
+    }
+
+    /// Check a chain code message is from another party.
+    fn check_sender(&self, sender: u16) -> Result<()> {
+        if sender == 0 || sender > self.parameters.parties || sender == self.party_signup.number {
+            return Err(self.blame(
+                sender,
+                "InvalidParty",
+                format!("key generation message from invalid party {}", sender),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check all the other parties sent a message for the round.
+    fn expect(&self, received: usize) -> Result<()> {
+        let expected = self.parameters.parties as usize - 1;
+        if received != expected {
+            return Err(self.failure(
+                "MissingMessages",
+                format!(
+                    "expected messages from {} parties but got {}",
+                    expected, received
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    fn blame(&self, sender: u16, kind: &str, message: String) -> Error {
+        ProtocolError {
+            round: self.current_round(),
+            kind: kind.to_string(),
+            culprits: vec![sender],
+            message,
+        }
+        .into()
+    }
+
+    fn failure(&self, kind: &str, message: String) -> Error {
+        ProtocolError {
+            round: self.current_round(),
+            kind: kind.to_string(),
+            culprits: vec![],
+            message,
+        }
+        .into()
+    }
+
+    fn duplicate(&self, sender: u16) -> Error {
+        self.blame(
+            sender,
+            "DuplicateMessage",
+            format!("duplicate key generation message from party {}", sender),
+        )
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulate::route;
+
+    fn generators(parameters: &Parameters) -> Vec<KeyGenerator> {
+        (1..=parameters.parties)
+            .map(|number| {
+                KeyGenerator::new(
+                    parameters.clone(),
+                    PartySignup {
+                        number,
+                        uuid: "test".to_string(),
+                    },
+                )
+                .unwrap()
+            })
+            .collect()
+    }
+
+    /// Run the rounds before `round` and return the
+    /// messages for `round`.
+    fn run(generators: &mut [KeyGenerator], round: u16) -> Vec<RoundMsg<KeygenMessage>> {
+        let mut messages = Vec::new();
+        for generator in generators.iter_mut() {
+            messages.extend(generator.proceed().unwrap().1);
+        }
+        for _ in 1..round {
+            let mut outgoing = Vec::new();
+            for (position, generator) in generators.iter_mut().enumerate() {
+                for message in route(&messages, position as u16 + 1) {
+                    generator.handle_incoming(message).unwrap();
+                }
+                outgoing.extend(generator.proceed().unwrap().1);
+            }
+            messages = outgoing;
+        }
+        messages
+    }
+
+    #[test]
+    fn keygen_progress() {
+        let parameters = Parameters::default();
+        let mut generators = generators(&parameters);
+
+        let mut events = Vec::new();
+        let mut messages = Vec::new();
+        for generator in generators.iter_mut() {
+            let progress = generator.progress();
+            assert_eq!(progress.round, 0);
+            assert_eq!(progress.total_rounds, ROUNDS);
+            assert_eq!(progress.expected, 0);
+            let (_, outgoing) = generator
+                .proceed_with_progress(|event| events.push(event))
+                .unwrap();
+            messages.extend(outgoing);
+        }
+        for _ in 0..ROUNDS {
+            let mut outgoing = Vec::new();
+            for (position, generator) in generators.iter_mut().enumerate() {
+                assert_eq!(generator.received(), 0);
+                assert_eq!(generator.expected(), parameters.parties - 1);
+                for message in route(&messages, position as u16 + 1) {
+                    generator.handle_incoming(message).unwrap();
+                }
+                let progress = generator.progress();
+                assert_eq!(progress.received, progress.expected);
+                assert!(progress.wants_to_proceed);
+                outgoing.extend(
+                    generator
+                        .proceed_with_progress(|event| events.push(event))
+                        .unwrap()
+                        .1,
+                );
+            }
+            messages = outgoing;
+        }
+
+        // Every step is reported before and after by each party
+        let steps = [KeygenStep::PaillierKeys, KeygenStep::VerifyProofs];
+        for step in steps.iter() {
+            let count = events.iter().filter(|e| &e.step == step).count();
+            assert_eq!(count, parameters.parties as usize * 2);
+        }
+        assert_eq!(events.iter().filter(|e| e.done).count(), events.len() / 2);
+        assert!(events.iter().all(|e| e.total_rounds == ROUNDS));
+
+        for generator in generators.iter_mut() {
+            let progress = generator.progress();
+            assert!(progress.is_finished);
+            assert!(!progress.wants_to_proceed);
+            assert_eq!(progress.expected, 0);
+            assert!(generator.create().is_ok());
+        }
+    }
+
+    #[test]
+    fn chain_code() {
+        let parameters = Parameters::default();
+        let mut generators = generators(&parameters);
+        let messages = run(&mut generators, CHAIN_CODE_ROUND);
+        assert!(generators
+            .iter()
+            .all(|generator| generator.current_round() == CHAIN_CODE_ROUND));
+
+        // Contributions are encrypted for the receiver
+        let mut incoming = route(&messages, 1);
+        let mut tampered = incoming[0].clone();
+        if let KeygenMessage::Contribution(ciphertext) = &mut tampered.body {
+            ciphertext[0] ^= 1;
+        }
+        match generators[0].handle_incoming(tampered) {
+            Err(Error::Protocol(error)) => {
+                assert_eq!(error.kind, INVALID_CHAIN_CODE);
+                assert_eq!(error.culprits, vec![incoming[0].sender]);
+            }
+            _ => panic!("expected invalid chain code"),
+        }
+        let misrouted = route(&messages, 2)
+            .into_iter()
+            .find(|message| message.sender == 3)
+            .unwrap();
+        assert!(generators[0].handle_incoming(misrouted).is_err());
+
+        // A duplicate does not replace the first contribution
+        let first = incoming.remove(0);
+        generators[0].handle_incoming(first.clone()).unwrap();
+        match generators[0].handle_incoming(first) {
+            Err(Error::Protocol(error)) => assert_eq!(error.kind, "DuplicateMessage"),
+            _ => panic!("expected duplicate message"),
+        }
+        match generators[0].handle_incoming(Msg {
+            sender: 2,
+            receiver: None,
+            body: KeygenMessage::Confirm([0u8; 32]),
+        }) {
+            Err(Error::Protocol(error)) => assert_eq!(error.kind, "WrongRound"),
+            _ => panic!("expected wrong round"),
+        }
+
+        let mut outgoing = Vec::new();
+        for (position, generator) in generators.iter_mut().enumerate() {
+            let incoming = route(&messages, position as u16 + 1);
+            for message in incoming.into_iter().skip(if position == 0 { 1 } else { 0 }) {
+                generator.handle_incoming(message).unwrap();
+            }
+            outgoing.extend(generator.proceed().unwrap().1);
+        }
+
+        // A party that reports a different chain code is blamed
+        let mut confirmations = route(&outgoing, 1);
+        confirmations[0].body = KeygenMessage::Confirm([0u8; 32]);
+        let forger = confirmations[0].sender;
+        for message in confirmations {
+            generators[0].handle_incoming(message).unwrap();
+        }
+        match generators[0].proceed() {
+            Err(Error::Protocol(error)) => {
+                assert_eq!(error.kind, INVALID_CHAIN_CODE);
+                assert_eq!(error.culprits, vec![forger]);
+            }
+            _ => panic!("expected invalid chain code"),
+        }
+
+        // The other parties agree on the chain code
+        let key_shares = generators[1..]
+            .iter_mut()
+            .enumerate()
+            .map(|(position, generator)| {
+                for message in route(&outgoing, position as u16 + 2) {
+                    generator.handle_incoming(message).unwrap();
+                }
+                generator.proceed().unwrap();
+                generator.create().unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert!(key_shares[0].chain_code.is_some());
+        assert_eq!(key_shares[0].chain_code, key_shares[1].chain_code);
+    }
+}
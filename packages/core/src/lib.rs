@@ -0,0 +1,125 @@
+//! GG2020 key generation and signing from [multi-party-ecdsa](https://github.com/ZenGo-X/multi-party-ecdsa)
+//! with typed inputs, outputs and errors.
+//!
+//! The state machines are driven by passing incoming messages to
+//! `handle_incoming()` and sending the messages returned by
+//! `proceed()` to the other parties; they do not depend on a
+//! transport so they are shared by the webassembly bindings and
+//! native parties.
+#![deny(missing_docs)]
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::{
+    keygen::Error as KeygenError,
+    sign::{Error as OfflineStageError, SignError},
+};
+use round_based::Msg;
+use thiserror::Error;
+
+pub mod batch;
+mod blame;
+pub mod keygen;
+pub mod presign;
+pub mod refresh;
+pub mod reshare;
+mod shares;
+pub mod sign;
+pub mod simulate;
+pub mod verify;
+
+pub use batch::BatchSigner;
+pub use blame::{Blame, ProtocolError};
+pub use keygen::{KeyGenerator, KeyShare, KeygenEvent, KeygenMessage, KeygenProgress, KeygenStep};
+pub use mpc_ecdsa_types::{address, Parameters, PartySignup, RoundMsg};
+pub use presign::{PresignaturePool, UsedSet};
+pub use refresh::{KeyRefresher, RefreshMessage};
+pub use reshare::{KeyResharer, ReshareKey, ReshareMessage};
+pub use shares::PublicKeys;
+pub use sign::{complete, Signature, Signer, ONLINE_ROUND};
+pub use verify::{Check, KeyShareReport};
+
+/// Errors generated by the protocols.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Parameters or a public key are not valid.
+    #[error(transparent)]
+    Types(#[from] mpc_ecdsa_types::Error),
+    /// A protocol round failed.
+    #[error(transparent)]
+    Protocol(#[from] ProtocolError),
+    /// Key generation could not be created.
+    #[error(transparent)]
+    Keygen(#[from] KeygenError),
+    /// Offline stage could not be created or completed.
+    #[error(transparent)]
+    OfflineStage(#[from] OfflineStageError),
+    /// Partial signature could not be created.
+    #[error(transparent)]
+    Sign(#[from] SignError),
+    /// Batch does not contain any messages.
+    #[error("batch must contain at least one message")]
+    EmptyBatch,
+    /// Batch stages diverged after a message was only accepted
+    /// by some of them.
+    #[error("batch signing failed in round {0} and cannot continue")]
+    BatchFailed(u16),
+    /// Key generation has already finished.
+    #[error("key generation has already finished")]
+    KeygenFinished,
+    /// Key generation has not finished.
+    #[error("key generation has not finished")]
+    KeygenNotFinished,
+    /// Key refresh has already finished.
+    #[error("key refresh has already finished")]
+    RefreshFinished,
+    /// Key refresh has not finished.
+    #[error("key refresh has not finished")]
+    RefreshNotFinished,
+    /// Resharing cannot start with the given inputs.
+    #[error("{0}")]
+    Reshare(String),
+    /// Resharing has already finished.
+    #[error("key resharing has already finished")]
+    ReshareFinished,
+    /// Resharing has not finished.
+    #[error("key resharing has not finished")]
+    ReshareNotFinished,
+    /// Offline stage has already completed.
+    #[error("offline stage has already completed")]
+    OfflineStageCompleted,
+    /// Offline stage has not completed yet.
+    #[error("offline stage has not completed (round {0})")]
+    OfflineStageNotFinished(u16),
+    /// Output of the offline stage has already been used.
+    #[error("completed offline stage unavailable, has partial() been called?")]
+    CompletedOfflineStage,
+    /// Presignature has already been consumed.
+    #[error("presignature {0} has already been used and must not be reused")]
+    PresignatureUsed(String),
+    /// Presignature is not in the pool.
+    #[error("presignature {0} does not exist")]
+    PresignatureNotFound(String),
+    /// Presignature is already in the pool.
+    #[error("presignature {0} already exists")]
+    PresignatureExists(String),
+    /// Presignature must be reserved before it is taken.
+    #[error("presignature {0} has not been reserved")]
+    PresignatureNotReserved(String),
+    /// Signature did not verify against the public key.
+    #[error("failed to verify signature: {0}")]
+    Verify(String),
+}
+
+/// Result type for the protocols.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Wrap the messages for a round in the round envelope.
+pub fn round_messages<B>(round: u16, messages: Vec<Msg<B>>) -> Vec<RoundMsg<B>> {
+    messages
+        .into_iter()
+        .map(|m| RoundMsg {
+            round,
+            sender: m.sender,
+            receiver: m.receiver,
+            body: m.body,
+        })
+        .collect::<Vec<_>>()
+}
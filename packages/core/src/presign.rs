@@ -0,0 +1,222 @@
+//! Presignatures for signing without the offline rounds.
+//!
+//! A signer that has completed the offline stage can export the
+//! result as a presignature instead of signing straight away; the
+//! presignature is later added to a pool which hands it out once
+//! for the online step when a message is known.
+//!
+//! Signing two different messages with the same presignature reveals
+//! the private key. A pool is only a cache of presignatures so the
+//! identifiers of consumed presignatures are kept in a `UsedSet` that
+//! belongs to the holder of the key share and outlives any pool: a
+//! new pool or a pool restored from an older export is created with
+//! the used set and refuses every presignature in it.
+//!
+//! A presignature is consumed in two steps; `reserve()` adds it to
+//! the used set which the holder must persist before calling `take()`
+//! to get the presignature for the partial signature.
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{Error, Result};
+
+/// Identifiers of presignatures that have been consumed.
+///
+/// The used set only grows; the holder must persist it every time
+/// it changes and never replace it with an earlier copy.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct UsedSet(BTreeSet<String>);
+
+impl UsedSet {
+    /// Whether a presignature has been consumed.
+    pub fn contains(&self, id: &str) -> bool {
+        self.0.contains(id)
+    }
+
+    /// Record a presignature as consumed.
+    ///
+    /// Fails if it has already been consumed.
+    pub fn consume(&mut self, id: &str) -> Result<()> {
+        if !self.0.insert(id.to_string()) {
+            return Err(Error::PresignatureUsed(id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Add the identifiers from another used set, for example one
+    /// persisted by an earlier version of the holder.
+    pub fn merge(&mut self, other: &UsedSet) {
+        self.0.extend(other.0.iter().cloned());
+    }
+
+    /// Identifiers of the consumed presignatures.
+    pub fn ids(&self) -> impl Iterator<Item = &String> {
+        self.0.iter()
+    }
+}
+
+/// Pool of single-use presignatures keyed by identifier.
+///
+/// The presignature type is a parameter so that the pool can hold
+/// presignatures that are encrypted until they are needed.
+pub struct PresignaturePool<P> {
+    used: UsedSet,
+    presignatures: BTreeMap<String, P>,
+    reserved: BTreeMap<String, P>,
+}
+
+impl<P> PresignaturePool<P> {
+    /// Create a pool from the used set of the holder and
+    /// presignatures from an earlier export.
+    ///
+    /// Presignatures that are in the used set are discarded so a
+    /// pool restored from an older export cannot reuse them.
+    pub fn new(used: UsedSet, presignatures: Vec<(String, P)>) -> Result<Self> {
+        let mut pool = Self {
+            used,
+            presignatures: Default::default(),
+            reserved: Default::default(),
+        };
+        for (id, presignature) in presignatures {
+            if !pool.used.contains(&id) {
+                pool.insert(id, presignature)?;
+            }
+        }
+        Ok(pool)
+    }
+
+    /// Add a presignature to the pool.
+    pub fn insert(&mut self, id: String, presignature: P) -> Result<()> {
+        if self.used.contains(&id) {
+            return Err(Error::PresignatureUsed(id));
+        }
+        if self.presignatures.contains_key(&id) {
+            return Err(Error::PresignatureExists(id));
+        }
+        self.presignatures.insert(id, presignature);
+        Ok(())
+    }
+
+    /// Identifiers of the available presignatures.
+    pub fn ids(&self) -> impl Iterator<Item = &String> {
+        self.presignatures.keys()
+    }
+
+    /// Available presignatures with their identifiers.
+    pub fn presignatures(&self) -> impl Iterator<Item = (&String, &P)> {
+        self.presignatures.iter()
+    }
+
+    /// Used set of the holder including presignatures
+    /// consumed by this pool.
+    pub fn used(&self) -> &UsedSet {
+        &self.used
+    }
+
+    /// Mark a presignature as consumed and return the used set
+    /// that must be persisted before the presignature is taken.
+    pub fn reserve(&mut self, id: &str) -> Result<&UsedSet> {
+        if self.used.contains(id) {
+            return Err(Error::PresignatureUsed(id.to_string()));
+        }
+        let presignature = self
+            .presignatures
+            .remove(id)
+            .ok_or_else(|| Error::PresignatureNotFound(id.to_string()))?;
+        self.used.consume(id)?;
+        self.reserved.insert(id.to_string(), presignature);
+        Ok(&self.used)
+    }
+
+    /// Take a reserved presignature to create the partial signature.
+    pub fn take(&mut self, id: &str) -> Result<P> {
+        self.reserved
+            .remove(id)
+            .ok_or_else(|| Error::PresignatureNotReserved(id.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(used: UsedSet, ids: &[&str]) -> PresignaturePool<String> {
+        let presignatures = ids
+            .iter()
+            .map(|id| (id.to_string(), format!("presignature {}", id)))
+            .collect();
+        PresignaturePool::new(used, presignatures).unwrap()
+    }
+
+    #[test]
+    fn reserve_and_take() {
+        let mut pool = pool(Default::default(), &["a", "b"]);
+        assert!(matches!(
+            pool.take("a"),
+            Err(Error::PresignatureNotReserved(_))
+        ));
+
+        let used = pool.reserve("a").unwrap().clone();
+        assert!(used.contains("a"));
+        assert_eq!(pool.take("a").unwrap(), "presignature a");
+        assert_eq!(pool.ids().collect::<Vec<_>>(), vec!["b"]);
+
+        // Each presignature is handed out once
+        assert!(matches!(pool.reserve("a"), Err(Error::PresignatureUsed(_))));
+        assert!(matches!(
+            pool.take("a"),
+            Err(Error::PresignatureNotReserved(_))
+        ));
+        assert!(matches!(
+            pool.insert("a".to_string(), String::new()),
+            Err(Error::PresignatureUsed(_))
+        ));
+        assert!(matches!(
+            pool.insert("b".to_string(), String::new()),
+            Err(Error::PresignatureExists(_))
+        ));
+        assert!(matches!(
+            pool.reserve("c"),
+            Err(Error::PresignatureNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn reuse_across_pools() {
+        let mut first = pool(Default::default(), &["a", "b"]);
+        let export = first
+            .presignatures()
+            .map(|(id, presignature)| (id.clone(), presignature.clone()))
+            .collect::<Vec<_>>();
+        first.reserve("a").unwrap();
+        let used = first.used().clone();
+
+        // A new pool with the used set of the holder refuses
+        // the consumed presignature
+        let mut fresh = pool(used.clone(), &[]);
+        assert!(matches!(
+            fresh.insert("a".to_string(), String::new()),
+            Err(Error::PresignatureUsed(_))
+        ));
+
+        // Restoring an export from before the presignature was
+        // consumed discards it
+        let mut restored = PresignaturePool::new(used, export).unwrap();
+        assert_eq!(restored.ids().collect::<Vec<_>>(), vec!["b"]);
+        assert!(matches!(
+            restored.reserve("a"),
+            Err(Error::PresignatureUsed(_))
+        ));
+        restored.reserve("b").unwrap();
+
+        // Used sets from different copies of the holder are combined
+        let mut used = first.used().clone();
+        used.merge(restored.used());
+        assert_eq!(used.ids().collect::<Vec<_>>(), vec!["a", "b"]);
+        assert!(matches!(used.consume("b"), Err(Error::PresignatureUsed(_))));
+
+        let value = serde_json::to_value(&used).unwrap();
+        assert_eq!(value, serde_json::json!(["a", "b"]));
+    }
+}
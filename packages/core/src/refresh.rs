@@ -0,0 +1,554 @@
+//! Proactive key share refresh.
+//!
+//! Every party samples a random polynomial of degree `t` whose
+//! constant term is zero and distributes evaluations of it to the
+//! other parties; adding the evaluations to an existing share yields
+//! a new share of the *same* secret so the public key and address do
+//! not change but shares from before the refresh can no longer be
+//! combined with shares from after the refresh.
+//!
+//! Each party also generates a new Paillier key and new ring-pedersen
+//! parameters (`h1`, `h2`, `N~`) with proofs that they are well formed
+//! and the refresh shares are encrypted to the new Paillier keys so
+//! that key material from an earlier epoch does not reveal the
+//! refreshed shares.
+//!
+//! All parties `n` must take part in a refresh and the protocol
+//! uses three broadcast rounds; in the last round every party
+//! broadcasts a digest of the keys and commitments it received so a
+//! party that sent different values to different parties is detected
+//! before the public key shares are updated.
+use curv::{
+    arithmetic::Converter,
+    elliptic::curves::{Point, Scalar, Secp256k1},
+    BigInt,
+};
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::{
+    party_i::Keys, state_machine::keygen::LocalKey,
+};
+use paillier::{Decrypt, Encrypt, EncryptionKey, Paillier, RawCiphertext, RawPlaintext};
+use round_based::Msg;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use zk_paillier::zkproofs::DLogStatement;
+
+use crate::{
+    shares::{evaluate, evaluate_commitments, PublicKeys},
+    Error, KeyShare, ProtocolError, Result, RoundMsg,
+};
+
+/// Number of rounds in the key refresh protocol.
+pub const ROUNDS: u16 = 3;
+
+/// Domain for the digest of the broadcast values.
+const ECHO_DOMAIN: &[u8] = b"mpc-ecdsa/refresh/echo";
+
+/// Kind of error when parties received different broadcast values.
+const INCONSISTENT_BROADCAST: &str = "InconsistentBroadcast";
+
+/// Message broadcast during a key refresh.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum RefreshMessage {
+    /// New Paillier key and ring-pedersen parameters for the sender.
+    Round1(PublicKeys),
+    /// Commitments to the refresh polynomial and the evaluations
+    /// for each other party encrypted to their new Paillier key.
+    Round2 {
+        /// Commitments to the coefficients of degree one and above.
+        commitments: Vec<Point<Secp256k1>>,
+        /// Encrypted evaluations keyed by party index.
+        shares: Vec<(u16, BigInt)>,
+    },
+    /// Digest of the keys and commitments broadcast by every party.
+    Round3([u8; 32]),
+}
+
+/// Round-based key share refresh.
+pub struct KeyRefresher {
+    local_key: LocalKey<Secp256k1>,
+    chain_code: Option<[u8; 32]>,
+    round: u16,
+    keys: Option<Keys>,
+    coefficients: Vec<Scalar<Secp256k1>>,
+    commitments: Vec<Point<Secp256k1>>,
+    encryption_keys: BTreeMap<u16, EncryptionKey>,
+    dlog_statements: BTreeMap<u16, DLogStatement>,
+    refresh_shares: BTreeMap<u16, (Vec<Point<Secp256k1>>, BigInt)>,
+    digests: BTreeMap<u16, [u8; 32]>,
+    output: Option<LocalKey<Secp256k1>>,
+}
+
+impl KeyRefresher {
+    /// Create a key refresher for an existing key share.
+    ///
+    /// Messages are sent using the index of the key share
+    /// as the party number; the chain code is not changed.
+    pub fn new(key_share: KeyShare) -> Self {
+        Self {
+            local_key: key_share.local_key,
+            chain_code: key_share.chain_code,
+            round: 0,
+            keys: None,
+            coefficients: Vec::new(),
+            commitments: Vec::new(),
+            encryption_keys: Default::default(),
+            dlog_statements: Default::default(),
+            refresh_shares: Default::default(),
+            digests: Default::default(),
+            output: None,
+        }
+    }
+
+    /// Handle an incoming message.
+    pub fn handle_incoming(&mut self, message: Msg<RefreshMessage>) -> Result<()> {
+        let sender = message.sender;
+        if sender == 0 || sender > self.local_key.n || sender == self.local_key.i {
+            return Err(self.blame(
+                sender,
+                "InvalidParty",
+                format!("refresh message from invalid party {}", sender),
+            ));
+        }
+
+        match message.body {
+            RefreshMessage::Round1(keys) if self.round == 1 => {
+                if self.encryption_keys.contains_key(&sender) {
+                    return Err(self.duplicate(sender));
+                }
+                if !keys.verify() {
+                    return Err(self.blame(
+                        sender,
+                        "InvalidProof",
+                        format!(
+                            "party {} sent invalid paillier key or ring-pedersen proofs",
+                            sender
+                        ),
+                    ));
+                }
+                self.encryption_keys.insert(sender, keys.ek);
+                self.dlog_statements.insert(sender, keys.dlog_statement);
+            }
+            RefreshMessage::Round2 {
+                commitments,
+                shares,
+            } if self.round == 2 => {
+                if self.refresh_shares.contains_key(&sender) {
+                    return Err(self.duplicate(sender));
+                }
+                if commitments.len() != self.local_key.t as usize {
+                    return Err(self.blame(
+                        sender,
+                        "InvalidCommitments",
+                        format!("party {} sent the wrong number of commitments", sender),
+                    ));
+                }
+                let share = shares
+                    .into_iter()
+                    .find(|(index, _)| *index == self.local_key.i)
+                    .map(|(_, share)| share)
+                    .ok_or_else(|| {
+                        self.blame(
+                            sender,
+                            "InvalidShare",
+                            format!("party {} did not send a refresh share", sender),
+                        )
+                    })?;
+                self.refresh_shares.insert(sender, (commitments, share));
+            }
+            RefreshMessage::Round3(digest) if self.round == 3 => {
+                if self.digests.contains_key(&sender) {
+                    return Err(self.duplicate(sender));
+                }
+                self.digests.insert(sender, digest);
+            }
+            _ => {
+                return Err(self.blame(
+                    sender,
+                    "WrongRound",
+                    format!("party {} sent a message for the wrong round", sender),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /// Proceed to the next round and return the round number
+    /// with the messages to send to the other parties.
+    pub fn proceed(&mut self) -> Result<(u16, Vec<RoundMsg<RefreshMessage>>)> {
+        let messages = match self.round {
+            0 => vec![self.round1()],
+            1 => vec![self.round2()?],
+            2 => vec![self.round3()?],
+            3 => {
+                self.finish()?;
+                vec![]
+            }
+            _ => return Err(Error::RefreshFinished),
+        };
+        self.round += 1;
+
+        let round = self.round;
+        let messages = messages
+            .into_iter()
+            .map(|body| RoundMsg {
+                round,
+                sender: self.local_key.i,
+                receiver: None,
+                body,
+            })
+            .collect::<Vec<_>>();
+        Ok((round, messages))
+    }
+
+    /// Create the refreshed key share.
+    ///
+    /// The previous key share must be discarded.
+    pub fn create(&mut self) -> Result<KeyShare> {
+        let local_key = self.output.take().ok_or(Error::RefreshNotFinished)?;
+        let public_key = local_key.public_key().to_bytes(false).to_vec();
+        Ok(KeyShare::new(local_key, public_key, self.chain_code)?)
+    }
+
+    /// Generate a new Paillier key and ring-pedersen parameters.
+    fn round1(&mut self) -> RefreshMessage {
+        let keys = Keys::create(self.local_key.i as usize);
+        let public_keys = PublicKeys::new(&keys);
+        self.encryption_keys
+            .insert(self.local_key.i, public_keys.ek.clone());
+        self.dlog_statements
+            .insert(self.local_key.i, public_keys.dlog_statement.clone());
+        self.keys = Some(keys);
+        RefreshMessage::Round1(public_keys)
+    }
+
+    /// Sample the refresh polynomial and encrypt the evaluations.
+    fn round2(&mut self) -> Result<RefreshMessage> {
+        self.expect(self.encryption_keys.len() - 1)?;
+
+        self.coefficients = (0..self.local_key.t)
+            .map(|_| Scalar::<Secp256k1>::random())
+            .collect();
+        self.commitments = self
+            .coefficients
+            .iter()
+            .map(|a| Point::<Secp256k1>::generator() * a)
+            .collect();
+
+        let shares = (1..=self.local_key.n)
+            .filter(|index| *index != self.local_key.i)
+            .map(|index| {
+                let share = evaluate_zero(&self.coefficients, index);
+                let ek = &self.encryption_keys[&index];
+                let ciphertext: BigInt =
+                    Paillier::encrypt(ek, RawPlaintext::from(share.to_bigint())).into();
+                (index, ciphertext)
+            })
+            .collect();
+
+        Ok(RefreshMessage::Round2 {
+            commitments: self.commitments.clone(),
+            shares,
+        })
+    }
+
+    /// Broadcast the digest of the values received.
+    fn round3(&mut self) -> Result<RefreshMessage> {
+        self.expect(self.refresh_shares.len())?;
+        Ok(RefreshMessage::Round3(self.digest()))
+    }
+
+    /// Verify the digests and refresh shares and compute the new key.
+    fn finish(&mut self) -> Result<()> {
+        self.expect(self.digests.len())?;
+        let digest = self.digest();
+        let culprits = self
+            .digests
+            .iter()
+            .filter(|(_, other)| **other != digest)
+            .map(|(sender, _)| *sender)
+            .collect::<Vec<_>>();
+        if !culprits.is_empty() {
+            return Err(ProtocolError {
+                round: self.round,
+                kind: INCONSISTENT_BROADCAST.to_string(),
+                culprits,
+                message: "parties received different keys or commitments".to_string(),
+            }
+            .into());
+        }
+
+        // The keys are only created in round one
+        let dk = self
+            .keys
+            .as_ref()
+            .map(|keys| keys.dk.clone())
+            .ok_or(Error::RefreshNotFinished)?;
+        let me = self.local_key.i;
+
+        let mut all_commitments = vec![self.commitments.clone()];
+        let mut x_i =
+            self.local_key.keys_linear.x_i.clone() + evaluate_zero(&self.coefficients, me);
+        for (sender, (commitments, ciphertext)) in &self.refresh_shares {
+            let plaintext: BigInt = Paillier::decrypt(&dk, RawCiphertext::from(ciphertext)).into();
+            let share = Scalar::<Secp256k1>::from_bigint(&plaintext);
+            if Point::<Secp256k1>::generator() * &share
+                != evaluate_commitments_zero(commitments, me)
+            {
+                return Err(self.blame(
+                    *sender,
+                    "InvalidShare",
+                    format!(
+                        "party {} sent a refresh share that does not match the commitments",
+                        sender
+                    ),
+                ));
+            }
+            x_i = x_i + share;
+            all_commitments.push(commitments.clone());
+        }
+
+        // The VSS scheme is the commitment to this party's own
+        // polynomial from key generation so it is not changed
+        let mut local_key = self.local_key.clone();
+        for (position, pk) in local_key.pk_vec.iter_mut().enumerate() {
+            let index = position as u16 + 1;
+            for commitments in &all_commitments {
+                *pk = &*pk + evaluate_commitments_zero(commitments, index);
+            }
+        }
+
+        if Point::<Secp256k1>::generator() * &x_i != local_key.pk_vec[me as usize - 1] {
+            return Err(ProtocolError {
+                round: self.round,
+                kind: "InvalidShare".to_string(),
+                culprits: vec![],
+                message: "refreshed key share does not match the public key shares".to_string(),
+            }
+            .into());
+        }
+
+        local_key.keys_linear.x_i = x_i;
+        local_key.paillier_dk = dk;
+        local_key.paillier_key_vec = self.encryption_keys.values().cloned().collect();
+        local_key.h1_h2_n_tilde_vec = self.dlog_statements.values().cloned().collect();
+        self.keys = None;
+        self.output = Some(local_key);
+        Ok(())
+    }
+
+    /// Digest of the keys and commitments from every party
+    /// ordered by party index.
+    fn digest(&self) -> [u8; 32] {
+        let me = self.local_key.i;
+        let mut hasher = Sha256::new().chain_update(ECHO_DOMAIN);
+        for index in 1..=self.local_key.n {
+            let ek = &self.encryption_keys[&index];
+            let dlog_statement = &self.dlog_statements[&index];
+            for value in [
+                &ek.n,
+                &dlog_statement.N,
+                &dlog_statement.g,
+                &dlog_statement.ni,
+            ]
+            .iter()
+            {
+                let bytes = value.to_bytes();
+                hasher.update((bytes.len() as u32).to_be_bytes());
+                hasher.update(&bytes);
+            }
+            let commitments = if index == me {
+                &self.commitments
+            } else {
+                &self.refresh_shares[&index].0
+            };
+            for commitment in commitments {
+                hasher.update(&commitment.to_bytes(true)[..]);
+            }
+        }
+        hasher.finalize().into()
+    }
+
+    /// Check all the other parties sent a message for the round.
+    fn expect(&self, received: usize) -> Result<()> {
+        let expected = self.local_key.n as usize - 1;
+        if received != expected {
+            return Err(ProtocolError {
+                round: self.round,
+                kind: "MissingMessages".to_string(),
+                culprits: vec![],
+                message: format!(
+                    "expected messages from {} parties but got {}",
+                    expected, received
+                ),
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    fn blame(&self, sender: u16, kind: &str, message: String) -> Error {
+        ProtocolError {
+            round: self.round,
+            kind: kind.to_string(),
+            culprits: vec![sender],
+            message,
+        }
+        .into()
+    }
+
+    fn duplicate(&self, sender: u16) -> Error {
+        self.blame(
+            sender,
+            "DuplicateMessage",
+            format!("duplicate refresh message from party {}", sender),
+        )
+    }
+}
+
+/// Evaluate a polynomial with a zero constant term given the
+/// coefficients of degree one and above.
+fn evaluate_zero(coefficients: &[Scalar<Secp256k1>], index: u16) -> Scalar<Secp256k1> {
+    evaluate(coefficients, index) * Scalar::<Secp256k1>::from(index)
+}
+
+/// Evaluate a polynomial with a zero constant term in the exponent
+/// given commitments to the coefficients of degree one and above.
+fn evaluate_commitments_zero(commitments: &[Point<Secp256k1>], index: u16) -> Point<Secp256k1> {
+    evaluate_commitments(commitments, index) * Scalar::<Secp256k1>::from(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{simulate, KeyShareReport, Parameters};
+
+    #[test]
+    fn refresh_and_sign() {
+        let key_shares = simulate::keygen(Parameters::default()).unwrap();
+        let refreshed = simulate::refresh(&key_shares).unwrap();
+        assert_eq!(refreshed.len(), key_shares.len());
+
+        for (old, new) in key_shares.iter().zip(refreshed.iter()) {
+            assert_eq!(old.public_key, new.public_key);
+            assert_eq!(old.address, new.address);
+            assert_eq!(old.chain_code, new.chain_code);
+            assert_eq!(old.local_key.i, new.local_key.i);
+            assert_ne!(old.local_key.keys_linear.x_i, new.local_key.keys_linear.x_i);
+            assert_ne!(old.local_key.paillier_dk.p, new.local_key.paillier_dk.p);
+            assert_ne!(
+                old.local_key.h1_h2_n_tilde_vec[0].N,
+                new.local_key.h1_h2_n_tilde_vec[0].N
+            );
+            let report = KeyShareReport::new(new);
+            assert!(report.valid, "{:?}", report);
+        }
+
+        let message = [9u8; 32];
+        let public_key = &key_shares[0].public_key;
+
+        // Every subset of refreshed shares signs for the same key
+        for signers in [[0, 1], [1, 2], [0, 2]].iter() {
+            let subset = signers
+                .iter()
+                .map(|&position| refreshed[position].clone())
+                .collect::<Vec<_>>();
+            for signature in simulate::sign(&subset, message).unwrap() {
+                assert_eq!(&signature.public_key, public_key);
+            }
+        }
+
+        // Shares from before the refresh cannot be combined
+        // with shares from after the refresh
+        let mixed = vec![key_shares[0].clone(), refreshed[1].clone()];
+        assert!(simulate::sign(&mixed, message).is_err());
+        let mixed = vec![refreshed[0].clone(), key_shares[2].clone()];
+        assert!(simulate::sign(&mixed, message).is_err());
+    }
+
+    #[test]
+    fn refresh_messages() {
+        let key_shares = simulate::keygen(Parameters::default()).unwrap();
+        let mut refresher = KeyRefresher::new(key_shares[0].clone());
+        let (_, messages) = refresher.proceed().unwrap();
+        assert_eq!(messages.len(), 1);
+        assert!(matches!(refresher.create(), Err(Error::RefreshNotFinished)));
+
+        // Messages from this party or out of range are rejected
+        for sender in [0, 1, 4].iter() {
+            let result = refresher.handle_incoming(Msg {
+                sender: *sender,
+                receiver: None,
+                body: messages[0].body.clone(),
+            });
+            match result {
+                Err(Error::Protocol(error)) => assert_eq!(error.kind, "InvalidParty"),
+                _ => panic!("expected invalid party"),
+            }
+        }
+
+        let mut other = KeyRefresher::new(key_shares[1].clone());
+        let (_, other_messages) = other.proceed().unwrap();
+        let message = Msg {
+            sender: 2,
+            receiver: None,
+            body: other_messages[0].body.clone(),
+        };
+        refresher.handle_incoming(message.clone()).unwrap();
+        match refresher.handle_incoming(message) {
+            Err(Error::Protocol(error)) => {
+                assert_eq!(error.kind, "DuplicateMessage");
+                assert_eq!(error.culprits, vec![2]);
+            }
+            _ => panic!("expected duplicate message"),
+        }
+
+        // Round two cannot start without every round one message
+        match refresher.proceed() {
+            Err(Error::Protocol(error)) => assert_eq!(error.kind, "MissingMessages"),
+            _ => panic!("expected missing messages"),
+        }
+    }
+
+    #[test]
+    fn inconsistent_broadcast() {
+        let key_shares = simulate::keygen(Parameters::default()).unwrap();
+        let mut refreshers = key_shares
+            .iter()
+            .map(|key_share| KeyRefresher::new(key_share.clone()))
+            .collect::<Vec<_>>();
+        let mut messages = Vec::new();
+        for refresher in refreshers.iter_mut() {
+            messages.extend(refresher.proceed().unwrap().1);
+        }
+        for _ in 1..ROUNDS {
+            let mut outgoing = Vec::new();
+            for (position, refresher) in refreshers.iter_mut().enumerate() {
+                for message in simulate::route(&messages, position as u16 + 1) {
+                    refresher.handle_incoming(message).unwrap();
+                }
+                outgoing.extend(refresher.proceed().unwrap().1);
+            }
+            messages = outgoing;
+        }
+
+        // Party 2 reports a digest for different broadcast values
+        for mut message in simulate::route(&messages, 1) {
+            if message.sender == 2 {
+                message.body = RefreshMessage::Round3([0u8; 32]);
+            }
+            refreshers[0].handle_incoming(message).unwrap();
+        }
+        match refreshers[0].proceed() {
+            Err(Error::Protocol(error)) => {
+                assert_eq!(error.kind, INCONSISTENT_BROADCAST);
+                assert_eq!(error.culprits, vec![2]);
+            }
+            _ => panic!("expected inconsistent broadcast"),
+        }
+        assert!(matches!(
+            refreshers[0].create(),
+            Err(Error::RefreshNotFinished)
+        ));
+    }
+}
@@ -0,0 +1,874 @@
+//! Resharing to a new committee.
+//!
+//! At least `t + 1` holders of an existing key share (the dealers)
+//! issue shares of the same secret to a new committee of `n'`
+//! parties with a signing threshold `t'`; the public key and address
+//! do not change.
+//!
+//! Every member of the new committee takes part in the session and
+//! the session party number is the index of the new key share.
+//! Holders that are leaving the committee can also be dealers; they
+//! join the session after the members of the new committee, do not
+//! send a Paillier key and do not receive a new key share. As only
+//! `t + 1` dealers are needed a lost key share can be replaced
+//! without the missing party.
+//!
+//! Resharing does not invalidate the existing key shares: any
+//! `t + 1` of them can still sign for the same key. Retired or lost
+//! key shares therefore remain usable until the holders delete them,
+//! so resharing alone does not remove a compromised share.
+//!
+//! Every member must know the key being reshared; the dealings are
+//! checked against it rather than against values reported by the
+//! dealers so dealers cannot substitute a different key.
+//!
+//! In the first round every party broadcasts a new Paillier key and
+//! ring-pedersen parameters with proofs that they are well formed;
+//! dealers also announce the index of their existing key share and
+//! the public key shares. In the second round each dealer multiplies
+//! their share by the Lagrange coefficient for the set of dealers and
+//! distributes evaluations of a random polynomial of degree `t'` with
+//! that constant term, encrypted to the Paillier key of each recipient.
+//!
+//! Dealers also send the secret chain code of the existing key shares
+//! encrypted to each recipient so that the new key shares derive the
+//! same child keys; every dealer must send the same chain code.
+use curv::{
+    arithmetic::Converter,
+    cryptographic_primitives::secret_sharing::feldman_vss::{ShamirSecretSharing, VerifiableSS},
+    elliptic::curves::{Point, Scalar, Secp256k1},
+    BigInt,
+};
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::{
+    party_i::{Keys, SharedKeys},
+    state_machine::keygen::LocalKey,
+};
+use paillier::{Decrypt, Encrypt, EncryptionKey, Paillier, RawCiphertext, RawPlaintext};
+use round_based::Msg;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use zk_paillier::zkproofs::DLogStatement;
+
+use crate::{
+    shares::{evaluate, evaluate_commitments, inconsistent_shares, lagrange, PublicKeys},
+    Error, KeyShare, Parameters, ProtocolError, Result, RoundMsg,
+};
+
+/// Number of rounds in the resharing protocol.
+pub const ROUNDS: u16 = 2;
+
+/// Key being reshared.
+///
+/// Known to every member of the new committee before the
+/// session starts, for example from the group that holds the
+/// existing key shares.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReshareKey {
+    /// Uncompressed public key being reshared.
+    #[serde(rename = "publicKey")]
+    pub public_key: Vec<u8>,
+    /// Threshold of the existing key shares.
+    pub threshold: u16,
+    /// Number of dealers that are not members of the new committee;
+    /// their party numbers follow the members of the new committee.
+    #[serde(default)]
+    pub retiring: u16,
+}
+
+/// Existing key share information announced by a dealer.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Dealer {
+    /// Index of the existing key share.
+    pub index: u16,
+    /// Public key shares for the existing key shares.
+    pub pk_vec: Vec<Point<Secp256k1>>,
+}
+
+/// Polynomial commitments and encrypted evaluations from a dealer.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Dealing {
+    /// Commitments to all the coefficients of the polynomial.
+    pub commitments: Vec<Point<Secp256k1>>,
+    /// Encrypted evaluations keyed by new party index.
+    pub shares: Vec<(u16, BigInt)>,
+    /// Encrypted chain code keyed by new party index; empty when
+    /// the existing key share does not have a chain code.
+    #[serde(default)]
+    pub chain_codes: Vec<(u16, BigInt)>,
+}
+
+/// Message broadcast during resharing.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ReshareMessage {
+    /// Paillier key and ring-pedersen parameters for the sender.
+    Round1 {
+        /// Public keys with the proofs they are well formed; not
+        /// sent by dealers that are leaving the committee.
+        keys: Option<PublicKeys>,
+        /// Set when the sender is a dealer.
+        dealer: Option<Dealer>,
+    },
+    /// Dealing from the sender; parties that are not
+    /// dealers send `None`.
+    Round2 {
+        /// The dealing.
+        dealing: Option<Dealing>,
+    },
+}
+
+/// Round-based resharing of a key to a new committee.
+pub struct KeyResharer {
+    parameters: Parameters,
+    index: u16,
+    public_key: Point<Secp256k1>,
+    threshold: u16,
+    retiring: u16,
+    local_key: Option<LocalKey<Secp256k1>>,
+    chain_code: Option<[u8; 32]>,
+    round: u16,
+    keys: Option<Keys>,
+    encryption_keys: BTreeMap<u16, EncryptionKey>,
+    dlog_statements: BTreeMap<u16, DLogStatement>,
+    dealers: BTreeMap<u16, Dealer>,
+    dealings: BTreeMap<u16, Option<(Vec<Point<Secp256k1>>, Scalar<Secp256k1>)>>,
+    chain_codes: BTreeMap<u16, Option<[u8; 32]>>,
+    output: Option<LocalKey<Secp256k1>>,
+}
+
+impl KeyResharer {
+    /// Create a resharer for a party in the session.
+    ///
+    /// The parameters are for the new committee and the party
+    /// number is the index for the new key share; party numbers
+    /// after the new committee are dealers that are leaving it.
+    ///
+    /// Dealers must provide their existing key share which must
+    /// be a share of the key being reshared.
+    pub fn new(
+        parameters: Parameters,
+        number: u16,
+        key: ReshareKey,
+        key_share: Option<KeyShare>,
+    ) -> Result<Self> {
+        parameters.validate()?;
+        let session_parties = parameters.parties as usize + key.retiring as usize;
+        if session_parties > u16::MAX as usize {
+            return Err(Error::Reshare("too many retiring dealers".to_string()));
+        }
+        if number == 0 || number as usize > session_parties {
+            return Err(Error::Reshare(format!(
+                "party number {} is out of range for {} parties",
+                number, session_parties
+            )));
+        }
+        if number > parameters.parties && key_share.is_none() {
+            return Err(Error::Reshare(format!(
+                "party {} is not in the new committee and must be a dealer",
+                number
+            )));
+        }
+        let public_key = Point::<Secp256k1>::from_bytes(&key.public_key)
+            .map_err(|_| Error::Reshare("public key being reshared is invalid".to_string()))?;
+        if key.threshold == 0 {
+            return Err(Error::Reshare(
+                "threshold of the existing key shares is invalid".to_string(),
+            ));
+        }
+        let (local_key, chain_code) = match key_share {
+            Some(key_share) => (Some(key_share.local_key), key_share.chain_code),
+            None => (None, None),
+        };
+        if let Some(local_key) = &local_key {
+            if local_key.public_key() != public_key
+                || local_key.t != key.threshold
+                || local_key.pk_vec.len() <= key.threshold as usize
+                || !inconsistent_shares(&local_key.pk_vec, &public_key, key.threshold).is_empty()
+            {
+                return Err(Error::Reshare(
+                    "key share is not for the key being reshared".to_string(),
+                ));
+            }
+        }
+
+        Ok(Self {
+            parameters,
+            index: number,
+            public_key,
+            threshold: key.threshold,
+            retiring: key.retiring,
+            local_key,
+            chain_code,
+            round: 0,
+            keys: None,
+            encryption_keys: Default::default(),
+            dlog_statements: Default::default(),
+            dealers: Default::default(),
+            dealings: Default::default(),
+            chain_codes: Default::default(),
+            output: None,
+        })
+    }
+
+    /// Handle an incoming message.
+    pub fn handle_incoming(&mut self, message: Msg<ReshareMessage>) -> Result<()> {
+        let sender = message.sender;
+        if sender == 0 || sender as usize > self.session_parties() || sender == self.index {
+            return Err(self.blame(
+                sender,
+                "InvalidParty",
+                format!("reshare message from invalid party {}", sender),
+            ));
+        }
+
+        match message.body {
+            ReshareMessage::Round1 { keys, dealer } if self.round == 1 => {
+                if self.encryption_keys.contains_key(&sender) || self.dealers.contains_key(&sender)
+                {
+                    return Err(self.duplicate(sender));
+                }
+                match (&keys, self.is_member(sender)) {
+                    (Some(keys), true) => {
+                        if !keys.verify() {
+                            return Err(self.blame(
+                                sender,
+                                "InvalidProof",
+                                format!(
+                                    "party {} sent invalid paillier key or ring-pedersen proofs",
+                                    sender
+                                ),
+                            ));
+                        }
+                    }
+                    (None, false) => {}
+                    (None, true) => {
+                        return Err(self.blame(
+                            sender,
+                            "InvalidKeys",
+                            format!("party {} did not send a paillier key", sender),
+                        ))
+                    }
+                    (Some(_), false) => {
+                        return Err(self.blame(
+                            sender,
+                            "InvalidKeys",
+                            format!(
+                                "party {} is leaving the committee and must not send keys",
+                                sender
+                            ),
+                        ))
+                    }
+                }
+                match &dealer {
+                    Some(dealer) => self.check_dealer(sender, dealer)?,
+                    None if !self.is_member(sender) => {
+                        return Err(self.blame(
+                            sender,
+                            "InvalidDealer",
+                            format!(
+                                "party {} is leaving the committee and is not a dealer",
+                                sender
+                            ),
+                        ))
+                    }
+                    None => {}
+                }
+                if let Some(keys) = keys {
+                    self.encryption_keys.insert(sender, keys.ek);
+                    self.dlog_statements.insert(sender, keys.dlog_statement);
+                }
+                if let Some(dealer) = dealer {
+                    self.dealers.insert(sender, dealer);
+                }
+            }
+            ReshareMessage::Round2 { dealing } if self.round == 2 => {
+                if self.dealings.contains_key(&sender) {
+                    return Err(self.duplicate(sender));
+                }
+                let dealing = match (dealing, self.dealers.contains_key(&sender)) {
+                    // Dealers leaving the committee do not receive shares
+                    (Some(_), true) if !self.is_member(self.index) => None,
+                    (Some(dealing), true) => {
+                        let (dealing, chain_code) = self.receive_dealing(sender, dealing)?;
+                        self.chain_codes.insert(sender, chain_code);
+                        Some(dealing)
+                    }
+                    (None, false) => None,
+                    _ => {
+                        return Err(self.blame(
+                            sender,
+                            "UnexpectedDealing",
+                            format!("party {} sent an unexpected dealing", sender),
+                        ))
+                    }
+                };
+                self.dealings.insert(sender, dealing);
+            }
+            _ => {
+                return Err(self.blame(
+                    sender,
+                    "WrongRound",
+                    format!("party {} sent a message for the wrong round", sender),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /// Proceed to the next round and return the round number
+    /// with the messages to send to the other parties.
+    pub fn proceed(&mut self) -> Result<(u16, Vec<RoundMsg<ReshareMessage>>)> {
+        let messages = match self.round {
+            0 => vec![self.round1()],
+            1 => vec![self.round2()?],
+            2 => {
+                self.finish()?;
+                vec![]
+            }
+            _ => return Err(Error::ReshareFinished),
+        };
+        self.round += 1;
+
+        let round = self.round;
+        let messages = messages
+            .into_iter()
+            .map(|body| RoundMsg {
+                round,
+                sender: self.index,
+                receiver: None,
+                body,
+            })
+            .collect::<Vec<_>>();
+        Ok((round, messages))
+    }
+
+    /// Create the new key share.
+    ///
+    /// Dealers that are leaving the committee do not receive
+    /// a key share and must delete their existing key share.
+    pub fn create(&mut self) -> Result<KeyShare> {
+        if !self.is_member(self.index) {
+            return Err(Error::Reshare(format!(
+                "party {} is leaving the committee and does not receive a key share",
+                self.index
+            )));
+        }
+        let local_key = self.output.take().ok_or(Error::ReshareNotFinished)?;
+        let public_key = local_key.public_key().to_bytes(false).to_vec();
+        Ok(KeyShare::new(local_key, public_key, self.chain_code)?)
+    }
+
+    /// Generate a Paillier key and ring-pedersen parameters
+    /// when this party is a member of the new committee.
+    fn round1(&mut self) -> ReshareMessage {
+        let keys = if self.is_member(self.index) {
+            Some(Keys::create(self.index as usize))
+        } else {
+            None
+        };
+        let public_keys = keys.as_ref().map(PublicKeys::new);
+        let dealer = self.local_key.as_ref().map(|local_key| Dealer {
+            index: local_key.i,
+            pk_vec: local_key.pk_vec.clone(),
+        });
+
+        if let Some(public_keys) = &public_keys {
+            self.encryption_keys
+                .insert(self.index, public_keys.ek.clone());
+            self.dlog_statements
+                .insert(self.index, public_keys.dlog_statement.clone());
+        }
+        if let Some(dealer) = &dealer {
+            self.dealers.insert(self.index, dealer.clone());
+        }
+        self.keys = keys;
+
+        ReshareMessage::Round1 {
+            keys: public_keys,
+            dealer,
+        }
+    }
+
+    /// Check the dealers and distribute the dealing
+    /// when this party is a dealer.
+    fn round2(&mut self) -> Result<ReshareMessage> {
+        self.expect(self.announced() - 1)?;
+        self.check_dealers()?;
+
+        let local_key = match &self.local_key {
+            Some(local_key) => local_key,
+            None => return Ok(ReshareMessage::Round2 { dealing: None }),
+        };
+
+        let lambda = lagrange(local_key.i, &self.dealer_indices(), 0);
+        let mut coefficients = vec![lambda * &local_key.keys_linear.x_i];
+        coefficients.extend((0..self.parameters.threshold).map(|_| Scalar::<Secp256k1>::random()));
+        let commitments = coefficients
+            .iter()
+            .map(|a| Point::<Secp256k1>::generator() * a)
+            .collect::<Vec<_>>();
+
+        let shares = (1..=self.parameters.parties)
+            .filter(|index| *index != self.index)
+            .map(|index| {
+                let share = evaluate(&coefficients, index);
+                let ek = &self.encryption_keys[&index];
+                let ciphertext: BigInt =
+                    Paillier::encrypt(ek, RawPlaintext::from(share.to_bigint())).into();
+                (index, ciphertext)
+            })
+            .collect();
+
+        let chain_codes = match &self.chain_code {
+            Some(chain_code) => (1..=self.parameters.parties)
+                .filter(|index| *index != self.index)
+                .map(|index| {
+                    let ek = &self.encryption_keys[&index];
+                    let ciphertext: BigInt =
+                        Paillier::encrypt(ek, RawPlaintext::from(BigInt::from_bytes(chain_code)))
+                            .into();
+                    (index, ciphertext)
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        if self.is_member(self.index) {
+            let own_share = evaluate(&coefficients, self.index);
+            self.dealings
+                .insert(self.index, Some((commitments.clone(), own_share)));
+            self.chain_codes.insert(self.index, self.chain_code);
+        } else {
+            self.dealings.insert(self.index, None);
+        }
+
+        Ok(ReshareMessage::Round2 {
+            dealing: Some(Dealing {
+                commitments,
+                shares,
+                chain_codes,
+            }),
+        })
+    }
+
+    /// Check the public key shares announced by a dealer are
+    /// shares of the key being reshared.
+    fn check_dealer(&self, sender: u16, dealer: &Dealer) -> Result<()> {
+        let n = dealer.pk_vec.len();
+        if n <= self.threshold as usize
+            || dealer.index == 0
+            || dealer.index as usize > n
+            || !inconsistent_shares(&dealer.pk_vec, &self.public_key, self.threshold).is_empty()
+        {
+            return Err(self.blame(
+                sender,
+                "InvalidDealer",
+                format!(
+                    "party {} is not a dealer for the key being reshared",
+                    sender
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Verify and decrypt a dealing and chain code for this party.
+    #[allow(clippy::type_complexity)]
+    fn receive_dealing(
+        &self,
+        sender: u16,
+        dealing: Dealing,
+    ) -> Result<((Vec<Point<Secp256k1>>, Scalar<Secp256k1>), Option<[u8; 32]>)> {
+        let invalid = |reason: &str| {
+            self.blame(
+                sender,
+                "InvalidDealing",
+                format!("party {} sent an invalid dealing: {}", sender, reason),
+            )
+        };
+
+        if dealing.commitments.len() != self.parameters.threshold as usize + 1 {
+            return Err(invalid("wrong number of commitments"));
+        }
+
+        // The constant term must commit to the dealer's
+        // existing share weighted by the Lagrange coefficient
+        let dealer = &self.dealers[&sender];
+        let lambda = lagrange(dealer.index, &self.dealer_indices(), 0);
+        if dealing.commitments[0] != &dealer.pk_vec[dealer.index as usize - 1] * &lambda {
+            return Err(invalid("commitment does not match the existing key share"));
+        }
+
+        let ciphertext = dealing
+            .shares
+            .into_iter()
+            .find(|(index, _)| *index == self.index)
+            .map(|(_, share)| share)
+            .ok_or_else(|| invalid("missing share"))?;
+        let dk = &self.keys.as_ref().unwrap().dk;
+        let plaintext: BigInt = Paillier::decrypt(dk, RawCiphertext::from(ciphertext)).into();
+        let share = Scalar::<Secp256k1>::from_bigint(&plaintext);
+        if Point::<Secp256k1>::generator() * &share
+            != evaluate_commitments(&dealing.commitments, self.index)
+        {
+            return Err(invalid("share does not match the commitments"));
+        }
+
+        let chain_code = if dealing.chain_codes.is_empty() {
+            None
+        } else {
+            let ciphertext = dealing
+                .chain_codes
+                .into_iter()
+                .find(|(index, _)| *index == self.index)
+                .map(|(_, chain_code)| chain_code)
+                .ok_or_else(|| invalid("missing chain code"))?;
+            let plaintext: BigInt = Paillier::decrypt(dk, RawCiphertext::from(ciphertext)).into();
+            let bytes = plaintext.to_bytes();
+            if bytes.len() > 32 {
+                return Err(invalid("chain code is too long"));
+            }
+            let mut chain_code = [0u8; 32];
+            chain_code[32 - bytes.len()..].copy_from_slice(&bytes);
+            Some(chain_code)
+        };
+        Ok(((dealing.commitments, share), chain_code))
+    }
+
+    /// Combine the dealings into the new key share.
+    fn finish(&mut self) -> Result<()> {
+        self.expect(self.dealings.len() - usize::from(self.local_key.is_some()))?;
+        if !self.is_member(self.index) {
+            return Ok(());
+        }
+        let keys = self.keys.take().ok_or(Error::ReshareNotFinished)?;
+
+        let dealings = self.dealings.values().flatten().collect::<Vec<_>>();
+        let x_i = dealings
+            .iter()
+            .fold(Scalar::<Secp256k1>::zero(), |acc, (_, share)| acc + share);
+
+        let commitments = (0..=self.parameters.threshold as usize)
+            .map(|k| {
+                dealings
+                    .iter()
+                    .fold(Point::<Secp256k1>::zero(), |acc, (commitments, _)| {
+                        acc + &commitments[k]
+                    })
+            })
+            .collect::<Vec<_>>();
+        if commitments[0] != self.public_key {
+            return Err(self.failure("dealings do not combine to the key being reshared"));
+        }
+
+        let pk_vec = (1..=self.parameters.parties)
+            .map(|index| evaluate_commitments(&commitments, index))
+            .collect::<Vec<_>>();
+        if Point::<Secp256k1>::generator() * &x_i != pk_vec[self.index as usize - 1] {
+            return Err(self.failure("new key share does not match the public key shares"));
+        }
+
+        let mut chain_codes = self.chain_codes.values();
+        let chain_code = chain_codes.next().copied().flatten();
+        if chain_codes.any(|other| *other != chain_code) {
+            return Err(self.failure("dealers disagree on the chain code"));
+        }
+        self.chain_code = chain_code;
+
+        self.output = Some(LocalKey {
+            paillier_dk: keys.dk,
+            pk_vec,
+            keys_linear: SharedKeys {
+                y: self.public_key.clone(),
+                x_i,
+            },
+            paillier_key_vec: self.encryption_keys.values().cloned().collect(),
+            y_sum_s: self.public_key.clone(),
+            h1_h2_n_tilde_vec: self.dlog_statements.values().cloned().collect(),
+            vss_scheme: VerifiableSS {
+                parameters: ShamirSecretSharing {
+                    threshold: self.parameters.threshold,
+                    share_count: self.parameters.parties,
+                },
+                commitments,
+            },
+            i: self.index,
+            t: self.parameters.threshold,
+            n: self.parameters.parties,
+        });
+        Ok(())
+    }
+
+    /// Check the dealers agree on the public key shares and
+    /// there are enough dealers to reconstruct the key.
+    fn check_dealers(&self) -> Result<()> {
+        let mut dealers = self.dealers.values();
+        let first = dealers
+            .next()
+            .ok_or_else(|| self.failure("no dealers for key resharing"))?;
+        if dealers.any(|dealer| dealer.pk_vec != first.pk_vec) {
+            return Err(self.failure("dealers disagree on the key being reshared"));
+        }
+
+        let mut indices = self.dealer_indices();
+        indices.dedup();
+        if indices.len() != self.dealers.len() {
+            return Err(self.failure("dealers have duplicate key share indices"));
+        }
+        if indices.len() <= self.threshold as usize {
+            return Err(self.failure(&format!(
+                "resharing requires {} dealers but only {} are available",
+                self.threshold + 1,
+                indices.len()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Number of parties in the session including dealers
+    /// that are leaving the committee.
+    fn session_parties(&self) -> usize {
+        self.parameters.parties as usize + self.retiring as usize
+    }
+
+    /// Whether a party is a member of the new committee.
+    fn is_member(&self, party: u16) -> bool {
+        party <= self.parameters.parties
+    }
+
+    /// Number of parties that sent the first round message
+    /// including this party.
+    fn announced(&self) -> usize {
+        self.encryption_keys
+            .keys()
+            .chain(self.dealers.keys())
+            .collect::<std::collections::BTreeSet<_>>()
+            .len()
+    }
+
+    /// Existing key share indices for the dealers in order.
+    fn dealer_indices(&self) -> Vec<u16> {
+        let mut indices = self
+            .dealers
+            .values()
+            .map(|dealer| dealer.index)
+            .collect::<Vec<_>>();
+        indices.sort_unstable();
+        indices
+    }
+
+    /// Check all the other parties sent a message for the round.
+    fn expect(&self, received: usize) -> Result<()> {
+        let expected = self.session_parties() - 1;
+        if received != expected {
+            return Err(ProtocolError {
+                round: self.round,
+                kind: "MissingMessages".to_string(),
+                culprits: vec![],
+                message: format!(
+                    "expected messages from {} parties but got {}",
+                    expected, received
+                ),
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    fn blame(&self, sender: u16, kind: &str, message: String) -> Error {
+        ProtocolError {
+            round: self.round,
+            kind: kind.to_string(),
+            culprits: vec![sender],
+            message,
+        }
+        .into()
+    }
+
+    fn failure(&self, message: &str) -> Error {
+        ProtocolError {
+            round: self.round,
+            kind: "InvalidReshare".to_string(),
+            culprits: vec![],
+            message: message.to_string(),
+        }
+        .into()
+    }
+
+    fn duplicate(&self, sender: u16) -> Error {
+        self.blame(
+            sender,
+            "DuplicateMessage",
+            format!("duplicate reshare message from party {}", sender),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{simulate, KeyShareReport};
+
+    fn key(key_share: &KeyShare) -> ReshareKey {
+        ReshareKey {
+            public_key: key_share.public_key.clone(),
+            threshold: key_share.local_key.t,
+            retiring: 0,
+        }
+    }
+
+    #[test]
+    fn reshare_and_sign() {
+        let key_shares = simulate::keygen(Parameters::default()).unwrap();
+        let parameters = Parameters {
+            parties: 4,
+            threshold: 2,
+        };
+
+        // Holders of the first two key shares deal to a new
+        // committee with two new members
+        let dealers = vec![
+            Some(key_shares[0].clone()),
+            Some(key_shares[1].clone()),
+            None,
+            None,
+        ];
+        let reshared =
+            simulate::reshare(parameters.clone(), key(&key_shares[0]), dealers.clone()).unwrap();
+        assert_eq!(reshared.len(), 4);
+        for (position, key_share) in reshared.iter().enumerate() {
+            assert_eq!(key_share.public_key, key_shares[0].public_key);
+            assert_eq!(key_share.address, key_shares[0].address);
+            assert_eq!(key_share.chain_code, key_shares[0].chain_code);
+            assert_eq!(key_share.local_key.i, position as u16 + 1);
+            assert_eq!(key_share.local_key.t, parameters.threshold);
+            assert_eq!(key_share.local_key.n, parameters.parties);
+            let report = KeyShareReport::new(key_share);
+            assert!(report.valid, "{:?}", report);
+        }
+
+        // The new committee signs without the old shares
+        let message = [3u8; 32];
+        let subset = vec![
+            reshared[0].clone(),
+            reshared[2].clone(),
+            reshared[3].clone(),
+        ];
+        for signature in simulate::sign(&subset, message).unwrap() {
+            assert_eq!(signature.public_key, key_shares[0].public_key);
+        }
+
+        // The new threshold must be crossed
+        assert!(simulate::sign(&reshared[1..3], message).is_err());
+
+        // Dealers must agree on the chain code
+        let mut dealers = dealers;
+        if let Some(dealer) = dealers[1].as_mut() {
+            dealer.chain_code = Some([0u8; 32]);
+        }
+        match simulate::reshare(parameters, key(&key_shares[0]), dealers) {
+            Err(Error::Protocol(error)) => assert_eq!(error.kind, "InvalidReshare"),
+            _ => panic!("expected dealers to disagree on the chain code"),
+        }
+    }
+
+    #[test]
+    fn reshare_with_retiring_dealer() {
+        let key_shares = simulate::keygen(Parameters::default()).unwrap();
+        let parameters = Parameters::default();
+        let mut retiring = key(&key_shares[0]);
+        retiring.retiring = 1;
+
+        // The holder of the third key share deals but leaves
+        // the committee and two new members join
+        let dealers = vec![
+            Some(key_shares[0].clone()),
+            None,
+            None,
+            Some(key_shares[2].clone()),
+        ];
+        let reshared = simulate::reshare(parameters.clone(), retiring.clone(), dealers).unwrap();
+        assert_eq!(reshared.len(), 3);
+        for key_share in reshared.iter() {
+            assert_eq!(key_share.public_key, key_shares[0].public_key);
+            let report = KeyShareReport::new(key_share);
+            assert!(report.valid, "{:?}", report);
+        }
+
+        let message = [5u8; 32];
+        for signature in simulate::sign(&reshared[1..], message).unwrap() {
+            assert_eq!(signature.public_key, key_shares[0].public_key);
+        }
+
+        // Existing key shares are still valid until they are deleted
+        for signature in simulate::sign(&key_shares[1..], message).unwrap() {
+            assert_eq!(signature.public_key, key_shares[0].public_key);
+        }
+
+        // A party outside of the new committee must be a dealer
+        let result = KeyResharer::new(parameters.clone(), 4, retiring.clone(), None);
+        assert!(matches!(result, Err(Error::Reshare(_))));
+        let result = KeyResharer::new(parameters, 5, retiring, Some(key_shares[1].clone()));
+        assert!(matches!(result, Err(Error::Reshare(_))));
+    }
+
+    #[test]
+    fn reshare_known_key() {
+        let key_shares = simulate::keygen(Parameters::default()).unwrap();
+        let other = simulate::keygen(Parameters::default()).unwrap();
+        let parameters = Parameters::default();
+
+        // Dealers cannot reshare a different key
+        let result = KeyResharer::new(
+            parameters.clone(),
+            1,
+            key(&other[0]),
+            Some(key_shares[0].clone()),
+        );
+        assert!(matches!(result, Err(Error::Reshare(_))));
+
+        let mut wrong_threshold = key(&key_shares[0]);
+        wrong_threshold.threshold = 2;
+        let result = KeyResharer::new(
+            parameters.clone(),
+            1,
+            wrong_threshold,
+            Some(key_shares[0].clone()),
+        );
+        assert!(matches!(result, Err(Error::Reshare(_))));
+
+        // A member that expects a different key rejects the dealers
+        let dealers = vec![
+            Some(key_shares[0].clone()),
+            Some(key_shares[1].clone()),
+            None,
+        ];
+        let mut resharers = dealers
+            .into_iter()
+            .enumerate()
+            .map(|(position, key_share)| {
+                let expected = if key_share.is_some() {
+                    key(&key_shares[0])
+                } else {
+                    key(&other[0])
+                };
+                KeyResharer::new(parameters.clone(), position as u16 + 1, expected, key_share)
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let messages = resharers
+            .iter_mut()
+            .flat_map(|resharer| resharer.proceed().unwrap().1)
+            .collect::<Vec<_>>();
+        let error = simulate::route(&messages, 3)
+            .into_iter()
+            .map(|message| resharers[2].handle_incoming(message))
+            .find_map(|result| result.err());
+        match error {
+            Some(Error::Protocol(error)) => {
+                assert_eq!(error.kind, "InvalidDealer");
+                assert_eq!(error.culprits, vec![1]);
+            }
+            _ => panic!("expected invalid dealer"),
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! Helpers shared by key refresh and resharing.
+//!
+//! Both protocols give every party a new Paillier key and new
+//! ring-pedersen parameters (`h1`, `h2`, `N~`) with proofs that they
+//! are well formed and distribute evaluations of a polynomial that
+//! are checked against commitments to its coefficients.
+use curv::elliptic::curves::{Point, Scalar, Secp256k1};
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::party_i::Keys;
+use paillier::EncryptionKey;
+use serde::{Deserialize, Serialize};
+use zk_paillier::zkproofs::{CompositeDLogProof, DLogStatement, NiCorrectKeyProof, SALT_STRING};
+
+/// Paillier key and ring-pedersen parameters for a party
+/// with the proofs that they are well formed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PublicKeys {
+    /// The Paillier encryption key.
+    pub ek: EncryptionKey,
+    /// Proof that the encryption key is well formed.
+    pub correct_key_proof: NiCorrectKeyProof,
+    /// The ring-pedersen parameters.
+    pub dlog_statement: DLogStatement,
+    /// Proof of the discrete log of `h2` in base `h1`.
+    pub composite_dlog_proof_base_h1: CompositeDLogProof,
+    /// Proof of the discrete log of `h1` in base `h2`.
+    pub composite_dlog_proof_base_h2: CompositeDLogProof,
+}
+
+impl PublicKeys {
+    /// Public keys and proofs for newly generated keys.
+    pub(crate) fn new(keys: &Keys) -> Self {
+        let dlog_statement = DLogStatement {
+            N: keys.N_tilde.clone(),
+            g: keys.h1.clone(),
+            ni: keys.h2.clone(),
+        };
+        let dlog_statement_base_h2 = DLogStatement {
+            N: keys.N_tilde.clone(),
+            g: keys.h2.clone(),
+            ni: keys.h1.clone(),
+        };
+        Self {
+            ek: keys.ek.clone(),
+            correct_key_proof: NiCorrectKeyProof::proof(&keys.dk, None),
+            composite_dlog_proof_base_h1: CompositeDLogProof::prove(&dlog_statement, &keys.xhi),
+            composite_dlog_proof_base_h2: CompositeDLogProof::prove(
+                &dlog_statement_base_h2,
+                &keys.xhi_inv,
+            ),
+            dlog_statement,
+        }
+    }
+
+    /// Verify the proofs for the Paillier key and
+    /// ring-pedersen parameters.
+    pub(crate) fn verify(&self) -> bool {
+        let dlog_statement_base_h2 = DLogStatement {
+            N: self.dlog_statement.N.clone(),
+            g: self.dlog_statement.ni.clone(),
+            ni: self.dlog_statement.g.clone(),
+        };
+        self.correct_key_proof.verify(&self.ek, SALT_STRING).is_ok()
+            && self
+                .composite_dlog_proof_base_h1
+                .verify(&self.dlog_statement)
+                .is_ok()
+            && self
+                .composite_dlog_proof_base_h2
+                .verify(&dlog_statement_base_h2)
+                .is_ok()
+    }
+}
+
+/// Lagrange coefficient for `index` in the set of `indices`
+/// evaluated at `x`.
+pub(crate) fn lagrange(index: u16, indices: &[u16], x: u16) -> Scalar<Secp256k1> {
+    let x = Scalar::<Secp256k1>::from(x);
+    let xi = Scalar::<Secp256k1>::from(index);
+    // The indices are distinct so the denominator is not zero
+    indices
+        .iter()
+        .filter(|&&k| k != index)
+        .fold(Scalar::<Secp256k1>::from(1u16), |lambda, &k| {
+            let xk = Scalar::<Secp256k1>::from(k);
+            let denominator = (&xi - &xk).invert().unwrap();
+            lambda * (&x - &xk) * denominator
+        })
+}
+
+/// Evaluate at `x` the polynomial in the exponent through the
+/// public key shares of the parties in the quorum.
+pub(crate) fn interpolate(pk_vec: &[Point<Secp256k1>], quorum: &[u16], x: u16) -> Point<Secp256k1> {
+    quorum.iter().fold(Point::zero(), |sum, &i| {
+        sum + &pk_vec[i as usize - 1] * &lagrange(i, quorum, x)
+    })
+}
+
+/// Public key shares that are not consistent with the public key
+/// for the threshold, empty when every share lies on a polynomial
+/// of degree `t` whose constant term is the public key.
+///
+/// The first `t + 1` shares determine the polynomial so the public
+/// key and every other share are interpolated from them; the
+/// caller must ensure there are more than `t` shares.
+pub(crate) fn inconsistent_shares(
+    pk_vec: &[Point<Secp256k1>],
+    public_key: &Point<Secp256k1>,
+    threshold: u16,
+) -> Vec<u16> {
+    let quorum = (1..=threshold + 1).collect::<Vec<_>>();
+    let mut indices = Vec::new();
+    if &interpolate(pk_vec, &quorum, 0) != public_key {
+        indices.push(0);
+    }
+    for index in threshold + 2..=pk_vec.len() as u16 {
+        if interpolate(pk_vec, &quorum, index) != pk_vec[index as usize - 1] {
+            indices.push(index);
+        }
+    }
+    indices
+}
+
+/// Evaluate a polynomial given all of its coefficients.
+pub(crate) fn evaluate(coefficients: &[Scalar<Secp256k1>], index: u16) -> Scalar<Secp256k1> {
+    let x = Scalar::<Secp256k1>::from(index);
+    coefficients
+        .iter()
+        .rev()
+        .fold(Scalar::<Secp256k1>::zero(), |acc, coefficient| {
+            acc * &x + coefficient
+        })
+}
+
+/// Evaluate a polynomial in the exponent given commitments
+/// to all of its coefficients.
+pub(crate) fn evaluate_commitments(
+    commitments: &[Point<Secp256k1>],
+    index: u16,
+) -> Point<Secp256k1> {
+    let x = Scalar::<Secp256k1>::from(index);
+    commitments
+        .iter()
+        .rev()
+        .fold(Point::<Secp256k1>::zero(), |acc, commitment| {
+            acc * &x + commitment
+        })
+}
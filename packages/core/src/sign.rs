@@ -0,0 +1,187 @@
+//! Message signing.
+use curv::{arithmetic::Converter, elliptic::curves::Secp256k1, BigInt};
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::{
+    party_i::{verify, SignatureRecid},
+    state_machine::{
+        keygen::LocalKey,
+        sign::{
+            CompletedOfflineStage, OfflineProtocolMessage, OfflineStage, PartialSignature,
+            SignManual,
+        },
+    },
+};
+use round_based::{Msg, StateMachine};
+use serde::{Deserialize, Serialize};
+
+use crate::{round_messages, Error, ProtocolError, Result, RoundMsg};
+
+/// Number of rounds in the offline stage.
+pub const ROUNDS: u16 = 6;
+
+/// Round number reported for failures in the online stage.
+pub const ONLINE_ROUND: u16 = 7;
+
+/// Signature generated by a signer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Signature {
+    /// The generated ECDSA signature.
+    pub signature: SignatureRecid,
+    /// The public key.
+    #[serde(rename = "publicKey")]
+    pub public_key: Vec<u8>,
+    /// Address generated from the public key.
+    pub address: String,
+}
+
+/// Round-based signing protocol.
+pub struct Signer {
+    participants: Vec<u16>,
+    inner: Option<OfflineStage>,
+    offline: Option<CompletedOfflineStage>,
+    completed: Option<(CompletedOfflineStage, BigInt)>,
+}
+
+impl Signer {
+    /// Create a signer.
+    ///
+    /// The index is the party number for the signing session
+    /// and participants are the key share indices of the parties
+    /// ordered by party number.
+    pub fn new(index: u16, participants: Vec<u16>, local_key: LocalKey<Secp256k1>) -> Result<Self> {
+        Ok(Self {
+            inner: Some(OfflineStage::new(index, participants.clone(), local_key)?),
+            participants,
+            offline: None,
+            completed: None,
+        })
+    }
+
+    /// Handle an incoming message.
+    pub fn handle_incoming(&mut self, message: Msg<OfflineProtocolMessage>) -> Result<()> {
+        let inner = self.offline_stage()?;
+        let round = inner.current_round();
+        inner
+            .handle_incoming(message)
+            .map_err(|e| ProtocolError::signing(round, &e, &self.participants))?;
+        Ok(())
+    }
+
+    /// Proceed to the next round and return the round number
+    /// with the messages to send to the other parties.
+    ///
+    /// Returns `None` when the state machine is waiting
+    /// for more messages.
+    pub fn proceed(&mut self) -> Result<Option<(u16, Vec<RoundMsg<OfflineProtocolMessage>>)>> {
+        let inner = self.offline_stage()?;
+        if !inner.wants_to_proceed() {
+            return Ok(None);
+        }
+        let round = inner.current_round();
+        inner
+            .proceed()
+            .map_err(|e| ProtocolError::signing(round, &e, &self.participants))?;
+        let messages = inner.message_queue().drain(..).collect();
+        let round = inner.current_round();
+        Ok(Some((round, round_messages(round, messages))))
+    }
+
+    /// Generate the partial signature for a 32 byte message digest
+    /// that must be sent to the other signing participants.
+    pub fn partial(&mut self, message: [u8; 32]) -> Result<PartialSignature> {
+        self.completed_offline_stage()?;
+        let completed_offline_stage = self.offline.take().unwrap();
+        let data = BigInt::from_bytes(&message);
+        let (_sign, partial) = SignManual::new(data.clone(), completed_offline_stage.clone())?;
+        self.completed = Some((completed_offline_stage, data));
+        Ok(partial)
+    }
+
+    /// Take the completed offline stage so the message
+    /// can be signed later.
+    pub fn presignature(&mut self) -> Result<CompletedOfflineStage> {
+        self.completed_offline_stage()?;
+        Ok(self.offline.take().unwrap())
+    }
+
+    /// Create and verify the signature.
+    pub fn create(&mut self, partials: &[PartialSignature]) -> Result<Signature> {
+        let (completed_offline_stage, data) =
+            self.completed.take().ok_or(Error::CompletedOfflineStage)?;
+        complete(completed_offline_stage, data, partials)
+    }
+
+    /// State machine for the offline stage.
+    fn offline_stage(&mut self) -> Result<&mut OfflineStage> {
+        self.inner.as_mut().ok_or(Error::OfflineStageCompleted)
+    }
+
+    /// Output of the offline stage.
+    fn completed_offline_stage(&mut self) -> Result<&CompletedOfflineStage> {
+        if self.offline.is_none() {
+            let inner = self.inner.as_mut().ok_or(Error::CompletedOfflineStage)?;
+            if !inner.is_finished() {
+                return Err(Error::OfflineStageNotFinished(inner.current_round()));
+            }
+            let completed_offline_stage =
+                inner.pick_output().ok_or(Error::CompletedOfflineStage)??;
+            self.offline = Some(completed_offline_stage);
+            self.inner = None;
+        }
+        Ok(self.offline.as_ref().unwrap())
+    }
+}
+
+/// Combine the partial signatures and verify the signature.
+pub fn complete(
+    completed_offline_stage: CompletedOfflineStage,
+    data: BigInt,
+    partials: &[PartialSignature],
+) -> Result<Signature> {
+    let pk = completed_offline_stage.public_key().clone();
+    // Failures in the online stage do not identify a party
+    let (sign, _partial) = SignManual::new(data.clone(), completed_offline_stage)
+        .map_err(|e| ProtocolError::signing(ONLINE_ROUND, &e, &[]))?;
+    let signature = sign
+        .complete(partials)
+        .map_err(|e| ProtocolError::signing(ONLINE_ROUND, &e, &[]))?;
+    verify(&signature, &pk, &data).map_err(|e| Error::Verify(format!("{:?}", e)))?;
+
+    let public_key = pk.to_bytes(false).to_vec();
+    Ok(Signature {
+        signature,
+        address: crate::address(&public_key)?,
+        public_key,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{simulate, Parameters};
+
+    #[test]
+    fn keygen_and_sign() {
+        let key_shares = simulate::keygen(Parameters::default()).unwrap();
+        assert_eq!(key_shares.len(), 3);
+        let public_key = &key_shares[0].public_key;
+        assert!(key_shares.iter().all(|k| &k.public_key == public_key));
+
+        let mut signer = Signer::new(1, vec![1, 2], key_shares[0].local_key.clone()).unwrap();
+        assert!(matches!(
+            signer.partial([0u8; 32]),
+            Err(Error::OfflineStageNotFinished(_))
+        ));
+        assert!(matches!(
+            signer.create(&[]),
+            Err(Error::CompletedOfflineStage)
+        ));
+
+        let message = [7u8; 32];
+        let signatures = simulate::sign(&key_shares[1..], message).unwrap();
+        assert_eq!(signatures.len(), 2);
+        for signature in signatures {
+            assert_eq!(&signature.public_key, public_key);
+            assert_eq!(signature.address, key_shares[0].address);
+        }
+    }
+}
@@ -0,0 +1,200 @@
+//! Run every party of a protocol in a single process.
+//!
+//! Messages are routed between the parties in memory so no server
+//! is required; used to generate test fixtures and by the tests
+//! for the protocols that build on a key share.
+use round_based::Msg;
+
+use crate::{
+    KeyGenerator, KeyRefresher, KeyResharer, KeyShare, Parameters, PartySignup, ReshareKey, Result,
+    RoundMsg, Signature, Signer,
+};
+
+/// Messages for a round that are delivered to a party.
+///
+/// Broadcast messages are delivered to every party except the
+/// sender and peer to peer messages only to the receiver.
+pub fn route<B: Clone>(messages: &[RoundMsg<B>], party: u16) -> Vec<Msg<B>> {
+    messages
+        .iter()
+        .filter(|m| m.sender != party && m.receiver.map(|r| r == party).unwrap_or(true))
+        .map(|m| Msg {
+            sender: m.sender,
+            receiver: m.receiver,
+            body: m.body.clone(),
+        })
+        .collect()
+}
+
+/// Generate a key share for every party.
+pub fn keygen(parameters: Parameters) -> Result<Vec<KeyShare>> {
+    let mut generators = (1..=parameters.parties)
+        .map(|number| {
+            KeyGenerator::new(
+                parameters.clone(),
+                PartySignup {
+                    number,
+                    uuid: "simulate".to_string(),
+                },
+            )
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut messages = Vec::new();
+    for generator in generators.iter_mut() {
+        messages.extend(generator.proceed()?.1);
+    }
+    for _ in 0..crate::keygen::ROUNDS {
+        let mut outgoing = Vec::new();
+        for (position, generator) in generators.iter_mut().enumerate() {
+            for message in route(&messages, position as u16 + 1) {
+                generator.handle_incoming(message)?;
+            }
+            outgoing.extend(generator.proceed()?.1);
+        }
+        messages = outgoing;
+    }
+
+    generators
+        .iter_mut()
+        .map(|generator| generator.create())
+        .collect()
+}
+
+/// Refresh every key share.
+///
+/// The key shares must be ordered by index.
+pub fn refresh(key_shares: &[KeyShare]) -> Result<Vec<KeyShare>> {
+    let mut refreshers = key_shares
+        .iter()
+        .map(|key_share| KeyRefresher::new(key_share.clone()))
+        .collect::<Vec<_>>();
+
+    let mut messages = Vec::new();
+    for refresher in refreshers.iter_mut() {
+        messages.extend(refresher.proceed()?.1);
+    }
+    for _ in 0..crate::refresh::ROUNDS {
+        let mut outgoing = Vec::new();
+        for (position, refresher) in refreshers.iter_mut().enumerate() {
+            for message in route(&messages, position as u16 + 1) {
+                refresher.handle_incoming(message)?;
+            }
+            outgoing.extend(refresher.proceed()?.1);
+        }
+        messages = outgoing;
+    }
+
+    refreshers
+        .iter_mut()
+        .map(|refresher| refresher.create())
+        .collect()
+}
+
+/// Reshare a key to a new committee.
+///
+/// There is an entry for every member of the new committee in
+/// order of party number; dealers have their existing key share and
+/// other members `None`. Entries after the new committee are the
+/// dealers that are leaving it and `key.retiring` must match.
+///
+/// Returns the key shares for the members of the new committee.
+pub fn reshare(
+    parameters: Parameters,
+    key: ReshareKey,
+    key_shares: Vec<Option<KeyShare>>,
+) -> Result<Vec<KeyShare>> {
+    let mut resharers = key_shares
+        .into_iter()
+        .enumerate()
+        .map(|(position, key_share)| {
+            KeyResharer::new(
+                parameters.clone(),
+                position as u16 + 1,
+                key.clone(),
+                key_share,
+            )
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut messages = Vec::new();
+    for resharer in resharers.iter_mut() {
+        messages.extend(resharer.proceed()?.1);
+    }
+    for _ in 0..crate::reshare::ROUNDS {
+        let mut outgoing = Vec::new();
+        for (position, resharer) in resharers.iter_mut().enumerate() {
+            for message in route(&messages, position as u16 + 1) {
+                resharer.handle_incoming(message)?;
+            }
+            outgoing.extend(resharer.proceed()?.1);
+        }
+        messages = outgoing;
+    }
+
+    resharers
+        .iter_mut()
+        .take(parameters.parties as usize)
+        .map(|resharer| resharer.create())
+        .collect()
+}
+
+/// Sign a 32 byte message digest with every key share.
+///
+/// The party numbers for the signing session are assigned in
+/// the order of the key shares; returns the signature created
+/// by each party.
+pub fn sign(key_shares: &[KeyShare], message: [u8; 32]) -> Result<Vec<Signature>> {
+    let participants = key_shares
+        .iter()
+        .map(|key_share| key_share.local_key.i)
+        .collect::<Vec<_>>();
+    let mut signers = key_shares
+        .iter()
+        .enumerate()
+        .map(|(position, key_share)| {
+            Signer::new(
+                position as u16 + 1,
+                participants.clone(),
+                key_share.local_key.clone(),
+            )
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut messages = Vec::new();
+    for signer in signers.iter_mut() {
+        if let Some((_, outgoing)) = signer.proceed()? {
+            messages.extend(outgoing);
+        }
+    }
+    for _ in 0..crate::sign::ROUNDS {
+        let mut outgoing = Vec::new();
+        for (position, signer) in signers.iter_mut().enumerate() {
+            for message in route(&messages, position as u16 + 1) {
+                signer.handle_incoming(message)?;
+            }
+            if let Some((_, messages)) = signer.proceed()? {
+                outgoing.extend(messages);
+            }
+        }
+        messages = outgoing;
+    }
+
+    let partials = signers
+        .iter_mut()
+        .map(|signer| signer.partial(message))
+        .collect::<Result<Vec<_>>>()?;
+    signers
+        .iter_mut()
+        .enumerate()
+        .map(|(position, signer)| {
+            let others = partials
+                .iter()
+                .enumerate()
+                .filter(|(other, _)| *other != position)
+                .map(|(_, partial)| partial.clone())
+                .collect::<Vec<_>>();
+            signer.create(&others)
+        })
+        .collect()
+}
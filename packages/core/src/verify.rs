@@ -0,0 +1,280 @@
+//! Key share integrity checks.
+//!
+//! A key share that was imported, restored from a backup or
+//! produced by a buggy client may be internally inconsistent; the
+//! signing rounds would then fail with errors that are hard to
+//! attribute so the invariants of the key share are checked up
+//! front and reported individually.
+use curv::{
+    arithmetic::{BitManipulation, Modulo, Primes},
+    elliptic::curves::{Point, Secp256k1},
+    BigInt,
+};
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::keygen::LocalKey;
+use serde::{Deserialize, Serialize};
+
+use crate::{shares::inconsistent_shares, KeyShare};
+
+/// Minimum bit length of a Paillier or ring-pedersen modulus.
+const MODULUS_BITS: usize = 2047;
+
+/// Rounds of primality testing for the Paillier primes.
+const PRIMALITY_ROUNDS: u32 = 32;
+
+/// Result of a single check.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Check {
+    /// Name of the check.
+    pub name: String,
+    /// Whether the check passed.
+    pub passed: bool,
+    /// Reasons the check failed.
+    pub errors: Vec<String>,
+}
+
+/// Report of the checks on a key share.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyShareReport {
+    /// Whether all the checks passed.
+    pub valid: bool,
+    /// Individual checks.
+    pub checks: Vec<Check>,
+}
+
+impl KeyShareReport {
+    /// Run all the checks for a key share.
+    pub fn new(key_share: &KeyShare) -> Self {
+        let local_key = &key_share.local_key;
+        let mut checks = vec![check("parameters", parameters(local_key))];
+        // Remaining checks index the vectors in the key
+        // so they are only safe when the shape is correct.
+        if checks[0].passed {
+            checks.push(check("publicKey", public_key(key_share)));
+            checks.push(check("address", address(key_share)));
+            checks.push(check("publicKeyShares", public_key_shares(local_key)));
+            checks.push(check("secretShare", secret_share(local_key)));
+            checks.push(check("paillierKeys", paillier_keys(local_key)));
+            checks.push(check("ringPedersen", ring_pedersen(local_key)));
+        }
+        Self {
+            valid: checks.iter().all(|check| check.passed),
+            checks,
+        }
+    }
+}
+
+fn check(name: &str, errors: Vec<String>) -> Check {
+    Check {
+        name: name.to_string(),
+        passed: errors.is_empty(),
+        errors,
+    }
+}
+
+/// Indices, threshold and the length of the per-party vectors.
+fn parameters(local_key: &LocalKey<Secp256k1>) -> Vec<String> {
+    let (i, t, n) = (local_key.i, local_key.t, local_key.n);
+    let mut errors = Vec::new();
+    if t == 0 || t >= n {
+        errors.push(format!("threshold {} is invalid for {} parties", t, n));
+    }
+    if i == 0 || i > n {
+        errors.push(format!("index {} is invalid for {} parties", i, n));
+    }
+    let lengths = [
+        ("public key shares", local_key.pk_vec.len()),
+        ("paillier keys", local_key.paillier_key_vec.len()),
+        (
+            "ring-pedersen parameters",
+            local_key.h1_h2_n_tilde_vec.len(),
+        ),
+    ];
+    for (name, length) in lengths.iter() {
+        if *length != n as usize {
+            errors.push(format!("expected {} {} but got {}", n, name, length));
+        }
+    }
+    let vss = &local_key.vss_scheme;
+    if vss.commitments.len() != t as usize + 1 {
+        errors.push(format!(
+            "expected {} vss commitments but got {}",
+            t + 1,
+            vss.commitments.len()
+        ));
+    }
+    if vss.parameters.threshold != t || vss.parameters.share_count != n {
+        errors.push(format!(
+            "vss parameters ({}, {}) do not match the key ({}, {})",
+            vss.parameters.threshold, vss.parameters.share_count, t, n
+        ));
+    }
+    errors
+}
+
+/// Stored public key matches the key.
+fn public_key(key_share: &KeyShare) -> Vec<String> {
+    let local_key = &key_share.local_key;
+    let mut errors = Vec::new();
+    if local_key.keys_linear.y != local_key.y_sum_s {
+        errors.push("shared public key does not match the public key".to_string());
+    }
+    if local_key.public_key().to_bytes(false).to_vec() != key_share.public_key {
+        errors.push("stored public key does not match the key".to_string());
+    }
+    errors
+}
+
+/// Stored address matches the key.
+fn address(key_share: &KeyShare) -> Vec<String> {
+    let public_key = key_share.local_key.public_key().to_bytes(false).to_vec();
+    let expected = match crate::address(&public_key) {
+        Ok(expected) => expected,
+        Err(e) => return vec![e.to_string()],
+    };
+    if expected.eq_ignore_ascii_case(&key_share.address) {
+        vec![]
+    } else {
+        vec![format!(
+            "stored address {} does not match {}",
+            key_share.address, expected
+        )]
+    }
+}
+
+/// Public key shares lie on a polynomial of degree `t` whose
+/// constant term is the public key.
+fn public_key_shares(local_key: &LocalKey<Secp256k1>) -> Vec<String> {
+    inconsistent_shares(&local_key.pk_vec, &local_key.y_sum_s, local_key.t)
+        .into_iter()
+        .map(|index| {
+            if index == 0 {
+                "public key shares do not interpolate to the public key".to_string()
+            } else {
+                format!(
+                    "public key share for party {} is not consistent with the other shares",
+                    index
+                )
+            }
+        })
+        .collect()
+}
+
+/// Secret share matches the public key share for this party.
+fn secret_share(local_key: &LocalKey<Secp256k1>) -> Vec<String> {
+    let pk = Point::<Secp256k1>::generator() * &local_key.keys_linear.x_i;
+    if pk == local_key.pk_vec[local_key.i as usize - 1] {
+        vec![]
+    } else {
+        vec!["secret share does not match the public key share".to_string()]
+    }
+}
+
+/// Paillier decryption key matches the encryption key for this
+/// party and every encryption key is well formed.
+fn paillier_keys(local_key: &LocalKey<Secp256k1>) -> Vec<String> {
+    let mut errors = Vec::new();
+    for (position, ek) in local_key.paillier_key_vec.iter().enumerate() {
+        let index = position + 1;
+        if ek.n.bit_length() < MODULUS_BITS || !ek.n.test_bit(0) {
+            errors.push(format!(
+                "paillier modulus for party {} is too small or even",
+                index
+            ));
+        }
+        if ek.nn != &ek.n * &ek.n {
+            errors.push(format!("paillier key for party {} is malformed", index));
+        }
+    }
+
+    let dk = &local_key.paillier_dk;
+    let ek = &local_key.paillier_key_vec[local_key.i as usize - 1];
+    if &dk.p * &dk.q != ek.n {
+        errors.push("paillier decryption key does not match the encryption key".to_string());
+    }
+    if dk.p == dk.q
+        || !dk.p.is_probable_prime(PRIMALITY_ROUNDS)
+        || !dk.q.is_probable_prime(PRIMALITY_ROUNDS)
+    {
+        errors.push("paillier decryption key primes are invalid".to_string());
+    }
+    errors
+}
+
+/// Ring-pedersen parameters `h1`, `h2` and `N~` are well formed.
+fn ring_pedersen(local_key: &LocalKey<Secp256k1>) -> Vec<String> {
+    let one = BigInt::from(1);
+    let mut errors = Vec::new();
+    for (position, statement) in local_key.h1_h2_n_tilde_vec.iter().enumerate() {
+        let index = position + 1;
+        let modulus = &statement.N;
+        if modulus.bit_length() < MODULUS_BITS || !modulus.test_bit(0) {
+            errors.push(format!("N~ for party {} is too small or even", index));
+        }
+        let (h1, h2) = (&statement.g, &statement.ni);
+        let in_range =
+            |h: &BigInt| h > &one && h < modulus && BigInt::mod_inv(h, modulus).is_some();
+        if !in_range(h1) || !in_range(h2) {
+            errors.push(format!("h1 or h2 for party {} is out of range", index));
+        }
+        if h1 == h2 {
+            errors.push(format!("h1 and h2 for party {} are equal", index));
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{simulate, Parameters};
+    use curv::elliptic::curves::Scalar;
+
+    fn failed(report: &KeyShareReport) -> Vec<&str> {
+        report
+            .checks
+            .iter()
+            .filter(|check| !check.passed)
+            .map(|check| check.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn key_share_report() {
+        let key_shares = simulate::keygen(Parameters {
+            parties: 4,
+            threshold: 2,
+        })
+        .unwrap();
+        for key_share in &key_shares {
+            let report = KeyShareReport::new(key_share);
+            assert!(report.valid, "{:?}", failed(&report));
+        }
+
+        // Share of another party that is not on the polynomial
+        let mut key_share = key_shares[0].clone();
+        key_share.local_key.pk_vec[3] = key_share.local_key.pk_vec[2].clone();
+        let report = KeyShareReport::new(&key_share);
+        assert!(!report.valid);
+        assert_eq!(failed(&report), vec!["publicKeyShares"]);
+
+        // Share of another party in the interpolation quorum
+        let mut key_share = key_shares[0].clone();
+        key_share.local_key.pk_vec[1] = key_share.local_key.pk_vec[2].clone();
+        assert_eq!(
+            failed(&KeyShareReport::new(&key_share)),
+            vec!["publicKeyShares"]
+        );
+
+        // Own secret share does not match the public key share
+        let mut key_share = key_shares[0].clone();
+        key_share.local_key.keys_linear.x_i = Scalar::random();
+        assert_eq!(
+            failed(&KeyShareReport::new(&key_share)),
+            vec!["secretShare"]
+        );
+
+        let mut key_share = key_shares[0].clone();
+        key_share.local_key.t = 4;
+        assert_eq!(failed(&KeyShareReport::new(&key_share)), vec!["parameters"]);
+    }
+}
@@ -0,0 +1,234 @@
+//! Types shared by the websocket server, the webassembly bindings
+//! and the snap.
+//!
+//! Key shares are persisted by clients and parameters are sent
+//! to the server so the serialized representation of these types
+//! must remain stable; the key share is versioned so that the
+//! format can evolve without breaking existing key shares.
+#![deny(missing_docs)]
+use serde::{Deserialize, Deserializer, Serialize};
+use sha3::{Digest, Keccak256};
+use thiserror::Error;
+
+/// Current version of the key share format.
+pub const KEY_SHARE_VERSION: u16 = 2;
+
+/// Errors generated by the shared types.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Error generated when a parties parameter is too small.
+    #[error("parties must be greater than one")]
+    PartiesTooSmall,
+    /// Error generated when a threshold parameter is too small.
+    #[error("threshold must be greater than zero")]
+    ThresholdTooSmall,
+    /// Error generated when the threshold exceeds the parties.
+    #[error("threshold must be less than parties")]
+    ThresholdRange,
+    /// Error generated when a key share version is not supported.
+    #[error("key share version {0} is not supported")]
+    UnsupportedVersion(u16),
+    /// Error generated when a public key is not 65 bytes
+    /// uncompressed.
+    #[error("public key must be 65 bytes uncompressed, got {0} bytes")]
+    PublicKey(usize),
+}
+
+/// Result type for the shared types.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Parameters used during key generation and signing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Parameters {
+    /// Number of parties `n`.
+    pub parties: u16,
+    /// Threshold for signing `t`.
+    ///
+    /// The threshold must be crossed (`t + 1`) for signing
+    /// to commence.
+    pub threshold: u16,
+}
+
+impl Parameters {
+    /// Ensure the parameters are valid for key generation.
+    pub fn validate(&self) -> Result<()> {
+        // If parties is less than two then may as well
+        // use a standard single-party ECDSA private key
+        if self.parties <= 1 {
+            return Err(Error::PartiesTooSmall);
+        }
+        // If threshold is zero then it only
+        // takes a single party to sign a request which
+        // defeats the point of MPC
+        if self.threshold == 0 {
+            return Err(Error::ThresholdTooSmall);
+        }
+        // Threshold must be in range `(t + 1) <= n`
+        if self.threshold >= self.parties {
+            return Err(Error::ThresholdRange);
+        }
+        Ok(())
+    }
+}
+
+impl Default for Parameters {
+    fn default() -> Self {
+        Self {
+            parties: 3,
+            threshold: 1,
+        }
+    }
+}
+
+/// Session information for a single party.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct PartySignup {
+    /// Unique index for the party.
+    pub number: u16,
+    /// Session identifier.
+    pub uuid: String,
+}
+
+/// Wrapper for a round `Msg` that includes the round
+/// number so that we can ensure round messages are grouped
+/// together and out of order messages can thus be handled correctly.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoundMsg<B> {
+    /// Round number.
+    pub round: u16,
+    /// Party that sent the message.
+    pub sender: u16,
+    /// Receiver for peer to peer messages.
+    pub receiver: Option<u16>,
+    /// Message body.
+    pub body: B,
+}
+
+/// Generated key share.
+///
+/// The local key type is a parameter so that consumers which
+/// only need the public parts do not depend on the protocol
+/// implementation.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeyShare<K> {
+    /// Version of the key share format.
+    ///
+    /// Key shares created before the format was versioned
+    /// are treated as the first version.
+    #[serde(default = "first_version", deserialize_with = "version")]
+    pub version: u16,
+    /// The secret private key.
+    #[serde(rename = "localKey")]
+    pub local_key: K,
+    /// The public key.
+    #[serde(rename = "publicKey")]
+    pub public_key: Vec<u8>,
+    /// Address generated from the public key.
+    pub address: String,
+    /// Secret chain code for BIP32 child key derivation.
+    ///
+    /// Generated jointly during key generation so it is only
+    /// known to the holders of the key shares; key shares from
+    /// the first version do not have a chain code.
+    #[serde(rename = "chainCode", default, skip_serializing_if = "Option::is_none")]
+    pub chain_code: Option<[u8; 32]>,
+}
+
+impl<K> KeyShare<K> {
+    /// Create a key share for an uncompressed public key (65 bytes).
+    pub fn new(local_key: K, public_key: Vec<u8>, chain_code: Option<[u8; 32]>) -> Result<Self> {
+        Ok(Self {
+            version: KEY_SHARE_VERSION,
+            local_key,
+            address: address(&public_key)?,
+            public_key,
+            chain_code,
+        })
+    }
+}
+
+fn first_version() -> u16 {
+    1
+}
+
+fn version<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<u16, D::Error> {
+    let version = u16::deserialize(deserializer)?;
+    if version == 0 || version > KEY_SHARE_VERSION {
+        return Err(serde::de::Error::custom(Error::UnsupportedVersion(version)));
+    }
+    Ok(version)
+}
+
+/// Compute the address of an uncompressed public key (65 bytes).
+pub fn address(public_key: &[u8]) -> Result<String> {
+    if public_key.len() != 65 || public_key[0] != 0x04 {
+        return Err(Error::PublicKey(public_key.len()));
+    }
+    // Remove the leading 0x04
+    let bytes = &public_key[1..];
+    let digest = Keccak256::digest(bytes);
+    let final_bytes = &digest[12..];
+    Ok(format!("0x{}", hex::encode(final_bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Uncompressed public key for the private key `1`.
+    const PUBLIC_KEY: &str = "0479be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8";
+
+    #[test]
+    fn validate_parameters() {
+        assert!(Parameters::default().validate().is_ok());
+        let invalid = [(1, 0), (3, 0), (3, 3), (3, 4)];
+        for (parties, threshold) in invalid.iter() {
+            let parameters = Parameters {
+                parties: *parties,
+                threshold: *threshold,
+            };
+            assert!(parameters.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn key_share_version() {
+        let public_key = hex::decode(PUBLIC_KEY).unwrap();
+        let key_share = KeyShare::new((), public_key, Some([7u8; 32])).unwrap();
+        assert_eq!(
+            key_share.address,
+            "0x7e5f4552091a69125d5dfcb7b8c2659029395bdf"
+        );
+
+        let value = serde_json::to_value(&key_share).unwrap();
+        assert_eq!(value["version"], json!(KEY_SHARE_VERSION));
+        assert_eq!(value["chainCode"], json!(vec![7u8; 32]));
+
+        let mut legacy = value.clone();
+        legacy.as_object_mut().unwrap().remove("version");
+        legacy.as_object_mut().unwrap().remove("chainCode");
+        let key_share: KeyShare<()> = serde_json::from_value(legacy).unwrap();
+        assert_eq!(key_share.version, 1);
+        assert!(key_share.chain_code.is_none());
+
+        let mut future = value;
+        future["version"] = json!(KEY_SHARE_VERSION + 1);
+        assert!(serde_json::from_value::<KeyShare<()>>(future).is_err());
+    }
+
+    #[test]
+    fn address_invalid_public_key() {
+        let public_key = hex::decode(PUBLIC_KEY).unwrap();
+        assert!(address(&public_key).is_ok());
+        assert!(matches!(address(&[]), Err(Error::PublicKey(0))));
+        assert!(matches!(
+            address(&public_key[..33]),
+            Err(Error::PublicKey(33))
+        ));
+        let mut compressed = public_key.clone();
+        compressed[0] = 0x02;
+        assert!(address(&compressed).is_err());
+        assert!(KeyShare::new((), Vec::new(), None).is_err());
+    }
+}
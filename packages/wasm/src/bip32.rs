@@ -0,0 +1,247 @@
+//! BIP32 non-hardened child key derivation for threshold keys.
+//!
+//! Non-hardened derivation only requires the parent public key and
+//! chain code so every party can compute the same child public key;
+//! the offset for the child key is public and is added to each key
+//! share so that the parties can sign for the child key.
+//!
+//! The chain code is generated jointly during key generation and
+//! stored in the key share; it is secret as anyone with the chain
+//! code and the public key can link child keys and recover the
+//! parent secret from a child secret.
+use curv::{
+    arithmetic::Converter,
+    elliptic::curves::{Point, Scalar, Secp256k1},
+    BigInt,
+};
+use hmac::{Hmac, Mac};
+use mpc_ecdsa_core::KeyShare;
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::keygen::LocalKey;
+use serde::{Deserialize, Serialize};
+use sha2::Sha512;
+use std::str::FromStr;
+use wasm_bindgen::prelude::*;
+
+/// First index for hardened derivation.
+const HARDENED: u32 = 0x80000000;
+
+/// Path of non-hardened child indices, for example `m/0/1`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DerivationPath(Vec<u32>);
+
+impl FromStr for DerivationPath {
+    type Err = JsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('/');
+        if parts.next() != Some("m") {
+            return Err(JsError::new(&format!(
+                "derivation path {} must start with m",
+                s
+            )));
+        }
+        let indices = parts
+            .map(|part| {
+                part.parse::<u32>()
+                    .ok()
+                    .filter(|index| *index < HARDENED)
+                    .ok_or_else(|| {
+                        JsError::new(&format!(
+                            "invalid non-hardened index {} in derivation path {}",
+                            part, s
+                        ))
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self(indices))
+    }
+}
+
+/// Child public key derived from a group public key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChildKey {
+    /// The derivation path.
+    pub path: String,
+    /// The child public key.
+    #[serde(rename = "publicKey")]
+    pub public_key: Vec<u8>,
+    /// Address generated from the child public key.
+    pub address: String,
+    /// Chain code for the child key.
+    #[serde(rename = "chainCode")]
+    pub chain_code: Vec<u8>,
+}
+
+/// Chain code stored in a key share.
+pub fn chain_code(key_share: &KeyShare) -> Result<[u8; 32], JsError> {
+    key_share
+        .chain_code
+        .ok_or_else(|| JsError::new("key share does not have a chain code"))
+}
+
+/// Derive the child public key, chain code and the offset to add
+/// to the parent secret.
+pub fn derive(
+    public_key: &Point<Secp256k1>,
+    chain_code: [u8; 32],
+    path: &DerivationPath,
+) -> Result<(Point<Secp256k1>, [u8; 32], Scalar<Secp256k1>), JsError> {
+    let mut public_key = public_key.clone();
+    let mut chain_code = chain_code;
+    let mut offset = Scalar::<Secp256k1>::zero();
+    for index in &path.0 {
+        let mut mac = Hmac::<Sha512>::new_from_slice(&chain_code)
+            .map_err(|e| JsError::new(&e.to_string()))?;
+        mac.update(&public_key.to_bytes(true));
+        mac.update(&index.to_be_bytes());
+        let digest = mac.finalize().into_bytes();
+        let (left, right) = digest.split_at(32);
+
+        let tweak = BigInt::from_bytes(left);
+        if &tweak >= Scalar::<Secp256k1>::group_order() {
+            return Err(JsError::new(&format!(
+                "invalid child key at index {}",
+                index
+            )));
+        }
+        let tweak = Scalar::<Secp256k1>::from_bigint(&tweak);
+        public_key = public_key + Point::<Secp256k1>::generator() * &tweak;
+        if public_key.is_zero() {
+            return Err(JsError::new(&format!(
+                "invalid child key at index {}",
+                index
+            )));
+        }
+        offset = offset + tweak;
+        chain_code.copy_from_slice(right);
+    }
+    Ok((public_key, chain_code, offset))
+}
+
+/// Tweak a key share so that it is a share of the child key.
+///
+/// Adding the same offset to every share yields shares of the
+/// parent secret plus the offset as the Lagrange coefficients
+/// for any signing set sum to one.
+pub fn tweak(local_key: &mut LocalKey<Secp256k1>, offset: &Scalar<Secp256k1>) {
+    let offset_point = Point::<Secp256k1>::generator() * offset;
+    local_key.keys_linear.x_i = &local_key.keys_linear.x_i + offset;
+    local_key.keys_linear.y = &local_key.keys_linear.y + &offset_point;
+    local_key.y_sum_s = &local_key.y_sum_s + &offset_point;
+    for pk in local_key.pk_vec.iter_mut() {
+        *pk = &*pk + &offset_point;
+    }
+    local_key.vss_scheme.commitments[0] = &local_key.vss_scheme.commitments[0] + &offset_point;
+}
+
+/// Local key for signing with a key share or, when a path
+/// is given, with the child key for the path.
+pub fn local_key(
+    key_share: KeyShare,
+    path: Option<String>,
+) -> Result<LocalKey<Secp256k1>, JsError> {
+    let path: DerivationPath = match path {
+        Some(path) => path.parse()?,
+        None => return Ok(key_share.local_key),
+    };
+    let chain_code = chain_code(&key_share)?;
+    let mut local_key = key_share.local_key;
+    let (_, _, offset) = derive(&local_key.public_key(), chain_code, &path)?;
+    tweak(&mut local_key, &offset);
+    Ok(local_key)
+}
+
+/// Derive a child public key and address from a key share.
+///
+/// The path must only contain non-hardened indices, for
+/// example `m/0/1`; fails when the key share does not have
+/// a chain code.
+#[wasm_bindgen(js_name = "deriveChildKey")]
+pub fn derive_child_key(key_share: JsValue, path: String) -> Result<JsValue, JsError> {
+    let key_share: KeyShare = key_share.into_serde()?;
+    let parsed: DerivationPath = path.parse()?;
+    let parent = key_share.local_key.public_key();
+    let (child, chain_code, _) = derive(&parent, chain_code(&key_share)?, &parsed)?;
+    let public_key = child.to_bytes(false).to_vec();
+    let child_key = ChildKey {
+        path,
+        address: crate::utils::address(&public_key)?,
+        public_key,
+        chain_code: chain_code.to_vec(),
+    };
+    Ok(JsValue::from_serde(&child_key)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Public derivation steps from the BIP32 test vectors; each is
+    /// the parent public key, chain code and secret, the path and the
+    /// child public key, chain code and secret.
+    const VECTORS: [[&str; 7]; 3] = [
+        // Test vector 1: m/0H -> m/0H/1
+        [
+            "035a784662a4a20a65bf6aab9ae98a6c068a81c52e4b032c0fb5400c706cfccc56",
+            "47fdacbd0f1097043b78c63c20c34ef4ed9a111d980047ad16282c7ae6236141",
+            "edb2e14f9ee77d26dd93b4ecede8d16ed408ce149b6cd80b0715a2d911a0afea",
+            "m/1",
+            "03501e454bf00751f24b1b489aa925215d66af2234e3891c3b21a52bedb3cd711c",
+            "2a7857631386ba23dacac34180dd1983734e444fdbf774041578e9b6adb37c19",
+            "3c6cb8d0f6a264c91ea8b5030fadaa8e538b020f0a387421a12de9319dc93368",
+        ],
+        // Test vector 1: m/0H/1/2H -> m/0H/1/2H/2/1000000000
+        [
+            "0357bfe1e341d01c69fe5654309956cbea516822fba8a601743a012a7896ee8dc2",
+            "04466b9cc8e161e966409ca52986c584f07e9dc81f735db683c3ff6ec7b1503f",
+            "cbce0d719ecf7431d88e6a89fa1483e02e35092af60c042b1df2ff59fa424dca",
+            "m/2/1000000000",
+            "022a471424da5e657499d1ff51cb43c47481a03b1e77f951fe64cec9f5a48f7011",
+            "c783e67b921d2beb8f6b389cc646d7263b4145701dadd2161548a8b078e65e9e",
+            "471b76e389e528d6de6d816857e012c5455051cad6660850e58372a6c3e6e7c8",
+        ],
+        // Test vector 2: m -> m/0
+        [
+            "03cbcaa9c98c877a26977d00825c956a238e8dddfbd322cce4f74b0b5bd6ace4a7",
+            "60499f801b896d83179a4374aeb7822aaeaceaa0db1f85ee3e904c4defbd9689",
+            "4b03d6fc340455b363f51020ad3ecca4f0850280cf436c70c727923f6db46c3e",
+            "m/0",
+            "02fc9e5af0ac8d9b3cecfe2a888e2117ba3d089d8585886c9c826b6b22a98d12ea",
+            "f0909affaa7ee7abe5dd4e100598d4dc53cd709d5a5c2cac40e7412f232f7c9c",
+            "abe74a98f6c7eabee0428f53798f0ab8aa1bd37873999041703c742f15ac7e1e",
+        ],
+    ];
+
+    fn scalar(value: &str) -> Scalar<Secp256k1> {
+        Scalar::<Secp256k1>::from_bigint(&BigInt::from_bytes(&hex::decode(value).unwrap()))
+    }
+
+    #[test]
+    fn test_vectors() {
+        for [public_key, chain_code, secret, path, child_key, child_code, child_secret] in
+            VECTORS.iter()
+        {
+            let parent = Point::<Secp256k1>::from_bytes(&hex::decode(public_key).unwrap()).unwrap();
+            let mut parent_code = [0u8; 32];
+            parent_code.copy_from_slice(&hex::decode(chain_code).unwrap());
+            // JsError does not implement Debug so cannot be unwrapped
+            let (child, code, offset) = path
+                .parse()
+                .and_then(|path| derive(&parent, parent_code, &path))
+                .unwrap_or_else(|_| panic!("failed to derive {}", path));
+            assert_eq!(hex::encode(&*child.to_bytes(true)), *child_key);
+            assert_eq!(hex::encode(code), *child_code);
+
+            // The offset added to the parent secret is the child secret
+            assert_eq!(scalar(secret) + offset, scalar(child_secret));
+        }
+    }
+
+    #[test]
+    fn derivation_path() {
+        let path = "m/0/2147483647".parse::<DerivationPath>().ok();
+        assert_eq!(path, Some(DerivationPath(vec![0, HARDENED - 1])));
+        let path = "m".parse::<DerivationPath>().ok();
+        assert_eq!(path, Some(DerivationPath::default()));
+    }
+}
@@ -0,0 +1,199 @@
+//! Bitcoin addresses and PSBT signing.
+//!
+//! Addresses are derived from the compressed form of the group
+//! public key so a key share can receive funds with a pay to
+//! public key hash (P2PKH) or pay to witness public key hash
+//! (P2WPKH) output; see the `psbt` module for signing.
+use bech32::{u5, ToBase32, Variant};
+use ripemd::Ripemd160;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use wasm_bindgen::prelude::*;
+
+use crate::utils::compress;
+
+pub mod psbt;
+mod transaction;
+
+/// Errors for Bitcoin addresses and PSBTs.
+#[derive(Debug)]
+pub enum Error {
+    /// Public key is not a valid SEC1 encoding of a point on the curve.
+    PublicKey,
+    /// Address could not be encoded.
+    Address(String),
+    /// PSBT could not be parsed.
+    Psbt(&'static str),
+    /// Input index is out of bounds.
+    InputIndex(usize),
+    /// Input does not include the output it spends.
+    MissingUtxo(usize),
+    /// Input spends a script that cannot be signed.
+    UnsupportedScript(usize),
+    /// Signature hash type is not supported.
+    SighashType(u32),
+    /// Public key cannot spend the script of an input.
+    KeyMismatch(usize),
+    /// Signature does not verify for the signature hash of an input.
+    Signature(usize),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::PublicKey => write!(f, "invalid public key"),
+            Error::Address(message) => write!(f, "invalid address: {}", message),
+            Error::Psbt(message) => write!(f, "invalid psbt: {}", message),
+            Error::InputIndex(index) => write!(f, "input {} does not exist", index),
+            Error::MissingUtxo(index) => {
+                write!(f, "input {} does not include the spent output", index)
+            }
+            Error::UnsupportedScript(index) => {
+                write!(f, "input {} spends an unsupported script", index)
+            }
+            Error::SighashType(kind) => write!(f, "unsupported sighash type {}", kind),
+            Error::KeyMismatch(index) => {
+                write!(f, "public key cannot spend the script of input {}", index)
+            }
+            Error::Signature(index) => {
+                write!(f, "signature does not verify for input {}", index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Network for an address.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Network {
+    /// Bitcoin mainnet.
+    Bitcoin,
+    /// Bitcoin testnet.
+    Testnet,
+    /// Local regression testing network.
+    Regtest,
+}
+
+impl Default for Network {
+    fn default() -> Self {
+        Network::Bitcoin
+    }
+}
+
+impl Network {
+    fn pubkey_hash_version(&self) -> u8 {
+        match self {
+            Network::Bitcoin => 0x00,
+            Network::Testnet | Network::Regtest => 0x6f,
+        }
+    }
+
+    fn hrp(&self) -> &'static str {
+        match self {
+            Network::Bitcoin => "bc",
+            Network::Testnet => "tb",
+            Network::Regtest => "bcrt",
+        }
+    }
+}
+
+/// Type of address.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressType {
+    /// Legacy pay to public key hash.
+    P2pkh,
+    /// Native segwit pay to witness public key hash.
+    P2wpkh,
+}
+
+impl Default for AddressType {
+    fn default() -> Self {
+        AddressType::P2wpkh
+    }
+}
+
+/// Options for deriving an address.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AddressOptions {
+    /// The address type.
+    #[serde(rename = "type")]
+    pub address_type: AddressType,
+    /// The network.
+    pub network: Network,
+}
+
+/// RIPEMD160 of the SHA256 of some bytes.
+pub(crate) fn hash160(bytes: &[u8]) -> [u8; 20] {
+    Ripemd160::digest(Sha256::digest(bytes)).into()
+}
+
+/// Double SHA256 of some bytes.
+pub(crate) fn sha256d(bytes: &[u8]) -> [u8; 32] {
+    Sha256::digest(Sha256::digest(bytes)).into()
+}
+
+/// Derive an address for a public key.
+pub fn address(public_key: &[u8], options: &AddressOptions) -> Result<String, Error> {
+    let hash = hash160(&compress(public_key).ok_or(Error::PublicKey)?);
+    match options.address_type {
+        AddressType::P2pkh => {
+            let mut payload = vec![options.network.pubkey_hash_version()];
+            payload.extend_from_slice(&hash);
+            let checksum = sha256d(&payload);
+            payload.extend_from_slice(&checksum[..4]);
+            Ok(bs58::encode(payload).into_string())
+        }
+        AddressType::P2wpkh => {
+            let mut data = vec![u5::try_from_u8(0).unwrap()];
+            data.extend(hash.to_base32());
+            bech32::encode(options.network.hrp(), data, Variant::Bech32)
+                .map_err(|e| Error::Address(e.to_string()))
+        }
+    }
+}
+
+/// Derive a Bitcoin address from the public key of a key share.
+///
+/// The options select the address `type` (`p2pkh` or `p2wpkh`,
+/// defaults to `p2wpkh`) and the `network` (`bitcoin`, `testnet`
+/// or `regtest`, defaults to `bitcoin`).
+#[wasm_bindgen(js_name = "bitcoinAddress")]
+pub fn bitcoin_address(public_key: JsValue, options: JsValue) -> Result<String, JsError> {
+    let public_key: Vec<u8> = public_key.into_serde()?;
+    let options: AddressOptions = if options.is_null() || options.is_undefined() {
+        Default::default()
+    } else {
+        options.into_serde()?
+    };
+    Ok(address(&public_key, &options)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Public key for the private key 1
+    const PUBLIC_KEY: &str = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+
+    #[test]
+    fn addresses() {
+        let public_key = hex::decode(PUBLIC_KEY).unwrap();
+        let p2pkh = AddressOptions {
+            address_type: AddressType::P2pkh,
+            network: Network::Bitcoin,
+        };
+        assert_eq!(
+            address(&public_key, &p2pkh).unwrap(),
+            "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH"
+        );
+        assert_eq!(
+            address(&public_key, &Default::default()).unwrap(),
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"
+        );
+    }
+}
@@ -0,0 +1,629 @@
+//! Partially signed Bitcoin transactions (BIP174).
+//!
+//! Computes the signature hash for an input spending a legacy
+//! or segwit v0 script (BIP143) so it can be passed to
+//! `Signer.partial()` and inserts the finished threshold signature
+//! into the input as a partial signature; finalizing and
+//! extracting the transaction is left to the wallet.
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::party_i::SignatureRecid;
+use wasm_bindgen::prelude::*;
+
+use super::transaction::{write_var_bytes, Reader, Transaction, TxOut};
+use super::{hash160, sha256d, Error};
+use crate::encoding::RawSignature;
+use crate::utils::compress;
+
+const MAGIC: &[u8] = b"psbt\xff";
+
+const GLOBAL_UNSIGNED_TX: u8 = 0x00;
+const IN_NON_WITNESS_UTXO: u8 = 0x00;
+const IN_WITNESS_UTXO: u8 = 0x01;
+const IN_PARTIAL_SIG: u8 = 0x02;
+const IN_SIGHASH_TYPE: u8 = 0x03;
+const IN_REDEEM_SCRIPT: u8 = 0x04;
+const IN_WITNESS_SCRIPT: u8 = 0x05;
+
+const SIGHASH_ALL: u32 = 0x01;
+const SIGHASH_NONE: u32 = 0x02;
+const SIGHASH_SINGLE: u32 = 0x03;
+const SIGHASH_ANYONECANPAY: u32 = 0x80;
+/// Bits of the sighash type that select which outputs are signed.
+const SIGHASH_OUTPUT_MASK: u32 = 0x1f;
+
+/// Key value pairs in the order they were parsed.
+type Map = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// Partially signed transaction.
+///
+/// Records are kept as raw key value pairs so that fields
+/// which are not used for signing are preserved.
+#[derive(Debug, Clone)]
+pub struct Psbt {
+    global: Map,
+    inputs: Vec<Map>,
+    outputs: Vec<Map>,
+    tx: Transaction,
+}
+
+impl Psbt {
+    /// Parse from the binary format.
+    pub fn parse(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader::new(bytes);
+        if reader.read(MAGIC.len())? != MAGIC {
+            return Err(Error::Psbt("invalid magic bytes"));
+        }
+        let global = parse_map(&mut reader)?;
+        let tx =
+            get(&global, GLOBAL_UNSIGNED_TX).ok_or(Error::Psbt("missing unsigned transaction"))?;
+        let tx = Transaction::parse(tx)?;
+        let inputs = (0..tx.inputs.len())
+            .map(|_| parse_map(&mut reader))
+            .collect::<Result<Vec<_>, _>>()?;
+        let outputs = (0..tx.outputs.len())
+            .map(|_| parse_map(&mut reader))
+            .collect::<Result<Vec<_>, _>>()?;
+        if !reader.is_empty() {
+            return Err(Error::Psbt("trailing data"));
+        }
+        Ok(Self {
+            global,
+            inputs,
+            outputs,
+            tx,
+        })
+    }
+
+    /// Serialize to the binary format.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut buffer = MAGIC.to_vec();
+        for map in std::iter::once(&self.global)
+            .chain(self.inputs.iter())
+            .chain(self.outputs.iter())
+        {
+            for (key, value) in map {
+                write_var_bytes(&mut buffer, key);
+                write_var_bytes(&mut buffer, value);
+            }
+            buffer.push(0x00);
+        }
+        buffer
+    }
+
+    /// Signature hash type for an input; defaults to `SIGHASH_ALL`.
+    pub fn sighash_type(&self, index: usize) -> Result<u32, Error> {
+        let input = self.inputs.get(index).ok_or(Error::InputIndex(index))?;
+        match get(input, IN_SIGHASH_TYPE) {
+            Some(value) if value.len() == 4 => {
+                let mut bytes = [0u8; 4];
+                bytes.copy_from_slice(value);
+                Ok(u32::from_le_bytes(bytes))
+            }
+            Some(_) => Err(Error::Psbt("invalid sighash type")),
+            None => Ok(SIGHASH_ALL),
+        }
+    }
+
+    /// Compute the hash to sign for an input.
+    pub fn sighash(&self, index: usize) -> Result<[u8; 32], Error> {
+        let input = self.inputs.get(index).ok_or(Error::InputIndex(index))?;
+        let sighash_type = self.sighash_type(index)?;
+        match sighash_type & !SIGHASH_ANYONECANPAY {
+            SIGHASH_ALL | SIGHASH_NONE => {}
+            SIGHASH_SINGLE if index < self.tx.outputs.len() => {}
+            _ => return Err(Error::SighashType(sighash_type)),
+        }
+
+        let utxo = self.utxo(index)?;
+        let script = get(input, IN_REDEEM_SCRIPT).unwrap_or(&utxo.script_pubkey);
+        if let Some(witness_script) = get(input, IN_WITNESS_SCRIPT) {
+            Ok(self.segwit_sighash(index, witness_script, utxo.value, sighash_type))
+        } else if is_p2wpkh(script) {
+            let script_code = p2pkh_script(&script[2..]);
+            Ok(self.segwit_sighash(index, &script_code, utxo.value, sighash_type))
+        } else if is_witness_program(script) {
+            Err(Error::UnsupportedScript(index))
+        } else {
+            Ok(self.legacy_sighash(index, script, sighash_type))
+        }
+    }
+
+    /// Insert a signature for an input as a partial signature.
+    ///
+    /// The public key must be able to spend the script of the input
+    /// and the signature must verify for the signature hash of the
+    /// input. The signature is normalized to a low `s` value as
+    /// required by the standardness rules.
+    pub fn insert_signature(
+        &mut self,
+        index: usize,
+        public_key: &[u8],
+        signature: &RawSignature,
+    ) -> Result<(), Error> {
+        let sighash = self.sighash(index)?;
+        let sighash_type = self.sighash_type(index)?;
+        let public_key = compress(public_key).ok_or(Error::PublicKey)?;
+        if !self.can_spend(index, &public_key)? {
+            return Err(Error::KeyMismatch(index));
+        }
+        let signature = signature.normalize_s();
+        if !signature.verify(&public_key, &sighash) {
+            return Err(Error::Signature(index));
+        }
+
+        let mut key = vec![IN_PARTIAL_SIG];
+        key.extend_from_slice(&public_key);
+        let mut value = signature.to_der();
+        value.push(sighash_type as u8);
+
+        let input = &mut self.inputs[index];
+        match input.iter_mut().find(|(existing, _)| existing == &key) {
+            Some((_, existing)) => *existing = value,
+            None => input.push((key, value)),
+        }
+        Ok(())
+    }
+
+    /// Determine if a public key can spend an input; the script
+    /// that is signed must pay to the hash of the key or include
+    /// the key.
+    fn can_spend(&self, index: usize, public_key: &[u8; 33]) -> Result<bool, Error> {
+        let input = &self.inputs[index];
+        let utxo = self.utxo(index)?;
+        let script = get(input, IN_WITNESS_SCRIPT)
+            .or_else(|| get(input, IN_REDEEM_SCRIPT))
+            .unwrap_or(&utxo.script_pubkey);
+        let hash = hash160(public_key);
+        Ok(if is_p2wpkh(script) {
+            script[2..] == hash
+        } else if is_p2pkh(script) {
+            script[3..23] == hash
+        } else {
+            pushes(script).iter().any(|data| *data == public_key)
+        })
+    }
+
+    /// Output spent by an input.
+    fn utxo(&self, index: usize) -> Result<TxOut, Error> {
+        let input = &self.inputs[index];
+        if let Some(utxo) = get(input, IN_WITNESS_UTXO) {
+            let mut reader = Reader::new(utxo);
+            return TxOut::parse(&mut reader);
+        }
+        if let Some(utxo) = get(input, IN_NON_WITNESS_UTXO) {
+            let previous = Transaction::parse(utxo)?;
+            let outpoint = &self.tx.inputs[index];
+            if previous.txid() != outpoint.txid {
+                return Err(Error::Psbt("previous transaction does not match input"));
+            }
+            return previous
+                .outputs
+                .get(outpoint.vout as usize)
+                .cloned()
+                .ok_or(Error::MissingUtxo(index));
+        }
+        Err(Error::MissingUtxo(index))
+    }
+
+    /// Original transaction digest algorithm.
+    ///
+    /// Follows Bitcoin Core in selecting the outputs with the low
+    /// bits of the sighash type so that any type other than
+    /// `SIGHASH_NONE` or `SIGHASH_SINGLE` signs every output.
+    fn legacy_sighash(&self, index: usize, script_code: &[u8], sighash_type: u32) -> [u8; 32] {
+        let kind = sighash_type & SIGHASH_OUTPUT_MASK;
+        let mut tx = self.tx.clone();
+        for (position, input) in tx.inputs.iter_mut().enumerate() {
+            if position == index {
+                input.script_sig = script_code.to_vec();
+            } else {
+                input.script_sig.clear();
+                if kind == SIGHASH_NONE || kind == SIGHASH_SINGLE {
+                    input.sequence = 0;
+                }
+            }
+        }
+        match kind {
+            SIGHASH_NONE => tx.outputs.clear(),
+            SIGHASH_SINGLE => {
+                tx.outputs.truncate(index + 1);
+                for output in &mut tx.outputs[..index] {
+                    output.value = u64::MAX;
+                    output.script_pubkey.clear();
+                }
+            }
+            _ => {}
+        }
+        if sighash_type & SIGHASH_ANYONECANPAY != 0 {
+            tx.inputs = vec![tx.inputs.swap_remove(index)];
+        }
+
+        let mut buffer = tx.serialize();
+        buffer.extend_from_slice(&sighash_type.to_le_bytes());
+        sha256d(&buffer)
+    }
+
+    /// Segwit v0 transaction digest algorithm (BIP143).
+    fn segwit_sighash(
+        &self,
+        index: usize,
+        script_code: &[u8],
+        value: u64,
+        sighash_type: u32,
+    ) -> [u8; 32] {
+        let kind = sighash_type & SIGHASH_OUTPUT_MASK;
+        let anyone_can_pay = sighash_type & SIGHASH_ANYONECANPAY != 0;
+        let tx = &self.tx;
+        let input = &tx.inputs[index];
+
+        let hash_prevouts = if anyone_can_pay {
+            [0u8; 32]
+        } else {
+            sha256d(
+                &tx.inputs
+                    .iter()
+                    .flat_map(|input| input.outpoint())
+                    .collect::<Vec<_>>(),
+            )
+        };
+        let hash_sequence = if anyone_can_pay || kind == SIGHASH_NONE || kind == SIGHASH_SINGLE {
+            [0u8; 32]
+        } else {
+            sha256d(
+                &tx.inputs
+                    .iter()
+                    .flat_map(|input| input.sequence.to_le_bytes())
+                    .collect::<Vec<_>>(),
+            )
+        };
+        let hash_outputs = match kind {
+            SIGHASH_NONE => [0u8; 32],
+            SIGHASH_SINGLE => {
+                let mut buffer = Vec::new();
+                tx.outputs[index].serialize(&mut buffer);
+                sha256d(&buffer)
+            }
+            _ => {
+                let mut buffer = Vec::new();
+                for output in &tx.outputs {
+                    output.serialize(&mut buffer);
+                }
+                sha256d(&buffer)
+            }
+        };
+
+        let mut buffer = tx.version.to_le_bytes().to_vec();
+        buffer.extend_from_slice(&hash_prevouts);
+        buffer.extend_from_slice(&hash_sequence);
+        buffer.extend_from_slice(&input.outpoint());
+        write_var_bytes(&mut buffer, script_code);
+        buffer.extend_from_slice(&value.to_le_bytes());
+        buffer.extend_from_slice(&input.sequence.to_le_bytes());
+        buffer.extend_from_slice(&hash_outputs);
+        buffer.extend_from_slice(&tx.lock_time.to_le_bytes());
+        buffer.extend_from_slice(&sighash_type.to_le_bytes());
+        sha256d(&buffer)
+    }
+}
+
+fn parse_map(reader: &mut Reader) -> Result<Map, Error> {
+    let mut map = Vec::new();
+    loop {
+        let key = reader.var_bytes()?;
+        if key.is_empty() {
+            return Ok(map);
+        }
+        let value = reader.var_bytes()?;
+        map.push((key.to_vec(), value.to_vec()));
+    }
+}
+
+/// Value for a key that is only a type byte.
+fn get(map: &Map, key_type: u8) -> Option<&[u8]> {
+    map.iter()
+        .find(|(key, _)| key.as_slice() == [key_type])
+        .map(|(_, value)| value.as_slice())
+}
+
+fn is_witness_program(script: &[u8]) -> bool {
+    (4..=42).contains(&script.len())
+        && (script[0] == 0x00 || (0x51..=0x60).contains(&script[0]))
+        && script[1] as usize == script.len() - 2
+}
+
+fn is_p2wpkh(script: &[u8]) -> bool {
+    script.len() == 22 && script[0] == 0x00 && script[1] == 0x14
+}
+
+fn is_p2pkh(script: &[u8]) -> bool {
+    script.len() == 25 && script[..3] == [0x76, 0xa9, 0x14] && script[23..] == [0x88, 0xac]
+}
+
+/// Data pushed by a script; parsing stops at the first
+/// push that runs past the end of the script.
+fn pushes(script: &[u8]) -> Vec<&[u8]> {
+    let mut reader = Reader::new(script);
+    let mut pushes = Vec::new();
+    while let Ok(opcode) = reader.u8() {
+        let length = match opcode {
+            0x01..=0x4b => Ok(opcode as usize),
+            0x4c => reader.u8().map(|length| length as usize),
+            0x4d => reader
+                .read(2)
+                .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as usize),
+            0x4e => reader.u32().map(|length| length as usize),
+            _ => continue,
+        };
+        match length.and_then(|length| reader.read(length)) {
+            Ok(data) => pushes.push(data),
+            Err(_) => break,
+        }
+    }
+    pushes
+}
+
+/// Script code for a public key hash.
+fn p2pkh_script(hash: &[u8]) -> Vec<u8> {
+    let mut script = vec![0x76, 0xa9, 0x14];
+    script.extend_from_slice(hash);
+    script.extend_from_slice(&[0x88, 0xac]);
+    script
+}
+
+/// Compute the hash to pass to `Signer.partial()` for a PSBT input.
+///
+/// The PSBT is the binary encoding as an array of bytes.
+#[wasm_bindgen(js_name = "psbtSighash")]
+pub fn psbt_sighash(psbt: JsValue, index: usize) -> Result<JsValue, JsError> {
+    let psbt: Vec<u8> = psbt.into_serde()?;
+    let psbt = Psbt::parse(&psbt)?;
+    Ok(JsValue::from_serde(&psbt.sighash(index)?.to_vec())?)
+}
+
+/// Insert a threshold signature for an input into a PSBT
+/// and return the updated PSBT bytes.
+///
+/// The public key must be able to spend the input and the
+/// signature must verify for the signature hash of the input.
+#[wasm_bindgen(js_name = "psbtInsertSignature")]
+pub fn psbt_insert_signature(
+    psbt: JsValue,
+    index: usize,
+    public_key: JsValue,
+    signature: JsValue,
+) -> Result<JsValue, JsError> {
+    let psbt: Vec<u8> = psbt.into_serde()?;
+    let public_key: Vec<u8> = public_key.into_serde()?;
+    let signature: SignatureRecid = signature.into_serde()?;
+    let mut psbt = Psbt::parse(&psbt)?;
+    psbt.insert_signature(index, &public_key, &RawSignature::from(&signature))?;
+    Ok(JsValue::from_serde(&psbt.serialize())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Native P2WPKH example from BIP143
+    const UNSIGNED_TX: &str = "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000";
+    const WITNESS_UTXO: &str = "0046c32300000000160014";
+    const PUBKEY_HASH: &str = "1d0f172a0ecb48aee1be1f2687d2963ae33f71a1";
+    const SIGHASH: &str = "c37af31116d1b27caf68aae9e3ac82f1477929014d5b917657d0eb49478cb670";
+
+    // Transaction in block 170 with the signature removed, spending
+    // the coinbase of block 9; the signature in the mainnet
+    // transaction verifies against the SIGHASH_ALL hash
+    const BLOCK_170_TX: &str = "0100000001c997a5e56e104102fa209c6a852dd90660a20b2d9c352423edce25857fcd37040000000000ffffffff0200ca9a3b00000000434104ae1a62fe09c5f51b13905f07f06b99a2f7159b2225f374cd378d71302fa28414e7aab37397f554a7df5f142c21c1b7303b8a0626f1baded5c72a704f7e6cd84cac00286bee0000000043410411db93e1dcdb8a016b49840f8c53bc1eb68a382e97b1482ecad7b148a6909a5cb2e0eaddfb84ccf9744464f82e160bfa9b8b64f9d4c03f999b8643f656b412a3ac00000000";
+    const BLOCK_9_COINBASE: &str = "01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff0704ffff001d0134ffffffff0100f2052a0100000043410411db93e1dcdb8a016b49840f8c53bc1eb68a382e97b1482ecad7b148a6909a5cb2e0eaddfb84ccf9744464f82e160bfa9b8b64f9d4c03f999b8643f656b412a3ac00000000";
+
+    // Cases from the Bitcoin Core `sighash.json` test data with the
+    // format `[tx, script, input index, hash type, expected hash]`;
+    // the expected hash is displayed in reverse byte order and the
+    // hash types have arbitrary high bits that are signed as is
+    const CORE_SIGHASH: [(&str, &str, usize, i32, &str); 6] = [
+        (
+            "4db591ab018adcef5f4f3f2060e41f7829ce3a07ea41d681e8cb70a0e37685561e4767ac3b0000000005000052acabd280e63601ae6ef20000000000036a636326c908f7",
+            "ac6a51526300630052",
+            0,
+            862877446,
+            "355ccaf30697c9c5b966e619a554d3323d7494c3ea280a9b0dfb73f953f5c1cb",
+        ),
+        (
+            "c33028b301d5093e1e8397270d75a0b009b2a6509a01861061ab022ca122a6ba935b8513320200000000ffffffff013bcf5a0500000000015200000000",
+            "",
+            0,
+            -513413204,
+            "6b1459536f51482f5dbf42d7e561896557461e1e3b6bf67871e2b51faae2832c",
+        ),
+        (
+            "2f7353dd02e395b0a4d16da0f7472db618857cd3de5b9e2789232952a9b154d249102245fd030000000151617fd88f103280b85b0a198198e438e7cab1a4c92ba58409709997cc7a65a619eb9eec3c0200000003636aabffffffff0397481c0200000000045300636a0dc97803000000000009d389030000000003ac6a53134007bb",
+            "0000536552526a",
+            0,
+            -1912746174,
+            "30c4cd4bd6b291f7e9489cc4b4440a083f93a7664ea1f93e77a9597dab8ded9c",
+        ),
+        (
+            "32fa0b0804e6ea101e137665a041cc2350b794e59bf42d9b09088b01cde806ec1bbea077df0200000008515153650000006506a11c55904258fa418e57b88b12724b81153260d3f4c9f080439789a391ab147aabb0fa0000000007000052ac51ab510986f2a15c0d5e05d20dc876dd2dafa435276d53da7b47c393f20900e55f163b97ce0b800000000008ab526a520065636a8087df7d4d9c985fb42308fb09dce704650719140aa6050e8955fa5d2ea46b464a333f870000000009636300636a6565006affffffff01994a0d040000000002536500000000",
+            "516563530065",
+            2,
+            -163068286,
+            "f58637277d2bc42e18358dc55f7e87e7043f5e33f4ce1fc974e715ef0d3d1c2a",
+        ),
+        (
+            "6f62138301436f33a00b84a26a0457ccbfc0f82403288b9cbae39986b34357cb2ff9b889b302000000045253655335a7ff6701bac9960400000000086552ab656352635200000000",
+            "6aac51",
+            0,
+            1444414211,
+            "502a2435fd02898d2ff3ab08a3c19078414b32ec9b73d64a944834efc9dae10c",
+        ),
+        (
+            "d3b7421e011f4de0f1cea9ba7458bf3486bee722519efab711a963fa8c100970cf7488b7bb0200000003525352dcd61b300148be5d05000000000000000000",
+            "535251536aac536a",
+            0,
+            -1960128125,
+            "29aa6d2d752d3310eba20442770ad345b7f6a35f96161ede5f07b33e92053e2a",
+        ),
+    ];
+
+    fn psbt() -> Vec<u8> {
+        let tx = hex::decode(UNSIGNED_TX).unwrap();
+        let utxo = hex::decode(format!("{}{}", WITNESS_UTXO, PUBKEY_HASH)).unwrap();
+        let mut bytes = MAGIC.to_vec();
+        write_var_bytes(&mut bytes, &[GLOBAL_UNSIGNED_TX]);
+        write_var_bytes(&mut bytes, &tx);
+        // End of global map and an empty first input
+        bytes.extend_from_slice(&[0x00, 0x00]);
+        write_var_bytes(&mut bytes, &[IN_WITNESS_UTXO]);
+        write_var_bytes(&mut bytes, &utxo);
+        // End of second input and both outputs
+        bytes.extend_from_slice(&[0x00, 0x00, 0x00]);
+        bytes
+    }
+
+    /// PSBT where every input spends an output of the same
+    /// previous transaction.
+    fn legacy_psbt(tx: &str, previous: &str) -> Psbt {
+        let tx = hex::decode(tx).unwrap();
+        let previous = hex::decode(previous).unwrap();
+        let unsigned = Transaction::parse(&tx).unwrap();
+        let mut bytes = MAGIC.to_vec();
+        write_var_bytes(&mut bytes, &[GLOBAL_UNSIGNED_TX]);
+        write_var_bytes(&mut bytes, &tx);
+        bytes.push(0x00);
+        for _ in &unsigned.inputs {
+            write_var_bytes(&mut bytes, &[IN_NON_WITNESS_UTXO]);
+            write_var_bytes(&mut bytes, &previous);
+            bytes.push(0x00);
+        }
+        bytes.extend(vec![0x00; unsigned.outputs.len()]);
+        Psbt::parse(&bytes).unwrap()
+    }
+
+    /// PSBT without any input or output records.
+    fn unsigned_psbt(tx: &str) -> Psbt {
+        let tx = Transaction::parse(&hex::decode(tx).unwrap()).unwrap();
+        Psbt {
+            global: Vec::new(),
+            inputs: vec![Vec::new(); tx.inputs.len()],
+            outputs: vec![Vec::new(); tx.outputs.len()],
+            tx,
+        }
+    }
+
+    fn set_sighash_type(psbt: &mut Psbt, index: usize, sighash_type: u32) {
+        let input = &mut psbt.inputs[index];
+        input.retain(|(key, _)| key.as_slice() != [IN_SIGHASH_TYPE]);
+        input.push((vec![IN_SIGHASH_TYPE], sighash_type.to_le_bytes().to_vec()));
+    }
+
+    #[test]
+    fn legacy_sighash() {
+        let psbt = legacy_psbt(BLOCK_170_TX, BLOCK_9_COINBASE);
+        assert_eq!(
+            hex::encode(psbt.sighash(0).unwrap()),
+            "7a05c6145f10101e9d6325494245adf1297d80f8f38d4d576d57cdba220bcb19"
+        );
+    }
+
+    #[test]
+    fn legacy_sighash_types() {
+        for &(tx, script, index, sighash_type, expected) in CORE_SIGHASH.iter() {
+            let psbt = unsigned_psbt(tx);
+            let script = hex::decode(script).unwrap();
+            let mut hash = psbt.legacy_sighash(index, &script, sighash_type as u32);
+            hash.reverse();
+            assert_eq!(hex::encode(hash), expected, "sighash type {}", sighash_type);
+        }
+
+        // Only the standard sighash types can be signed
+        let (tx, _, _, _, _) = CORE_SIGHASH[3];
+        let mut psbt = unsigned_psbt(tx);
+        set_sighash_type(&mut psbt, 0, 0x04);
+        assert!(matches!(psbt.sighash(0), Err(Error::SighashType(0x04))));
+
+        // SIGHASH_SINGLE without an output at the same index
+        set_sighash_type(&mut psbt, 2, SIGHASH_SINGLE);
+        assert!(matches!(
+            psbt.sighash(2),
+            Err(Error::SighashType(SIGHASH_SINGLE))
+        ));
+    }
+
+    #[test]
+    fn segwit_sighash() {
+        let bytes = psbt();
+        let psbt = Psbt::parse(&bytes).unwrap();
+        assert_eq!(psbt.serialize(), bytes);
+        assert_eq!(hex::encode(psbt.sighash(1).unwrap()), SIGHASH);
+        assert!(matches!(psbt.sighash(0), Err(Error::MissingUtxo(0))));
+        assert!(matches!(psbt.sighash(2), Err(Error::InputIndex(2))));
+    }
+
+    #[test]
+    fn insert_signature() {
+        let mut psbt = Psbt::parse(&psbt()).unwrap();
+        // Key and signature for the second input from BIP143
+        let public_key =
+            hex::decode("025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee6357")
+                .unwrap();
+        let mut signature = RawSignature {
+            r: [0u8; 32],
+            s: [0u8; 32],
+            recid: 0,
+        };
+        signature.r.copy_from_slice(
+            &hex::decode("3609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a")
+                .unwrap(),
+        );
+        signature.s.copy_from_slice(
+            &hex::decode("573a954c4518331561406f90300e8f3358f51928d43c212a8caed02de67eebee")
+                .unwrap(),
+        );
+        psbt.insert_signature(1, &public_key, &signature).unwrap();
+        psbt.insert_signature(1, &public_key, &signature).unwrap();
+
+        let mut psbt = Psbt::parse(&psbt.serialize()).unwrap();
+        let mut key = vec![IN_PARTIAL_SIG];
+        key.extend_from_slice(&public_key);
+        let records = psbt.inputs[1]
+            .iter()
+            .filter(|(existing, _)| existing == &key)
+            .collect::<Vec<_>>();
+        assert_eq!(records.len(), 1);
+        assert_eq!(
+            hex::encode(&records[0].1),
+            "304402203609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c4518331561406f90300e8f3358f51928d43c212a8caed02de67eebee01"
+        );
+
+        // Key that does not match the witness program
+        let other =
+            hex::decode("0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+                .unwrap();
+        assert!(matches!(
+            psbt.insert_signature(1, &other, &signature),
+            Err(Error::KeyMismatch(1))
+        ));
+
+        // Signature for another hash
+        set_sighash_type(&mut psbt, 1, SIGHASH_NONE);
+        assert!(matches!(
+            psbt.insert_signature(1, &public_key, &signature),
+            Err(Error::Signature(1))
+        ));
+    }
+
+    #[test]
+    fn script_pushes() {
+        let public_key = [0x02; 33];
+        let mut script = vec![0x51, 0x21];
+        script.extend_from_slice(&public_key);
+        script.extend_from_slice(&[0x4c, 0x01, 0xff, 0x52, 0xae]);
+        assert_eq!(pushes(&script), vec![&public_key[..], &[0xff][..]]);
+        assert!(pushes(&[0x4c, 0x05, 0x01]).is_empty());
+
+        let mut p2pkh = vec![0x76, 0xa9, 0x14];
+        p2pkh.extend_from_slice(&[0x11; 20]);
+        p2pkh.extend_from_slice(&[0x88, 0xac]);
+        assert!(is_p2pkh(&p2pkh));
+        assert!(!is_p2pkh(&p2pkh[..24]));
+    }
+}
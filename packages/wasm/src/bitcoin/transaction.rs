@@ -0,0 +1,207 @@
+//! Bitcoin transaction serialization.
+use super::{sha256d, Error};
+
+/// Reader for consensus encoded values.
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.position == self.bytes.len()
+    }
+
+    pub(crate) fn read(&mut self, length: usize) -> Result<&'a [u8], Error> {
+        let end = self
+            .position
+            .checked_add(length)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or(Error::Psbt("unexpected end of data"))?;
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.read(1)?[0])
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, Error> {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(self.read(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64, Error> {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(self.read(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    pub(crate) fn var_int(&mut self) -> Result<u64, Error> {
+        Ok(match self.u8()? {
+            0xfd => {
+                let mut bytes = [0u8; 2];
+                bytes.copy_from_slice(self.read(2)?);
+                u16::from_le_bytes(bytes) as u64
+            }
+            0xfe => self.u32()? as u64,
+            0xff => self.u64()?,
+            value => value as u64,
+        })
+    }
+
+    pub(crate) fn var_bytes(&mut self) -> Result<&'a [u8], Error> {
+        let length = self.var_int()?;
+        self.read(length as usize)
+    }
+}
+
+pub(crate) fn write_var_int(buffer: &mut Vec<u8>, value: u64) {
+    match value {
+        0..=0xfc => buffer.push(value as u8),
+        0xfd..=0xffff => {
+            buffer.push(0xfd);
+            buffer.extend_from_slice(&(value as u16).to_le_bytes());
+        }
+        0x10000..=0xffff_ffff => {
+            buffer.push(0xfe);
+            buffer.extend_from_slice(&(value as u32).to_le_bytes());
+        }
+        _ => {
+            buffer.push(0xff);
+            buffer.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+}
+
+pub(crate) fn write_var_bytes(buffer: &mut Vec<u8>, bytes: &[u8]) {
+    write_var_int(buffer, bytes.len() as u64);
+    buffer.extend_from_slice(bytes);
+}
+
+/// Transaction input.
+#[derive(Debug, Clone)]
+pub(crate) struct TxIn {
+    pub txid: [u8; 32],
+    pub vout: u32,
+    pub script_sig: Vec<u8>,
+    pub sequence: u32,
+}
+
+impl TxIn {
+    pub(crate) fn outpoint(&self) -> Vec<u8> {
+        let mut buffer = self.txid.to_vec();
+        buffer.extend_from_slice(&self.vout.to_le_bytes());
+        buffer
+    }
+}
+
+/// Transaction output.
+#[derive(Debug, Clone)]
+pub(crate) struct TxOut {
+    pub value: u64,
+    pub script_pubkey: Vec<u8>,
+}
+
+impl TxOut {
+    pub(crate) fn parse(reader: &mut Reader) -> Result<Self, Error> {
+        Ok(Self {
+            value: reader.u64()?,
+            script_pubkey: reader.var_bytes()?.to_vec(),
+        })
+    }
+
+    pub(crate) fn serialize(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.value.to_le_bytes());
+        write_var_bytes(buffer, &self.script_pubkey);
+    }
+}
+
+/// Transaction; witness data is skipped when parsing.
+#[derive(Debug, Clone)]
+pub(crate) struct Transaction {
+    pub version: u32,
+    pub inputs: Vec<TxIn>,
+    pub outputs: Vec<TxOut>,
+    pub lock_time: u32,
+}
+
+impl Transaction {
+    pub(crate) fn parse(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader::new(bytes);
+        let version = reader.u32()?;
+        let mut count = reader.var_int()?;
+        // Segwit marker and flag
+        let witness = count == 0;
+        if witness {
+            if reader.u8()? != 1 {
+                return Err(Error::Psbt("invalid segwit flag"));
+            }
+            count = reader.var_int()?;
+        }
+
+        let mut inputs = Vec::new();
+        for _ in 0..count {
+            let mut txid = [0u8; 32];
+            txid.copy_from_slice(reader.read(32)?);
+            inputs.push(TxIn {
+                txid,
+                vout: reader.u32()?,
+                script_sig: reader.var_bytes()?.to_vec(),
+                sequence: reader.u32()?,
+            });
+        }
+
+        let mut outputs = Vec::new();
+        for _ in 0..reader.var_int()? {
+            outputs.push(TxOut::parse(&mut reader)?);
+        }
+
+        if witness {
+            for _ in 0..inputs.len() {
+                for _ in 0..reader.var_int()? {
+                    reader.var_bytes()?;
+                }
+            }
+        }
+
+        let lock_time = reader.u32()?;
+        if !reader.is_empty() {
+            return Err(Error::Psbt("trailing transaction data"));
+        }
+        Ok(Self {
+            version,
+            inputs,
+            outputs,
+            lock_time,
+        })
+    }
+
+    /// Serialize without witness data.
+    pub(crate) fn serialize(&self) -> Vec<u8> {
+        let mut buffer = self.version.to_le_bytes().to_vec();
+        write_var_int(&mut buffer, self.inputs.len() as u64);
+        for input in &self.inputs {
+            buffer.extend_from_slice(&input.outpoint());
+            write_var_bytes(&mut buffer, &input.script_sig);
+            buffer.extend_from_slice(&input.sequence.to_le_bytes());
+        }
+        write_var_int(&mut buffer, self.outputs.len() as u64);
+        for output in &self.outputs {
+            output.serialize(&mut buffer);
+        }
+        buffer.extend_from_slice(&self.lock_time.to_le_bytes());
+        buffer
+    }
+
+    /// Transaction identifier in internal byte order.
+    pub(crate) fn txid(&self) -> [u8; 32] {
+        sha256d(&self.serialize())
+    }
+}
@@ -0,0 +1,333 @@
+//! Signature encodings.
+//!
+//! Converts the `SignatureRecid` produced by signing into the
+//! byte encodings expected by other software: Ethereum `r || s || v`
+//! (with `v` as 27/28 or EIP-155 with a chain id), the 64 byte
+//! compact `r || s` form and ASN.1 DER.
+use curv::{
+    arithmetic::Converter,
+    elliptic::curves::{Point, Scalar, Secp256k1},
+    BigInt,
+};
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::party_i::{self, SignatureRecid};
+use serde::Deserialize;
+use wasm_bindgen::prelude::*;
+
+/// Order of the secp256k1 group.
+const ORDER: [u8; 32] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe,
+    0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b, 0xbf, 0xd2, 0x5e, 0x8c, 0xd0, 0x36, 0x41, 0x41,
+];
+
+/// Half the order of the secp256k1 group (rounded down).
+const HALF_ORDER: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
+/// Signature as big-endian bytes with the recovery identifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawSignature {
+    /// The `r` value.
+    pub r: [u8; 32],
+    /// The `s` value.
+    pub s: [u8; 32],
+    /// Recovery identifier (0 or 1).
+    pub recid: u8,
+}
+
+impl RawSignature {
+    /// Determine if `s` is in the lower half of the group order.
+    pub fn is_low_s(&self) -> bool {
+        self.s <= HALF_ORDER
+    }
+
+    /// Replace a high `s` with `n - s` and flip the recovery
+    /// identifier so the signature still recovers the same key.
+    pub fn normalize_s(mut self) -> Self {
+        if !self.is_low_s() {
+            let mut borrow = 0i16;
+            for i in (0..32).rev() {
+                let mut value = ORDER[i] as i16 - self.s[i] as i16 - borrow;
+                borrow = if value < 0 {
+                    value += 256;
+                    1
+                } else {
+                    0
+                };
+                self.s[i] = value as u8;
+            }
+            self.recid ^= 1;
+        }
+        self
+    }
+
+    /// Ethereum `v` value; 27 or 28 unless a chain id is given
+    /// in which case `chain_id * 2 + 35 + recid` (EIP-155).
+    ///
+    /// Returns `None` when `v` does not fit in 64 bits.
+    pub fn v(&self, chain_id: Option<u64>) -> Option<u64> {
+        match chain_id {
+            Some(chain_id) => chain_id
+                .checked_mul(2)
+                .and_then(|v| v.checked_add(35 + self.recid as u64)),
+            None => Some(27 + self.recid as u64),
+        }
+    }
+
+    /// Ethereum `r || s || v` encoding.
+    ///
+    /// Returns `None` when `v` does not fit in a single byte
+    /// which happens for chain identifiers greater than 109.
+    pub fn to_rsv(self, chain_id: Option<u64>) -> Option<[u8; 65]> {
+        let v = self.v(chain_id)?;
+        if v > u8::MAX as u64 {
+            return None;
+        }
+        let mut bytes = [0u8; 65];
+        bytes[..64].copy_from_slice(&self.to_compact());
+        bytes[64] = v as u8;
+        Some(bytes)
+    }
+
+    /// Compact `r || s` encoding.
+    pub fn to_compact(self) -> [u8; 64] {
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(&self.r);
+        bytes[32..].copy_from_slice(&self.s);
+        bytes
+    }
+
+    /// Verify the signature for a message digest using a SEC1
+    /// encoded public key.
+    ///
+    /// Both `r` and `s` must be in the range `1..n`; a high `s`
+    /// is accepted.
+    pub fn verify(&self, public_key: &[u8], digest: &[u8; 32]) -> bool {
+        let in_range = |value: &[u8; 32]| value != &[0u8; 32] && value < &ORDER;
+        if !in_range(&self.r) || !in_range(&self.s) {
+            return false;
+        }
+        let public_key = match Point::<Secp256k1>::from_bytes(public_key) {
+            Ok(public_key) => public_key,
+            Err(_) => return false,
+        };
+        let signature = SignatureRecid {
+            r: Scalar::from_bigint(&BigInt::from_bytes(&self.r)),
+            s: Scalar::from_bigint(&BigInt::from_bytes(&self.s)),
+            recid: self.recid,
+        };
+        party_i::verify(&signature, &public_key, &BigInt::from_bytes(digest)).is_ok()
+    }
+
+    /// ASN.1 DER encoding.
+    pub fn to_der(self) -> Vec<u8> {
+        let r = der_integer(&self.r);
+        let s = der_integer(&self.s);
+        let mut bytes = vec![0x30, (r.len() + s.len()) as u8];
+        bytes.extend(r);
+        bytes.extend(s);
+        bytes
+    }
+}
+
+impl From<&SignatureRecid> for RawSignature {
+    fn from(signature: &SignatureRecid) -> Self {
+        let mut r = [0u8; 32];
+        let mut s = [0u8; 32];
+        r.copy_from_slice(&signature.r.to_bytes());
+        s.copy_from_slice(&signature.s.to_bytes());
+        Self {
+            r,
+            s,
+            recid: signature.recid,
+        }
+    }
+}
+
+/// Encode a 32 byte unsigned big-endian value as a DER integer.
+fn der_integer(value: &[u8; 32]) -> Vec<u8> {
+    let start = value.iter().position(|b| *b != 0).unwrap_or(31);
+    let value = &value[start..];
+    let mut bytes = vec![0x02];
+    if value[0] & 0x80 != 0 {
+        bytes.push(value.len() as u8 + 1);
+        bytes.push(0);
+    } else {
+        bytes.push(value.len() as u8);
+    }
+    bytes.extend_from_slice(value);
+    bytes
+}
+
+/// Signature encoding formats.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureFormat {
+    /// Ethereum `r || s || v` (65 bytes).
+    Rsv,
+    /// Compact `r || s` (64 bytes).
+    Compact,
+    /// ASN.1 DER.
+    Der,
+}
+
+impl Default for SignatureFormat {
+    fn default() -> Self {
+        SignatureFormat::Rsv
+    }
+}
+
+/// Options for encoding a signature.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EncodeOptions {
+    /// The encoding format.
+    pub format: SignatureFormat,
+    /// Chain identifier for EIP-155 `v` values.
+    pub chain_id: Option<u64>,
+    /// Normalize to a low `s` value.
+    pub low_s: bool,
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        Self {
+            format: Default::default(),
+            chain_id: None,
+            low_s: true,
+        }
+    }
+}
+
+/// Encode a signature.
+///
+/// The options select the `format` (`rsv`, `compact` or `der`),
+/// an optional `chainId` for EIP-155 and whether to normalize to
+/// a low `s` value (`lowS`, defaults to `true`).
+#[wasm_bindgen(js_name = "encodeSignature")]
+pub fn encode_signature(signature: JsValue, options: JsValue) -> Result<JsValue, JsError> {
+    let signature: SignatureRecid = signature.into_serde()?;
+    let options: EncodeOptions = if options.is_null() || options.is_undefined() {
+        Default::default()
+    } else {
+        options.into_serde()?
+    };
+
+    let mut raw = RawSignature::from(&signature);
+    if options.low_s {
+        raw = raw.normalize_s();
+    }
+    let bytes = match options.format {
+        SignatureFormat::Rsv => raw
+            .to_rsv(options.chain_id)
+            .ok_or_else(|| JsError::new("chain id is too large for a 65 byte signature"))?
+            .to_vec(),
+        SignatureFormat::Compact => raw.to_compact().to_vec(),
+        SignatureFormat::Der => raw.to_der(),
+    };
+    Ok(JsValue::from_serde(&bytes)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Example transaction signature from EIP-155
+    const R: &str = "28ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276";
+    const S: &str = "67cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83";
+    const HIGH_S: &str = "98341627668089e51348fccfb4c7ff31c55912f2d2e47ef09652acf665fad3be";
+    // Signing hash and public key for the EIP-155 example
+    const DIGEST: &str = "daf5a779ae972f972197303d7b574746c7ef83eadac0f2791ad23db92e4c8e53";
+    const PUBLIC_KEY: &str = "024bc2a31265153f07e70e0bab08724e6b85e217f8cd628ceb62974247bb493382";
+
+    fn bytes32(value: &str) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        bytes.copy_from_slice(&hex::decode(value).unwrap());
+        bytes
+    }
+
+    fn signature() -> RawSignature {
+        RawSignature {
+            r: bytes32(R),
+            s: bytes32(S),
+            recid: 0,
+        }
+    }
+
+    #[test]
+    fn rsv_and_eip155() {
+        let sig = signature();
+        let rsv = sig.to_rsv(None).unwrap();
+        assert_eq!(hex::encode(&rsv[..64]), format!("{}{}", R, S));
+        assert_eq!(rsv[64], 27);
+        assert_eq!(sig.to_rsv(Some(1)).unwrap()[64], 37);
+        assert_eq!(sig.v(Some(1337)), Some(2709));
+        assert_eq!(sig.v(Some(u64::MAX / 2 - 17)), Some(u64::MAX));
+        assert!(sig.v(Some(u64::MAX / 2 - 16)).is_none());
+        assert!(sig.v(Some(u64::MAX)).is_none());
+        assert!(sig.to_rsv(Some(1337)).is_none());
+    }
+
+    #[test]
+    fn compact() {
+        assert_eq!(hex::encode(signature().to_compact()), format!("{}{}", R, S));
+    }
+
+    #[test]
+    fn der() {
+        assert_eq!(
+            hex::encode(signature().to_der()),
+            format!("30440220{}0220{}", R, S)
+        );
+
+        let mut r = [0u8; 32];
+        r[1] = 0x80;
+        let mut s = [0u8; 32];
+        s[31] = 1;
+        let sig = RawSignature { r, s, recid: 0 };
+        assert_eq!(
+            hex::encode(sig.to_der()),
+            "302502200080000000000000000000000000000000000000000000000000000000000000020101"
+        );
+    }
+
+    #[test]
+    fn low_s() {
+        let sig = signature();
+        assert!(sig.is_low_s());
+        assert_eq!(sig.normalize_s(), sig);
+
+        let high = RawSignature {
+            s: bytes32(HIGH_S),
+            recid: 1,
+            ..sig
+        };
+        assert!(!high.is_low_s());
+        assert_eq!(high.normalize_s(), sig);
+    }
+
+    #[test]
+    fn verify_signature() {
+        let public_key = hex::decode(PUBLIC_KEY).unwrap();
+        let digest = bytes32(DIGEST);
+        let sig = signature();
+        assert!(sig.verify(&public_key, &digest));
+        assert!(RawSignature {
+            s: bytes32(HIGH_S),
+            ..sig
+        }
+        .verify(&public_key, &digest));
+
+        let mut other = digest;
+        other[0] ^= 1;
+        assert!(!sig.verify(&public_key, &other));
+        assert!(!RawSignature {
+            r: [0u8; 32],
+            ..sig
+        }
+        .verify(&public_key, &digest));
+        assert!(!RawSignature { s: ORDER, ..sig }.verify(&public_key, &digest));
+        assert!(!sig.verify(&public_key[1..], &digest));
+    }
+}
@@ -0,0 +1,78 @@
+//! Authenticated encryption for protocol state that leaves
+//! the webassembly module.
+//!
+//! Values are serialized as JSON and encrypted with
+//! ChaCha20-Poly1305 using a 32 byte key supplied by the caller;
+//! the identifier is authenticated so a blob cannot be restored
+//! under a different identifier.
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+const ERR_KEY_LENGTH: &str = "encryption key must be 32 bytes";
+const ERR_DECRYPT: &str = "failed to decrypt, wrong key or corrupted data";
+
+/// Encrypted value with a public identifier.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedBlob {
+    /// Identifier for the value.
+    pub id: String,
+    /// Nonce used for encryption.
+    pub nonce: Vec<u8>,
+    /// The encrypted value.
+    pub ciphertext: Vec<u8>,
+}
+
+fn cipher(key: &[u8]) -> Result<ChaCha20Poly1305, JsError> {
+    if key.len() != 32 {
+        return Err(JsError::new(ERR_KEY_LENGTH));
+    }
+    Ok(ChaCha20Poly1305::new(Key::from_slice(key)))
+}
+
+/// Encrypt a value.
+pub(crate) fn seal<T: Serialize>(
+    key: &[u8],
+    id: String,
+    value: &T,
+) -> Result<EncryptedBlob, JsError> {
+    let cipher = cipher(key)?;
+    let mut nonce = [0u8; 12];
+    getrandom::getrandom(&mut nonce).map_err(|e| JsError::new(&e.to_string()))?;
+    let plaintext = serde_json::to_vec(value)?;
+    let ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: &plaintext,
+                aad: id.as_bytes(),
+            },
+        )
+        .map_err(|_| JsError::new("failed to encrypt"))?;
+    Ok(EncryptedBlob {
+        id,
+        nonce: nonce.to_vec(),
+        ciphertext,
+    })
+}
+
+/// Decrypt a value.
+pub(crate) fn open<T: DeserializeOwned>(key: &[u8], blob: &EncryptedBlob) -> Result<T, JsError> {
+    let cipher = cipher(key)?;
+    if blob.nonce.len() != 12 {
+        return Err(JsError::new(ERR_DECRYPT));
+    }
+    let plaintext = cipher
+        .decrypt(
+            Nonce::from_slice(&blob.nonce),
+            Payload {
+                msg: &blob.ciphertext,
+                aad: blob.id.as_bytes(),
+            },
+        )
+        .map_err(|_| JsError::new(ERR_DECRYPT))?;
+    Ok(serde_json::from_slice(&plaintext)?)
+}
@@ -0,0 +1,38 @@
+//! EIP-191 signed data as used by `personal_sign`.
+use wasm_bindgen::prelude::*;
+
+use crate::utils::keccak256;
+
+/// Digest of a message prefixed with
+/// `"\x19Ethereum Signed Message:\n" + len(message)`.
+pub fn personal_message_hash(message: &[u8]) -> [u8; 32] {
+    let mut prefixed = format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
+    prefixed.extend_from_slice(message);
+    keccak256(&prefixed)
+}
+
+/// Compute the `personal_sign` digest of a message to pass
+/// to `Signer.partial()`.
+///
+/// Message is a `Uint8Array` wrapped into a sequence using
+/// `Array.from` so it is deserialized correctly.
+#[wasm_bindgen(js_name = "hashMessage")]
+pub fn hash_message(message: JsValue) -> Result<JsValue, JsError> {
+    let message: Vec<u8> = message.into_serde()?;
+    Ok(JsValue::from_serde(
+        &personal_message_hash(&message).to_vec(),
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn personal_message() {
+        assert_eq!(
+            hex::encode(personal_message_hash(b"Hello World")),
+            "a1de988600a42c4b4ab089b619297c17d53cffae5d5120d82d8a92d0bb3b78f2"
+        );
+    }
+}
@@ -0,0 +1,454 @@
+//! EIP-712 typed structured data hashing.
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use wasm_bindgen::prelude::*;
+
+use super::{decode_hex, Address, Error, Quantity};
+use crate::utils::keccak256;
+
+const DOMAIN: &str = "EIP712Domain";
+
+/// Member of a struct type.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TypedField {
+    /// Name of the member.
+    pub name: String,
+    /// Type of the member.
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+/// Typed data in the format accepted by `eth_signTypedData_v4`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypedData {
+    /// Struct type definitions.
+    pub types: BTreeMap<String, Vec<TypedField>>,
+    /// Type of the message.
+    pub primary_type: String,
+    /// Values for the domain separator.
+    pub domain: Value,
+    /// The message to sign.
+    #[serde(default)]
+    pub message: Value,
+}
+
+impl TypedData {
+    /// Digest to be signed by the threshold signers.
+    pub fn hash(&self) -> Result<[u8; 32], Error> {
+        let mut encoded = vec![0x19, 0x01];
+        encoded.extend_from_slice(&self.domain_separator()?);
+        if self.primary_type != DOMAIN {
+            encoded.extend_from_slice(&self.hash_struct(&self.primary_type, &self.message)?);
+        }
+        Ok(keccak256(&encoded))
+    }
+
+    /// Hash of the `EIP712Domain` struct.
+    pub fn domain_separator(&self) -> Result<[u8; 32], Error> {
+        self.hash_struct(DOMAIN, &self.domain)
+    }
+
+    /// Hash of a struct value.
+    pub fn hash_struct(&self, name: &str, value: &Value) -> Result<[u8; 32], Error> {
+        Ok(keccak256(&self.encode_data(name, value)?))
+    }
+
+    /// Encode a struct type with the types it references
+    /// appended in alphabetical order.
+    pub fn encode_type(&self, name: &str) -> Result<String, Error> {
+        let mut dependencies = BTreeSet::new();
+        self.dependencies(name, &mut dependencies);
+        dependencies.remove(name);
+
+        let mut encoded = self.encode_struct_type(name)?;
+        for dependency in dependencies {
+            encoded.push_str(&self.encode_struct_type(dependency)?);
+        }
+        Ok(encoded)
+    }
+
+    fn encode_struct_type(&self, name: &str) -> Result<String, Error> {
+        let fields = self
+            .fields(name)?
+            .iter()
+            .map(|field| format!("{} {}", field.kind, field.name))
+            .collect::<Vec<_>>();
+        Ok(format!("{}({})", name, fields.join(",")))
+    }
+
+    fn encode_data(&self, name: &str, value: &Value) -> Result<Vec<u8>, Error> {
+        let mut encoded = keccak256(self.encode_type(name)?.as_bytes()).to_vec();
+        for field in self.fields(name)? {
+            let member = value
+                .get(&field.name)
+                .ok_or_else(|| Error::TypedData(format!("missing {} in {}", field.name, name)))?;
+            encoded.extend_from_slice(&self.encode_value(&field.kind, member)?);
+        }
+        Ok(encoded)
+    }
+
+    fn encode_value(&self, kind: &str, value: &Value) -> Result<[u8; 32], Error> {
+        if let Some(item) = kind.strip_suffix(']') {
+            let item = &item[..item.rfind('[').ok_or_else(|| invalid_type(kind))?];
+            let items = value.as_array().ok_or_else(|| invalid_value(kind, value))?;
+            let mut encoded = Vec::with_capacity(items.len() * 32);
+            for value in items {
+                encoded.extend_from_slice(&self.encode_value(item, value)?);
+            }
+            return Ok(keccak256(&encoded));
+        }
+
+        if self.types.contains_key(kind) {
+            return self.hash_struct(kind, value);
+        }
+
+        let mut word = [0u8; 32];
+        match kind {
+            "string" => {
+                let value = value.as_str().ok_or_else(|| invalid_value(kind, value))?;
+                word = keccak256(value.as_bytes());
+            }
+            "bytes" => {
+                let value = value.as_str().ok_or_else(|| invalid_value(kind, value))?;
+                word = keccak256(&decode_hex(value)?);
+            }
+            "bool" => {
+                let value = value.as_bool().ok_or_else(|| invalid_value(kind, value))?;
+                word[31] = value as u8;
+            }
+            "address" => {
+                let value = value.as_str().ok_or_else(|| invalid_value(kind, value))?;
+                word[12..].copy_from_slice(&Address::try_from(value.to_string())?.0);
+            }
+            _ if kind.starts_with("bytes") => {
+                let size = type_size(kind, "bytes")?;
+                let value = value.as_str().ok_or_else(|| invalid_value(kind, value))?;
+                let bytes = decode_hex(value)?;
+                if bytes.len() > size {
+                    return Err(invalid_value(kind, value));
+                }
+                word[..bytes.len()].copy_from_slice(&bytes);
+            }
+            _ if kind.starts_with("uint") => {
+                word = integer(kind, value, type_size(kind, "uint")?, false)?;
+            }
+            _ if kind.starts_with("int") => {
+                word = integer(kind, value, type_size(kind, "int")?, true)?;
+            }
+            _ => return Err(invalid_type(kind)),
+        }
+        Ok(word)
+    }
+
+    fn fields(&self, name: &str) -> Result<&[TypedField], Error> {
+        match self.types.get(name) {
+            Some(fields) => Ok(fields),
+            // Domain without any fields
+            None if name == DOMAIN => Ok(&[]),
+            None => Err(invalid_type(name)),
+        }
+    }
+
+    fn dependencies<'a>(&'a self, name: &'a str, found: &mut BTreeSet<&'a str>) {
+        if found.contains(name) {
+            return;
+        }
+        if let Some(fields) = self.types.get(name) {
+            found.insert(name);
+            for field in fields {
+                let kind = field.kind.split('[').next().unwrap_or_default();
+                self.dependencies(kind, found);
+            }
+        }
+    }
+}
+
+/// Size of a sized type such as `bytes32` or `uint256`;
+/// the size is a number of bytes for `bytes` and bits for integers.
+fn type_size(kind: &str, prefix: &str) -> Result<usize, Error> {
+    let size = &kind[prefix.len()..];
+    if size.is_empty() && prefix != "bytes" {
+        return Ok(256);
+    }
+    let size: usize = size.parse().map_err(|_| invalid_type(kind))?;
+    let valid = if prefix == "bytes" {
+        (1..=32).contains(&size)
+    } else {
+        (8..=256).step_by(8).any(|bits| bits == size)
+    };
+    if valid {
+        Ok(size)
+    } else {
+        Err(invalid_type(kind))
+    }
+}
+
+/// Encode an integer as a 32 byte word using two's complement
+/// for negative values.
+///
+/// The value must fit in the declared number of bits.
+fn integer(kind: &str, value: &Value, bits: usize, signed: bool) -> Result<[u8; 32], Error> {
+    let (negative, quantity) = match value {
+        Value::Number(number) if matches!(number.as_i64(), Some(n) if n < 0) => (
+            true,
+            Quantity::from(number.as_i64().unwrap().unsigned_abs()),
+        ),
+        Value::String(number) if number.starts_with('-') => (true, number[1..].parse()?),
+        _ => (
+            false,
+            serde_json::from_value(value.clone()).map_err(|_| invalid_value(kind, value))?,
+        ),
+    };
+    if negative && !signed {
+        return Err(invalid_value(kind, value));
+    }
+    // Signed integers range from -2^(bits-1) to 2^(bits-1)-1
+    let bytes = quantity.as_bytes();
+    let length = bit_length(bytes);
+    let fits = if !signed {
+        length <= bits
+    } else if negative {
+        length < bits
+            || (length == bits && bytes[1..].iter().all(|b| *b == 0) && bytes[0].is_power_of_two())
+    } else {
+        length < bits
+    };
+    if !fits {
+        return Err(invalid_value(kind, value));
+    }
+    let mut word = quantity.to_word()?;
+    if negative {
+        let mut carry = true;
+        for byte in word.iter_mut().rev() {
+            let (value, overflow) = (!*byte).overflowing_add(carry as u8);
+            *byte = value;
+            carry = overflow;
+        }
+    }
+    Ok(word)
+}
+
+/// Number of bits needed for minimal big-endian bytes.
+fn bit_length(bytes: &[u8]) -> usize {
+    match bytes.first() {
+        Some(first) => bytes.len() * 8 - first.leading_zeros() as usize,
+        None => 0,
+    }
+}
+
+fn invalid_type(kind: &str) -> Error {
+    Error::TypedData(format!("unknown type {}", kind))
+}
+
+fn invalid_value<T: std::fmt::Display + ?Sized>(kind: &str, value: &T) -> Error {
+    Error::TypedData(format!("invalid {} value {}", kind, value))
+}
+
+/// Compute the EIP-712 digest of typed data to pass
+/// to `Signer.partial()`.
+#[wasm_bindgen(js_name = "hashTypedData")]
+pub fn hash_typed_data(typed_data: JsValue) -> Result<JsValue, JsError> {
+    let typed_data: TypedData = typed_data.into_serde()?;
+    Ok(JsValue::from_serde(&typed_data.hash()?.to_vec())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Example from EIP-712
+    const MAIL: &str = r#"{
+        "types": {
+            "EIP712Domain": [
+                {"name": "name", "type": "string"},
+                {"name": "version", "type": "string"},
+                {"name": "chainId", "type": "uint256"},
+                {"name": "verifyingContract", "type": "address"}
+            ],
+            "Person": [
+                {"name": "name", "type": "string"},
+                {"name": "wallet", "type": "address"}
+            ],
+            "Mail": [
+                {"name": "from", "type": "Person"},
+                {"name": "to", "type": "Person"},
+                {"name": "contents", "type": "string"}
+            ]
+        },
+        "primaryType": "Mail",
+        "domain": {
+            "name": "Ether Mail",
+            "version": "1",
+            "chainId": 1,
+            "verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+        },
+        "message": {
+            "from": {
+                "name": "Cow",
+                "wallet": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"
+            },
+            "to": {
+                "name": "Bob",
+                "wallet": "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"
+            },
+            "contents": "Hello, Bob!"
+        }
+    }"#;
+
+    #[test]
+    fn mail() {
+        let typed_data: TypedData = serde_json::from_str(MAIL).unwrap();
+        assert_eq!(
+            typed_data.encode_type("Mail").unwrap(),
+            "Mail(Person from,Person to,string contents)Person(string name,address wallet)"
+        );
+        assert_eq!(
+            hex::encode(typed_data.domain_separator().unwrap()),
+            "f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f"
+        );
+        assert_eq!(
+            hex::encode(typed_data.hash_struct("Mail", &typed_data.message).unwrap()),
+            "c52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e"
+        );
+        assert_eq!(
+            hex::encode(typed_data.hash().unwrap()),
+            "be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2"
+        );
+    }
+
+    // Example with arrays from eth_signTypedData_v4; Group is
+    // declared but not referenced by Mail
+    const MAIL_ARRAYS: &str = r#"{
+        "types": {
+            "EIP712Domain": [
+                {"name": "name", "type": "string"},
+                {"name": "version", "type": "string"},
+                {"name": "chainId", "type": "uint256"},
+                {"name": "verifyingContract", "type": "address"}
+            ],
+            "Group": [
+                {"name": "name", "type": "string"},
+                {"name": "members", "type": "Person[]"}
+            ],
+            "Mail": [
+                {"name": "from", "type": "Person"},
+                {"name": "to", "type": "Person[]"},
+                {"name": "contents", "type": "string"}
+            ],
+            "Person": [
+                {"name": "name", "type": "string"},
+                {"name": "wallets", "type": "address[]"}
+            ]
+        },
+        "primaryType": "Mail",
+        "domain": {
+            "chainId": 1,
+            "name": "Ether Mail",
+            "verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC",
+            "version": "1"
+        },
+        "message": {
+            "contents": "Hello, Bob!",
+            "from": {
+                "name": "Cow",
+                "wallets": [
+                    "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826",
+                    "0xDeaDbeefdEAdbeefdEadbEEFdeadbeEFdEaDbeeF"
+                ]
+            },
+            "to": [
+                {
+                    "name": "Bob",
+                    "wallets": [
+                        "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB",
+                        "0xB0BdaBea57B0BDABeA57b0bdABEA57b0BDabEa57",
+                        "0xB0B0b0b0b0b0B000000000000000000000000000"
+                    ]
+                }
+            ]
+        }
+    }"#;
+
+    #[test]
+    fn mail_arrays() {
+        let typed_data: TypedData = serde_json::from_str(MAIL_ARRAYS).unwrap();
+        assert_eq!(
+            typed_data.encode_type("Mail").unwrap(),
+            "Mail(Person from,Person[] to,string contents)Person(string name,address[] wallets)"
+        );
+        assert_eq!(
+            typed_data.encode_type("Group").unwrap(),
+            "Group(string name,Person[] members)Person(string name,address[] wallets)"
+        );
+        assert_eq!(
+            hex::encode(typed_data.domain_separator().unwrap()),
+            "f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f"
+        );
+        assert_eq!(
+            hex::encode(typed_data.hash_struct("Mail", &typed_data.message).unwrap()),
+            "eb4221181ff3f1a83ea7313993ca9218496e424604ba9492bb4052c03d5c3df8"
+        );
+        assert_eq!(
+            hex::encode(typed_data.hash().unwrap()),
+            "a85c2e2b118698e88db68a8105b794a8cc7cec074e89ef991cb4f5f533819cc2"
+        );
+
+        let mut members = typed_data.message["to"].as_array().unwrap().clone();
+        members.push(typed_data.message["from"].clone());
+        let group = serde_json::json!({ "name": "Team", "members": members });
+        assert_eq!(
+            hex::encode(typed_data.hash_struct("Group", &group).unwrap()),
+            "ec9b2138299746c82602a981bfcf53e4999e92b5009317d0f7f58d84b897e47d"
+        );
+    }
+
+    #[test]
+    fn signed_integer() {
+        let minus_one = integer("int8", &Value::from(-1), 8, true).unwrap();
+        assert_eq!(minus_one, [0xff; 32]);
+        let minus_two = integer("int256", &Value::from("-2"), 256, true).unwrap();
+        assert_eq!(minus_two[31], 0xfe);
+        assert!(integer("uint8", &Value::from(-1), 8, false).is_err());
+    }
+
+    #[test]
+    fn integer_range() {
+        let typed_data = |kind: &str| -> TypedData {
+            serde_json::from_value(serde_json::json!({
+                "types": {
+                    "EIP712Domain": [],
+                    "Value": [{"name": "value", "type": kind}]
+                },
+                "primaryType": "Value",
+                "domain": {},
+                "message": {}
+            }))
+            .unwrap()
+        };
+        let encode = |kind: &str, value: Value| {
+            typed_data(kind).hash_struct("Value", &serde_json::json!({ "value": value }))
+        };
+
+        assert!(encode("uint8", Value::from(255)).is_ok());
+        assert!(encode("uint8", Value::from(256)).is_err());
+        assert!(encode("uint8", Value::from(300)).is_err());
+        assert!(encode("uint8", Value::from(-1)).is_err());
+        assert!(encode("uint16", Value::from("0x10000")).is_err());
+
+        assert!(encode("int8", Value::from(127)).is_ok());
+        assert!(encode("int8", Value::from(-128)).is_ok());
+        assert!(encode("int8", Value::from(128)).is_err());
+        assert!(encode("int8", Value::from(-129)).is_err());
+        assert!(encode("int8", Value::from(-1000)).is_err());
+        assert!(encode("int8", Value::from("-1000")).is_err());
+
+        let max = format!("0x{}", "ff".repeat(32));
+        assert!(encode("uint256", Value::from(max.as_str())).is_ok());
+        assert!(encode("int256", Value::from(max.as_str())).is_err());
+        let min = format!("-0x8{}", "0".repeat(63));
+        assert!(encode("int256", Value::from(min.as_str())).is_ok());
+    }
+}
@@ -0,0 +1,190 @@
+//! Ethereum transactions and message hashing.
+use serde::Deserialize;
+use std::convert::TryFrom;
+use std::fmt;
+
+pub mod eip191;
+pub mod eip712;
+pub mod rlp;
+pub mod transaction;
+
+/// Errors parsing Ethereum values.
+#[derive(Debug)]
+pub enum Error {
+    /// Value is not valid hex.
+    Hex(String),
+    /// Value is not a valid unsigned integer.
+    Quantity(String),
+    /// Value has the wrong length.
+    Length(&'static str, usize),
+    /// Chain identifier or the EIP-155 `v` value does not fit in 64 bits.
+    ChainId,
+    /// Typed data is not valid.
+    TypedData(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Hex(value) => write!(f, "invalid hex value {}", value),
+            Error::Quantity(value) => write!(f, "invalid quantity {}", value),
+            Error::Length(name, length) => write!(f, "{} must be {} bytes", name, length),
+            Error::ChainId => write!(f, "chain id is too large"),
+            Error::TypedData(message) => write!(f, "invalid typed data: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Decode a hex string with an optional `0x` prefix.
+pub(crate) fn decode_hex(value: &str) -> Result<Vec<u8>, Error> {
+    let hex = value.strip_prefix("0x").unwrap_or(value);
+    if hex.len() % 2 == 1 {
+        hex::decode(format!("0{}", hex))
+    } else {
+        hex::decode(hex)
+    }
+    .map_err(|_| Error::Hex(value.to_string()))
+}
+
+/// Unsigned integer as minimal big-endian bytes.
+///
+/// Deserialized from a number, a decimal string or a
+/// `0x` prefixed hex string.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "QuantityValue")]
+pub struct Quantity(Vec<u8>);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum QuantityValue {
+    Number(u64),
+    String(String),
+}
+
+impl Quantity {
+    /// Minimal big-endian bytes; empty for zero.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Convert to a `u64`.
+    pub fn as_u64(&self) -> Option<u64> {
+        if self.0.len() > 8 {
+            return None;
+        }
+        Some(self.0.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64))
+    }
+
+    /// Left pad to a 32 byte word.
+    pub fn to_word(&self) -> Result<[u8; 32], Error> {
+        if self.0.len() > 32 {
+            return Err(Error::Length("integer", 32));
+        }
+        let mut word = [0u8; 32];
+        word[32 - self.0.len()..].copy_from_slice(&self.0);
+        Ok(word)
+    }
+}
+
+impl From<u64> for Quantity {
+    fn from(value: u64) -> Self {
+        Quantity(strip_zeros(&value.to_be_bytes()).to_vec())
+    }
+}
+
+impl TryFrom<QuantityValue> for Quantity {
+    type Error = Error;
+
+    fn try_from(value: QuantityValue) -> Result<Self, Self::Error> {
+        match value {
+            QuantityValue::Number(value) => Ok(value.into()),
+            QuantityValue::String(value) => value.parse(),
+        }
+    }
+}
+
+impl std::str::FromStr for Quantity {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value.starts_with("0x") {
+            return Ok(Quantity(strip_zeros(&decode_hex(value)?).to_vec()));
+        }
+        if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(Error::Quantity(value.to_string()));
+        }
+        // Big-endian bytes multiplied by ten for each digit
+        let mut bytes: Vec<u8> = Vec::new();
+        for digit in value.bytes().map(|b| b - b'0') {
+            let mut carry = digit as u16;
+            for byte in bytes.iter_mut().rev() {
+                let product = *byte as u16 * 10 + carry;
+                *byte = product as u8;
+                carry = product >> 8;
+            }
+            if carry > 0 {
+                bytes.insert(0, carry as u8);
+            }
+        }
+        Ok(Quantity(strip_zeros(&bytes).to_vec()))
+    }
+}
+
+/// Hex encoded bytes.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Bytes(pub Vec<u8>);
+
+impl TryFrom<String> for Bytes {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Ok(Bytes(decode_hex(&value)?))
+    }
+}
+
+/// Hex encoded 20 byte address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Address(pub [u8; 20]);
+
+impl TryFrom<String> for Address {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let bytes = decode_hex(&value)?;
+        if bytes.len() != 20 {
+            return Err(Error::Length("address", 20));
+        }
+        let mut address = [0u8; 20];
+        address.copy_from_slice(&bytes);
+        Ok(Address(address))
+    }
+}
+
+/// Hex encoded 32 byte value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Word(pub [u8; 32]);
+
+impl TryFrom<String> for Word {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let bytes = decode_hex(&value)?;
+        if bytes.len() != 32 {
+            return Err(Error::Length("storage key", 32));
+        }
+        let mut word = [0u8; 32];
+        word.copy_from_slice(&bytes);
+        Ok(Word(word))
+    }
+}
+
+/// Remove leading zero bytes.
+pub(crate) fn strip_zeros(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+    &bytes[start..]
+}
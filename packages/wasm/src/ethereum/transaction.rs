@@ -0,0 +1,422 @@
+//! Legacy, EIP-2930 and EIP-1559 transactions.
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::party_i::SignatureRecid;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use super::{rlp, Address, Bytes, Error, Quantity, Word};
+use crate::encoding::RawSignature;
+use crate::utils::keccak256;
+
+/// Entry in an access list.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessListItem {
+    /// Address that is accessed.
+    pub address: Address,
+    /// Storage keys that are accessed.
+    #[serde(default)]
+    pub storage_keys: Vec<Word>,
+}
+
+/// Legacy transaction; signed with EIP-155 replay protection
+/// when a chain id is given.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegacyTransaction {
+    /// Chain identifier.
+    pub chain_id: Option<Quantity>,
+    /// Sender nonce.
+    pub nonce: Quantity,
+    /// Gas price in wei.
+    pub gas_price: Quantity,
+    /// Gas limit.
+    #[serde(alias = "gas")]
+    pub gas_limit: Quantity,
+    /// Recipient, omitted for contract creation.
+    pub to: Option<Address>,
+    /// Value in wei.
+    #[serde(default)]
+    pub value: Quantity,
+    /// Input data.
+    #[serde(default)]
+    pub data: Bytes,
+}
+
+/// EIP-2930 transaction with an access list.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessListTransaction {
+    /// Chain identifier.
+    pub chain_id: Quantity,
+    /// Sender nonce.
+    pub nonce: Quantity,
+    /// Gas price in wei.
+    pub gas_price: Quantity,
+    /// Gas limit.
+    #[serde(alias = "gas")]
+    pub gas_limit: Quantity,
+    /// Recipient, omitted for contract creation.
+    pub to: Option<Address>,
+    /// Value in wei.
+    #[serde(default)]
+    pub value: Quantity,
+    /// Input data.
+    #[serde(default)]
+    pub data: Bytes,
+    /// Access list.
+    #[serde(default)]
+    pub access_list: Vec<AccessListItem>,
+}
+
+/// EIP-1559 transaction with a priority fee.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DynamicFeeTransaction {
+    /// Chain identifier.
+    pub chain_id: Quantity,
+    /// Sender nonce.
+    pub nonce: Quantity,
+    /// Maximum priority fee per gas in wei.
+    pub max_priority_fee_per_gas: Quantity,
+    /// Maximum fee per gas in wei.
+    pub max_fee_per_gas: Quantity,
+    /// Gas limit.
+    #[serde(alias = "gas")]
+    pub gas_limit: Quantity,
+    /// Recipient, omitted for contract creation.
+    pub to: Option<Address>,
+    /// Value in wei.
+    #[serde(default)]
+    pub value: Quantity,
+    /// Input data.
+    #[serde(default)]
+    pub data: Bytes,
+    /// Access list.
+    #[serde(default)]
+    pub access_list: Vec<AccessListItem>,
+}
+
+/// Ethereum transaction.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Transaction {
+    /// Legacy transaction.
+    Legacy(LegacyTransaction),
+    /// EIP-2930 transaction.
+    Eip2930(AccessListTransaction),
+    /// EIP-1559 transaction.
+    Eip1559(DynamicFeeTransaction),
+}
+
+/// Signed transaction ready to be broadcast.
+#[derive(Debug, Clone, Serialize)]
+pub struct SignedTransaction {
+    /// Hex encoded raw transaction.
+    pub raw: String,
+    /// Hex encoded transaction hash.
+    pub hash: String,
+}
+
+impl Transaction {
+    /// Bytes that are hashed for signing.
+    pub fn signing_payload(&self) -> Vec<u8> {
+        let mut fields = self.fields();
+        match self {
+            Transaction::Legacy(tx) => {
+                if let Some(chain_id) = &tx.chain_id {
+                    fields.push(quantity(chain_id));
+                    fields.push(rlp::encode_bytes(&[]));
+                    fields.push(rlp::encode_bytes(&[]));
+                }
+                rlp::encode_list(&fields)
+            }
+            _ => self.envelope(&fields),
+        }
+    }
+
+    /// Hash to be signed by the threshold signers.
+    pub fn signing_hash(&self) -> [u8; 32] {
+        keccak256(&self.signing_payload())
+    }
+
+    /// Raw signed transaction.
+    ///
+    /// The signature is normalized to a low `s` value.
+    pub fn encode_signed(&self, signature: &RawSignature) -> Result<Vec<u8>, Error> {
+        let signature = signature.normalize_s();
+        let mut fields = self.fields();
+        let v = match self {
+            Transaction::Legacy(tx) => {
+                let chain_id = tx
+                    .chain_id
+                    .as_ref()
+                    .map(|chain_id| chain_id.as_u64().ok_or(Error::ChainId))
+                    .transpose()?;
+                signature.v(chain_id).ok_or(Error::ChainId)?
+            }
+            _ => signature.recid as u64,
+        };
+        fields.push(quantity(&v.into()));
+        fields.push(rlp::encode_bytes(super::strip_zeros(&signature.r)));
+        fields.push(rlp::encode_bytes(super::strip_zeros(&signature.s)));
+        Ok(match self {
+            Transaction::Legacy(_) => rlp::encode_list(&fields),
+            _ => self.envelope(&fields),
+        })
+    }
+
+    /// Encoded fields common to the unsigned and signed forms.
+    fn fields(&self) -> Vec<Vec<u8>> {
+        match self {
+            Transaction::Legacy(tx) => vec![
+                quantity(&tx.nonce),
+                quantity(&tx.gas_price),
+                quantity(&tx.gas_limit),
+                to(&tx.to),
+                quantity(&tx.value),
+                rlp::encode_bytes(&tx.data.0),
+            ],
+            Transaction::Eip2930(tx) => vec![
+                quantity(&tx.chain_id),
+                quantity(&tx.nonce),
+                quantity(&tx.gas_price),
+                quantity(&tx.gas_limit),
+                to(&tx.to),
+                quantity(&tx.value),
+                rlp::encode_bytes(&tx.data.0),
+                access_list(&tx.access_list),
+            ],
+            Transaction::Eip1559(tx) => vec![
+                quantity(&tx.chain_id),
+                quantity(&tx.nonce),
+                quantity(&tx.max_priority_fee_per_gas),
+                quantity(&tx.max_fee_per_gas),
+                quantity(&tx.gas_limit),
+                to(&tx.to),
+                quantity(&tx.value),
+                rlp::encode_bytes(&tx.data.0),
+                access_list(&tx.access_list),
+            ],
+        }
+    }
+
+    /// Prefix the encoded fields with the transaction type.
+    fn envelope(&self, fields: &[Vec<u8>]) -> Vec<u8> {
+        let kind = match self {
+            Transaction::Legacy(_) => unreachable!(),
+            Transaction::Eip2930(_) => 0x01,
+            Transaction::Eip1559(_) => 0x02,
+        };
+        let mut encoded = vec![kind];
+        encoded.extend(rlp::encode_list(fields));
+        encoded
+    }
+}
+
+fn quantity(value: &Quantity) -> Vec<u8> {
+    rlp::encode_bytes(value.as_bytes())
+}
+
+fn to(address: &Option<Address>) -> Vec<u8> {
+    match address {
+        Some(address) => rlp::encode_bytes(&address.0),
+        None => rlp::encode_bytes(&[]),
+    }
+}
+
+fn access_list(items: &[AccessListItem]) -> Vec<u8> {
+    let items = items
+        .iter()
+        .map(|item| {
+            let keys = item
+                .storage_keys
+                .iter()
+                .map(|key| rlp::encode_bytes(&key.0))
+                .collect::<Vec<_>>();
+            rlp::encode_list(&[rlp::encode_bytes(&item.address.0), rlp::encode_list(&keys)])
+        })
+        .collect::<Vec<_>>();
+    rlp::encode_list(&items)
+}
+
+/// Compute the hash of a transaction to pass to `Signer.partial()`.
+///
+/// The transaction `type` is one of `legacy`, `eip2930` or `eip1559`;
+/// quantities may be numbers, decimal strings or hex strings.
+#[wasm_bindgen(js_name = "transactionHash")]
+pub fn transaction_hash(transaction: JsValue) -> Result<JsValue, JsError> {
+    let transaction: Transaction = transaction.into_serde()?;
+    Ok(JsValue::from_serde(&transaction.signing_hash().to_vec())?)
+}
+
+/// Assemble a signed raw transaction from a signature.
+#[wasm_bindgen(js_name = "signTransaction")]
+pub fn sign_transaction(transaction: JsValue, signature: JsValue) -> Result<JsValue, JsError> {
+    let transaction: Transaction = transaction.into_serde()?;
+    let signature: SignatureRecid = signature.into_serde()?;
+    let raw = transaction.encode_signed(&RawSignature::from(&signature))?;
+    let signed = SignedTransaction {
+        hash: format!("0x{}", hex::encode(keccak256(&raw))),
+        raw: format!("0x{}", hex::encode(&raw)),
+    };
+    Ok(JsValue::from_serde(&signed)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Example transaction from EIP-155
+    const TX: &str = r#"{
+        "type": "legacy",
+        "chainId": 1,
+        "nonce": 9,
+        "gasPrice": "20000000000",
+        "gasLimit": 21000,
+        "to": "0x3535353535353535353535353535353535353535",
+        "value": "1000000000000000000"
+    }"#;
+
+    // Mainnet EIP-2930 transaction with an access list from the
+    // ethers-rs test suite; the signature recovers the sender
+    // 0xe9c790e8fde820ded558a4771b72eec916c04763
+    const ACCESS_LIST_TX: &str = r#"{
+        "type": "eip2930",
+        "chainId": 1,
+        "nonce": 9215,
+        "gasPrice": "43000000000",
+        "gas": 1000000,
+        "to": "0x0000000000a8fb09af944ab3baf7a9b3e1ab29d8",
+        "value": 0,
+        "data": "0x200200001525000000000b69ffb300000000557b933a7c2c45672b610f8954a3deb39a51a8cae53ec727dbdeb9e2d5456c3be40cff031ab40a55724d5c9c618a2152e99a45649a3b8cf198321f46720b722f4ec38f99ba3bb1303258d2e816e6a95b25647e01bd0967c1b9599fa3521939871d1d0888",
+        "accessList": [
+            { "address": "0x724d5c9c618a2152e99a45649a3b8cf198321f46" },
+            { "address": "0x720b722f4ec38f99ba3bb1303258d2e816e6a95b" },
+            {
+                "address": "0x25647e01bd0967c1b9599fa3521939871d1d0888",
+                "storageKeys": []
+            }
+        ]
+    }"#;
+
+    // Ropsten EIP-1559 transaction 0x8243...c34d with storage keys
+    // in the access list from the ethers-rs test suite
+    const DYNAMIC_FEE_TX: &str = r#"{
+        "type": "eip1559",
+        "chainId": 3,
+        "nonce": 2,
+        "maxPriorityFeePerGas": "0x3b9aca00",
+        "maxFeePerGas": "0x3b9aca0e",
+        "gasLimit": "0x6a40",
+        "to": "0x8210357f377e901f18e45294e86a2a32215cc3c9",
+        "value": "0x7b",
+        "accessList": [
+            {
+                "address": "0x8ba1f109551bd432803012645ac136ddd64dba72",
+                "storageKeys": [
+                    "0x0000000000000000000000000000000000000000000000000000000000000000",
+                    "0x0000000000000000000000000000000000000000000000000000000000000042"
+                ]
+            }
+        ]
+    }"#;
+
+    fn raw_signature(r: &str, s: &str, recid: u8) -> RawSignature {
+        let mut signature = RawSignature {
+            r: [0u8; 32],
+            s: [0u8; 32],
+            recid,
+        };
+        signature.r.copy_from_slice(&hex::decode(r).unwrap());
+        signature.s.copy_from_slice(&hex::decode(s).unwrap());
+        signature
+    }
+
+    #[test]
+    fn legacy_eip155() {
+        let tx: Transaction = serde_json::from_str(TX).unwrap();
+        assert_eq!(
+            hex::encode(tx.signing_payload()),
+            "ec098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a764000080018080"
+        );
+        assert_eq!(
+            hex::encode(tx.signing_hash()),
+            "daf5a779ae972f972197303d7b574746c7ef83eadac0f2791ad23db92e4c8e53"
+        );
+
+        let signature = raw_signature(
+            "28ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276",
+            "67cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83",
+            0,
+        );
+        assert_eq!(
+            hex::encode(tx.encode_signed(&signature).unwrap()),
+            "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83"
+        );
+
+        // Chain id that fits in 64 bits but overflows the EIP-155 `v`
+        let mut tx = tx;
+        if let Transaction::Legacy(legacy) = &mut tx {
+            legacy.chain_id = Some(serde_json::from_str("\"0xffffffffffffffff\"").unwrap());
+        }
+        assert!(matches!(tx.encode_signed(&signature), Err(Error::ChainId)));
+    }
+
+    #[test]
+    fn access_list_eip2930() {
+        let tx: Transaction = serde_json::from_str(ACCESS_LIST_TX).unwrap();
+        assert_eq!(
+            hex::encode(tx.signing_payload()),
+            "01f8e3018223ff850a02ffee00830f4240940000000000a8fb09af944ab3baf7a9b3e1ab29d880b876200200001525000000000b69ffb300000000557b933a7c2c45672b610f8954a3deb39a51a8cae53ec727dbdeb9e2d5456c3be40cff031ab40a55724d5c9c618a2152e99a45649a3b8cf198321f46720b722f4ec38f99ba3bb1303258d2e816e6a95b25647e01bd0967c1b9599fa3521939871d1d0888f845d694724d5c9c618a2152e99a45649a3b8cf198321f46c0d694720b722f4ec38f99ba3bb1303258d2e816e6a95bc0d69425647e01bd0967c1b9599fa3521939871d1d0888c0"
+        );
+        assert_eq!(
+            hex::encode(tx.signing_hash()),
+            "23f3f4e09e30b5de557870b1e811f8300a805ebe786fbbbf16e124e79565905b"
+        );
+
+        let signature = raw_signature(
+            "8323efae7b9993bd31a58da7924359d24b5504aa2b33194fcc5ae206e65d2e62",
+            "54ce201e3b4b5cd38eb17c56ee2f9111b2e164efcd57b3e70fa308a0a51f7014",
+            1,
+        );
+        let raw = tx.encode_signed(&signature).unwrap();
+        assert_eq!(
+            hex::encode(&raw),
+            "01f90126018223ff850a02ffee00830f4240940000000000a8fb09af944ab3baf7a9b3e1ab29d880b876200200001525000000000b69ffb300000000557b933a7c2c45672b610f8954a3deb39a51a8cae53ec727dbdeb9e2d5456c3be40cff031ab40a55724d5c9c618a2152e99a45649a3b8cf198321f46720b722f4ec38f99ba3bb1303258d2e816e6a95b25647e01bd0967c1b9599fa3521939871d1d0888f845d694724d5c9c618a2152e99a45649a3b8cf198321f46c0d694720b722f4ec38f99ba3bb1303258d2e816e6a95bc0d69425647e01bd0967c1b9599fa3521939871d1d0888c001a08323efae7b9993bd31a58da7924359d24b5504aa2b33194fcc5ae206e65d2e62a054ce201e3b4b5cd38eb17c56ee2f9111b2e164efcd57b3e70fa308a0a51f7014"
+        );
+    }
+
+    #[test]
+    fn dynamic_fee_eip1559() {
+        let tx: Transaction = serde_json::from_str(DYNAMIC_FEE_TX).unwrap();
+        assert_eq!(
+            hex::encode(tx.signing_payload()),
+            "02f8830302843b9aca00843b9aca0e826a40948210357f377e901f18e45294e86a2a32215cc3c97b80f85bf859948ba1f109551bd432803012645ac136ddd64dba72f842a00000000000000000000000000000000000000000000000000000000000000000a00000000000000000000000000000000000000000000000000000000000000042"
+        );
+        assert_eq!(
+            hex::encode(tx.signing_hash()),
+            "fcc5c2a59582ddc38a94b29adcbbf5975898be47a18593b2116d0389a634ec5d"
+        );
+
+        let expected = "02f8c60302843b9aca00843b9aca0e826a40948210357f377e901f18e45294e86a2a32215cc3c97b80f85bf859948ba1f109551bd432803012645ac136ddd64dba72f842a00000000000000000000000000000000000000000000000000000000000000000a0000000000000000000000000000000000000000000000000000000000000004201a0f13b5088108f783f4b6048d4be456971118aabfb88be96bb541d734b6c2b20dca013fb7eb25a7d5df42a176cd4c6a086e19163ed7cd8ffba015f939d24f66bc17a";
+        let signature = raw_signature(
+            "f13b5088108f783f4b6048d4be456971118aabfb88be96bb541d734b6c2b20dc",
+            "13fb7eb25a7d5df42a176cd4c6a086e19163ed7cd8ffba015f939d24f66bc17a",
+            1,
+        );
+        let raw = tx.encode_signed(&signature).unwrap();
+        assert_eq!(hex::encode(&raw), expected);
+        assert_eq!(
+            hex::encode(keccak256(&raw)),
+            "824384376c5972498c6fcafe71fd8cad1689f64e7d5e270d025a898638c0c34d"
+        );
+
+        // The high-s form of the signature is normalized
+        let high_s = raw_signature(
+            "f13b5088108f783f4b6048d4be456971118aabfb88be96bb541d734b6c2b20dc",
+            "ec04814da582a20bd5e8932b395f791d294aef69d648e63a603ec167d9ca7fc7",
+            0,
+        );
+        assert_eq!(hex::encode(tx.encode_signed(&high_s).unwrap()), expected);
+    }
+}
@@ -0,0 +1,591 @@
+//! Distributed key generation.
+//!
+//! The first round broadcasts commitments to the coefficients of
+//! each party's polynomial and an ephemeral key with a Schnorr proof
+//! of knowledge of the constant term that also binds the ephemeral
+//! key; the second round sends every
+//! other party its evaluation of the polynomial encrypted with a key
+//! derived from the ephemeral keys so the relay cannot read the shares.
+use curv::elliptic::curves::{Curve, Point, Scalar};
+use round_based::Msg;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use wasm_bindgen::prelude::*;
+
+use super::{
+    blame, evaluate, evaluate_commitments, failure, hash_to_scalar, Bip340, Ciphersuite,
+    Ed25519Sha512, FrostCurve, FrostKeyShare, FrostLocalKey, LocalKey,
+};
+use crate::encrypt::{self, EncryptedBlob};
+use crate::gg2020::blame::ProtocolError;
+use crate::gg2020::keygen::PartySignup;
+use crate::Parameters;
+
+/// Number of rounds for key generation.
+pub const ROUNDS: u16 = 2;
+
+/// Proof of knowledge of a secret.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Proof<E: Curve> {
+    /// Commitment to the proof nonce.
+    pub r: Point<E>,
+    /// Response to the challenge.
+    pub mu: Scalar<E>,
+}
+
+/// Message sent during key generation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub enum KeygenMessage<E: Curve> {
+    /// Commitments to the polynomial and the ephemeral key.
+    Round1 {
+        /// Commitments to every coefficient.
+        commitments: Vec<Point<E>>,
+        /// Proof of knowledge of the constant term.
+        proof: Proof<E>,
+        /// Ephemeral key for encrypting shares.
+        ephemeral_key: Point<E>,
+    },
+    /// Encrypted evaluation of the polynomial for the receiver.
+    Round2 {
+        /// The encrypted share.
+        share: EncryptedBlob,
+    },
+}
+
+/// Key generation state for a party.
+pub struct Dkg<C: Ciphersuite> {
+    index: u16,
+    threshold: u16,
+    parties: u16,
+    context: Vec<u8>,
+    round: u16,
+    coefficients: Vec<Scalar<C::Curve>>,
+    ephemeral: Scalar<C::Curve>,
+    commitments: BTreeMap<u16, (Vec<Point<C::Curve>>, Point<C::Curve>)>,
+    shares: BTreeMap<u16, Scalar<C::Curve>>,
+    output: Option<LocalKey<C::Curve>>,
+    suite: PhantomData<C>,
+}
+
+impl<C: Ciphersuite> Dkg<C> {
+    /// Create the key generation state for a party.
+    ///
+    /// The context is bound into the proofs so they cannot
+    /// be replayed in another session.
+    pub fn new(
+        index: u16,
+        threshold: u16,
+        parties: u16,
+        context: Vec<u8>,
+    ) -> Result<Self, ProtocolError> {
+        if threshold == 0 || threshold >= parties || index == 0 || index > parties {
+            return Err(failure(
+                0,
+                "InvalidParameters",
+                format!(
+                    "invalid party {} for threshold {} with {} parties",
+                    index, threshold, parties
+                ),
+            ));
+        }
+        Ok(Self {
+            index,
+            threshold,
+            parties,
+            context,
+            round: 0,
+            coefficients: Vec::new(),
+            ephemeral: Scalar::<C::Curve>::random(),
+            commitments: Default::default(),
+            shares: Default::default(),
+            output: None,
+            suite: PhantomData,
+        })
+    }
+
+    /// Current round.
+    pub fn current_round(&self) -> u16 {
+        self.round
+    }
+
+    /// Determine if key generation has finished.
+    pub fn is_finished(&self) -> bool {
+        self.output.is_some()
+    }
+
+    /// Take the generated key share.
+    pub fn pick_output(&mut self) -> Option<LocalKey<C::Curve>> {
+        self.output.take()
+    }
+
+    /// Handle an incoming message.
+    pub fn handle_incoming(
+        &mut self,
+        message: Msg<KeygenMessage<C::Curve>>,
+    ) -> Result<(), ProtocolError> {
+        let sender = message.sender;
+        if sender == 0 || sender > self.parties || sender == self.index {
+            return Err(failure(
+                self.round,
+                "InvalidSender",
+                format!("key generation message from invalid party {}", sender),
+            ));
+        }
+
+        match message.body {
+            KeygenMessage::Round1 {
+                commitments,
+                proof,
+                ephemeral_key,
+            } if self.round == 1 => {
+                if self.commitments.contains_key(&sender) {
+                    return Err(self.blame(sender, "DuplicateMessage"));
+                }
+                if commitments.len() != self.threshold as usize + 1 {
+                    return Err(self.blame(sender, "InvalidCommitments"));
+                }
+                if ephemeral_key.is_zero() {
+                    return Err(self.blame(sender, "InvalidEphemeralKey"));
+                }
+                if !self.verify_proof(sender, &commitments[0], &ephemeral_key, &proof) {
+                    return Err(self.blame(sender, "InvalidProof"));
+                }
+                self.commitments
+                    .insert(sender, (commitments, ephemeral_key));
+            }
+            KeygenMessage::Round2 { share } if self.round == 2 => {
+                if self.shares.contains_key(&sender) {
+                    return Err(self.blame(sender, "DuplicateMessage"));
+                }
+                let share = self
+                    .decrypt(sender, &share)
+                    .ok_or_else(|| self.blame(sender, "InvalidShare"))?;
+                let (commitments, _) = &self.commitments[&sender];
+                if Point::<C::Curve>::generator() * &share
+                    != evaluate_commitments(commitments, self.index)
+                {
+                    return Err(self.blame(sender, "InvalidShare"));
+                }
+                self.shares.insert(sender, share);
+            }
+            _ => return Err(self.blame(sender, "WrongRound")),
+        }
+        Ok(())
+    }
+
+    /// Proceed to the next round.
+    pub fn proceed(&mut self) -> Result<Vec<Msg<KeygenMessage<C::Curve>>>, ProtocolError> {
+        let messages = match self.round {
+            0 => vec![self.round1()],
+            1 => self.round2()?,
+            2 => {
+                self.finish()?;
+                vec![]
+            }
+            _ => {
+                return Err(failure(
+                    self.round,
+                    "Finished",
+                    "key generation has already finished".to_string(),
+                ))
+            }
+        };
+        self.round += 1;
+        Ok(messages)
+    }
+
+    /// Sample the polynomial and prove knowledge of the constant term.
+    fn round1(&mut self) -> Msg<KeygenMessage<C::Curve>> {
+        self.coefficients = (0..=self.threshold)
+            .map(|_| Scalar::<C::Curve>::random())
+            .collect();
+        let commitments: Vec<Point<C::Curve>> = self
+            .coefficients
+            .iter()
+            .map(|a| Point::<C::Curve>::generator() * a)
+            .collect();
+
+        let ephemeral_key = Point::<C::Curve>::generator() * &self.ephemeral;
+        let k = Scalar::<C::Curve>::random();
+        let r = Point::<C::Curve>::generator() * &k;
+        let c = self.proof_challenge(self.index, &commitments[0], &ephemeral_key, &r);
+        let mu = k + &self.coefficients[0] * c;
+
+        self.commitments
+            .insert(self.index, (commitments.clone(), ephemeral_key.clone()));
+
+        Msg {
+            sender: self.index,
+            receiver: None,
+            body: KeygenMessage::Round1 {
+                commitments,
+                proof: Proof { r, mu },
+                ephemeral_key,
+            },
+        }
+    }
+
+    /// Send each other party an encrypted evaluation of the polynomial.
+    fn round2(&mut self) -> Result<Vec<Msg<KeygenMessage<C::Curve>>>, ProtocolError> {
+        self.expect(self.commitments.len() - 1)?;
+        let own = evaluate(&self.coefficients, self.index);
+        self.shares.insert(self.index, own);
+
+        (1..=self.parties)
+            .filter(|index| *index != self.index)
+            .map(|index| {
+                let share = evaluate(&self.coefficients, index);
+                let key = self.shared_key(index);
+                let id = format!("frost:{}:{}", self.index, index);
+                let share = encrypt::seal(&key, id, &share).map_err(|_| {
+                    failure(
+                        self.round,
+                        "Encryption",
+                        "failed to encrypt share".to_string(),
+                    )
+                })?;
+                Ok(Msg {
+                    sender: self.index,
+                    receiver: Some(index),
+                    body: KeygenMessage::Round2 { share },
+                })
+            })
+            .collect()
+    }
+
+    /// Compute the key share and the public key shares.
+    fn finish(&mut self) -> Result<(), ProtocolError> {
+        self.expect(self.shares.len() - 1)?;
+
+        let mut secret_share = self
+            .shares
+            .values()
+            .fold(Scalar::<C::Curve>::zero(), |acc, share| acc + share);
+        let mut group_key = self
+            .commitments
+            .values()
+            .fold(Point::<C::Curve>::zero(), |acc, (commitments, _)| {
+                acc + &commitments[0]
+            });
+        let mut verifying_shares = (1..=self.parties)
+            .map(|index| {
+                self.commitments
+                    .values()
+                    .fold(Point::<C::Curve>::zero(), |acc, (commitments, _)| {
+                        acc + evaluate_commitments(commitments, index)
+                    })
+            })
+            .collect::<Vec<Point<C::Curve>>>();
+
+        // Negate the secret so the group key has an even y coordinate
+        if C::is_odd(&group_key) {
+            secret_share = Scalar::<C::Curve>::zero() - &secret_share;
+            group_key = Point::<C::Curve>::zero() - &group_key;
+            for share in verifying_shares.iter_mut() {
+                *share = Point::<C::Curve>::zero() - &*share;
+            }
+        }
+
+        if Point::<C::Curve>::generator() * &secret_share
+            != verifying_shares[self.index as usize - 1]
+        {
+            return Err(failure(
+                self.round,
+                "InvalidKeyShare",
+                "key share does not match the public key shares".to_string(),
+            ));
+        }
+
+        self.coefficients.clear();
+        self.output = Some(LocalKey {
+            i: self.index,
+            t: self.threshold,
+            n: self.parties,
+            secret_share,
+            group_key,
+            verifying_shares,
+        });
+        Ok(())
+    }
+
+    fn proof_challenge(
+        &self,
+        index: u16,
+        commitment: &Point<C::Curve>,
+        ephemeral_key: &Point<C::Curve>,
+        r: &Point<C::Curve>,
+    ) -> Scalar<C::Curve> {
+        hash_to_scalar(
+            "keygen-proof",
+            &[
+                &index.to_be_bytes(),
+                &self.context,
+                &commitment.to_bytes(true),
+                &ephemeral_key.to_bytes(true),
+                &r.to_bytes(true),
+            ],
+        )
+    }
+
+    fn verify_proof(
+        &self,
+        index: u16,
+        commitment: &Point<C::Curve>,
+        ephemeral_key: &Point<C::Curve>,
+        proof: &Proof<C::Curve>,
+    ) -> bool {
+        let c = self.proof_challenge(index, commitment, ephemeral_key, &proof.r);
+        Point::<C::Curve>::generator() * &proof.mu == &proof.r + commitment * &c
+    }
+
+    /// Key for encrypting shares between this party and another.
+    fn shared_key(&self, other: u16) -> Vec<u8> {
+        let (_, ephemeral_key) = &self.commitments[&other];
+        let point = ephemeral_key * &self.ephemeral;
+        Sha256::digest(&point.to_bytes(true)).to_vec()
+    }
+
+    fn decrypt(&self, sender: u16, share: &EncryptedBlob) -> Option<Scalar<C::Curve>> {
+        if share.id != format!("frost:{}:{}", sender, self.index) {
+            return None;
+        }
+        encrypt::open(&self.shared_key(sender), share).ok()
+    }
+
+    /// Check all the other parties sent a message for the round.
+    fn expect(&self, received: usize) -> Result<(), ProtocolError> {
+        let expected = self.parties as usize - 1;
+        if received != expected {
+            return Err(failure(
+                self.round,
+                "MissingMessages",
+                format!(
+                    "expected messages from {} parties but got {}",
+                    expected, received
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    fn blame(&self, sender: u16, kind: &str) -> ProtocolError {
+        blame(
+            self.round,
+            kind,
+            vec![sender],
+            format!("party {} sent an invalid key generation message", sender),
+        )
+    }
+}
+
+enum Inner {
+    Secp256k1(Dkg<Bip340>),
+    Ed25519(Dkg<Ed25519Sha512>),
+}
+
+/// Round-based FROST key share generator.
+#[wasm_bindgen]
+pub struct FrostKeyGenerator {
+    inner: Inner,
+}
+
+#[wasm_bindgen]
+impl FrostKeyGenerator {
+    /// Create a key generator for a curve (`secp256k1` or `ed25519`).
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        curve: JsValue,
+        parameters: JsValue,
+        party_signup: JsValue,
+    ) -> Result<FrostKeyGenerator, JsValue> {
+        let curve: FrostCurve = curve.into_serde().map_err(JsError::from)?;
+        let params: Parameters = parameters.into_serde().map_err(JsError::from)?;
+        let party_signup: PartySignup = party_signup.into_serde().map_err(JsError::from)?;
+        let context = party_signup.uuid.into_bytes();
+        let (index, t, n) = (party_signup.number, params.threshold, params.parties);
+        let inner = match curve {
+            FrostCurve::Secp256k1 => Inner::Secp256k1(Dkg::new(index, t, n, context)?),
+            FrostCurve::Ed25519 => Inner::Ed25519(Dkg::new(index, t, n, context)?),
+        };
+        Ok(Self { inner })
+    }
+
+    /// Handle an incoming message.
+    ///
+    /// Protocol failures throw a `ProtocolError`.
+    #[wasm_bindgen(js_name = "handleIncoming")]
+    pub fn handle_incoming(&mut self, message: JsValue) -> Result<(), JsValue> {
+        match &mut self.inner {
+            Inner::Secp256k1(dkg) => {
+                dkg.handle_incoming(message.into_serde().map_err(JsError::from)?)?
+            }
+            Inner::Ed25519(dkg) => {
+                dkg.handle_incoming(message.into_serde().map_err(JsError::from)?)?
+            }
+        }
+        Ok(())
+    }
+
+    /// Proceed to the next round.
+    ///
+    /// Protocol failures throw a `ProtocolError`.
+    pub fn proceed(&mut self) -> Result<JsValue, JsValue> {
+        let value = match &mut self.inner {
+            Inner::Secp256k1(dkg) => round_messages(dkg.proceed()?, dkg.current_round()),
+            Inner::Ed25519(dkg) => round_messages(dkg.proceed()?, dkg.current_round()),
+        };
+        Ok(value.map_err(JsError::from)?)
+    }
+
+    /// Create the key share.
+    pub fn create(&mut self) -> Result<JsValue, JsError> {
+        let key_share = match &mut self.inner {
+            Inner::Secp256k1(dkg) => dkg.pick_output().map(|local_key| FrostKeyShare {
+                public_key: Bip340::encode_point(&local_key.group_key),
+                local_key: FrostLocalKey::Secp256k1(local_key),
+            }),
+            Inner::Ed25519(dkg) => dkg.pick_output().map(|local_key| FrostKeyShare {
+                public_key: Ed25519Sha512::encode_point(&local_key.group_key),
+                local_key: FrostLocalKey::Ed25519(local_key),
+            }),
+        }
+        .ok_or_else(|| JsError::new("key generation has not finished"))?;
+        Ok(JsValue::from_serde(&key_share)?)
+    }
+}
+
+/// Convert messages for a round to `RoundMsg` values.
+pub(crate) fn round_messages<B: Serialize>(
+    messages: Vec<Msg<B>>,
+    round: u16,
+) -> Result<JsValue, serde_json::Error> {
+    let messages = mpc_ecdsa_core::round_messages(round, messages);
+    JsValue::from_serde(&(round, &messages))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use curv::elliptic::curves::Secp256k1;
+
+    /// Run key generation for all parties.
+    pub(crate) fn keygen<C: Ciphersuite>(t: u16, n: u16) -> Vec<LocalKey<C::Curve>> {
+        let mut parties = (1..=n)
+            .map(|i| Dkg::<C>::new(i, t, n, b"session".to_vec()).unwrap())
+            .collect::<Vec<_>>();
+        for _ in 0..=ROUNDS {
+            let messages = parties
+                .iter_mut()
+                .flat_map(|party| party.proceed().unwrap())
+                .collect::<Vec<_>>();
+            for message in messages {
+                for party in parties.iter_mut() {
+                    let to_party = match message.receiver {
+                        Some(receiver) => receiver == party.index,
+                        None => message.sender != party.index,
+                    };
+                    if to_party {
+                        party.handle_incoming(message.clone()).unwrap();
+                    }
+                }
+            }
+        }
+        parties
+            .iter_mut()
+            .map(|party| party.pick_output().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn keygen_secp256k1() {
+        let keys = keygen::<Bip340>(1, 3);
+        assert!(keys.iter().all(|key| key.group_key == keys[0].group_key));
+        assert!(!Bip340::is_odd(&keys[0].group_key));
+    }
+
+    #[test]
+    fn keygen_ed25519() {
+        let keys = keygen::<Ed25519Sha512>(2, 4);
+        assert!(keys.iter().all(|key| key.group_key == keys[0].group_key));
+    }
+
+    #[test]
+    fn invalid_share_blames_sender() {
+        let mut alice = Dkg::<Bip340>::new(1, 1, 2, vec![]).unwrap();
+        let mut bob = Dkg::<Bip340>::new(2, 1, 2, vec![]).unwrap();
+        let a1 = alice.proceed().unwrap();
+        let b1 = bob.proceed().unwrap();
+        alice.handle_incoming(b1[0].clone()).unwrap();
+        bob.handle_incoming(a1[0].clone()).unwrap();
+        let mut a2 = alice.proceed().unwrap();
+        bob.proceed().unwrap();
+        if let KeygenMessage::Round2 { share } = &mut a2[0].body {
+            share.ciphertext[0] ^= 1;
+        }
+        let error = bob.handle_incoming(a2[0].clone()).unwrap_err();
+        assert_eq!(error.culprits, vec![1]);
+    }
+
+    #[test]
+    fn round1_checks() {
+        let mut alice = Dkg::<Bip340>::new(1, 1, 2, vec![]).unwrap();
+        let mut bob = Dkg::<Bip340>::new(2, 1, 2, vec![]).unwrap();
+        let mut other = Dkg::<Bip340>::new(1, 1, 2, vec![]).unwrap();
+        let a1 = alice.proceed().unwrap().remove(0);
+        let replay = other.proceed().unwrap().remove(0);
+        bob.proceed().unwrap();
+
+        let ephemeral_key = |message: &Msg<KeygenMessage<Secp256k1>>| match &message.body {
+            KeygenMessage::Round1 { ephemeral_key, .. } => ephemeral_key.clone(),
+            _ => unreachable!(),
+        };
+        let with_key = |key: Point<Secp256k1>| {
+            let mut message = a1.clone();
+            if let KeygenMessage::Round1 { ephemeral_key, .. } = &mut message.body {
+                *ephemeral_key = key;
+            }
+            message
+        };
+
+        // The proof binds the ephemeral key
+        let error = bob
+            .handle_incoming(with_key(ephemeral_key(&replay)))
+            .unwrap_err();
+        assert_eq!(error.kind, "InvalidProof");
+        let error = bob.handle_incoming(with_key(Point::zero())).unwrap_err();
+        assert_eq!(error.kind, "InvalidEphemeralKey");
+        assert_eq!(error.culprits, vec![1]);
+
+        // A second message does not replace the first
+        bob.handle_incoming(a1.clone()).unwrap();
+        let error = bob.handle_incoming(replay).unwrap_err();
+        assert_eq!(error.kind, "DuplicateMessage");
+        assert_eq!(bob.commitments[&1].1, ephemeral_key(&a1));
+    }
+
+    #[test]
+    fn duplicate_share_is_not_stored() {
+        let mut alice = Dkg::<Bip340>::new(1, 1, 2, vec![]).unwrap();
+        let mut bob = Dkg::<Bip340>::new(2, 1, 2, vec![]).unwrap();
+        let a1 = alice.proceed().unwrap();
+        let b1 = bob.proceed().unwrap();
+        alice.handle_incoming(b1[0].clone()).unwrap();
+        bob.handle_incoming(a1[0].clone()).unwrap();
+        let a2 = alice.proceed().unwrap();
+        let b2 = bob.proceed().unwrap();
+        alice.handle_incoming(b2[0].clone()).unwrap();
+        bob.handle_incoming(a2[0].clone()).unwrap();
+        let error = bob.handle_incoming(a2[0].clone()).unwrap_err();
+        assert_eq!(error.kind, "DuplicateMessage");
+
+        alice.proceed().unwrap();
+        bob.proceed().unwrap();
+        let alice = alice.pick_output().unwrap();
+        let bob = bob.pick_output().unwrap();
+        assert_eq!(alice.group_key, bob.group_key);
+    }
+}
@@ -0,0 +1,334 @@
+//! FROST threshold Schnorr signatures.
+//!
+//! Key generation is the Pedersen DKG from the FROST paper: every
+//! party commits to a random polynomial with a proof of knowledge
+//! of the constant term and sends evaluations to the other parties;
+//! signing uses two rounds (nonce commitments then signature shares)
+//! so it is much cheaper than GG2020.
+//!
+//! Signatures are plain BIP340 signatures over secp256k1 (for
+//! Taproot) or Ed25519 signatures and verify with any standard
+//! implementation; for BIP340 the group key and the group nonce are
+//! negated when required so that both have an even `y` coordinate.
+//!
+//! Messages use the same `RoundMsg` shape as GG2020 so they are
+//! relayed by the server without changes.
+use curv::{
+    arithmetic::Converter,
+    elliptic::curves::{Curve, Ed25519, Point, Scalar, Secp256k1},
+    BigInt,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::gg2020::blame::ProtocolError;
+
+pub mod keygen;
+pub mod sign;
+
+/// Curve and signature scheme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FrostCurve {
+    /// BIP340 Schnorr signatures over secp256k1.
+    Secp256k1,
+    /// Ed25519 signatures.
+    Ed25519,
+}
+
+/// Signature scheme specific encoding and challenge.
+pub trait Ciphersuite {
+    /// The curve.
+    type Curve: Curve;
+
+    /// Determine if a point must be negated before use.
+    fn is_odd(point: &Point<Self::Curve>) -> bool;
+
+    /// Encoding of a point in signatures and public keys.
+    fn encode_point(point: &Point<Self::Curve>) -> Vec<u8>;
+
+    /// Encoding of a scalar in signatures.
+    fn encode_scalar(scalar: &Scalar<Self::Curve>) -> Vec<u8>;
+
+    /// Signature challenge for a nonce, public key and message.
+    fn challenge(
+        nonce: &Point<Self::Curve>,
+        public_key: &Point<Self::Curve>,
+        message: &[u8],
+    ) -> Scalar<Self::Curve>;
+
+    /// Hash to a scalar with a domain separation tag; `H1` in RFC 9591.
+    fn hash_to_field(tag: &[u8], message: &[u8]) -> Scalar<Self::Curve>;
+
+    /// Hash with a domain separation tag; `H4` and `H5` in RFC 9591.
+    fn hash(tag: &[u8], message: &[u8]) -> Vec<u8>;
+}
+
+/// BIP340 Schnorr signatures.
+///
+/// Binding factors are computed as for the FROST(secp256k1, SHA-256)
+/// ciphersuite from RFC 9591.
+pub struct Bip340;
+
+impl Bip340 {
+    /// Context string for FROST(secp256k1, SHA-256).
+    const CONTEXT: &'static [u8] = b"FROST-secp256k1-SHA256-v1";
+}
+
+impl Ciphersuite for Bip340 {
+    type Curve = Secp256k1;
+
+    fn is_odd(point: &Point<Secp256k1>) -> bool {
+        point.to_bytes(true)[0] == 0x03
+    }
+
+    fn encode_point(point: &Point<Secp256k1>) -> Vec<u8> {
+        point.to_bytes(true)[1..].to_vec()
+    }
+
+    fn encode_scalar(scalar: &Scalar<Secp256k1>) -> Vec<u8> {
+        big_endian(scalar)
+    }
+
+    fn challenge(
+        nonce: &Point<Secp256k1>,
+        public_key: &Point<Secp256k1>,
+        message: &[u8],
+    ) -> Scalar<Secp256k1> {
+        let tag = Sha256::digest(b"BIP0340/challenge");
+        let digest = Sha256::new()
+            .chain_update(&tag)
+            .chain_update(&tag)
+            .chain_update(Self::encode_point(nonce))
+            .chain_update(Self::encode_point(public_key))
+            .chain_update(message)
+            .finalize();
+        Scalar::from_bigint(&BigInt::from_bytes(&digest))
+    }
+
+    fn hash_to_field(tag: &[u8], message: &[u8]) -> Scalar<Secp256k1> {
+        let dst = [Self::CONTEXT, tag].concat();
+        let uniform = expand_message_xmd(message, &dst);
+        Scalar::from_bigint(&BigInt::from_bytes(&uniform))
+    }
+
+    fn hash(tag: &[u8], message: &[u8]) -> Vec<u8> {
+        Sha256::new()
+            .chain_update(Self::CONTEXT)
+            .chain_update(tag)
+            .chain_update(message)
+            .finalize()
+            .to_vec()
+    }
+}
+
+/// Ed25519 signatures.
+///
+/// Binding factors are computed as for the FROST(Ed25519, SHA-512)
+/// ciphersuite from RFC 9591.
+pub struct Ed25519Sha512;
+
+impl Ed25519Sha512 {
+    /// Context string for FROST(Ed25519, SHA-512).
+    const CONTEXT: &'static [u8] = b"FROST-ED25519-SHA512-v1";
+}
+
+impl Ciphersuite for Ed25519Sha512 {
+    type Curve = Ed25519;
+
+    fn is_odd(_point: &Point<Ed25519>) -> bool {
+        false
+    }
+
+    fn encode_point(point: &Point<Ed25519>) -> Vec<u8> {
+        point.to_bytes(true).to_vec()
+    }
+
+    fn encode_scalar(scalar: &Scalar<Ed25519>) -> Vec<u8> {
+        let mut bytes = big_endian(scalar);
+        bytes.reverse();
+        bytes
+    }
+
+    fn challenge(
+        nonce: &Point<Ed25519>,
+        public_key: &Point<Ed25519>,
+        message: &[u8],
+    ) -> Scalar<Ed25519> {
+        let mut digest = Sha512::new()
+            .chain_update(Self::encode_point(nonce))
+            .chain_update(Self::encode_point(public_key))
+            .chain_update(message)
+            .finalize()
+            .to_vec();
+        digest.reverse();
+        Scalar::from_bigint(&BigInt::from_bytes(&digest))
+    }
+
+    fn hash_to_field(tag: &[u8], message: &[u8]) -> Scalar<Ed25519> {
+        let mut digest = Self::hash(tag, message);
+        digest.reverse();
+        Scalar::from_bigint(&BigInt::from_bytes(&digest))
+    }
+
+    fn hash(tag: &[u8], message: &[u8]) -> Vec<u8> {
+        Sha512::new()
+            .chain_update(Self::CONTEXT)
+            .chain_update(tag)
+            .chain_update(message)
+            .finalize()
+            .to_vec()
+    }
+}
+
+/// Key share for a party.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", bound = "")]
+pub struct LocalKey<E: Curve> {
+    /// Index of the party.
+    pub i: u16,
+    /// Threshold for signing.
+    pub t: u16,
+    /// Total number of parties.
+    pub n: u16,
+    /// Share of the group secret.
+    pub secret_share: Scalar<E>,
+    /// Group public key.
+    pub group_key: Point<E>,
+    /// Public key shares for every party.
+    pub verifying_shares: Vec<Point<E>>,
+}
+
+/// Generated FROST key share.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FrostKeyShare {
+    /// The key share for the curve.
+    pub local_key: FrostLocalKey,
+    /// Public key encoded for the signature scheme; the 32 byte
+    /// x-only key for BIP340 or the 32 byte Ed25519 key.
+    pub public_key: Vec<u8>,
+}
+
+/// Key share for a curve.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "curve", rename_all = "lowercase")]
+pub enum FrostLocalKey {
+    /// Key share for BIP340.
+    Secp256k1(LocalKey<Secp256k1>),
+    /// Key share for Ed25519.
+    Ed25519(LocalKey<Ed25519>),
+}
+
+/// Signature generated by FROST signers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FrostSignature {
+    /// The curve.
+    pub curve: FrostCurve,
+    /// The 64 byte signature.
+    pub signature: Vec<u8>,
+    /// Public key encoded for the signature scheme.
+    pub public_key: Vec<u8>,
+}
+
+/// Scalar as 32 big-endian bytes.
+fn big_endian<E: Curve>(scalar: &Scalar<E>) -> Vec<u8> {
+    let bytes = scalar.to_bigint().to_bytes();
+    let mut padded = vec![0u8; 32 - bytes.len()];
+    padded.extend_from_slice(&bytes);
+    padded
+}
+
+/// `expand_message_xmd` from RFC 9380 with SHA-256 for the 48 bytes
+/// that are reduced to a secp256k1 scalar.
+fn expand_message_xmd(message: &[u8], dst: &[u8]) -> Vec<u8> {
+    const LENGTH: usize = 48;
+    let dst_prime = [dst, &[dst.len() as u8]].concat();
+    let b0 = Sha256::new()
+        .chain_update([0u8; 64])
+        .chain_update(message)
+        .chain_update((LENGTH as u16).to_be_bytes())
+        .chain_update([0u8])
+        .chain_update(&dst_prime)
+        .finalize();
+    let mut uniform = Vec::with_capacity(64);
+    let mut previous = vec![0u8; 32];
+    for i in 1..=2u8 {
+        let xored = b0
+            .iter()
+            .zip(&previous)
+            .map(|(a, b)| a ^ b)
+            .collect::<Vec<_>>();
+        previous = Sha256::new()
+            .chain_update(xored)
+            .chain_update([i])
+            .chain_update(&dst_prime)
+            .finalize()
+            .to_vec();
+        uniform.extend_from_slice(&previous);
+    }
+    uniform.truncate(LENGTH);
+    uniform
+}
+
+/// Hash some values to a scalar with a domain separation tag.
+pub(crate) fn hash_to_scalar<E: Curve>(tag: &str, values: &[&[u8]]) -> Scalar<E> {
+    let mut hasher = Sha512::new()
+        .chain_update(b"FROST-")
+        .chain_update(tag.as_bytes());
+    for value in values {
+        hasher.update(&(value.len() as u64).to_be_bytes());
+        hasher.update(value);
+    }
+    Scalar::from_bigint(&BigInt::from_bytes(&hasher.finalize()))
+}
+
+/// Evaluate a polynomial.
+pub(crate) fn evaluate<E: Curve>(coefficients: &[Scalar<E>], index: u16) -> Scalar<E> {
+    let x = Scalar::<E>::from(index);
+    coefficients
+        .iter()
+        .rev()
+        .fold(Scalar::<E>::zero(), |acc, coefficient| {
+            acc * &x + coefficient
+        })
+}
+
+/// Evaluate a polynomial in the exponent from commitments
+/// to the coefficients.
+pub(crate) fn evaluate_commitments<E: Curve>(commitments: &[Point<E>], index: u16) -> Point<E> {
+    let x = Scalar::<E>::from(index);
+    commitments
+        .iter()
+        .rev()
+        .fold(Point::<E>::zero(), |acc, commitment| acc * &x + commitment)
+}
+
+/// Lagrange coefficient at zero for a party in a signing set.
+pub(crate) fn lagrange<E: Curve>(index: u16, participants: &[u16]) -> Scalar<E> {
+    let x = Scalar::<E>::from(index);
+    let mut numerator = Scalar::<E>::from(1u16);
+    let mut denominator = Scalar::<E>::from(1u16);
+    for other in participants.iter().filter(|other| **other != index) {
+        let other = Scalar::<E>::from(*other);
+        numerator = numerator * &other;
+        denominator = denominator * (&other - &x);
+    }
+    numerator * denominator.invert().expect("participants are distinct")
+}
+
+/// Protocol error that blames some parties.
+pub(crate) fn blame(round: u16, kind: &str, culprits: Vec<u16>, message: String) -> ProtocolError {
+    ProtocolError {
+        round,
+        kind: kind.to_string(),
+        culprits,
+        message,
+    }
+}
+
+/// Protocol error that cannot be attributed to a party.
+pub(crate) fn failure(round: u16, kind: &str, message: String) -> ProtocolError {
+    blame(round, kind, vec![], message)
+}
@@ -0,0 +1,639 @@
+//! Two round signing.
+//!
+//! The first round broadcasts commitments to a pair of nonces and
+//! the second round broadcasts signature shares; the nonces are bound
+//! to the group key, the message and the commitments of every signer
+//! so shares cannot be combined across signing sessions.
+//!
+//! Binding factors are computed as in RFC 9591 so they match the
+//! test vectors for the corresponding ciphersuite.
+use curv::elliptic::curves::{Curve, Point, Scalar};
+use round_based::Msg;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use wasm_bindgen::prelude::*;
+
+use super::keygen::round_messages;
+use super::{
+    blame, failure, lagrange, Bip340, Ciphersuite, Ed25519Sha512, FrostCurve, FrostKeyShare,
+    FrostLocalKey, FrostSignature, LocalKey,
+};
+use crate::gg2020::blame::ProtocolError;
+
+/// Number of rounds for signing.
+pub const ROUNDS: u16 = 2;
+
+/// Message sent during signing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub enum SignMessage<E: Curve> {
+    /// Commitments to the nonces.
+    Round1 {
+        /// Commitment to the hiding nonce.
+        hiding: Point<E>,
+        /// Commitment to the binding nonce.
+        binding: Point<E>,
+    },
+    /// Signature share.
+    Round2 {
+        /// The signature share.
+        share: Scalar<E>,
+    },
+}
+
+/// Hiding and binding nonce commitments for each signer.
+type Commitments<E> = BTreeMap<u16, (Point<E>, Point<E>)>;
+
+/// Group commitment computed once all nonce commitments are known.
+struct GroupCommitment<E: Curve> {
+    /// Nonce for the signature.
+    nonce: Point<E>,
+    /// Whether the nonces were negated.
+    negated: bool,
+    /// Challenge for the signature.
+    challenge: Scalar<E>,
+    /// Nonce commitment for each signer.
+    commitments: BTreeMap<u16, Point<E>>,
+}
+
+/// Signing state for a party.
+pub struct Signing<C: Ciphersuite> {
+    index: u16,
+    participants: Vec<u16>,
+    local_key: LocalKey<C::Curve>,
+    message: Vec<u8>,
+    round: u16,
+    nonces: Option<(Scalar<C::Curve>, Scalar<C::Curve>)>,
+    commitments: Commitments<C::Curve>,
+    group: Option<GroupCommitment<C::Curve>>,
+    shares: BTreeMap<u16, Scalar<C::Curve>>,
+    output: Option<Vec<u8>>,
+    suite: PhantomData<C>,
+}
+
+impl<C: Ciphersuite> Signing<C> {
+    /// Create the signing state for a party.
+    ///
+    /// The index is the party number for the signing session and
+    /// participants are the key share indices for each party
+    /// number in the session.
+    pub fn new(
+        index: u16,
+        participants: Vec<u16>,
+        local_key: LocalKey<C::Curve>,
+        message: Vec<u8>,
+    ) -> Result<Self, ProtocolError> {
+        let mut unique = participants.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        let valid = participants.len() > local_key.t as usize
+            && unique.len() == participants.len()
+            && participants.iter().all(|i| *i >= 1 && *i <= local_key.n)
+            && index >= 1
+            && participants.get(index as usize - 1) == Some(&local_key.i);
+        if !valid {
+            return Err(failure(
+                0,
+                "InvalidParameters",
+                format!(
+                    "invalid signing party {} for participants {:?}",
+                    index, participants
+                ),
+            ));
+        }
+        Ok(Self {
+            index,
+            participants,
+            local_key,
+            message,
+            round: 0,
+            nonces: None,
+            commitments: Default::default(),
+            group: None,
+            shares: Default::default(),
+            output: None,
+            suite: PhantomData,
+        })
+    }
+
+    /// Current round.
+    pub fn current_round(&self) -> u16 {
+        self.round
+    }
+
+    /// Take the generated signature.
+    pub fn pick_output(&mut self) -> Option<Vec<u8>> {
+        self.output.take()
+    }
+
+    /// Handle an incoming message.
+    pub fn handle_incoming(
+        &mut self,
+        message: Msg<SignMessage<C::Curve>>,
+    ) -> Result<(), ProtocolError> {
+        let sender = message.sender;
+        if sender == 0 || sender as usize > self.participants.len() || sender == self.index {
+            return Err(failure(
+                self.round,
+                "InvalidSender",
+                format!("signing message from invalid party {}", sender),
+            ));
+        }
+
+        match message.body {
+            SignMessage::Round1 { hiding, binding } if self.round == 1 => {
+                if self.commitments.contains_key(&sender) {
+                    return Err(self.blame(sender, "DuplicateMessage"));
+                }
+                if hiding.is_zero() || binding.is_zero() {
+                    return Err(self.blame(sender, "InvalidCommitments"));
+                }
+                self.commitments.insert(sender, (hiding, binding));
+            }
+            SignMessage::Round2 { share } if self.round == 2 => {
+                if self.shares.contains_key(&sender) {
+                    return Err(self.blame(sender, "DuplicateMessage"));
+                }
+                if !self.verify_share(sender, &share) {
+                    return Err(self.blame(sender, "InvalidShare"));
+                }
+                self.shares.insert(sender, share);
+            }
+            _ => return Err(self.blame(sender, "WrongRound")),
+        }
+        Ok(())
+    }
+
+    /// Proceed to the next round.
+    pub fn proceed(&mut self) -> Result<Vec<Msg<SignMessage<C::Curve>>>, ProtocolError> {
+        let body = match self.round {
+            0 => Some(self.round1()),
+            1 => Some(self.round2()?),
+            2 => {
+                self.finish()?;
+                None
+            }
+            _ => {
+                return Err(failure(
+                    self.round,
+                    "Finished",
+                    "signing has already finished".to_string(),
+                ))
+            }
+        };
+        self.round += 1;
+        Ok(body
+            .map(|body| Msg {
+                sender: self.index,
+                receiver: None,
+                body,
+            })
+            .into_iter()
+            .collect())
+    }
+
+    /// Generate the nonces.
+    fn round1(&mut self) -> SignMessage<C::Curve> {
+        let (d, e) = (Scalar::<C::Curve>::random(), Scalar::<C::Curve>::random());
+        let hiding = Point::<C::Curve>::generator() * &d;
+        let binding = Point::<C::Curve>::generator() * &e;
+        self.nonces = Some((d, e));
+        self.commitments
+            .insert(self.index, (hiding.clone(), binding.clone()));
+        SignMessage::Round1 { hiding, binding }
+    }
+
+    /// Compute the group commitment and the signature share.
+    fn round2(&mut self) -> Result<SignMessage<C::Curve>, ProtocolError> {
+        self.expect(self.commitments.len() - 1)?;
+
+        let by_identifier = self
+            .commitments
+            .iter()
+            .map(|(party, commitments)| (self.key_index(*party), commitments.clone()))
+            .collect::<Commitments<_>>();
+        let factors =
+            binding_factors::<C>(&self.local_key.group_key, &self.message, &by_identifier);
+
+        let mut rho = BTreeMap::new();
+        let mut commitments = BTreeMap::new();
+        let mut nonce = Point::<C::Curve>::zero();
+        for (party, (hiding, binding)) in &self.commitments {
+            let factor = factors[&self.key_index(*party)].clone();
+            let commitment = hiding + binding * &factor;
+            nonce = nonce + &commitment;
+            commitments.insert(*party, commitment);
+            rho.insert(*party, factor);
+        }
+
+        // Negate the nonces so the group nonce has an even y coordinate
+        let negated = C::is_odd(&nonce);
+        if negated {
+            nonce = Point::<C::Curve>::zero() - &nonce;
+        }
+        let challenge = C::challenge(&nonce, &self.local_key.group_key, &self.message);
+
+        let (d, e) = self.nonces.take().unwrap();
+        let mut k = d + e * &rho[&self.index];
+        if negated {
+            k = Scalar::<C::Curve>::zero() - &k;
+        }
+        let lambda = lagrange::<C::Curve>(self.local_key.i, &self.participants);
+        let share = k + lambda * &self.local_key.secret_share * &challenge;
+
+        self.group = Some(GroupCommitment {
+            nonce,
+            negated,
+            challenge,
+            commitments,
+        });
+        self.shares.insert(self.index, share.clone());
+        Ok(SignMessage::Round2 { share })
+    }
+
+    /// Aggregate the signature shares.
+    fn finish(&mut self) -> Result<(), ProtocolError> {
+        self.expect(self.shares.len() - 1)?;
+        let group = self.group.as_ref().unwrap();
+        let z = self
+            .shares
+            .values()
+            .fold(Scalar::<C::Curve>::zero(), |acc, share| acc + share);
+        if Point::<C::Curve>::generator() * &z
+            != &group.nonce + &self.local_key.group_key * &group.challenge
+        {
+            return Err(failure(
+                self.round,
+                "InvalidSignature",
+                "aggregated signature is not valid".to_string(),
+            ));
+        }
+        let mut signature = C::encode_point(&group.nonce);
+        signature.extend(C::encode_scalar(&z));
+        self.output = Some(signature);
+        Ok(())
+    }
+
+    /// Verify a signature share from another party.
+    fn verify_share(&self, sender: u16, share: &Scalar<C::Curve>) -> bool {
+        let group = self.group.as_ref().unwrap();
+        let key_index = self.key_index(sender);
+        let mut commitment = group.commitments[&sender].clone();
+        if group.negated {
+            commitment = Point::<C::Curve>::zero() - &commitment;
+        }
+        let lambda = lagrange::<C::Curve>(key_index, &self.participants);
+        let verifying_share = &self.local_key.verifying_shares[key_index as usize - 1];
+        Point::<C::Curve>::generator() * share
+            == commitment + verifying_share * &(lambda * &group.challenge)
+    }
+
+    /// Key share index for a party number.
+    fn key_index(&self, party: u16) -> u16 {
+        self.participants[party as usize - 1]
+    }
+
+    /// Check all the other signers sent a message for the round.
+    fn expect(&self, received: usize) -> Result<(), ProtocolError> {
+        let expected = self.participants.len() - 1;
+        if received != expected {
+            return Err(failure(
+                self.round,
+                "MissingMessages",
+                format!(
+                    "expected messages from {} parties but got {}",
+                    expected, received
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    fn blame(&self, sender: u16, kind: &str) -> ProtocolError {
+        blame(
+            self.round,
+            kind,
+            vec![sender],
+            format!("party {} sent an invalid signing message", sender),
+        )
+    }
+}
+
+/// Binding factor for each signer from the nonce commitments keyed
+/// by key share index.
+///
+/// Follows `compute_binding_factors` from RFC 9591: the prefix is
+/// `encode(group_pk) || H4(msg) || H5(encoded commitments)` and each
+/// binding factor is `H1(prefix || encode(identifier))`.
+fn binding_factors<C: Ciphersuite>(
+    group_key: &Point<C::Curve>,
+    message: &[u8],
+    commitments: &Commitments<C::Curve>,
+) -> BTreeMap<u16, Scalar<C::Curve>> {
+    let identifier = |index: u16| C::encode_scalar(&Scalar::<C::Curve>::from(index));
+
+    let mut encoded = Vec::new();
+    for (index, (hiding, binding)) in commitments {
+        encoded.extend(identifier(*index));
+        encoded.extend_from_slice(&hiding.to_bytes(true));
+        encoded.extend_from_slice(&binding.to_bytes(true));
+    }
+
+    let mut prefix = group_key.to_bytes(true).to_vec();
+    prefix.extend(C::hash(b"msg", message));
+    prefix.extend(C::hash(b"com", &encoded));
+
+    commitments
+        .keys()
+        .map(|index| {
+            let input = [&prefix[..], &identifier(*index)].concat();
+            (*index, C::hash_to_field(b"rho", &input))
+        })
+        .collect()
+}
+
+enum Inner {
+    Secp256k1(Signing<Bip340>),
+    Ed25519(Signing<Ed25519Sha512>),
+}
+
+/// Round-based FROST signer.
+#[wasm_bindgen]
+pub struct FrostSigner {
+    inner: Inner,
+    public_key: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl FrostSigner {
+    /// Create a signer for a message.
+    ///
+    /// The index is the party number for the signing session and
+    /// participants are the key share indices for each party.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        index: u16,
+        participants: JsValue,
+        key_share: JsValue,
+        message: JsValue,
+    ) -> Result<FrostSigner, JsValue> {
+        let participants: Vec<u16> = participants.into_serde().map_err(JsError::from)?;
+        let key_share: FrostKeyShare = key_share.into_serde().map_err(JsError::from)?;
+        let message: Vec<u8> = message.into_serde().map_err(JsError::from)?;
+        let inner = match key_share.local_key {
+            FrostLocalKey::Secp256k1(local_key) => {
+                Inner::Secp256k1(Signing::new(index, participants, local_key, message)?)
+            }
+            FrostLocalKey::Ed25519(local_key) => {
+                Inner::Ed25519(Signing::new(index, participants, local_key, message)?)
+            }
+        };
+        Ok(Self {
+            inner,
+            public_key: key_share.public_key,
+        })
+    }
+
+    /// Handle an incoming message.
+    ///
+    /// Protocol failures throw a `ProtocolError`.
+    #[wasm_bindgen(js_name = "handleIncoming")]
+    pub fn handle_incoming(&mut self, message: JsValue) -> Result<(), JsValue> {
+        match &mut self.inner {
+            Inner::Secp256k1(signing) => {
+                signing.handle_incoming(message.into_serde().map_err(JsError::from)?)?
+            }
+            Inner::Ed25519(signing) => {
+                signing.handle_incoming(message.into_serde().map_err(JsError::from)?)?
+            }
+        }
+        Ok(())
+    }
+
+    /// Proceed to the next round.
+    ///
+    /// Protocol failures throw a `ProtocolError`.
+    pub fn proceed(&mut self) -> Result<JsValue, JsValue> {
+        let value = match &mut self.inner {
+            Inner::Secp256k1(signing) => {
+                round_messages(signing.proceed()?, signing.current_round())
+            }
+            Inner::Ed25519(signing) => round_messages(signing.proceed()?, signing.current_round()),
+        };
+        Ok(value.map_err(JsError::from)?)
+    }
+
+    /// Create the signature.
+    pub fn create(&mut self) -> Result<JsValue, JsError> {
+        let (curve, signature) = match &mut self.inner {
+            Inner::Secp256k1(signing) => (FrostCurve::Secp256k1, signing.pick_output()),
+            Inner::Ed25519(signing) => (FrostCurve::Ed25519, signing.pick_output()),
+        };
+        let signature = FrostSignature {
+            curve,
+            signature: signature.ok_or_else(|| JsError::new("signing has not finished"))?,
+            public_key: self.public_key.clone(),
+        };
+        Ok(JsValue::from_serde(&signature)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frost::keygen::tests::keygen;
+
+    fn sign<C: Ciphersuite>(keys: &[LocalKey<C::Curve>], message: &[u8]) -> Vec<u8> {
+        let participants = keys.iter().map(|key| key.i).collect::<Vec<_>>();
+        let mut signers = keys
+            .iter()
+            .enumerate()
+            .map(|(position, key)| {
+                Signing::<C>::new(
+                    position as u16 + 1,
+                    participants.clone(),
+                    key.clone(),
+                    message.to_vec(),
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+        for _ in 0..=ROUNDS {
+            let messages = signers
+                .iter_mut()
+                .flat_map(|signer| signer.proceed().unwrap())
+                .collect::<Vec<_>>();
+            for message in messages {
+                for signer in signers
+                    .iter_mut()
+                    .filter(|signer| signer.index != message.sender)
+                {
+                    signer.handle_incoming(message.clone()).unwrap();
+                }
+            }
+        }
+        let signatures = signers
+            .iter_mut()
+            .map(|signer| signer.pick_output().unwrap())
+            .collect::<Vec<_>>();
+        assert!(signatures.iter().all(|s| s == &signatures[0]));
+        signatures[0].clone()
+    }
+
+    #[test]
+    fn sign_secp256k1() {
+        use ::secp256k1::{schnorrsig, Message, Secp256k1};
+
+        let keys = keygen::<Bip340>(1, 3);
+        let message = [7u8; 32];
+        let signature = sign::<Bip340>(&keys[1..], &message);
+
+        // Verify with libsecp256k1
+        let public_key =
+            schnorrsig::PublicKey::from_slice(&Bip340::encode_point(&keys[0].group_key)).unwrap();
+        let signature = schnorrsig::Signature::from_slice(&signature).unwrap();
+        let secp = Secp256k1::verification_only();
+        secp.schnorrsig_verify(
+            &signature,
+            &Message::from_slice(&message).unwrap(),
+            &public_key,
+        )
+        .unwrap();
+        assert!(secp
+            .schnorrsig_verify(
+                &signature,
+                &Message::from_slice(&[8u8; 32]).unwrap(),
+                &public_key
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn sign_ed25519() {
+        use ed25519_dalek::{PublicKey, Signature};
+        use std::convert::TryFrom;
+
+        let keys = keygen::<Ed25519Sha512>(1, 3);
+        let signature = sign::<Ed25519Sha512>(&[keys[0].clone(), keys[2].clone()], b"message");
+
+        // Verify with ed25519-dalek
+        let public_key =
+            PublicKey::from_bytes(&Ed25519Sha512::encode_point(&keys[0].group_key)).unwrap();
+        let signature = Signature::try_from(&signature[..]).unwrap();
+        public_key.verify_strict(b"message", &signature).unwrap();
+        assert!(public_key.verify_strict(b"other", &signature).is_err());
+    }
+
+    /// Check the binding factors and the group commitment for
+    /// participants 1 and 3 in the RFC 9591 test vectors.
+    fn rfc9591<C: Ciphersuite>(
+        group_key: &str,
+        commitments: [(u16, &str, &str); 2],
+        factors: [&str; 2],
+        group_commitment: &str,
+    ) {
+        let point =
+            |value: &str| Point::<C::Curve>::from_bytes(&hex::decode(value).unwrap()).unwrap();
+        let commitments = commitments
+            .iter()
+            .map(|(index, hiding, binding)| (*index, (point(hiding), point(binding))))
+            .collect::<Commitments<_>>();
+        let rho = binding_factors::<C>(&point(group_key), b"test", &commitments);
+        assert_eq!(hex::encode(C::encode_scalar(&rho[&1])), factors[0]);
+        assert_eq!(hex::encode(C::encode_scalar(&rho[&3])), factors[1]);
+
+        let nonce = commitments.iter().fold(
+            Point::<C::Curve>::zero(),
+            |acc, (index, (hiding, binding))| acc + hiding + binding * &rho[index],
+        );
+        assert_eq!(nonce, point(group_commitment));
+    }
+
+    #[test]
+    fn binding_factors_secp256k1() {
+        // RFC 9591 appendix E.5, FROST(secp256k1, SHA-256)
+        rfc9591::<Bip340>(
+            "02f37c34b66ced1fb51c34a90bdae006901f10625cc06c4f64663b0eae87d87b4f",
+            [
+                (
+                    1,
+                    "03c699af97d26bb4d3f05232ec5e1938c12f1e6ae97643c8f8f11c9820303f1904",
+                    "02fa2aaccd51b948c9dc1a325d77226e98a5a3fe65fe9ba213761a60123040a45e",
+                ),
+                (
+                    3,
+                    "03077507ba327fc074d2793955ef3410ee3f03b82b4cdc2370f71d865beb926ef6",
+                    "02ad53031ddfbbacfc5fbda3d3b0c2445c8e3e99cbc4ca2db2aa283fa68525b135",
+                ),
+            ],
+            [
+                "3e08fe561e075c653cbfd46908a10e7637c70c74f0a77d5fd45d1a750c739ec6",
+                "93f79041bb3fd266105be251adaeb5fd7f8b104fb554a4ba9a0becea48ddbfd7",
+            ],
+            "0205b6d04d3774c8929413e3c76024d54149c372d57aae62574ed74319b5ea14d0",
+        );
+    }
+
+    #[test]
+    fn binding_factors_ed25519() {
+        // RFC 9591 appendix E.1, FROST(Ed25519, SHA-512)
+        rfc9591::<Ed25519Sha512>(
+            "15d21ccd7ee42959562fc8aa63224c8851fb3ec85a3faf66040d380fb9738673",
+            [
+                (
+                    1,
+                    "b5aa8ab305882a6fc69cbee9327e5a45e54c08af61ae77cb8207be3d2ce13de3",
+                    "67e98ab55aa310c3120418e5050c9cf76cf387cb20ac9e4b6fdb6f82a469f932",
+                ),
+                (
+                    3,
+                    "cfbdb165bd8aad6eb79deb8d287bcc0ab6658ae57fdcc98ed12c0669e90aec91",
+                    "7487bc41a6e712eea2f2af24681b58b1cf1da278ea11fe4e8b78398965f13552",
+                ),
+            ],
+            [
+                "f2cb9d7dd9beff688da6fcc83fa89046b3479417f47f55600b106760eb3b5603",
+                "b087686bf35a13f3dc78e780a34b0fe8a77fef1b9938c563f5573d71d8d7890f",
+            ],
+            "36282629c383bb820a88b71cae937d41f2f2adfcc3d02e55507e2fb9e2dd3cbe",
+        );
+    }
+
+    #[test]
+    fn duplicate_commitments_are_not_stored() {
+        let keys = keygen::<Bip340>(1, 2);
+        let participants = vec![1, 2];
+        let signer = |index: u16| {
+            Signing::<Bip340>::new(
+                index,
+                participants.clone(),
+                keys[index as usize - 1].clone(),
+                vec![7u8; 32],
+            )
+            .unwrap()
+        };
+        let (mut alice, mut bob) = (signer(1), signer(2));
+        let a1 = alice.proceed().unwrap().remove(0);
+        let b1 = bob.proceed().unwrap().remove(0);
+        alice.handle_incoming(b1).unwrap();
+        bob.handle_incoming(a1).unwrap();
+
+        let replay = signer(1).proceed().unwrap().remove(0);
+        let error = bob.handle_incoming(replay).unwrap_err();
+        assert_eq!(error.kind, "DuplicateMessage");
+        assert_eq!(error.culprits, vec![1]);
+
+        // The signature shares are computed with the
+        // commitments that were received first
+        let a2 = alice.proceed().unwrap().remove(0);
+        let b2 = bob.proceed().unwrap().remove(0);
+        bob.handle_incoming(a2.clone()).unwrap();
+        let error = bob.handle_incoming(a2).unwrap_err();
+        assert_eq!(error.kind, "DuplicateMessage");
+        alice.handle_incoming(b2).unwrap();
+        alice.proceed().unwrap();
+        bob.proceed().unwrap();
+        assert_eq!(alice.pick_output(), bob.pick_output());
+    }
+}
@@ -0,0 +1,90 @@
+//! Sign several messages in one session.
+//!
+//! The offline stages for the batch are run by the core crate;
+//! see `mpc_ecdsa_core::batch` for the message format.
+use mpc_ecdsa_core::{BatchSigner as OfflineBatchSigner, KeyShare, RoundMsg};
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::sign::{
+    OfflineProtocolMessage, PartialSignature,
+};
+use std::convert::TryInto;
+use wasm_bindgen::prelude::*;
+
+use super::blame::throw;
+use crate::bip32;
+
+/// Round-based signing protocol for a batch of messages.
+#[wasm_bindgen]
+pub struct BatchSigner {
+    inner: OfflineBatchSigner,
+}
+
+#[wasm_bindgen]
+impl BatchSigner {
+    /// Create a signer for a batch of 32 byte message digests.
+    ///
+    /// When a non-hardened derivation path is given the
+    /// signatures are for the child key.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        index: JsValue,
+        participants: JsValue,
+        key_share: JsValue,
+        messages: JsValue,
+        path: Option<String>,
+    ) -> Result<BatchSigner, JsError> {
+        let index: u16 = index.into_serde()?;
+        let participants: Vec<u16> = participants.into_serde()?;
+        let key_share: KeyShare = key_share.into_serde()?;
+        let messages: Vec<Vec<u8>> = messages.into_serde()?;
+        let messages = messages
+            .iter()
+            .map(|message| Ok(message.as_slice().try_into()?))
+            .collect::<Result<Vec<[u8; 32]>, JsError>>()?;
+        let local_key = bip32::local_key(key_share, path)?;
+        Ok(BatchSigner {
+            inner: OfflineBatchSigner::new(index, participants, local_key, &messages)?,
+        })
+    }
+
+    /// Handle an incoming message.
+    ///
+    /// The message must include the round number. Protocol
+    /// failures throw a `ProtocolError`.
+    #[wasm_bindgen(js_name = "handleIncoming")]
+    pub fn handle_incoming(&mut self, message: JsValue) -> Result<(), JsValue> {
+        let message: RoundMsg<Vec<OfflineProtocolMessage>> =
+            message.into_serde().map_err(JsError::from)?;
+        self.inner.handle_incoming(message).map_err(throw)
+    }
+
+    /// Proceed to the next round.
+    ///
+    /// Protocol failures throw a `ProtocolError`.
+    pub fn proceed(&mut self) -> Result<JsValue, JsValue> {
+        let value = match self.inner.proceed().map_err(throw)? {
+            Some((round, messages)) => JsValue::from_serde(&(round, &messages)),
+            None => JsValue::from_serde(&false),
+        };
+        Ok(value.map_err(JsError::from)?)
+    }
+
+    /// Complete the offline stages and return the partial
+    /// signatures for every message in the batch that must be
+    /// sent to the other signing participants.
+    pub fn partial(&mut self) -> Result<JsValue, JsError> {
+        Ok(JsValue::from_serde(&self.inner.partial()?)?)
+    }
+
+    /// Create and verify the signatures.
+    ///
+    /// The partials are the values returned by `partial()` for
+    /// each of the other participants; the signatures are in the
+    /// same order as the messages.
+    ///
+    /// Protocol failures throw a `ProtocolError`.
+    pub fn create(&mut self, partials: JsValue) -> Result<JsValue, JsValue> {
+        let partials: Vec<Vec<PartialSignature>> = partials.into_serde().map_err(JsError::from)?;
+        let signatures = self.inner.create(&partials).map_err(throw)?;
+        Ok(JsValue::from_serde(&signatures).map_err(JsError::from)?)
+    }
+}
@@ -0,0 +1,63 @@
+//! Structured errors for identifiable abort.
+//!
+//! The blame for a failed round is determined by the core crate;
+//! this module converts protocol failures into values that are
+//! thrown to Javascript.
+use mpc_ecdsa_core::{Blame, Error};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+/// Error thrown when a protocol round fails.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProtocolError {
+    /// Round that failed.
+    pub round: u16,
+    /// Kind of error.
+    pub kind: String,
+    /// Parties to blame, empty when the failure cannot be
+    /// attributed to a party.
+    ///
+    /// For key generation these are party numbers and for
+    /// signing the key share indices of the participants.
+    pub culprits: Vec<u16>,
+    /// Error message.
+    pub message: String,
+}
+
+impl ProtocolError {
+    /// Create a protocol error for a failed round.
+    pub fn new<E: Blame>(round: u16, error: &E) -> Self {
+        mpc_ecdsa_core::ProtocolError::new(round, error).into()
+    }
+
+    /// Create a protocol error for a failed signing round.
+    pub fn signing<E: Blame>(round: u16, error: &E, participants: &[u16]) -> Self {
+        mpc_ecdsa_core::ProtocolError::signing(round, error, participants).into()
+    }
+}
+
+impl From<mpc_ecdsa_core::ProtocolError> for ProtocolError {
+    fn from(error: mpc_ecdsa_core::ProtocolError) -> Self {
+        Self {
+            round: error.round,
+            kind: error.kind,
+            culprits: error.culprits,
+            message: error.message,
+        }
+    }
+}
+
+impl From<ProtocolError> for JsValue {
+    fn from(error: ProtocolError) -> Self {
+        JsValue::from_serde(&error).unwrap_or_else(|_| JsValue::from_str(&error.message))
+    }
+}
+
+/// Convert an error from the core crate; protocol failures
+/// are thrown as a `ProtocolError`.
+pub(crate) fn throw(error: Error) -> JsValue {
+    match error {
+        Error::Protocol(error) => ProtocolError::from(error).into(),
+        error => JsError::from(error).into(),
+    }
+}
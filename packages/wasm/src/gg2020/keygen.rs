@@ -1,113 +1,111 @@
 //! Key generation.
-use curv::elliptic::curves::secp256_k1::Secp256k1;
-use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::keygen::{
-    Keygen, LocalKey, ProtocolMessage,
-};
-
+use js_sys::Function;
+use mpc_ecdsa_core::KeyGenerator as Generator;
 use wasm_bindgen::prelude::*;
 
-use crate::Parameters;
-use serde::{Deserialize, Serialize};
+use super::blame::throw;
 
-use round_based::{Msg, StateMachine};
+pub use mpc_ecdsa_core::{KeyShare, KeygenEvent, KeygenProgress, KeygenStep, PartySignup};
 
 //use crate::{console_log, log};
 
-/// Wrapper for a round `Msg` that includes the round
-/// number so that we can ensure round messages are grouped
-/// together and out of order messages can thus be handled correctly.
-#[derive(Serialize)]
-struct RoundMsg {
-    round: u16,
-    sender: u16,
-    receiver: Option<u16>,
-    body: ProtocolMessage,
-}
-
-impl RoundMsg {
-    fn from_round(
-        round: u16,
-        messages: Vec<Msg<<Keygen as StateMachine>::MessageBody>>,
-    ) -> Vec<Self> {
-        messages
-            .into_iter()
-            .map(|m| RoundMsg {
-                round,
-                sender: m.sender,
-                receiver: m.receiver,
-                body: m.body,
-            })
-            .collect::<Vec<_>>()
-    }
-}
-
-/// Session information for a single party.
-#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
-pub struct PartySignup {
-    /// Unique index for the party.
-    pub number: u16,
-    /// Session identifier.
-    pub uuid: String,
-}
-
-/// Generated key share.
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct KeyShare {
-    /// The secret private key.
-    #[serde(rename = "localKey")]
-    pub local_key: LocalKey<Secp256k1>,
-    /// The public key.
-    #[serde(rename = "publicKey")]
-    pub public_key: Vec<u8>,
-    /// Address generated from the public key.
-    pub address: String,
-}
-
 /// Round-based key share generator.
 #[wasm_bindgen]
 pub struct KeyGenerator {
-    inner: Keygen,
+    inner: Generator,
+    progress: Option<Function>,
 }
 
 #[wasm_bindgen]
 impl KeyGenerator {
     /// Create a key generator.
+    ///
+    /// The optional progress callback is called with an event
+    /// before and after the expensive step of each round such as
+    /// generating the Paillier key; errors thrown by the callback
+    /// are ignored.
     #[wasm_bindgen(constructor)]
-    pub fn new(parameters: JsValue, party_signup: JsValue) -> Result<KeyGenerator, JsError> {
-        let params: Parameters = parameters.into_serde()?;
-        let PartySignup { number, uuid } = party_signup.into_serde::<PartySignup>()?;
-        let (party_num_int, _uuid) = (number, uuid);
+    pub fn new(
+        parameters: JsValue,
+        party_signup: JsValue,
+        progress: Option<Function>,
+    ) -> Result<KeyGenerator, JsError> {
         Ok(Self {
-            inner: Keygen::new(party_num_int, params.threshold, params.parties)?,
+            inner: Generator::new(parameters.into_serde()?, party_signup.into_serde()?)?,
+            progress,
         })
     }
 
+    /// Current round.
+    #[wasm_bindgen(js_name = "currentRound")]
+    pub fn current_round(&self) -> u16 {
+        self.inner.current_round()
+    }
+
+    /// Total number of rounds.
+    #[wasm_bindgen(js_name = "totalRounds")]
+    pub fn total_rounds(&self) -> u16 {
+        self.inner.total_rounds()
+    }
+
+    /// Number of messages received for the current round.
+    pub fn received(&self) -> u16 {
+        self.inner.received()
+    }
+
+    /// Number of messages expected for the current round.
+    pub fn expected(&self) -> u16 {
+        self.inner.expected()
+    }
+
+    /// Whether key generation has finished.
+    #[wasm_bindgen(js_name = "isFinished")]
+    pub fn is_finished(&self) -> bool {
+        self.inner.is_finished()
+    }
+
+    /// Whether enough messages have been received to proceed
+    /// to the next round.
+    #[wasm_bindgen(js_name = "wantsToProceed")]
+    pub fn wants_to_proceed(&self) -> bool {
+        self.inner.wants_to_proceed()
+    }
+
+    /// Progress of key generation.
+    pub fn progress(&self) -> Result<JsValue, JsError> {
+        Ok(JsValue::from_serde(&self.inner.progress())?)
+    }
+
     /// Handle an incoming message.
+    ///
+    /// Protocol failures throw a `ProtocolError`.
     #[wasm_bindgen(js_name = "handleIncoming")]
-    pub fn handle_incoming(&mut self, message: JsValue) -> Result<(), JsError> {
-        let message: Msg<<Keygen as StateMachine>::MessageBody> = message.into_serde()?;
-        self.inner.handle_incoming(message)?;
-        Ok(())
+    pub fn handle_incoming(&mut self, message: JsValue) -> Result<(), JsValue> {
+        let message = message.into_serde().map_err(JsError::from)?;
+        self.inner.handle_incoming(message).map_err(throw)
     }
 
     /// Proceed to the next round.
-    pub fn proceed(&mut self) -> Result<JsValue, JsError> {
-        self.inner.proceed()?;
-        let messages = self.inner.message_queue().drain(..).collect();
-        let round = self.inner.current_round();
-        let messages = RoundMsg::from_round(round, messages);
-        Ok(JsValue::from_serde(&(round, &messages))?)
+    ///
+    /// Protocol failures throw a `ProtocolError`.
+    pub fn proceed(&mut self) -> Result<JsValue, JsValue> {
+        let progress = &self.progress;
+        let (round, messages) = self
+            .inner
+            .proceed_with_progress(|event| notify(progress, &event))
+            .map_err(throw)?;
+        Ok(JsValue::from_serde(&(round, &messages)).map_err(JsError::from)?)
     }
 
     /// Create the key share.
     pub fn create(&mut self) -> Result<JsValue, JsError> {
-        let local_key = self.inner.pick_output().unwrap()?;
-        let public_key = local_key.public_key().to_bytes(false).to_vec();
-        let key_share = KeyShare {
-            local_key,
-            address: crate::utils::address(&public_key),
-            public_key,
-        };
-        Ok(JsValue::from_serde(&key_share)?)
+        Ok(JsValue::from_serde(&self.inner.create()?)?)
+    }
+}
+
+/// Call the progress callback with an event.
+fn notify(progress: &Option<Function>, event: &KeygenEvent) {
+    if let (Some(progress), Ok(event)) = (progress, JsValue::from_serde(event)) {
+        let _ = progress.call1(&JsValue::NULL, &event);
     }
 }
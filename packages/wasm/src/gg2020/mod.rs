@@ -1,2 +1,8 @@
+pub mod batch;
+pub mod blame;
 pub mod keygen;
+pub mod presign;
+pub mod refresh;
+pub mod reshare;
 pub mod sign;
+pub mod verify;
@@ -0,0 +1,142 @@
+//! Presignatures for signing without the offline rounds.
+//!
+//! The pool and the used set are implemented by the core crate;
+//! see `mpc_ecdsa_core::presign` for how reuse is refused. This
+//! pool keeps the presignatures encrypted until they are needed.
+//!
+//! The holder of the key share owns the used set and passes it to
+//! every pool; after `reserve()` the returned used set must be
+//! persisted *before* `partial()` is called.
+use curv::{arithmetic::Converter, BigInt};
+use mpc_ecdsa_core::{PresignaturePool as Pool, UsedSet};
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::sign::{
+    CompletedOfflineStage, PartialSignature, SignManual,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::TryInto;
+use wasm_bindgen::prelude::*;
+
+use super::blame::throw;
+use super::sign::complete;
+use crate::encrypt::{self, EncryptedBlob};
+
+/// Persisted state of a presignature pool.
+///
+/// The used set is persisted separately by the holder.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PoolState {
+    /// Presignatures that are available.
+    pub presignatures: Vec<EncryptedBlob>,
+}
+
+/// Pool of encrypted single-use presignatures.
+#[wasm_bindgen]
+pub struct PresignaturePool {
+    key: Vec<u8>,
+    inner: Pool<EncryptedBlob>,
+    pending: HashMap<String, (CompletedOfflineStage, BigInt)>,
+}
+
+#[wasm_bindgen]
+impl PresignaturePool {
+    /// Create a presignature pool.
+    ///
+    /// The key is the 32 byte key used to encrypt the presignatures,
+    /// used is the used set persisted by the holder and the state is
+    /// a value previously returned by `export()`; presignatures in
+    /// the used set are discarded.
+    #[wasm_bindgen(constructor)]
+    pub fn new(key: Vec<u8>, used: JsValue, state: JsValue) -> Result<PresignaturePool, JsError> {
+        let used: UsedSet = used.into_serde()?;
+        let state: PoolState = if state.is_null() || state.is_undefined() {
+            Default::default()
+        } else {
+            state.into_serde()?
+        };
+        let presignatures = state
+            .presignatures
+            .into_iter()
+            .map(|presignature| {
+                check(&key, &presignature)?;
+                Ok((presignature.id.clone(), presignature))
+            })
+            .collect::<Result<Vec<_>, JsError>>()?;
+        Ok(Self {
+            inner: Pool::new(used, presignatures)?,
+            key,
+            pending: Default::default(),
+        })
+    }
+
+    /// Add an encrypted presignature to the pool.
+    pub fn insert(&mut self, presignature: JsValue) -> Result<(), JsError> {
+        let presignature: EncryptedBlob = presignature.into_serde()?;
+        check(&self.key, &presignature)?;
+        Ok(self.inner.insert(presignature.id.clone(), presignature)?)
+    }
+
+    /// Identifiers of the available presignatures.
+    pub fn ids(&self) -> Result<JsValue, JsError> {
+        let ids = self.inner.ids().collect::<Vec<_>>();
+        Ok(JsValue::from_serde(&ids)?)
+    }
+
+    /// Used set including the presignatures consumed by this pool.
+    pub fn used(&self) -> Result<JsValue, JsError> {
+        Ok(JsValue::from_serde(self.inner.used())?)
+    }
+
+    /// Consume a presignature and return the used set that must
+    /// be persisted before `partial()` is called.
+    pub fn reserve(&mut self, id: String) -> Result<JsValue, JsError> {
+        Ok(JsValue::from_serde(self.inner.reserve(&id)?)?)
+    }
+
+    /// Return a partial signature for the message using a reserved
+    /// presignature that must be sent to the other participants.
+    pub fn partial(&mut self, id: String, message: JsValue) -> Result<JsValue, JsError> {
+        let message: Vec<u8> = message.into_serde()?;
+        let message: [u8; 32] = message.as_slice().try_into()?;
+        let presignature = self.inner.take(&id)?;
+
+        let completed: CompletedOfflineStage = encrypt::open(&self.key, &presignature)?;
+        let data = BigInt::from_bytes(&message);
+        let (_sign, partial) = SignManual::new(data.clone(), completed.clone())?;
+        self.pending.insert(id, (completed, data));
+
+        Ok(JsValue::from_serde(&partial)?)
+    }
+
+    /// Create and verify the signature.
+    ///
+    /// Protocol failures throw a `ProtocolError`.
+    pub fn create(&mut self, id: String, partials: JsValue) -> Result<JsValue, JsValue> {
+        let partials: Vec<PartialSignature> = partials.into_serde().map_err(JsError::from)?;
+        let (completed, data) = self.pending.remove(&id).ok_or_else(|| {
+            JsError::new(&format!("presignature {} has no partial signature", id))
+        })?;
+        let result = complete(completed, data, &partials).map_err(throw)?;
+        Ok(JsValue::from_serde(&result).map_err(JsError::from)?)
+    }
+
+    /// Export the available presignatures so that they can
+    /// be persisted.
+    pub fn export(&self) -> Result<JsValue, JsError> {
+        let state = PoolState {
+            presignatures: self
+                .inner
+                .presignatures()
+                .map(|(_, presignature)| presignature.clone())
+                .collect(),
+        };
+        Ok(JsValue::from_serde(&state)?)
+    }
+}
+
+/// Check a presignature can be decrypted now rather than
+/// failing when a message needs to be signed.
+fn check(key: &[u8], presignature: &EncryptedBlob) -> Result<(), JsError> {
+    let _: CompletedOfflineStage = encrypt::open(key, presignature)?;
+    Ok(())
+}
@@ -0,0 +1,54 @@
+//! Proactive key share refresh.
+//!
+//! The refresh protocol is run by the core crate; see
+//! `mpc_ecdsa_core::refresh` for the rounds and messages.
+use mpc_ecdsa_core::{KeyRefresher as Refresher, KeyShare, RefreshMessage};
+use round_based::Msg;
+use wasm_bindgen::prelude::*;
+
+use super::blame::throw;
+
+/// Round-based key share refresh.
+#[wasm_bindgen]
+pub struct KeyRefresher {
+    inner: Refresher,
+}
+
+#[wasm_bindgen]
+impl KeyRefresher {
+    /// Create a key refresher for an existing key share.
+    ///
+    /// Messages are sent using the index of the key share
+    /// as the party number.
+    #[wasm_bindgen(constructor)]
+    pub fn new(key_share: JsValue) -> Result<KeyRefresher, JsError> {
+        let key_share: KeyShare = key_share.into_serde()?;
+        Ok(Self {
+            inner: Refresher::new(key_share),
+        })
+    }
+
+    /// Handle an incoming message.
+    ///
+    /// Protocol failures throw a `ProtocolError`.
+    #[wasm_bindgen(js_name = "handleIncoming")]
+    pub fn handle_incoming(&mut self, message: JsValue) -> Result<(), JsValue> {
+        let message: Msg<RefreshMessage> = message.into_serde().map_err(JsError::from)?;
+        self.inner.handle_incoming(message).map_err(throw)
+    }
+
+    /// Proceed to the next round.
+    ///
+    /// Protocol failures throw a `ProtocolError`.
+    pub fn proceed(&mut self) -> Result<JsValue, JsValue> {
+        let (round, messages) = self.inner.proceed().map_err(throw)?;
+        Ok(JsValue::from_serde(&(round, &messages)).map_err(JsError::from)?)
+    }
+
+    /// Create the refreshed key share.
+    ///
+    /// The previous key share must be discarded.
+    pub fn create(&mut self) -> Result<JsValue, JsError> {
+        Ok(JsValue::from_serde(&self.inner.create()?)?)
+    }
+}
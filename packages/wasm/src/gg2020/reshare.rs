@@ -0,0 +1,74 @@
+//! Resharing to a new committee.
+//!
+//! The resharing protocol is run by the core crate; see
+//! `mpc_ecdsa_core::reshare` for the rounds and messages.
+use mpc_ecdsa_core::{KeyResharer as Resharer, KeyShare, ReshareKey, ReshareMessage};
+use round_based::Msg;
+use wasm_bindgen::prelude::*;
+
+use super::blame::throw;
+use super::keygen::PartySignup;
+use crate::Parameters;
+
+/// Round-based resharing of a key to a new committee.
+#[wasm_bindgen]
+pub struct KeyResharer {
+    inner: Resharer,
+}
+
+#[wasm_bindgen]
+impl KeyResharer {
+    /// Create a resharer for a party in the session.
+    ///
+    /// The parameters are for the new committee and the party
+    /// signup number is the index for the new key share; dealers
+    /// that are leaving the committee sign up after the members.
+    ///
+    /// The key is the public key being reshared with the threshold
+    /// of the existing key shares and the number of dealers that are
+    /// leaving the committee; it must be known to every party.
+    ///
+    /// Dealers must provide their existing key share; other
+    /// parties should pass `null`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        parameters: JsValue,
+        party_signup: JsValue,
+        key: JsValue,
+        key_share: JsValue,
+    ) -> Result<KeyResharer, JsError> {
+        let parameters: Parameters = parameters.into_serde()?;
+        let PartySignup { number, .. } = party_signup.into_serde::<PartySignup>()?;
+        let key: ReshareKey = key.into_serde()?;
+        let key_share: Option<KeyShare> = if key_share.is_null() || key_share.is_undefined() {
+            None
+        } else {
+            Some(key_share.into_serde()?)
+        };
+        Ok(Self {
+            inner: Resharer::new(parameters, number, key, key_share)?,
+        })
+    }
+
+    /// Handle an incoming message.
+    ///
+    /// Protocol failures throw a `ProtocolError`.
+    #[wasm_bindgen(js_name = "handleIncoming")]
+    pub fn handle_incoming(&mut self, message: JsValue) -> Result<(), JsValue> {
+        let message: Msg<ReshareMessage> = message.into_serde().map_err(JsError::from)?;
+        self.inner.handle_incoming(message).map_err(throw)
+    }
+
+    /// Proceed to the next round.
+    ///
+    /// Protocol failures throw a `ProtocolError`.
+    pub fn proceed(&mut self) -> Result<JsValue, JsValue> {
+        let (round, messages) = self.inner.proceed().map_err(throw)?;
+        Ok(JsValue::from_serde(&(round, &messages)).map_err(JsError::from)?)
+    }
+
+    /// Create the new key share.
+    pub fn create(&mut self) -> Result<JsValue, JsError> {
+        Ok(JsValue::from_serde(&self.inner.create()?)?)
+    }
+}
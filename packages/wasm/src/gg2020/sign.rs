@@ -1,110 +1,63 @@
 //! Message signing.
-use curv::{arithmetic::Converter, elliptic::curves::Secp256k1, BigInt};
-use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::{
-    party_i::{verify, SignatureRecid},
-    state_machine::{
-        keygen::LocalKey,
-        sign::{
-            CompletedOfflineStage, OfflineProtocolMessage, OfflineStage, PartialSignature,
-            SignManual,
-        },
-    },
-};
+use mpc_ecdsa_core::{KeyShare, Signer as OfflineSigner};
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::sign::PartialSignature;
 
-use round_based::{Msg, StateMachine};
-use serde::{Deserialize, Serialize};
+use super::blame::throw;
+use crate::bip32;
+use crate::encrypt;
 use std::convert::TryInto;
 use wasm_bindgen::prelude::*;
 
-//use crate::{console_log, log};
-
-const ERR_COMPLETED_OFFLINE_STAGE: &str =
-    "completed offline stage unavailable, has partial() been called?";
-
-/// Wrapper for a round `Msg` that includes the round
-/// number so that we can ensure round messages are grouped
-/// together and out of order messages can thus be handled correctly.
-#[derive(Serialize)]
-struct RoundMsg {
-    round: u16,
-    sender: u16,
-    receiver: Option<u16>,
-    body: OfflineProtocolMessage,
-}
-
-impl RoundMsg {
-    fn from_round(
-        round: u16,
-        messages: Vec<Msg<<OfflineStage as StateMachine>::MessageBody>>,
-    ) -> Vec<Self> {
-        messages
-            .into_iter()
-            .map(|m| RoundMsg {
-                round,
-                sender: m.sender,
-                receiver: m.receiver,
-                body: m.body,
-            })
-            .collect::<Vec<_>>()
-    }
-}
+pub use mpc_ecdsa_core::{complete, Signature, ONLINE_ROUND};
 
-/// Signature generated by a signer.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Signature {
-    /// The generated ECDSA signature.
-    pub signature: SignatureRecid,
-    /// The public key.
-    #[serde(rename = "publicKey")]
-    pub public_key: Vec<u8>,
-    /// Address generated from the public key.
-    pub address: String,
-}
+//use crate::{console_log, log};
 
 /// Round-based signing protocol.
 #[wasm_bindgen]
 pub struct Signer {
-    inner: OfflineStage,
-    completed: Option<(CompletedOfflineStage, BigInt)>,
+    inner: OfflineSigner,
 }
 
 #[wasm_bindgen]
 impl Signer {
     /// Create a signer.
+    ///
+    /// When a non-hardened derivation path is given the
+    /// signature is for the child key.
     #[wasm_bindgen(constructor)]
     pub fn new(
         index: JsValue,
         participants: JsValue,
-        local_key: JsValue,
+        key_share: JsValue,
+        path: Option<String>,
     ) -> Result<Signer, JsError> {
         let index: u16 = index.into_serde()?;
         let participants: Vec<u16> = participants.into_serde()?;
-        let local_key: LocalKey<Secp256k1> = local_key.into_serde()?;
+        let key_share: KeyShare = key_share.into_serde()?;
+        let local_key = bip32::local_key(key_share, path)?;
         Ok(Signer {
-            inner: OfflineStage::new(index, participants.clone(), local_key)?,
-            completed: None,
+            inner: OfflineSigner::new(index, participants, local_key)?,
         })
     }
 
     /// Handle an incoming message.
+    ///
+    /// Protocol failures throw a `ProtocolError`.
     #[wasm_bindgen(js_name = "handleIncoming")]
-    pub fn handle_incoming(&mut self, message: JsValue) -> Result<(), JsError> {
-        let message: Msg<<OfflineStage as StateMachine>::MessageBody> = message.into_serde()?;
-        self.inner.handle_incoming(message)?;
-        Ok(())
+    pub fn handle_incoming(&mut self, message: JsValue) -> Result<(), JsValue> {
+        let message = message.into_serde().map_err(JsError::from)?;
+        self.inner.handle_incoming(message).map_err(throw)
     }
 
     /// Proceed to the next round.
-    pub fn proceed(&mut self) -> Result<JsValue, JsError> {
-        if self.inner.wants_to_proceed() {
-            self.inner.proceed()?;
-            let messages = self.inner.message_queue().drain(..).collect();
-            let round = self.inner.current_round();
-            let messages = RoundMsg::from_round(round, messages);
-            Ok(JsValue::from_serde(&(round, &messages))?)
-        } else {
-            Ok(JsValue::from_serde(&false)?)
-        }
+    ///
+    /// Protocol failures throw a `ProtocolError`.
+    pub fn proceed(&mut self) -> Result<JsValue, JsValue> {
+        let value = match self.inner.proceed().map_err(throw)? {
+            Some((round, messages)) => JsValue::from_serde(&(round, &messages)),
+            None => JsValue::from_serde(&false),
+        };
+        Ok(value.map_err(JsError::from)?)
     }
 
     /// Generate the completed offline stage and store the result
@@ -115,38 +68,27 @@ impl Signer {
     pub fn partial(&mut self, message: JsValue) -> Result<JsValue, JsError> {
         let message: Vec<u8> = message.into_serde()?;
         let message: [u8; 32] = message.as_slice().try_into()?;
-        let completed_offline_stage = self.inner.pick_output().unwrap()?;
-        let data = BigInt::from_bytes(&message);
-        let (_sign, partial) = SignManual::new(data.clone(), completed_offline_stage.clone())?;
-
-        self.completed = Some((completed_offline_stage, data));
+        Ok(JsValue::from_serde(&self.inner.partial(message)?)?)
+    }
 
-        Ok(JsValue::from_serde(&partial)?)
+    /// Export the completed offline stage as an encrypted
+    /// presignature so that the message can be signed later
+    /// using a `PresignaturePool`.
+    ///
+    /// The identifier must be the same for all the participants
+    /// and unique for each offline stage, typically the session
+    /// identifier is used. The key is a 32 byte encryption key.
+    pub fn presignature(&mut self, id: String, key: Vec<u8>) -> Result<JsValue, JsError> {
+        let presignature = encrypt::seal(&key, id, &self.inner.presignature()?)?;
+        Ok(JsValue::from_serde(&presignature)?)
     }
 
     /// Create and verify the signature.
-    pub fn create(&mut self, partials: JsValue) -> Result<JsValue, JsError> {
-        let partials: Vec<PartialSignature> = partials.into_serde()?;
-
-        let (completed_offline_stage, data) = self
-            .completed
-            .take()
-            .ok_or_else(|| JsError::new(ERR_COMPLETED_OFFLINE_STAGE))?;
-        let pk = completed_offline_stage.public_key().clone();
-
-        let (sign, _partial) = SignManual::new(data.clone(), completed_offline_stage.clone())?;
-
-        let signature = sign.complete(&partials)?;
-        verify(&signature, &pk, &data)
-            .map_err(|e| JsError::new(&format!("failed to verify signature: {:?}", e)))?;
-
-        let public_key = pk.to_bytes(false).to_vec();
-        let result = Signature {
-            signature,
-            address: crate::utils::address(&public_key),
-            public_key,
-        };
-
-        Ok(JsValue::from_serde(&result)?)
+    ///
+    /// Protocol failures throw a `ProtocolError`.
+    pub fn create(&mut self, partials: JsValue) -> Result<JsValue, JsValue> {
+        let partials: Vec<PartialSignature> = partials.into_serde().map_err(JsError::from)?;
+        let result = self.inner.create(&partials).map_err(throw)?;
+        Ok(JsValue::from_serde(&result).map_err(JsError::from)?)
     }
 }
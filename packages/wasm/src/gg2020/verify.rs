@@ -0,0 +1,22 @@
+//! Key share integrity checks.
+//!
+//! The checks are run by the core crate; see
+//! `mpc_ecdsa_core::verify` for the individual checks.
+use mpc_ecdsa_core::KeyShareReport;
+use wasm_bindgen::prelude::*;
+
+use super::keygen::KeyShare;
+
+/// Check a key share is intact and consistent.
+///
+/// Verifies the stored public key and address, that the public key
+/// shares interpolate to the public key, that the secret share
+/// matches the public key share and that the Paillier keys and
+/// ring-pedersen (`h1`, `h2`, `N~`) parameters are well formed.
+/// Returns a report with the result of each check rather than
+/// throwing on the first failure.
+#[wasm_bindgen(js_name = "verifyKeyShare")]
+pub fn verify_key_share(key_share: JsValue) -> Result<JsValue, JsError> {
+    let key_share: KeyShare = key_share.into_serde()?;
+    Ok(JsValue::from_serde(&KeyShareReport::new(&key_share))?)
+}
@@ -1,6 +1,5 @@
 //! Webassembly bindings to the GG2020 protocol in [multi-party-ecdsa](https://github.com/ZenGo-X/multi-party-ecdsa) for MPC key generation and signing.
 #![deny(missing_docs)]
-use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
 extern crate wasm_bindgen;
@@ -30,39 +29,50 @@ pub fn start() {
 // Required for rayon thread support
 pub use wasm_bindgen_rayon::init_thread_pool;
 
+mod bip32;
+mod bitcoin;
+mod encoding;
+mod encrypt;
+mod ethereum;
+mod frost;
 mod gg2020;
+mod public_key;
 mod utils;
 
 // Expose these types for API documentation.
-pub use gg2020::keygen::{KeyGenerator, KeyShare, PartySignup};
+pub use bip32::{derive_child_key, ChildKey};
+pub use bitcoin::psbt::{psbt_insert_signature, psbt_sighash, Psbt};
+pub use bitcoin::{bitcoin_address, AddressOptions, AddressType, Network};
+pub use encoding::{encode_signature, EncodeOptions, RawSignature, SignatureFormat};
+pub use encrypt::EncryptedBlob;
+pub use ethereum::eip191::hash_message;
+pub use ethereum::eip712::{hash_typed_data, TypedData, TypedField};
+pub use ethereum::transaction::{
+    sign_transaction, transaction_hash, SignedTransaction, Transaction,
+};
+pub use frost::keygen::FrostKeyGenerator;
+pub use frost::sign::FrostSigner;
+pub use frost::{FrostCurve, FrostKeyShare, FrostLocalKey, FrostSignature};
+pub use gg2020::batch::BatchSigner;
+pub use gg2020::blame::ProtocolError;
+pub use gg2020::keygen::{
+    KeyGenerator, KeyShare, KeygenEvent, KeygenProgress, KeygenStep, PartySignup,
+};
+pub use gg2020::presign::{PoolState, PresignaturePool};
+pub use gg2020::refresh::KeyRefresher;
+pub use gg2020::reshare::KeyResharer;
 pub use gg2020::sign::{Signature, Signer};
-
-/// Parameters used during key generation.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Parameters {
-    /// Number of parties `n`.
-    pub parties: u16,
-    /// Threshold for signing `t`.
-    ///
-    /// The threshold must be crossed (`t + 1`) for signing
-    /// to commence.
-    pub threshold: u16,
-}
-
-impl Default for Parameters {
-    fn default() -> Self {
-        return Self {
-            parties: 3,
-            threshold: 1,
-        };
-    }
-}
+pub use gg2020::verify::verify_key_share;
+pub use mpc_ecdsa_core::Parameters;
+pub use mpc_ecdsa_core::{Check, KeyShareReport};
+pub use public_key::{
+    checksum_address, export_public_key, import_public_key, parse_address, Jwk, PublicKeyFormat,
+};
 
 /// Compute the Keccak256 hash of a value.
 #[wasm_bindgen]
 pub fn keccak256(message: JsValue) -> Result<JsValue, JsError> {
-    use sha3::{Digest, Keccak256};
     let message: Vec<u8> = message.into_serde()?;
-    let digest = Keccak256::digest(&message).to_vec();
+    let digest = utils::keccak256(&message).to_vec();
     Ok(JsValue::from_serde(&digest)?)
 }
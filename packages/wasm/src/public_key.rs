@@ -0,0 +1,348 @@
+//! Public key export formats.
+//!
+//! Key shares store the group public key as the 65 byte uncompressed
+//! SEC1 encoding; verifiers outside of this library usually expect
+//! the compressed SEC1 encoding as hex, an X.509 `SubjectPublicKeyInfo`
+//! (SPKI) as DER or PEM, a JSON Web Key (JWK) or an EIP-55 checksum
+//! address so keys can be converted to and from those formats.
+use curv::elliptic::curves::{Point, Secp256k1};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use wasm_bindgen::prelude::*;
+
+use crate::utils::{compress, keccak256};
+
+/// DER prefix of an SPKI for an uncompressed secp256k1 key;
+/// the `id-ecPublicKey` and `secp256k1` object identifiers followed
+/// by the header of the bit string holding the key.
+const SPKI_PREFIX: [u8; 23] = [
+    0x30, 0x56, 0x30, 0x10, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x05, 0x2b,
+    0x81, 0x04, 0x00, 0x0a, 0x03, 0x42, 0x00,
+];
+
+/// DER prefix of an SPKI for a compressed secp256k1 key.
+const SPKI_COMPRESSED_PREFIX: [u8; 23] = [
+    0x30, 0x36, 0x30, 0x10, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x05, 0x2b,
+    0x81, 0x04, 0x00, 0x0a, 0x03, 0x22, 0x00,
+];
+
+const PEM_HEADER: &str = "-----BEGIN PUBLIC KEY-----";
+const PEM_FOOTER: &str = "-----END PUBLIC KEY-----";
+
+/// Errors converting public keys.
+#[derive(Debug)]
+pub enum Error {
+    /// Bytes are not a valid SEC1 encoding of a point on the curve.
+    PublicKey,
+    /// Value is not valid hex.
+    Hex,
+    /// Value is not valid base64.
+    Base64,
+    /// DER is not an SPKI for a secp256k1 key.
+    Spki,
+    /// PEM is not a public key.
+    Pem,
+    /// JWK is not a secp256k1 key.
+    Jwk(&'static str),
+    /// Address is malformed or has an invalid checksum.
+    Address,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::PublicKey => write!(f, "invalid public key"),
+            Error::Hex => write!(f, "invalid hex"),
+            Error::Base64 => write!(f, "invalid base64"),
+            Error::Spki => write!(f, "not a secp256k1 subject public key info"),
+            Error::Pem => write!(f, "not a PEM encoded public key"),
+            Error::Jwk(message) => write!(f, "invalid jwk: {}", message),
+            Error::Address => write!(f, "invalid address"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Public key encoding.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PublicKeyFormat {
+    /// Compressed SEC1 encoding as a hex string.
+    Compressed,
+    /// DER encoded `SubjectPublicKeyInfo` bytes.
+    Spki,
+    /// PEM encoded `SubjectPublicKeyInfo`.
+    Pem,
+    /// JSON Web Key.
+    Jwk,
+}
+
+/// JSON Web Key for a secp256k1 public key (RFC 7517, RFC 8812).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Jwk {
+    /// Key type, always `EC`.
+    pub kty: String,
+    /// Curve, always `secp256k1`.
+    pub crv: String,
+    /// Base64url encoded `x` coordinate.
+    pub x: String,
+    /// Base64url encoded `y` coordinate.
+    pub y: String,
+}
+
+/// Uncompressed SEC1 encoding of a public key given in compressed
+/// or uncompressed form; the point must be on the curve.
+pub fn uncompressed(public_key: &[u8]) -> Result<[u8; 65], Error> {
+    let point = Point::<Secp256k1>::from_bytes(public_key).map_err(|_| Error::PublicKey)?;
+    let mut bytes = [0u8; 65];
+    bytes.copy_from_slice(&point.to_bytes(false));
+    Ok(bytes)
+}
+
+/// Compressed SEC1 encoding of a public key as hex.
+pub fn to_compressed_hex(public_key: &[u8]) -> Result<String, Error> {
+    Ok(hex::encode(compress(public_key).ok_or(Error::PublicKey)?))
+}
+
+/// Parse a SEC1 encoded public key from hex.
+pub fn from_compressed_hex(value: &str) -> Result<[u8; 65], Error> {
+    let value = value.strip_prefix("0x").unwrap_or(value);
+    uncompressed(&hex::decode(value).map_err(|_| Error::Hex)?)
+}
+
+/// DER encoded `SubjectPublicKeyInfo` for a public key.
+pub fn to_spki(public_key: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut der = SPKI_PREFIX.to_vec();
+    der.extend_from_slice(&uncompressed(public_key)?);
+    Ok(der)
+}
+
+/// Parse a DER encoded `SubjectPublicKeyInfo` holding a compressed
+/// or uncompressed secp256k1 key.
+pub fn from_spki(der: &[u8]) -> Result<[u8; 65], Error> {
+    let (prefix, key) = der.split_at(SPKI_PREFIX.len().min(der.len()));
+    match (prefix, key.len()) {
+        (prefix, 65) if prefix == SPKI_PREFIX => uncompressed(key),
+        (prefix, 33) if prefix == SPKI_COMPRESSED_PREFIX => uncompressed(key),
+        _ => Err(Error::Spki),
+    }
+}
+
+/// PEM encoded `SubjectPublicKeyInfo` for a public key.
+pub fn to_pem(public_key: &[u8]) -> Result<String, Error> {
+    let encoded = base64::encode(to_spki(public_key)?);
+    let mut pem = format!("{}\n", PEM_HEADER);
+    for line in encoded.as_bytes().chunks(64) {
+        pem.push_str(std::str::from_utf8(line).unwrap());
+        pem.push('\n');
+    }
+    pem.push_str(PEM_FOOTER);
+    pem.push('\n');
+    Ok(pem)
+}
+
+/// Parse a PEM encoded `SubjectPublicKeyInfo`.
+pub fn from_pem(pem: &str) -> Result<[u8; 65], Error> {
+    let body = pem
+        .trim()
+        .strip_prefix(PEM_HEADER)
+        .and_then(|pem| pem.strip_suffix(PEM_FOOTER))
+        .ok_or(Error::Pem)?;
+    let body = body.split_whitespace().collect::<String>();
+    from_spki(&base64::decode(body).map_err(|_| Error::Base64)?)
+}
+
+/// JSON Web Key for a public key.
+pub fn to_jwk(public_key: &[u8]) -> Result<Jwk, Error> {
+    let public_key = uncompressed(public_key)?;
+    Ok(Jwk {
+        kty: "EC".to_string(),
+        crv: "secp256k1".to_string(),
+        x: base64::encode_config(&public_key[1..33], base64::URL_SAFE_NO_PAD),
+        y: base64::encode_config(&public_key[33..], base64::URL_SAFE_NO_PAD),
+    })
+}
+
+/// Parse a JSON Web Key for a secp256k1 public key.
+pub fn from_jwk(jwk: &Jwk) -> Result<[u8; 65], Error> {
+    if jwk.kty != "EC" {
+        return Err(Error::Jwk("key type must be EC"));
+    }
+    if jwk.crv != "secp256k1" {
+        return Err(Error::Jwk("curve must be secp256k1"));
+    }
+    let mut public_key = vec![0x04];
+    for coordinate in [&jwk.x, &jwk.y].iter() {
+        let bytes = base64::decode_config(coordinate, base64::URL_SAFE_NO_PAD)
+            .map_err(|_| Error::Base64)?;
+        if bytes.len() != 32 {
+            return Err(Error::Jwk("coordinates must be 32 bytes"));
+        }
+        public_key.extend_from_slice(&bytes);
+    }
+    uncompressed(&public_key)
+}
+
+/// EIP-55 mixed case checksum encoding of a 20 byte address.
+fn checksum(address: &[u8]) -> String {
+    let lower = hex::encode(address);
+    let digest = keccak256(lower.as_bytes());
+    let encoded = lower
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (digest[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0x0f;
+            if nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect::<String>();
+    format!("0x{}", encoded)
+}
+
+/// Ethereum address for a public key with an EIP-55 checksum.
+pub fn to_checksum_address(public_key: &[u8]) -> Result<String, Error> {
+    let public_key = uncompressed(public_key)?;
+    let digest = keccak256(&public_key[1..]);
+    Ok(checksum(&digest[12..]))
+}
+
+/// Parse an Ethereum address and return the checksum encoding.
+///
+/// Mixed case addresses must have a valid checksum, all lower
+/// or upper case addresses are accepted without a checksum.
+pub fn from_checksum_address(address: &str) -> Result<String, Error> {
+    let digits = address.strip_prefix("0x").ok_or(Error::Address)?;
+    let bytes = hex::decode(digits).map_err(|_| Error::Address)?;
+    if bytes.len() != 20 {
+        return Err(Error::Address);
+    }
+    let encoded = checksum(&bytes);
+    let mixed = digits.chars().any(|c| c.is_ascii_lowercase())
+        && digits.chars().any(|c| c.is_ascii_uppercase());
+    if mixed && encoded[2..] != *digits {
+        return Err(Error::Address);
+    }
+    Ok(encoded)
+}
+
+/// Export a public key in another format.
+///
+/// The public key is a SEC1 encoded key (usually the `publicKey`
+/// of a key share) and the format is one of `compressed` (hex string),
+/// `spki` (DER bytes), `pem` (string) or `jwk` (object).
+#[wasm_bindgen(js_name = "exportPublicKey")]
+pub fn export_public_key(public_key: JsValue, format: JsValue) -> Result<JsValue, JsError> {
+    let public_key: Vec<u8> = public_key.into_serde()?;
+    let format: PublicKeyFormat = format.into_serde()?;
+    let value = match format {
+        PublicKeyFormat::Compressed => JsValue::from_serde(&to_compressed_hex(&public_key)?)?,
+        PublicKeyFormat::Spki => JsValue::from_serde(&to_spki(&public_key)?)?,
+        PublicKeyFormat::Pem => JsValue::from_serde(&to_pem(&public_key)?)?,
+        PublicKeyFormat::Jwk => JsValue::from_serde(&to_jwk(&public_key)?)?,
+    };
+    Ok(value)
+}
+
+/// Parse a public key exported using `exportPublicKey()` and
+/// return the 65 byte uncompressed SEC1 encoding.
+#[wasm_bindgen(js_name = "importPublicKey")]
+pub fn import_public_key(value: JsValue, format: JsValue) -> Result<JsValue, JsError> {
+    let format: PublicKeyFormat = format.into_serde()?;
+    let public_key = match format {
+        PublicKeyFormat::Compressed => from_compressed_hex(&value.into_serde::<String>()?)?,
+        PublicKeyFormat::Spki => from_spki(&value.into_serde::<Vec<u8>>()?)?,
+        PublicKeyFormat::Pem => from_pem(&value.into_serde::<String>()?)?,
+        PublicKeyFormat::Jwk => from_jwk(&value.into_serde::<Jwk>()?)?,
+    };
+    Ok(JsValue::from_serde(&public_key.to_vec())?)
+}
+
+/// Ethereum address with an EIP-55 checksum for a public key.
+#[wasm_bindgen(js_name = "checksumAddress")]
+pub fn checksum_address(public_key: JsValue) -> Result<String, JsError> {
+    let public_key: Vec<u8> = public_key.into_serde()?;
+    Ok(to_checksum_address(&public_key)?)
+}
+
+/// Validate an Ethereum address and return the EIP-55
+/// checksum encoding.
+#[wasm_bindgen(js_name = "parseAddress")]
+pub fn parse_address(address: String) -> Result<String, JsError> {
+    Ok(from_checksum_address(&address)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Public key for the private key 1
+    const COMPRESSED: &str = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+    const UNCOMPRESSED: &str = "0479be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798\
+                                483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8";
+
+    fn public_key() -> Vec<u8> {
+        hex::decode(UNCOMPRESSED).unwrap()
+    }
+
+    #[test]
+    fn compressed_hex() {
+        assert_eq!(to_compressed_hex(&public_key()).unwrap(), COMPRESSED);
+        assert_eq!(
+            &from_compressed_hex(COMPRESSED).unwrap()[..],
+            &public_key()[..]
+        );
+    }
+
+    #[test]
+    fn spki_and_pem() {
+        let der = to_spki(&public_key()).unwrap();
+        assert_eq!(der.len(), 88);
+        assert_eq!(&from_spki(&der).unwrap()[..], &public_key()[..]);
+
+        let mut compressed = SPKI_COMPRESSED_PREFIX.to_vec();
+        compressed.extend_from_slice(&hex::decode(COMPRESSED).unwrap());
+        assert_eq!(&from_spki(&compressed).unwrap()[..], &public_key()[..]);
+        assert!(from_spki(&der[..40]).is_err());
+
+        let pem = to_pem(&public_key()).unwrap();
+        assert_eq!(
+            pem,
+            "-----BEGIN PUBLIC KEY-----\n\
+             MFYwEAYHKoZIzj0CAQYFK4EEAAoDQgAEeb5mfvncu6xVoGKVzocLBwKb/NstzijZ\n\
+             WfKBWxb4F5hIOtp3JqPEZV2k+/wOEQio/Re0SKaFVBmcR9CP+xDUuA==\n\
+             -----END PUBLIC KEY-----\n"
+        );
+        assert_eq!(&from_pem(&pem).unwrap()[..], &public_key()[..]);
+    }
+
+    #[test]
+    fn jwk() {
+        let jwk = to_jwk(&public_key()).unwrap();
+        assert_eq!(jwk.kty, "EC");
+        assert_eq!(jwk.crv, "secp256k1");
+        assert_eq!(jwk.x, "eb5mfvncu6xVoGKVzocLBwKb_NstzijZWfKBWxb4F5g");
+        assert_eq!(jwk.y, "SDradyajxGVdpPv8DhEIqP0XtEimhVQZnEfQj_sQ1Lg");
+        assert_eq!(&from_jwk(&jwk).unwrap()[..], &public_key()[..]);
+        assert!(from_jwk(&Jwk {
+            crv: "P-256".to_string(),
+            ..jwk
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn addresses() {
+        let address = to_checksum_address(&public_key()).unwrap();
+        assert_eq!(address, "0x7E5F4552091A69125d5DfCb7b8C2659029395Bdf");
+        assert_eq!(
+            from_checksum_address(&address.to_lowercase()).unwrap(),
+            address
+        );
+        assert!(from_checksum_address("0x7e5F4552091A69125d5DfCb7b8C2659029395Bdf").is_err());
+        assert!(from_checksum_address("0x7E5F4552091A69125d5DfCb7b8C2659029395B").is_err());
+    }
+}
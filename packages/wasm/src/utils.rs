@@ -1,10 +1,51 @@
+use curv::elliptic::curves::{Point, Secp256k1};
 use sha3::{Digest, Keccak256};
 
 /// Compute the address of an uncompressed public key (65 bytes).
-pub(crate) fn address(public_key: &Vec<u8>) -> String {
-    // Remove the leading 0x04
-    let bytes = &public_key[1..];
-    let digest = Keccak256::digest(bytes);
-    let final_bytes = &digest[12..];
-    format!("0x{}", hex::encode(&final_bytes))
+pub(crate) fn address(public_key: &[u8]) -> mpc_ecdsa_core::Result<String> {
+    Ok(mpc_ecdsa_core::address(public_key)?)
+}
+
+/// Compute the Keccak256 digest of some bytes.
+pub(crate) fn keccak256(bytes: &[u8]) -> [u8; 32] {
+    Keccak256::digest(bytes).into()
+}
+
+/// Compressed SEC1 encoding of a public key given in compressed
+/// or uncompressed form.
+///
+/// Returns `None` when the bytes are not the encoding of a point
+/// on the curve.
+pub(crate) fn compress(public_key: &[u8]) -> Option<[u8; 33]> {
+    let point = Point::<Secp256k1>::from_bytes(public_key).ok()?;
+    let mut compressed = [0u8; 33];
+    compressed.copy_from_slice(&point.to_bytes(true));
+    Some(compressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Public key for the private key 1
+    const COMPRESSED: &str = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+    const UNCOMPRESSED: &str = "0479be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798\
+                                483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8";
+
+    #[test]
+    fn compress_public_key() {
+        let uncompressed = hex::decode(UNCOMPRESSED).unwrap();
+        let compressed = hex::decode(COMPRESSED).unwrap();
+        assert_eq!(hex::encode(compress(&uncompressed).unwrap()), COMPRESSED);
+        assert_eq!(hex::encode(compress(&compressed).unwrap()), COMPRESSED);
+        assert!(compress(&uncompressed[..64]).is_none());
+
+        // Points that are not on the curve are rejected
+        let mut off_curve = uncompressed.clone();
+        off_curve[64] ^= 1;
+        assert!(compress(&off_curve).is_none());
+        let mut out_of_range = vec![0x02];
+        out_of_range.extend_from_slice(&[0xff; 32]);
+        assert!(compress(&out_of_range).is_none());
+    }
 }
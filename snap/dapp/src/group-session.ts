@@ -25,9 +25,9 @@ async function prepareTransport(
 ): Promise<void> {
   const stream = new WebSocketStream(websocket, group.uuid, session.uuid, kind);
   const expected =
-    kind === SessionKind.KEYGEN
-      ? group.params.parties - 1
-      : group.params.threshold;
+    kind === SessionKind.SIGN
+      ? group.params.threshold
+      : group.params.parties - 1;
 
   const sink = new WebSocketSink(websocket, expected, session.uuid);
   dispatch(setTransport({ stream, sink }));
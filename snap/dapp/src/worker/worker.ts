@@ -1,7 +1,27 @@
 import init, {
   initThreadPool,
+  BatchSigner,
+  bitcoinAddress,
+  checksumAddress,
+  deriveChildKey,
+  encodeSignature,
+  exportPublicKey,
+  FrostKeyGenerator,
+  FrostSigner,
+  hashMessage,
+  hashTypedData,
+  importPublicKey,
   KeyGenerator,
+  KeyRefresher,
+  KeyResharer,
+  parseAddress,
+  PresignaturePool,
+  psbtInsertSignature,
+  psbtSighash,
   Signer,
+  signTransaction,
+  transactionHash,
+  verifyKeyShare,
 } from "@metamask/mpc-ecdsa-wasm";
 import * as Comlink from "comlink";
 
@@ -25,6 +45,26 @@ void (async function () {
 })();
 
 Comlink.expose({
+  BatchSigner,
+  bitcoinAddress,
+  checksumAddress,
+  deriveChildKey,
+  encodeSignature,
+  exportPublicKey,
+  FrostKeyGenerator,
+  FrostSigner,
+  hashMessage,
+  hashTypedData,
+  importPublicKey,
   KeyGenerator,
+  KeyRefresher,
+  KeyResharer,
+  parseAddress,
+  PresignaturePool,
+  psbtInsertSignature,
+  psbtSighash,
   Signer,
+  signTransaction,
+  transactionHash,
+  verifyKeyShare,
 });
@@ -32,20 +32,8 @@ pub struct NamedKeyShare {
     share: KeyShare,
 }
 
-// TODO: share this KeyShare type with the main MPC webassembly module
-
 /// Generated key share.
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct KeyShare {
-    /// The secret private key.
-    #[serde(rename = "localKey")]
-    pub local_key: LocalKey<Secp256k1>,
-    /// The public key.
-    #[serde(rename = "publicKey")]
-    pub public_key: Vec<u8>,
-    /// Address generated from the public key.
-    pub address: String,
-}
+pub type KeyShare = mpc_ecdsa_types::KeyShare<LocalKey<Secp256k1>>;
 
 /// Error thrown by the library.
 #[derive(Debug, Error)]